@@ -1,11 +1,17 @@
 use std::cmp::max;
+#[cfg(unix)]
+use std::mem;
 use std::path::PathBuf;
 
 use clap::{crate_authors, crate_description, crate_name, crate_version, App, Arg};
+#[cfg(unix)]
+use clap::{AppSettings, ArgMatches, SubCommand};
 use log::{self, error, LevelFilter};
+use serde::{Deserialize, Serialize};
 use serde_yaml::Value;
 
-use alacritty_terminal::config::Program;
+use alacritty_terminal::asciicast::Header;
+use alacritty_terminal::config::{Config as TermConfig, Program};
 
 use crate::config::serde_utils;
 use crate::config::window::DEFAULT_NAME;
@@ -30,8 +36,16 @@ pub struct Options {
     pub command: Option<Program>,
     pub hold: bool,
     pub working_directory: Option<PathBuf>,
+    pub record: Option<PathBuf>,
+    pub play: Option<Playback>,
+    pub screenshot: Option<PathBuf>,
     pub config_path: Option<PathBuf>,
     pub config_options: Value,
+    pub ipc_config_options: Value,
+    #[cfg(unix)]
+    pub socket: Option<PathBuf>,
+    #[cfg(unix)]
+    pub message: Option<MessageOptions>,
 }
 
 impl Default for Options {
@@ -47,8 +61,16 @@ impl Default for Options {
             command: None,
             hold: false,
             working_directory: None,
+            record: None,
+            play: None,
+            screenshot: None,
             config_path: None,
             config_options: Value::Null,
+            ipc_config_options: Value::Null,
+            #[cfg(unix)]
+            socket: None,
+            #[cfg(unix)]
+            message: None,
         }
     }
 }
@@ -62,9 +84,15 @@ impl Options {
             version = format!("{} ({})", version, commit_hash);
         }
 
+        let title_help = format!("Defines the window title [default: {}]", DEFAULT_NAME);
+        let class_help =
+            format!("Defines window class/app_id on X11/Wayland [default: {}]", DEFAULT_NAME);
+        let config_file_help =
+            format!("Specify alternative configuration file [default: {}]", CONFIG_PATH);
+
         let mut options = Options::default();
 
-        let matches = App::new(crate_name!())
+        let app = App::new(crate_name!())
             .version(version.as_str())
             .author(crate_authors!("\n"))
             .about(crate_description!())
@@ -79,7 +107,7 @@ impl Options {
                     .long("title")
                     .short("t")
                     .takes_value(true)
-                    .help(&format!("Defines the window title [default: {}]", DEFAULT_NAME)),
+                    .help(&title_help),
             )
             .arg(
                 Arg::with_name("class")
@@ -87,10 +115,7 @@ impl Options {
                     .value_name("instance> | <instance>,<general")
                     .takes_value(true)
                     .use_delimiter(true)
-                    .help(&format!(
-                        "Defines window class/app_id on X11/Wayland [default: {}]",
-                        DEFAULT_NAME
-                    )),
+                    .help(&class_help),
             )
             .arg(
                 Arg::with_name("embed").long("embed").takes_value(true).help(
@@ -117,9 +142,12 @@ impl Options {
                     .takes_value(true)
                     .help("Start the shell in the specified working directory"),
             )
-            .arg(Arg::with_name("config-file").long("config-file").takes_value(true).help(
-                &format!("Specify alternative configuration file [default: {}]", CONFIG_PATH),
-            ))
+            .arg(
+                Arg::with_name("config-file")
+                    .long("config-file")
+                    .takes_value(true)
+                    .help(&config_file_help),
+            )
             .arg(
                 Arg::with_name("command")
                     .long("command")
@@ -130,6 +158,40 @@ impl Options {
                     .help("Command and args to execute (must be last argument)"),
             )
             .arg(Arg::with_name("hold").long("hold").help("Remain open after child process exits"))
+            .arg(
+                Arg::with_name("record")
+                    .long("record")
+                    .takes_value(true)
+                    .conflicts_with("play")
+                    .help("Record the session to an asciicast file"),
+            )
+            .arg(
+                Arg::with_name("play")
+                    .long("play")
+                    .takes_value(true)
+                    .help("Play back an asciicast recording instead of starting a shell"),
+            )
+            .arg(
+                Arg::with_name("speed")
+                    .long("speed")
+                    .takes_value(true)
+                    .requires("play")
+                    .validator(|speed| match speed.parse::<f64>() {
+                        Ok(speed) if speed > 0. => Ok(()),
+                        _ => Err(String::from("speed must be a positive number")),
+                    })
+                    .help("Playback speed multiplier [default: 1]"),
+            )
+            .arg(
+                Arg::with_name("screenshot")
+                    .long("screenshot")
+                    .takes_value(true)
+                    .conflicts_with("record")
+                    .help(
+                        "Render the terminal to a PNG file without opening a window, using the \
+                         output of the command, the recording or STDIN",
+                    ),
+            )
             .arg(
                 Arg::with_name("option")
                     .long("option")
@@ -137,8 +199,19 @@ impl Options {
                     .multiple(true)
                     .takes_value(true)
                     .help("Override configuration file options [example: cursor.style=Beam]"),
+            );
+
+        #[cfg(unix)]
+        let app = app
+            .arg(
+                Arg::with_name("socket")
+                    .long("socket")
+                    .takes_value(true)
+                    .help("Path for IPC socket creation"),
             )
-            .get_matches();
+            .subcommand(message_subcommand());
+
+        let matches = app.get_matches();
 
         if matches.is_present("ref-test") {
             options.ref_test = true;
@@ -190,6 +263,25 @@ impl Options {
             options.hold = true;
         }
 
+        options.record = matches.value_of("record").map(PathBuf::from);
+        options.screenshot = matches.value_of("screenshot").map(PathBuf::from);
+
+        if let Some(path) = matches.value_of("play") {
+            // The validator guarantees that the speed is a valid number.
+            let speed = matches.value_of("speed").map_or(1., |speed| speed.parse().unwrap());
+            options.play = Some(Playback { path: PathBuf::from(path), speed });
+
+            // Size the window to match the recorded terminal.
+            if let Ok(header) = Header::read(path) {
+                let dimensions = format!(
+                    "window.dimensions={{columns: {}, lines: {}}}",
+                    header.width, header.height
+                );
+                let value = option_as_value(&dimensions).unwrap();
+                options.config_options = serde_utils::merge(value, options.config_options);
+            }
+        }
+
         if let Some(config_options) = matches.values_of("option") {
             for option in config_options {
                 match option_as_value(option) {
@@ -201,6 +293,12 @@ impl Options {
             }
         }
 
+        #[cfg(unix)]
+        {
+            options.socket = matches.value_of("socket").map(PathBuf::from);
+            options.message = matches.subcommand_matches("msg").and_then(message_options);
+        }
+
         options
     }
 
@@ -209,27 +307,52 @@ impl Options {
         self.config_path.clone()
     }
 
-    /// CLI config options as deserializable serde value.
-    pub fn config_options(&self) -> &Value {
-        &self.config_options
+    /// CLI and IPC config options as deserializable serde value.
+    pub fn config_options(&self) -> Value {
+        serde_utils::merge(self.config_options.clone(), self.ipc_config_options.clone())
     }
 
-    /// Override configuration file with options from the CLI.
-    pub fn override_config(&self, config: &mut Config) {
-        if let Some(working_directory) = &self.working_directory {
-            if working_directory.is_dir() {
-                config.working_directory = Some(working_directory.to_owned());
-            } else {
-                error!("Invalid working directory: {:?}", working_directory);
-            }
+    /// Update the config options received through the IPC socket.
+    #[cfg(unix)]
+    pub fn update_ipc_config(&mut self, ipc_config: &IpcConfig) {
+        if ipc_config.reset {
+            self.ipc_config_options = Value::Null;
         }
 
-        if let Some(command) = &self.command {
-            config.shell = Some(command.clone());
+        for option in &ipc_config.options {
+            match option_as_value(option) {
+                Ok(value) => {
+                    let ipc_config_options =
+                        mem::replace(&mut self.ipc_config_options, Value::Null);
+                    self.ipc_config_options = serde_utils::merge(ipc_config_options, value);
+                },
+                Err(_) => error!("Invalid IPC config option: {:?}", option),
+            }
         }
+    }
 
-        config.hold = self.hold;
+    /// Options for the terminal of the initial window.
+    pub fn window_options(&self) -> WindowOptions {
+        let command = match &self.command {
+            Some(command) => {
+                let mut command_args = vec![command.program().to_owned()];
+                command_args.extend_from_slice(command.args());
+                command_args
+            },
+            None => Vec::new(),
+        };
+
+        WindowOptions {
+            working_directory: self.working_directory.clone(),
+            command,
+            hold: self.hold,
+            record: self.record.clone(),
+            play: self.play.clone(),
+        }
+    }
 
+    /// Override configuration file with options from the CLI.
+    pub fn override_config(&self, config: &mut Config) {
         if let Some(title) = self.title.clone() {
             config.ui_config.window.title = title
         }
@@ -253,6 +376,177 @@ impl Options {
     }
 }
 
+/// Available socket messages.
+#[cfg(unix)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum SocketMessage {
+    /// Create a new terminal window.
+    CreateWindow(WindowOptions),
+
+    /// Update the runtime configuration overrides.
+    Config(IpcConfig),
+
+    /// Execute a key binding action.
+    Action(String),
+}
+
+/// Options for sending a message to a running Alacritty instance.
+#[cfg(unix)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessageOptions {
+    /// IPC socket connection path override.
+    pub socket: Option<PathBuf>,
+
+    /// Message which should be sent.
+    pub message: SocketMessage,
+}
+
+/// Terminal options for a new window.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct WindowOptions {
+    /// Start the shell in the specified working directory.
+    pub working_directory: Option<PathBuf>,
+
+    /// Command and args to execute instead of the shell.
+    pub command: Vec<String>,
+
+    /// Remain open after child process exits.
+    pub hold: bool,
+
+    /// Record the session to an asciicast file.
+    pub record: Option<PathBuf>,
+
+    /// Play back an asciicast recording instead of starting a shell.
+    pub play: Option<Playback>,
+}
+
+/// Playback of an asciicast recording.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Playback {
+    /// Path of the recording.
+    pub path: PathBuf,
+
+    /// Playback speed multiplier.
+    pub speed: f64,
+}
+
+impl WindowOptions {
+    /// Override the PTY configuration with the window's options.
+    pub fn override_pty_config<T>(&self, config: &mut TermConfig<T>) {
+        if let Some(working_directory) = &self.working_directory {
+            if working_directory.is_dir() {
+                config.working_directory = Some(working_directory.to_owned());
+            } else {
+                error!("Invalid working directory: {:?}", working_directory);
+            }
+        }
+
+        if let Some((program, args)) = self.command.split_first() {
+            let program = program.to_owned();
+            config.shell = Some(Program::WithArgs { program, args: args.to_vec() });
+        }
+
+        config.hold = self.hold;
+    }
+}
+
+/// Configuration changes received through IPC.
+#[cfg(unix)]
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq, Eq)]
+pub struct IpcConfig {
+    /// Config options in the format of `parent.field=value`.
+    pub options: Vec<String>,
+
+    /// Clear all previous runtime configuration changes.
+    pub reset: bool,
+}
+
+/// Build the `msg` subcommand for talking to a running instance.
+#[cfg(unix)]
+fn message_subcommand() -> App<'static, 'static> {
+    SubCommand::with_name("msg")
+        .about("Send a message to the Alacritty socket")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .arg(
+            Arg::with_name("socket")
+                .long("socket")
+                .short("s")
+                .takes_value(true)
+                .help("IPC socket connection path override"),
+        )
+        .subcommand(
+            SubCommand::with_name("create-window")
+                .about("Create a new terminal window")
+                .arg(
+                    Arg::with_name("working-directory")
+                        .long("working-directory")
+                        .takes_value(true)
+                        .help("Start the shell in the specified working directory"),
+                )
+                .arg(
+                    Arg::with_name("hold")
+                        .long("hold")
+                        .help("Remain open after child process exits"),
+                )
+                .arg(
+                    Arg::with_name("command")
+                        .long("command")
+                        .short("e")
+                        .multiple(true)
+                        .takes_value(true)
+                        .allow_hyphen_values(true)
+                        .help("Command and args to execute (must be last argument)"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("config")
+                .about("Update the Alacritty configuration")
+                .arg(
+                    Arg::with_name("options")
+                        .multiple(true)
+                        .required_unless("reset")
+                        .help("Configuration file options [example: cursor.style=Beam]"),
+                )
+                .arg(
+                    Arg::with_name("reset")
+                        .long("reset")
+                        .short("r")
+                        .help("Clear all runtime configuration changes"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("action").about("Execute a key binding action").arg(
+                Arg::with_name("action")
+                    .required(true)
+                    .help("Name of the action [example: IncreaseFontSize]"),
+            ),
+        )
+}
+
+/// Convert the matches of the `msg` subcommand to the message which should be sent.
+#[cfg(unix)]
+fn message_options(matches: &ArgMatches<'_>) -> Option<MessageOptions> {
+    let message = match matches.subcommand() {
+        ("create-window", Some(matches)) => SocketMessage::CreateWindow(WindowOptions {
+            working_directory: matches.value_of("working-directory").map(PathBuf::from),
+            command: matches.values_of("command").into_iter().flatten().map(String::from).collect(),
+            hold: matches.is_present("hold"),
+            record: None,
+            play: None,
+        }),
+        ("config", Some(matches)) => SocketMessage::Config(IpcConfig {
+            options: matches.values_of("options").into_iter().flatten().map(String::from).collect(),
+            reset: matches.is_present("reset"),
+        }),
+        ("action", Some(matches)) => SocketMessage::Action(matches.value_of("action")?.to_owned()),
+        _ => return None,
+    };
+
+    let socket = matches.value_of("socket").map(PathBuf::from);
+
+    Some(MessageOptions { socket, message })
+}
+
 /// Format an option in the format of `parent.field=value` to a serde Value.
 fn option_as_value(option: &str) -> Result<Value, serde_yaml::Error> {
     let mut yaml_text = String::with_capacity(option.len());
@@ -341,6 +635,45 @@ mod tests {
         assert!(value.is_err());
     }
 
+    #[test]
+    #[cfg(unix)]
+    fn ipc_config_overrides() {
+        let mut options =
+            Options { config_options: option_as_value("a=1").unwrap(), ..Options::default() };
+
+        let ipc_config = IpcConfig { options: vec![String::from("b=2")], reset: false };
+        options.update_ipc_config(&ipc_config);
+
+        let mut expected = Mapping::new();
+        expected.insert(Value::String(String::from("a")), Value::Number(1.into()));
+        expected.insert(Value::String(String::from("b")), Value::Number(2.into()));
+        assert_eq!(options.config_options(), Value::Mapping(expected));
+
+        let ipc_config = IpcConfig { options: Vec::new(), reset: true };
+        options.update_ipc_config(&ipc_config);
+
+        assert_eq!(options.config_options(), option_as_value("a=1").unwrap());
+    }
+
+    #[test]
+    fn window_options_override_pty_config() {
+        let options = WindowOptions {
+            working_directory: None,
+            command: vec![String::from("sh"), String::from("-c"), String::from("true")],
+            hold: true,
+            record: None,
+            play: None,
+        };
+
+        let mut config = TermConfig::<()>::default();
+        options.override_pty_config(&mut config);
+
+        let shell = config.shell.unwrap();
+        assert_eq!(shell.program(), "sh");
+        assert_eq!(shell.args(), &[String::from("-c"), String::from("true")]);
+        assert!(config.hold);
+    }
+
     #[test]
     fn float_option_as_value() {
         let value = option_as_value("float=3.4").unwrap();
@@ -16,6 +16,8 @@ use alacritty_terminal::config::Program;
 use alacritty_terminal::term::TermMode;
 use alacritty_terminal::vi_mode::ViMotion;
 
+use crate::config::ui_config::Hint;
+
 /// Describes a state and action to take in that state.
 ///
 /// This is the shared component of `MouseBinding` and `KeyBinding`.
@@ -91,6 +93,10 @@ pub enum Action {
     #[config(skip)]
     Command(Program),
 
+    /// Regex keyboard hints.
+    #[config(skip)]
+    Hint(Hint),
+
     /// Move vi mode cursor.
     #[config(skip)]
     ViMotion(ViMotion),
@@ -109,6 +115,12 @@ pub enum Action {
     /// Store current selection into clipboard.
     Copy,
 
+    /// Store current selection into clipboard as HTML with inline styles.
+    CopyAsHtml,
+
+    /// Store current selection into clipboard as text with ANSI escape sequences.
+    CopyAsAnsi,
+
     #[cfg(not(any(target_os = "macos", windows)))]
     /// Store current selection into selection buffer.
     CopySelection,
@@ -149,9 +161,30 @@ pub enum Action {
     /// Scroll all the way to the bottom.
     ScrollToBottom,
 
+    /// Scroll to the previous shell prompt.
+    ScrollToPreviousPrompt,
+
+    /// Scroll to the next shell prompt.
+    ScrollToNextPrompt,
+
+    /// Select the output of the command at the vi mode cursor or the last command.
+    SelectCommandOutput,
+
+    /// Store the output of the last command into clipboard.
+    CopyLastCommandOutput,
+
     /// Clear the display buffer(s) to remove history.
     ClearHistory,
 
+    /// Write the entire scrollback buffer to a text file.
+    SaveBufferAsText,
+
+    /// Write the entire scrollback buffer to a file with ANSI escape sequences.
+    SaveBufferAsAnsi,
+
+    /// Write the entire scrollback buffer to an HTML file.
+    SaveBufferAsHtml,
+
     /// Hide the Alacritty window.
     Hide,
 
@@ -167,6 +200,12 @@ pub enum Action {
     /// Spawn a new instance of Alacritty.
     SpawnNewInstance,
 
+    /// Create a new Alacritty window.
+    CreateNewWindow,
+
+    /// Start or stop recording the session to an asciicast file.
+    ToggleRecording,
+
     /// Toggle fullscreen.
     ToggleFullscreen,
 
@@ -177,6 +216,36 @@ pub enum Action {
     /// Clear active selection.
     ClearSelection,
 
+    /// Extend the selection one cell to the left.
+    SelectLeft,
+
+    /// Extend the selection one cell to the right.
+    SelectRight,
+
+    /// Extend the selection one line up.
+    SelectUp,
+
+    /// Extend the selection one line down.
+    SelectDown,
+
+    /// Extend the selection to the start of the previous semantic block.
+    SelectSemanticLeft,
+
+    /// Extend the selection to the end of the next semantic block.
+    SelectSemanticRight,
+
+    /// Extend the block selection one cell to the left.
+    SelectBlockLeft,
+
+    /// Extend the block selection one cell to the right.
+    SelectBlockRight,
+
+    /// Extend the block selection one line up.
+    SelectBlockUp,
+
+    /// Extend the block selection one line down.
+    SelectBlockDown,
+
     /// Toggle vi mode.
     ToggleViMode,
 
@@ -217,6 +286,24 @@ impl From<SearchAction> for Action {
     }
 }
 
+impl Action {
+    /// Look up an action by the name used for it in key bindings.
+    #[cfg(unix)]
+    pub fn from_name(name: &str) -> Option<Self> {
+        let value = SerdeValue::String(name.to_owned());
+
+        if let Ok(vi_action) = ViAction::deserialize(value.clone()) {
+            Some(vi_action.into())
+        } else if let Ok(vi_motion) = ViMotion::deserialize(value.clone()) {
+            Some(vi_motion.into())
+        } else if let Ok(search_action) = SearchAction::deserialize(value.clone()) {
+            Some(search_action.into())
+        } else {
+            Action::deserialize(value).ok()
+        }
+    }
+}
+
 /// Display trait used for error logging.
 impl Display for Action {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -239,6 +326,8 @@ pub enum ViAction {
     ToggleBlockSelection,
     /// Toggle semantic vi selection.
     ToggleSemanticSelection,
+    /// Copy the selection, or start a yank operator when nothing is selected.
+    Yank,
     /// Jump to the beginning of the next match.
     SearchNext,
     /// Jump to the beginning of the previous match.
@@ -249,6 +338,10 @@ pub enum ViAction {
     SearchEnd,
     /// Launch the URL below the vi mode cursor.
     Open,
+    /// Jump to the previous position in the jump list.
+    JumpOlder,
+    /// Jump to the next position in the jump list.
+    JumpNewer,
 }
 
 /// Search mode specific actions.
@@ -270,6 +363,16 @@ pub enum SearchAction {
     SearchHistoryPrevious,
     /// Go to the next regex in the search history.
     SearchHistoryNext,
+    /// Show or hide the list of all search matches.
+    SearchToggleResults,
+    /// Toggle matching the search text literally, instead of as a regex.
+    SearchToggleLiteral,
+    /// Toggle case sensitive search.
+    SearchToggleCaseSensitive,
+    /// Toggle case insensitive search.
+    SearchToggleCaseInsensitive,
+    /// Toggle matching only whole words.
+    SearchToggleWholeWord,
 }
 
 macro_rules! bindings {
@@ -363,6 +466,18 @@ pub fn default_key_bindings() -> Vec<KeyBinding> {
             ~BindingMode::VI, ~BindingMode::SEARCH; Action::Esc("\x1b[5;2~".into());
         PageDown, ModifiersState::SHIFT, +BindingMode::ALT_SCREEN,
             ~BindingMode::VI, ~BindingMode::SEARCH; Action::Esc("\x1b[6;2~".into());
+        Left,  ModifiersState::SHIFT, ~BindingMode::ALT_SCREEN, ~BindingMode::VI,
+            ~BindingMode::SEARCH; Action::SelectLeft;
+        Right, ModifiersState::SHIFT, ~BindingMode::ALT_SCREEN, ~BindingMode::VI,
+            ~BindingMode::SEARCH; Action::SelectRight;
+        Up,    ModifiersState::SHIFT, ~BindingMode::ALT_SCREEN, ~BindingMode::VI,
+            ~BindingMode::SEARCH; Action::SelectUp;
+        Down,  ModifiersState::SHIFT, ~BindingMode::ALT_SCREEN, ~BindingMode::VI,
+            ~BindingMode::SEARCH; Action::SelectDown;
+        Left,  ModifiersState::SHIFT | ModifiersState::CTRL, ~BindingMode::ALT_SCREEN,
+            ~BindingMode::VI, ~BindingMode::SEARCH; Action::SelectSemanticLeft;
+        Right, ModifiersState::SHIFT | ModifiersState::CTRL, ~BindingMode::ALT_SCREEN,
+            ~BindingMode::VI, ~BindingMode::SEARCH; Action::SelectSemanticRight;
         Home,  +BindingMode::APP_CURSOR, ~BindingMode::VI, ~BindingMode::SEARCH;
             Action::Esc("\x1bOH".into());
         Home,  ~BindingMode::APP_CURSOR, ~BindingMode::VI, ~BindingMode::SEARCH;
@@ -441,9 +556,8 @@ pub fn default_key_bindings() -> Vec<KeyBinding> {
             Action::ScrollHalfPageUp;
         D,      ModifiersState::CTRL,  +BindingMode::VI, ~BindingMode::SEARCH;
             Action::ScrollHalfPageDown;
-        Y,                             +BindingMode::VI, ~BindingMode::SEARCH; Action::Copy;
         Y,                             +BindingMode::VI, ~BindingMode::SEARCH;
-            Action::ClearSelection;
+            ViAction::Yank;
         Slash,                         +BindingMode::VI, ~BindingMode::SEARCH;
             Action::SearchForward;
         Slash,  ModifiersState::SHIFT, +BindingMode::VI, ~BindingMode::SEARCH;
@@ -462,6 +576,10 @@ pub fn default_key_bindings() -> Vec<KeyBinding> {
             ViAction::SearchPrevious;
         Return,                        +BindingMode::VI, ~BindingMode::SEARCH;
             ViAction::Open;
+        O,      ModifiersState::CTRL,  +BindingMode::VI, ~BindingMode::SEARCH;
+            ViAction::JumpOlder;
+        I,      ModifiersState::CTRL,  +BindingMode::VI, ~BindingMode::SEARCH;
+            ViAction::JumpNewer;
         K,                             +BindingMode::VI, ~BindingMode::SEARCH;
             ViMotion::Up;
         J,                             +BindingMode::VI, ~BindingMode::SEARCH;
@@ -504,6 +622,10 @@ pub fn default_key_bindings() -> Vec<KeyBinding> {
             ViMotion::WordRightEnd;
         Key5,   ModifiersState::SHIFT, +BindingMode::VI, ~BindingMode::SEARCH;
             ViMotion::Bracket;
+        LBracket,                      +BindingMode::VI, ~BindingMode::SEARCH;
+            ViMotion::PreviousPrompt;
+        RBracket,                      +BindingMode::VI, ~BindingMode::SEARCH;
+            ViMotion::NextPrompt;
         Return,                        +BindingMode::SEARCH, +BindingMode::VI;
             SearchAction::SearchConfirm;
         Escape,                        +BindingMode::SEARCH; SearchAction::SearchCancel;
@@ -514,6 +636,15 @@ pub fn default_key_bindings() -> Vec<KeyBinding> {
         N,      ModifiersState::CTRL,  +BindingMode::SEARCH; SearchAction::SearchHistoryNext;
         Up,                            +BindingMode::SEARCH; SearchAction::SearchHistoryPrevious;
         Down,                          +BindingMode::SEARCH; SearchAction::SearchHistoryNext;
+        R,      ModifiersState::CTRL,  +BindingMode::SEARCH; SearchAction::SearchToggleResults;
+        R,      ModifiersState::ALT,   +BindingMode::SEARCH; SearchAction::SearchToggleLiteral;
+        C,      ModifiersState::ALT,   +BindingMode::SEARCH;
+            SearchAction::SearchToggleCaseSensitive;
+        I,      ModifiersState::ALT,   +BindingMode::SEARCH;
+            SearchAction::SearchToggleCaseInsensitive;
+        W,      ModifiersState::ALT,   +BindingMode::SEARCH; SearchAction::SearchToggleWholeWord;
+        Tab,                           +BindingMode::SEARCH; SearchAction::SearchFocusNext;
+        Tab,    ModifiersState::SHIFT, +BindingMode::SEARCH; SearchAction::SearchFocusPrevious;
         Return,                        +BindingMode::SEARCH, ~BindingMode::VI;
             SearchAction::SearchFocusNext;
         Return, ModifiersState::SHIFT, +BindingMode::SEARCH, ~BindingMode::VI;
@@ -544,17 +675,29 @@ pub fn default_key_bindings() -> Vec<KeyBinding> {
 
     for (index, mods) in modifiers.drain(..).enumerate() {
         let modifiers_code = index + 2;
+
+        // Arrow keys used for keyboard selection only send escapes in the alternate screen.
+        let arrow_mode = |selection: bool| {
+            if selection {
+                BindingMode::ALT_SCREEN
+            } else {
+                BindingMode::empty()
+            }
+        };
+        let vertical_mode = arrow_mode(modifiers_code == 2);
+        let horizontal_mode = arrow_mode(matches!(modifiers_code, 2 | 6));
+
         bindings.extend(bindings!(
             KeyBinding;
             Delete, mods, ~BindingMode::VI, ~BindingMode::SEARCH;
                 Action::Esc(format!("\x1b[3;{}~", modifiers_code));
-            Up,     mods, ~BindingMode::VI, ~BindingMode::SEARCH;
+            Up,     mods, +vertical_mode, ~BindingMode::VI, ~BindingMode::SEARCH;
                 Action::Esc(format!("\x1b[1;{}A", modifiers_code));
-            Down,   mods, ~BindingMode::VI, ~BindingMode::SEARCH;
+            Down,   mods, +vertical_mode, ~BindingMode::VI, ~BindingMode::SEARCH;
                 Action::Esc(format!("\x1b[1;{}B", modifiers_code));
-            Right,  mods, ~BindingMode::VI, ~BindingMode::SEARCH;
+            Right,  mods, +horizontal_mode, ~BindingMode::VI, ~BindingMode::SEARCH;
                 Action::Esc(format!("\x1b[1;{}C", modifiers_code));
-            Left,   mods, ~BindingMode::VI, ~BindingMode::SEARCH;
+            Left,   mods, +horizontal_mode, ~BindingMode::VI, ~BindingMode::SEARCH;
                 Action::Esc(format!("\x1b[1;{}D", modifiers_code));
             F1,     mods, ~BindingMode::VI, ~BindingMode::SEARCH;
                 Action::Esc(format!("\x1b[1;{}P", modifiers_code));
@@ -674,7 +817,7 @@ pub fn platform_key_bindings() -> Vec<KeyBinding> {
             Action::Esc("\x0c".into());
         K, ModifiersState::LOGO, ~BindingMode::VI, ~BindingMode::SEARCH;  Action::ClearHistory;
         V, ModifiersState::LOGO, ~BindingMode::VI; Action::Paste;
-        N, ModifiersState::LOGO; Action::SpawnNewInstance;
+        N, ModifiersState::LOGO; Action::CreateNewWindow;
         F, ModifiersState::CTRL | ModifiersState::LOGO; Action::ToggleFullscreen;
         C, ModifiersState::LOGO; Action::Copy;
         C, ModifiersState::LOGO, +BindingMode::VI, ~BindingMode::SEARCH; Action::ClearSelection;
@@ -1135,6 +1278,10 @@ impl<'a> Deserialize<'a> for KeyBinding {
 pub struct ModsWrapper(ModifiersState);
 
 impl ModsWrapper {
+    pub fn new(mods: ModifiersState) -> Self {
+        Self(mods)
+    }
+
     pub fn into_inner(self) -> ModifiersState {
         self.0
     }
@@ -1383,4 +1530,76 @@ mod tests {
         assert!(!binding.is_triggered_by(BindingMode::ALT_SCREEN, mods, &t));
         assert!(!binding.is_triggered_by(BindingMode::ALT_SCREEN | BindingMode::VI, mods, &t));
     }
+
+    #[test]
+    #[cfg(unix)]
+    fn action_from_name() {
+        assert_eq!(Action::from_name("Paste"), Some(Action::Paste));
+        assert_eq!(Action::from_name("ToggleViMode"), Some(Action::ToggleViMode));
+        assert_eq!(
+            Action::from_name("ToggleNormalSelection"),
+            Some(Action::ViAction(ViAction::ToggleNormalSelection))
+        );
+        assert_eq!(Action::from_name("Up"), Some(Action::ViMotion(ViMotion::Up)));
+        assert_eq!(Action::from_name("Invalid"), None);
+    }
+
+    #[test]
+    fn default_selection_bindings() {
+        let bindings = default_key_bindings();
+        let actions = |mode: BindingMode, mods: ModifiersState, key: VirtualKeyCode| {
+            bindings
+                .iter()
+                .filter(|binding| binding.is_triggered_by(mode, mods, &Key::Keycode(key)))
+                .map(|binding| binding.action.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let shift_ctrl = ModifiersState::SHIFT | ModifiersState::CTRL;
+        let shift_alt = ModifiersState::SHIFT | ModifiersState::ALT;
+        assert_eq!(
+            actions(BindingMode::empty(), ModifiersState::SHIFT, VirtualKeyCode::Left),
+            vec![Action::SelectLeft]
+        );
+        assert_eq!(
+            actions(BindingMode::empty(), shift_ctrl, VirtualKeyCode::Right),
+            vec![Action::SelectSemanticRight]
+        );
+        assert_eq!(
+            actions(BindingMode::ALT_SCREEN, ModifiersState::SHIFT, VirtualKeyCode::Up),
+            vec![Action::Esc("\x1b[1;2A".into())]
+        );
+        assert_eq!(
+            actions(BindingMode::empty(), shift_ctrl, VirtualKeyCode::Up),
+            vec![Action::Esc("\x1b[1;6A".into())]
+        );
+        assert_eq!(
+            actions(BindingMode::empty(), shift_alt, VirtualKeyCode::Left),
+            vec![Action::Esc("\x1b[1;4D".into())]
+        );
+    }
+
+    #[test]
+    fn default_search_toggle_bindings() {
+        let bindings = default_key_bindings();
+        let actions = |mode: BindingMode, key: VirtualKeyCode| {
+            bindings
+                .iter()
+                .filter(|binding| {
+                    binding.is_triggered_by(mode, ModifiersState::ALT, &Key::Keycode(key))
+                })
+                .map(|binding| binding.action.clone())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            actions(BindingMode::SEARCH, VirtualKeyCode::R),
+            vec![SearchAction::SearchToggleLiteral.into()]
+        );
+        assert_eq!(
+            actions(BindingMode::SEARCH, VirtualKeyCode::I),
+            vec![SearchAction::SearchToggleCaseInsensitive.into()]
+        );
+        assert!(actions(BindingMode::empty(), VirtualKeyCode::W).is_empty());
+    }
 }
@@ -1,6 +1,7 @@
 use std::fmt;
 
 use crossfont::Size as FontSize;
+use rustybuzz::Feature;
 use serde::de::{self, Visitor};
 use serde::{Deserialize, Deserializer};
 
@@ -14,7 +15,7 @@ use crate::config::ui_config::Delta;
 /// field in this struct. It might be nice in the future to have defaults for
 /// each value independently. Alternatively, maybe erroring when the user
 /// doesn't provide complete config is Ok.
-#[derive(ConfigDeserialize, Default, Debug, Clone, PartialEq, Eq)]
+#[derive(ConfigDeserialize, Debug, Clone, PartialEq, Eq)]
 pub struct Font {
     /// Extra spacing per character.
     pub offset: Delta<i8>,
@@ -38,6 +39,29 @@ pub struct Font {
 
     /// Font size in points.
     size: Size,
+
+    /// OpenType features used for text shaping.
+    pub features: Vec<FontFeature>,
+
+    /// Draw box drawing, block element and Powerline characters without using the font.
+    pub builtin_box_drawing: bool,
+}
+
+impl Default for Font {
+    fn default() -> Font {
+        Font {
+            builtin_box_drawing: true,
+            offset: Default::default(),
+            glyph_offset: Default::default(),
+            use_thin_strokes: Default::default(),
+            normal: Default::default(),
+            bold: Default::default(),
+            italic: Default::default(),
+            bold_italic: Default::default(),
+            size: Default::default(),
+            features: Default::default(),
+        }
+    }
 }
 
 impl Font {
@@ -109,6 +133,37 @@ impl SecondaryFontDescription {
     }
 }
 
+/// OpenType feature, like `liga` or `-calt`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FontFeature(Feature);
+
+// Features only consist of integers, so their equality is total.
+impl Eq for FontFeature {}
+
+impl FontFeature {
+    #[inline]
+    pub fn feature(self) -> Feature {
+        self.0
+    }
+}
+
+impl<'de> Deserialize<'de> for FontFeature {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+
+        // Features always apply to the entire text, so ranges are rejected.
+        match value.parse::<Feature>() {
+            Ok(feature) if feature.start == 0 && feature.end == u32::max_value() => {
+                Ok(FontFeature(feature))
+            },
+            _ => Err(de::Error::custom(format!("invalid OpenType feature: {:?}", value))),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct Size(FontSize);
 
@@ -14,6 +14,7 @@ pub mod color;
 pub mod debug;
 pub mod font;
 pub mod monitor;
+pub mod recording;
 pub mod serde_utils;
 pub mod ui_config;
 pub mod window;
@@ -99,7 +100,7 @@ impl From<serde_yaml::Error> for Error {
 
 /// Load the configuration file.
 pub fn load(options: &Options) -> Config {
-    let config_options = options.config_options().clone();
+    let config_options = options.config_options();
     let config_path = options.config_path().or_else(installed_config);
 
     // Load the config using the following fallback behavior:
@@ -121,18 +122,24 @@ pub fn load(options: &Options) -> Config {
     // Override config with CLI options.
     options.override_config(&mut config);
 
+    // Create key bindings for regex hints.
+    config.ui_config.generate_hint_bindings();
+
     config
 }
 
 /// Attempt to reload the configuration file.
 pub fn reload(config_path: &Path, options: &Options) -> Result<Config> {
     // Load config, propagating errors.
-    let config_options = options.config_options().clone();
+    let config_options = options.config_options();
     let mut config = load_from(config_path, config_options)?;
 
     // Override config with CLI options.
     options.override_config(&mut config);
 
+    // Create key bindings for regex hints.
+    config.ui_config.generate_hint_bindings();
+
     Ok(config)
 }
 
@@ -6,16 +6,18 @@ use std::time::Duration;
 use log::{debug, error};
 use notify::{watcher, DebouncedEvent, RecursiveMode, Watcher};
 
+use glutin::event_loop::EventLoopProxy;
+
 use alacritty_terminal::thread;
 
-use crate::event::{Event, EventProxy};
+use crate::event::{Event, EventType};
 
 #[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
 const DEBOUNCE_DELAY: Duration = Duration::from_millis(10);
 #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
 const DEBOUNCE_DELAY: Duration = Duration::from_millis(1000);
 
-pub fn watch(mut paths: Vec<PathBuf>, event_proxy: EventProxy) {
+pub fn watch(mut paths: Vec<PathBuf>, event_proxy: EventLoopProxy<Event>) {
     // Canonicalize all paths, filtering out the ones that do not exist.
     paths = paths
         .drain(..)
@@ -82,7 +84,8 @@ pub fn watch(mut paths: Vec<PathBuf>, event_proxy: EventProxy) {
                     }
 
                     // Always reload the primary configuration file.
-                    event_proxy.send_event(Event::ConfigReload(paths[0].clone()));
+                    let event = Event::new(EventType::ConfigReload(paths[0].clone()), None);
+                    let _ = event_proxy.send_event(event);
                 },
                 _ => {},
             }
@@ -0,0 +1,28 @@
+use std::env;
+use std::path::PathBuf;
+
+use alacritty_config_derive::ConfigDeserialize;
+
+/// Session recording configuration.
+#[derive(ConfigDeserialize, Default, Clone, Debug, PartialEq, Eq)]
+pub struct Recording {
+    /// Record user input, including passwords, in addition to the terminal output.
+    pub input: bool,
+
+    /// Directory for recordings started with the `ToggleRecording` action.
+    directory: Option<PathBuf>,
+}
+
+impl Recording {
+    /// Directory for recordings started with the `ToggleRecording` action.
+    ///
+    /// Defaults to `alacritty/recordings` in the user's data directory.
+    pub fn directory(&self) -> PathBuf {
+        match &self.directory {
+            Some(directory) => directory.clone(),
+            None => dirs::data_dir()
+                .map(|dir| dir.join("alacritty").join("recordings"))
+                .unwrap_or_else(env::temp_dir),
+        }
+    }
+}
@@ -1,17 +1,23 @@
 use std::path::PathBuf;
 
+use glutin::event::{ModifiersState, VirtualKeyCode};
 use log::error;
+use serde::de::Error as SerdeError;
 use serde::{Deserialize, Deserializer};
+use unicode_width::UnicodeWidthChar;
 
 use alacritty_config_derive::ConfigDeserialize;
-use alacritty_terminal::config::{Percentage, LOG_TARGET_CONFIG};
+use alacritty_terminal::config::{Percentage, Program, LOG_TARGET_CONFIG};
 
 use crate::config::bell::BellConfig;
-use crate::config::bindings::{self, Binding, KeyBinding, MouseBinding};
+use crate::config::bindings::{
+    self, Action, Binding, BindingMode, Key, KeyBinding, ModsWrapper, MouseBinding,
+};
 use crate::config::color::Colors;
 use crate::config::debug::Debug;
 use crate::config::font::Font;
 use crate::config::mouse::Mouse;
+use crate::config::recording::Recording;
 use crate::config::window::WindowConfig;
 
 #[derive(ConfigDeserialize, Debug, PartialEq)]
@@ -33,6 +39,9 @@ pub struct UIConfig {
     /// Live config reload.
     pub live_config_reload: bool,
 
+    /// Offer IPC through a unix socket.
+    pub ipc_socket: bool,
+
     /// Bell configuration.
     pub bell: BellConfig,
 
@@ -42,6 +51,12 @@ pub struct UIConfig {
     /// Should draw bold text with brighter colors instead of bold font.
     pub draw_bold_text_with_bright_colors: bool,
 
+    /// Regex hints for interacting with terminal content.
+    pub hints: Hints,
+
+    /// Session recording configuration.
+    pub recording: Recording,
+
     /// Path where config was loaded from.
     #[config(skip)]
     pub config_paths: Vec<PathBuf>,
@@ -61,6 +76,7 @@ impl Default for UIConfig {
         Self {
             alt_send_esc: true,
             live_config_reload: true,
+            ipc_socket: true,
             font: Default::default(),
             window: Default::default(),
             mouse: Default::default(),
@@ -72,11 +88,28 @@ impl Default for UIConfig {
             bell: Default::default(),
             colors: Default::default(),
             draw_bold_text_with_bright_colors: Default::default(),
+            hints: Default::default(),
+            recording: Default::default(),
         }
     }
 }
 
 impl UIConfig {
+    /// Generate key bindings for all keyboard hints.
+    pub fn generate_hint_bindings(&mut self) {
+        for hint in &self.hints.enabled {
+            let binding = KeyBinding {
+                trigger: hint.binding.key,
+                mods: hint.binding.mods.into_inner(),
+                mode: BindingMode::empty(),
+                notmode: BindingMode::empty(),
+                action: Action::Hint(hint.clone()),
+            };
+
+            self.key_bindings.0.push(binding);
+        }
+    }
+
     #[inline]
     pub fn background_opacity(&self) -> f32 {
         self.background_opacity.as_f32()
@@ -161,6 +194,126 @@ where
     Ok(bindings)
 }
 
+/// Regex terminal hints.
+#[derive(ConfigDeserialize, Debug, PartialEq, Eq)]
+pub struct Hints {
+    /// Characters for the hint labels.
+    alphabet: HintsAlphabet,
+
+    /// All configured terminal hints.
+    pub enabled: Vec<Hint>,
+}
+
+impl Default for Hints {
+    fn default() -> Self {
+        // Add URL hint by default.
+        #[cfg(not(any(target_os = "macos", windows)))]
+        let program = Program::Just(String::from("xdg-open"));
+        #[cfg(target_os = "macos")]
+        let program = Program::Just(String::from("open"));
+        #[cfg(windows)]
+        let program = Program::Just(String::from("explorer"));
+
+        let url_hint = Hint {
+            regex: String::from(URL_REGEX),
+            action: HintAction::Command(program),
+            binding: HintBinding {
+                key: Key::Keycode(VirtualKeyCode::U),
+                mods: ModsWrapper::new(ModifiersState::SHIFT | ModifiersState::CTRL),
+            },
+        };
+
+        Self { alphabet: Default::default(), enabled: vec![url_hint] }
+    }
+}
+
+impl Hints {
+    /// Characters for the hint labels.
+    pub fn alphabet(&self) -> &str {
+        &self.alphabet.0
+    }
+}
+
+/// Regex matching URLs for the default hint.
+const URL_REGEX: &str = "(mailto:|gemini:|gopher:|https:|http:|news:|file:|git:|ssh:|ftp:)\
+                         [^\u{0000}-\u{001F}\u{007F}-\u{009F}<>\"\\s{-}\\^⟨⟩`]+";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct HintsAlphabet(String);
+
+impl Default for HintsAlphabet {
+    fn default() -> Self {
+        Self(String::from("jfkdls;ahgurieowpq"))
+    }
+}
+
+impl<'de> Deserialize<'de> for HintsAlphabet {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+
+        let mut character_count = 0;
+        for character in value.chars() {
+            if character.width() != Some(1) {
+                return Err(D::Error::custom("characters must be of width 1"));
+            }
+            character_count += 1;
+        }
+
+        if character_count < 2 {
+            return Err(D::Error::custom("must include at least 2 characters"));
+        }
+
+        Ok(Self(value))
+    }
+}
+
+/// Built-in actions for hint mode.
+#[derive(ConfigDeserialize, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HintInternalAction {
+    /// Copy the text to the clipboard.
+    Copy,
+
+    /// Write the text to the PTY/search.
+    Paste,
+}
+
+/// Actions for hint bindings.
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum HintAction {
+    /// Built-in hint action.
+    #[serde(rename = "action")]
+    Action(HintInternalAction),
+
+    /// Command the text will be piped to.
+    #[serde(rename = "command")]
+    Command(Program),
+}
+
+/// Hint configuration.
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Hint {
+    /// Regex for finding matches.
+    pub regex: String,
+
+    /// Action executed when this hint is triggered.
+    #[serde(flatten)]
+    pub action: HintAction,
+
+    /// Binding required to search for this hint.
+    binding: HintBinding,
+}
+
+/// Binding for triggering a keyboard hint.
+#[derive(Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct HintBinding {
+    pub key: Key,
+    #[serde(default)]
+    pub mods: ModsWrapper,
+}
+
 /// A delta for a point in a 2 dimensional plane.
 #[derive(ConfigDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub struct Delta<T: Default> {
@@ -1,17 +1,30 @@
+#[cfg(not(windows))]
+use std::error::Error;
 use std::ffi::OsStr;
 use std::fmt::Debug;
+#[cfg(not(any(target_os = "macos", windows)))]
+use std::fs;
 use std::io;
 #[cfg(not(windows))]
+use std::os::unix::io::RawFd;
+#[cfg(not(windows))]
 use std::os::unix::process::CommandExt;
 #[cfg(windows)]
 use std::os::windows::process::CommandExt;
+#[cfg(not(windows))]
+use std::path::PathBuf;
 use std::process::{Command, Stdio};
 
+#[cfg(not(windows))]
+use libc::pid_t;
 use log::{debug, warn};
 
 #[cfg(windows)]
 use winapi::um::winbase::{CREATE_NEW_PROCESS_GROUP, CREATE_NO_WINDOW};
 
+#[cfg(target_os = "macos")]
+use crate::macos;
+
 /// Start the daemon and log error on failure.
 pub fn start_daemon<I, S>(program: &str, args: I)
 where
@@ -74,3 +87,30 @@ where
             .map(|_| ())
     }
 }
+
+/// Get the working directory of the PTY's foreground process.
+///
+/// Falls back to the shell's working directory if there is no foreground process.
+#[cfg(not(windows))]
+pub fn foreground_process_path(
+    master_fd: RawFd,
+    shell_pid: u32,
+) -> Result<PathBuf, Box<dyn Error>> {
+    let mut pid = unsafe { libc::tcgetpgrp(master_fd) };
+    if pid < 0 {
+        pid = shell_pid as pid_t;
+    }
+
+    #[cfg(not(any(target_os = "macos", target_os = "freebsd")))]
+    let link_path = format!("/proc/{}/cwd", pid);
+    #[cfg(target_os = "freebsd")]
+    let link_path = format!("/compat/linux/proc/{}/cwd", pid);
+
+    #[cfg(not(target_os = "macos"))]
+    let cwd = fs::read_link(link_path)?;
+
+    #[cfg(target_os = "macos")]
+    let cwd = macos::proc::cwd(pid)?;
+
+    Ok(cwd)
+}
@@ -27,6 +27,12 @@ impl<'a> From<&'a Colors> for List {
 }
 
 impl List {
+    /// Colors of all indices.
+    #[inline]
+    pub fn as_array(&self) -> &[Rgb; COUNT] {
+        &self.0
+    }
+
     pub fn fill_named(&mut self, colors: &Colors) {
         // Normals.
         self[NamedColor::Black] = colors.normal.black;
@@ -1,28 +1,28 @@
-use std::cmp::max;
 use std::mem;
 use std::ops::RangeInclusive;
 
 use alacritty_terminal::ansi::{Color, CursorShape, NamedColor};
 use alacritty_terminal::config::Config;
 use alacritty_terminal::event::EventListener;
-use alacritty_terminal::grid::{Dimensions, Indexed};
-use alacritty_terminal::index::{Column, Direction, Line, Point};
-use alacritty_terminal::term::cell::{Cell, Flags};
+use alacritty_terminal::graphics::GraphicCell;
+use alacritty_terminal::grid::Indexed;
+use alacritty_terminal::index::{Line, Point};
+use alacritty_terminal::term::cell::{Cell, Flags, Hyperlink};
 use alacritty_terminal::term::color::{CellRgb, Rgb};
-use alacritty_terminal::term::search::{RegexIter, RegexSearch};
+use alacritty_terminal::term::search::RegexSearch;
 use alacritty_terminal::term::{
     RenderableContent as TerminalContent, RenderableCursor as TerminalCursor, Term, TermMode,
 };
 
 use crate::config::ui_config::UIConfig;
 use crate::display::color::{List, DIM_FACTOR};
+use crate::display::damage::DamageTracker;
+use crate::display::hint::{visible_regex_match_iter, HintState};
+use crate::renderer::shaping::ShapedGlyph;
 
 /// Minimum contrast between a fixed cursor color and the cell's background.
 pub const MIN_CURSOR_CONTRAST: f64 = 1.5;
 
-/// Maximum number of linewraps followed outside of the viewport during search highlighting.
-const MAX_SEARCH_LINES: usize = 100;
-
 /// Renderable terminal content.
 ///
 /// This provides the terminal cursor and an iterator over all non-empty cells.
@@ -31,19 +31,23 @@ pub struct RenderableContent<'a> {
     terminal_cursor: TerminalCursor,
     cursor: Option<RenderableCursor>,
     search: RenderableSearch,
+    hint: RenderableHint<'a>,
     config: &'a Config<UIConfig>,
     colors: &'a List,
+    damage: Option<&'a DamageTracker>,
 }
 
 impl<'a> RenderableContent<'a> {
     pub fn new<T: EventListener>(
         term: &'a Term<T>,
         dfas: Option<&RegexSearch>,
+        hint: &'a HintState,
         config: &'a Config<UIConfig>,
         colors: &'a List,
         show_cursor: bool,
     ) -> Self {
         let search = dfas.map(|dfas| RenderableSearch::new(&term, dfas)).unwrap_or_default();
+        let hint = RenderableHint::new(&term, hint);
         let terminal_content = term.renderable_content();
 
         // Copy the cursor and override its shape if necessary.
@@ -54,7 +58,26 @@ impl<'a> RenderableContent<'a> {
             terminal_cursor.shape = CursorShape::HollowBlock;
         }
 
-        Self { cursor: None, terminal_content, terminal_cursor, search, config, colors }
+        Self {
+            cursor: None,
+            terminal_content,
+            terminal_cursor,
+            search,
+            hint,
+            config,
+            colors,
+            damage: None,
+        }
+    }
+
+    /// Skip all cells in lines which did not change since the last frame.
+    ///
+    /// The cursor's line is always damaged, so the cursor is still available.
+    pub fn with_damage(mut self, damage: &'a DamageTracker) -> Self {
+        if !damage.is_fully_damaged() {
+            self.damage = Some(damage);
+        }
+        self
     }
 
     /// Viewport offset.
@@ -135,6 +158,10 @@ impl<'a> Iterator for RenderableContent<'a> {
     fn next(&mut self) -> Option<Self::Item> {
         loop {
             let cell = self.terminal_content.display_iter.next()?;
+            if self.damage.map_or(false, |damage| !damage.is_damaged(cell.point.line)) {
+                continue;
+            }
+
             let mut cell = RenderableCell::new(self, cell);
 
             if self.terminal_cursor.point == cell.point {
@@ -169,9 +196,13 @@ pub struct RenderableCell {
     pub point: Point,
     pub fg: Rgb,
     pub bg: Rgb,
+    pub underline: Rgb,
     pub bg_alpha: f32,
     pub flags: Flags,
     pub is_match: bool,
+    pub hyperlink: Option<Hyperlink>,
+    pub graphic: Option<GraphicCell>,
+    pub shaped: ShapedGlyph,
 }
 
 impl RenderableCell {
@@ -179,6 +210,8 @@ impl RenderableCell {
         // Lookup RGB values.
         let mut fg_rgb = Self::compute_fg_rgb(content, cell.fg, cell.flags);
         let mut bg_rgb = Self::compute_bg_rgb(content, cell.bg);
+        let mut underline_rgb =
+            cell.underline_color().map(|color| Self::compute_bg_rgb(content, color));
 
         let mut bg_alpha = if cell.flags.contains(Flags::INVERSE) {
             mem::swap(&mut fg_rgb, &mut bg_rgb);
@@ -192,9 +225,29 @@ impl RenderableCell {
             .selection
             .map_or(false, |selection| selection.contains_cell(&cell, content.terminal_cursor));
         let mut is_match = false;
+        let mut character = cell.c;
+        let mut zerowidth = cell.zerowidth().map(|zerowidth| zerowidth.to_vec());
 
         let colors = &content.config.ui_config.colors;
-        if is_selected {
+        if let Some((label_character, is_first)) = content.hint.advance(cell.point) {
+            // Replace the cell's content with the hint label.
+            let (config_fg, config_bg) = if is_first {
+                (colors.hints.start.foreground, colors.hints.start.background)
+            } else {
+                (colors.hints.end.foreground, colors.hints.end.background)
+            };
+            let hint_fg = config_fg.color(fg_rgb, bg_rgb);
+            bg_rgb = config_bg.color(fg_rgb, bg_rgb);
+            fg_rgb = hint_fg;
+
+            if config_bg != CellRgb::CellBackground {
+                bg_alpha = 1.0;
+            }
+
+            character = label_character;
+            zerowidth = None;
+            underline_rgb = None;
+        } else if is_selected {
             let config_bg = colors.selection.background;
             let selected_fg = colors.selection.foreground.color(fg_rgb, bg_rgb);
             bg_rgb = config_bg.color(fg_rgb, bg_rgb);
@@ -208,6 +261,8 @@ impl RenderableCell {
             } else if config_bg != CellRgb::CellBackground {
                 bg_alpha = 1.0;
             }
+
+            underline_rgb = None;
         } else if content.search.advance(cell.point) {
             // Highlight the cell if it is part of a search match.
             let config_bg = colors.search.matches.background;
@@ -220,26 +275,33 @@ impl RenderableCell {
             }
 
             is_match = true;
+            underline_rgb = None;
         }
 
         RenderableCell {
-            character: cell.c,
-            zerowidth: cell.zerowidth().map(|zerowidth| zerowidth.to_vec()),
+            character,
+            zerowidth,
             point: cell.point,
             fg: fg_rgb,
             bg: bg_rgb,
+            underline: underline_rgb.unwrap_or(fg_rgb),
             bg_alpha,
             flags: cell.flags,
             is_match,
+            hyperlink: cell.hyperlink(),
+            graphic: cell.graphic().cloned(),
+            shaped: ShapedGlyph::Character,
         }
     }
 
     /// Check if cell contains any renderable content.
     fn is_empty(&self) -> bool {
         self.bg_alpha == 0.
-            && !self.flags.intersects(Flags::UNDERLINE | Flags::STRIKEOUT | Flags::DOUBLE_UNDERLINE)
+            && !self.flags.intersects(Flags::ALL_UNDERLINES | Flags::STRIKEOUT)
             && self.character == ' '
             && self.zerowidth.is_none()
+            && self.hyperlink.is_none()
+            && self.graphic.is_none()
     }
 
     /// Get the RGB color from a cell's foreground color.
@@ -352,38 +414,15 @@ pub struct RenderableSearch {
 impl RenderableSearch {
     /// Create a new renderable search iterator.
     pub fn new<T>(term: &Term<T>, dfas: &RegexSearch) -> Self {
-        let viewport_end = term.grid().display_offset();
-        let viewport_start = viewport_end + term.screen_lines().0 - 1;
-
-        // Compute start of the first and end of the last line.
-        let start_point = Point::new(viewport_start, Column(0));
-        let mut start = term.line_search_left(start_point);
-        let end_point = Point::new(viewport_end, term.cols() - 1);
-        let mut end = term.line_search_right(end_point);
-
-        // Set upper bound on search before/after the viewport to prevent excessive blocking.
-        if start.line > viewport_start + MAX_SEARCH_LINES {
-            if start.line == 0 {
-                // Do not highlight anything if this line is the last.
-                return Self::default();
-            } else {
-                // Start at next line if this one is too long.
-                start.line -= 1;
-            }
-        }
-        end.line = max(end.line, viewport_end.saturating_sub(MAX_SEARCH_LINES));
-
-        // Create an iterater for the current regex search for all visible matches.
-        let iter = RegexIter::new(start, end, Direction::Right, term, dfas)
-            .skip_while(move |rm| rm.end().line > viewport_start)
-            .take_while(move |rm| rm.start().line >= viewport_end)
+        let matches = visible_regex_match_iter(term, dfas)
             .map(|rm| {
                 let viewport_start = term.grid().clamp_buffer_to_visible(*rm.start());
                 let viewport_end = term.grid().clamp_buffer_to_visible(*rm.end());
                 viewport_start..=viewport_end
-            });
+            })
+            .collect();
 
-        Self { matches: iter.collect(), index: 0 }
+        Self { matches, index: 0 }
     }
 
     /// Advance the search tracker to the next point.
@@ -402,3 +441,57 @@ impl RenderableSearch {
         false
     }
 }
+
+/// Hint label highlight tracking.
+struct RenderableHint<'a> {
+    /// All visible hint matches.
+    matches: Vec<RangeInclusive<Point>>,
+
+    /// Key labels of the visible matches.
+    labels: &'a [Vec<char>],
+
+    /// Index of the last match checked.
+    index: usize,
+}
+
+impl<'a> RenderableHint<'a> {
+    /// Create a new renderable hint tracker.
+    fn new<T>(term: &Term<T>, hint: &'a HintState) -> Self {
+        let matches = hint
+            .matches()
+            .iter()
+            .map(|bounds| {
+                let start = term.grid().clamp_buffer_to_visible(*bounds.start());
+                let end = term.grid().clamp_buffer_to_visible(*bounds.end());
+                start..=end
+            })
+            .collect();
+
+        Self { matches, labels: hint.labels(), index: 0 }
+    }
+
+    /// Advance the hint tracker to the next point.
+    ///
+    /// This will return the label character for the point and whether it is the first character
+    /// of the label, if the point is part of a hint label.
+    fn advance(&mut self, point: Point) -> Option<(char, bool)> {
+        while let Some(bounds) = self.matches.get(self.index) {
+            if bounds.start() > &point {
+                break;
+            } else if bounds.end() < &point {
+                self.index += 1;
+            } else {
+                // Labels are only drawn at the start of the match.
+                let start = bounds.start();
+                if point.line != start.line {
+                    return None;
+                }
+
+                let label_position = point.column.0 - start.column.0;
+                let label = self.labels.get(self.index)?;
+                return label.get(label_position).map(|c| (*c, label_position == 0));
+            }
+        }
+        None
+    }
+}
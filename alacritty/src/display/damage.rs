@@ -0,0 +1,200 @@
+//! Tracking of the window regions which need to be redrawn.
+
+use std::mem;
+
+use glutin::Rect;
+
+use alacritty_terminal::event::EventListener;
+use alacritty_terminal::grid::Dimensions;
+use alacritty_terminal::index::{Column, Line};
+use alacritty_terminal::term::cell::Flags;
+use alacritty_terminal::term::damage::TermDamage;
+use alacritty_terminal::term::{SizeInfo, Term};
+
+/// Viewport lines which changed since the last frame.
+#[derive(Debug)]
+pub struct DamageTracker {
+    /// Redraw the entire window, ignoring the damage of individual lines.
+    full: bool,
+
+    /// Damage of each viewport line.
+    lines: Vec<bool>,
+
+    /// Window size during the last frame.
+    size_info: Option<SizeInfo>,
+
+    /// Hints or search matches were highlighted during the last frame.
+    highlighted: bool,
+
+    /// Elements were drawn on top of the grid during the last frame.
+    overlay: bool,
+}
+
+impl Default for DamageTracker {
+    fn default() -> Self {
+        Self { full: true, lines: Vec::new(), size_info: None, highlighted: false, overlay: false }
+    }
+}
+
+impl DamageTracker {
+    /// Redraw the entire window with the next frame.
+    #[inline]
+    pub fn mark_fully_damaged(&mut self) {
+        self.full = true;
+    }
+
+    #[inline]
+    pub fn is_fully_damaged(&self) -> bool {
+        self.full
+    }
+
+    /// Check if a viewport line needs to be redrawn.
+    #[inline]
+    pub fn is_damaged(&self, line: Line) -> bool {
+        self.full || self.lines.get(line.0).copied().unwrap_or(true)
+    }
+
+    /// Take the damage accumulated by the terminal since the last frame.
+    ///
+    /// Highlighted hints and search matches are not part of the terminal's damage, so everything
+    /// is redrawn while they are visible and once after they disappear.
+    pub fn collect<T: EventListener>(
+        &mut self,
+        terminal: &mut Term<T>,
+        size_info: &SizeInfo,
+        highlighted: bool,
+    ) {
+        let last_size_info = self.size_info.replace(*size_info);
+        let last_highlighted = mem::replace(&mut self.highlighted, highlighted);
+        self.full |= highlighted || last_highlighted || last_size_info != Some(*size_info);
+
+        let num_lines = terminal.screen_lines().0;
+        self.lines.clear();
+        self.lines.resize(num_lines, false);
+
+        match terminal.damage() {
+            TermDamage::Full => self.full = true,
+            TermDamage::Partial(damaged_lines) => {
+                for bounds in damaged_lines {
+                    self.lines[bounds.line.0] = true;
+                }
+            },
+        }
+        terminal.reset_damage();
+
+        if self.full {
+            return;
+        }
+
+        // Redraw wrapped lines entirely, so URLs spanning multiple lines are detected again.
+        let grid = terminal.grid();
+        let last_column = Column(grid.cols().0 - 1);
+        let display_offset = grid.display_offset();
+        let wraps = |line: usize| {
+            let row = &grid[num_lines + display_offset - line - 1];
+            row[last_column].flags.contains(Flags::WRAPLINE)
+        };
+
+        for line in 0..num_lines.saturating_sub(1) {
+            if self.lines[line] && wraps(line) {
+                self.lines[line + 1] = true;
+            }
+        }
+
+        for line in (1..num_lines).rev() {
+            if self.lines[line] && wraps(line - 1) {
+                self.lines[line - 1] = true;
+            }
+        }
+    }
+
+    /// Damaged regions of the window, using OpenGL's bottom-left origin.
+    ///
+    /// Each region spans the entire width of the window, to include the padding.
+    pub fn rects(&self, size_info: &SizeInfo) -> Vec<Rect> {
+        let mut rects = Vec::new();
+
+        let mut start = None;
+        for line in 0..=self.lines.len() {
+            let damaged = self.lines.get(line).copied().unwrap_or(false);
+            match (start, damaged) {
+                (None, true) => start = Some(line),
+                (Some(start_line), false) => {
+                    rects.push(lines_rect(size_info, start_line, line));
+                    start = None;
+                },
+                _ => (),
+            }
+        }
+
+        rects
+    }
+
+    /// Reset the damage after a frame was drawn.
+    ///
+    /// This returns the regions of the window which need to be presented. No regions are
+    /// returned when the entire window changed, including when elements like the message bar are
+    /// drawn on top of the grid.
+    pub fn finish_frame(&mut self, size_info: &SizeInfo, overlay: bool) -> Vec<Rect> {
+        let last_overlay = mem::replace(&mut self.overlay, overlay);
+        let rects =
+            if self.full || overlay || last_overlay { Vec::new() } else { self.rects(size_info) };
+
+        self.full = false;
+
+        rects
+    }
+}
+
+/// Window region covered by the viewport lines from `start` up to `end`.
+///
+/// The edges are rounded the same way the lines are rasterized, so clearing the region does not
+/// touch the pixels of adjacent lines.
+fn lines_rect(size_info: &SizeInfo, start: usize, end: usize) -> Rect {
+    // The grid is drawn into a viewport with integer padding, scaled to the exact padding.
+    let padding_y = size_info.padding_y() as i32;
+    let height = size_info.height() as i32;
+    let viewport_height = (height - 2 * padding_y) as f32;
+    let scale = viewport_height / (size_info.height() - 2. * size_info.padding_y());
+
+    let edge =
+        |line: usize| padding_y + (line as f32 * size_info.cell_height() * scale).round() as i32;
+    let (top, bottom) = (edge(start), edge(end));
+
+    Rect {
+        x: 0,
+        y: (height - bottom).max(0) as u32,
+        width: size_info.width() as u32,
+        height: (bottom - top).max(0) as u32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_rects() {
+        let size = SizeInfo::new(100., 110., 10., 20., 5., 5., false);
+        let lines = vec![true, false, true, true, false];
+        let mut damage = DamageTracker { lines, ..DamageTracker::default() };
+
+        // Everything is redrawn until the first frame has been drawn.
+        assert!(damage.is_fully_damaged());
+        assert_eq!(damage.finish_frame(&size, false), Vec::new());
+
+        let rects = damage.rects(&size);
+        assert_eq!(
+            rects,
+            vec![
+                Rect { x: 0, y: 85, width: 100, height: 20 },
+                Rect { x: 0, y: 25, width: 100, height: 40 },
+            ]
+        );
+
+        // Overlays require presenting the entire window, including the frame after.
+        assert_eq!(damage.finish_frame(&size, true), Vec::new());
+        assert_eq!(damage.finish_frame(&size, false), Vec::new());
+        assert_eq!(damage.finish_frame(&size, false), rects);
+    }
+}
@@ -0,0 +1,273 @@
+//! Rendering of terminal frames without a window.
+//!
+//! This uses the software renderer to draw the terminal into memory, which can be saved as PNG
+//! screenshot on machines without a GPU or display server.
+
+use std::error::Error as StdError;
+use std::fs::File;
+use std::io::{self, BufWriter, Read};
+use std::mem;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use log::info;
+
+use crossfont::{Rasterize, Rasterizer};
+
+use alacritty_terminal::ansi;
+use alacritty_terminal::asciicast::{Cast, EventKind};
+use alacritty_terminal::config::Config as TermConfig;
+use alacritty_terminal::event::{Event, EventListener, Notify};
+use alacritty_terminal::event_loop::{EventLoop as PtyEventLoop, Notifier};
+use alacritty_terminal::grid::Dimensions as _;
+use alacritty_terminal::index::{Column, Line};
+use alacritty_terminal::sync::FairMutex;
+use alacritty_terminal::term::{SizeInfo, Term};
+use alacritty_terminal::tty;
+
+use crate::cli::Options;
+use crate::config::window::Dimensions;
+use crate::config::Config;
+use crate::display::color::List;
+use crate::display::content::RenderableContent;
+use crate::display::hint::HintState;
+use crate::display::{compute_cell_size, window_size, Error, Frame, FrameRenderer};
+use crate::event::SearchState;
+use crate::renderer::software::SoftwareRenderer;
+use crate::renderer::GlyphCache;
+
+/// Terminal size used when the configuration does not specify any dimensions.
+const DEFAULT_DIMENSIONS: Dimensions = Dimensions { columns: Column(80), lines: Line(24) };
+
+/// Event listener answering terminal requests, all other events are discarded since there is no
+/// window to update.
+#[derive(Clone)]
+struct Responder {
+    /// Channel of the PTY event loop, if the terminal is connected to a child process.
+    notifier: Arc<Mutex<Option<Notifier>>>,
+
+    /// Colors reported for requests of colors not set by the application.
+    colors: List,
+}
+
+impl Responder {
+    fn new(colors: List) -> Self {
+        Self { notifier: Arc::new(Mutex::new(None)), colors }
+    }
+
+    /// Write a response to the PTY.
+    fn respond(&self, text: String) {
+        if let Some(notifier) = &mut *self.notifier.lock().unwrap() {
+            notifier.notify(text.into_bytes());
+        }
+    }
+}
+
+impl EventListener for Responder {
+    fn send_event(&self, event: Event) {
+        match event {
+            // Without a window there's no clipboard to read from.
+            Event::ClipboardLoad(_, format) => self.respond(format("")),
+            Event::ColorRequest(index, format) => self.respond(format(self.colors[index])),
+            _ => (),
+        }
+    }
+}
+
+/// Display drawing into memory instead of a window.
+pub struct Headless {
+    pub size_info: SizeInfo,
+
+    /// Mapped RGB values for each terminal color.
+    pub colors: List,
+
+    /// State of the keyboard hints.
+    pub hint_state: HintState,
+
+    renderer: SoftwareRenderer,
+    glyph_cache: GlyphCache,
+}
+
+impl Headless {
+    pub fn new(config: &Config) -> Result<Headless, Error> {
+        // Without a monitor there is no scale factor to account for.
+        let dpr = 1.;
+
+        let font = config.ui_config.font.clone();
+        let rasterizer = Rasterizer::new(dpr as f32, config.ui_config.font.use_thin_strokes)?;
+
+        let mut renderer = SoftwareRenderer::default();
+        let glyph_cache =
+            renderer.with_loader(|mut api| GlyphCache::new(rasterizer, &font, dpr, &mut api))?;
+        let (cell_width, cell_height) = compute_cell_size(config, &glyph_cache.font_metrics());
+
+        let dimensions = config.ui_config.window.dimensions().unwrap_or(DEFAULT_DIMENSIONS);
+        let size_info = size_info(config, dimensions, cell_width, cell_height);
+        renderer.resize(&size_info);
+
+        info!("Cell size: {} x {}", cell_width, cell_height);
+        info!("Width: {}, Height: {}", size_info.width(), size_info.height());
+
+        Ok(Self {
+            size_info,
+            colors: List::from(&config.ui_config.colors),
+            hint_state: HintState::new(config.ui_config.hints.alphabet()),
+            renderer,
+            glyph_cache,
+        })
+    }
+
+    /// Resize the frame buffer to fit the terminal dimensions.
+    pub fn resize(&mut self, config: &Config, dimensions: Dimensions) {
+        let cell_width = self.size_info.cell_width();
+        let cell_height = self.size_info.cell_height();
+        self.size_info = size_info(config, dimensions, cell_width, cell_height);
+        self.renderer.resize(&self.size_info);
+    }
+
+    /// Draw the terminal into the frame buffer.
+    ///
+    /// The terminal is resized to make room for the search bar while a search is active.
+    pub fn draw<T: EventListener>(
+        &mut self,
+        terminal: &mut Term<T>,
+        config: &Config,
+        search_state: &SearchState,
+    ) {
+        let search_active = search_state.regex().is_some();
+        let mut size_info = self.size_info;
+        if search_active {
+            size_info.reserve_lines(1);
+        }
+        if terminal.screen_lines() != size_info.screen_lines() {
+            terminal.resize(size_info);
+        }
+
+        // Update visible hint matches, since the terminal content might have changed.
+        self.hint_state.update_matches(terminal);
+
+        // Collect renderable content.
+        let dfas = search_state.dfas();
+        let content = RenderableContent::new(
+            terminal,
+            dfas,
+            &self.hint_state,
+            config,
+            &self.colors,
+            !search_active,
+        );
+        let mut frame = Frame::new(terminal, content, config, search_state, &size_info);
+
+        let background_color = frame.background_color;
+        self.renderer
+            .with_api(&config.ui_config, &size_info, |mut api| api.clear(background_color));
+
+        let mut frame_renderer =
+            FrameRenderer::new(&mut self.renderer, &mut self.glyph_cache, config, &size_info);
+        let lines = frame_renderer.draw_cells(mem::take(&mut frame.grid_cells), |_| ());
+        frame_renderer.draw_lines(lines, frame.cursor);
+        frame_renderer.draw_line_indicator(&frame, search_active);
+
+        if let Some(regex) = search_state.regex() {
+            frame_renderer.draw_search(&frame, search_state, regex);
+        }
+    }
+
+    /// Save the last frame as PNG.
+    pub fn write_png(&self, path: &Path) -> Result<(), Box<dyn StdError>> {
+        let file = BufWriter::new(File::create(path)?);
+        self.renderer.write_png(file)?;
+        Ok(())
+    }
+}
+
+/// Render a screenshot to `path` without opening a window.
+///
+/// The terminal content is produced by the command passed with `-e`, by the recording passed
+/// with `--play` or by the bytes read from STDIN.
+pub fn run(config: &Config, options: &Options, path: &Path) -> Result<(), Box<dyn StdError>> {
+    let mut headless = Headless::new(config)?;
+    let size_info = headless.size_info;
+
+    let responder = Responder::new(headless.colors);
+    let terminal = Arc::new(FairMutex::new(Term::new(config, size_info, responder.clone())));
+
+    if let Some(playback) = &options.play {
+        // Replay the entire recording without delays, applying resizes in order.
+        let cast = Cast::open(&playback.path)?;
+        let mut terminal = terminal.lock();
+        let mut parser = ansi::Processor::new();
+        for event in &cast.events {
+            if let Some((columns, lines)) = event.dimensions() {
+                let dimensions = Dimensions { columns: Column(columns), lines: Line(lines) };
+                headless.resize(config, dimensions);
+                terminal.resize(headless.size_info);
+            } else if event.kind == EventKind::Output {
+                advance(&mut parser, &mut terminal, event.data.as_bytes());
+            }
+        }
+    } else if options.command.is_some() {
+        let mut pty_config = TermConfig::<()> {
+            shell: config.shell.clone(),
+            working_directory: config.working_directory.clone(),
+            ..TermConfig::default()
+        };
+        options.window_options().override_pty_config(&mut pty_config);
+
+        // Run the command until it exits, draining all of its output.
+        let pty = tty::new(&pty_config, &size_info, None);
+        let event_loop =
+            PtyEventLoop::new(Arc::clone(&terminal), responder.clone(), pty, true, false);
+        *responder.notifier.lock().unwrap() = Some(Notifier(event_loop.channel()));
+
+        let result = event_loop.spawn().join();
+        responder.notifier.lock().unwrap().take();
+
+        // Present updates which were still being synchronized when the command exited.
+        if let Ok((_, mut state)) = result {
+            state.flush_sync(&mut terminal.lock());
+        }
+    } else {
+        let mut bytes = Vec::new();
+        io::stdin().read_to_end(&mut bytes)?;
+        advance(&mut ansi::Processor::new(), &mut terminal.lock(), &bytes);
+    }
+
+    headless.draw(&mut terminal.lock(), config, &SearchState::new());
+    headless.write_png(path)?;
+
+    info!("Saved screenshot to {:?}", path);
+
+    Ok(())
+}
+
+/// Size of a frame buffer fitting the terminal dimensions.
+fn size_info(
+    config: &Config,
+    dimensions: Dimensions,
+    cell_width: f32,
+    cell_height: f32,
+) -> SizeInfo {
+    // Without a monitor there is no scale factor to account for.
+    let dpr = 1.;
+
+    let size = window_size(config, dimensions, cell_width, cell_height, dpr);
+    let padding = config.ui_config.window.padding(dpr);
+
+    SizeInfo::new(
+        size.width as f32,
+        size.height as f32,
+        cell_width,
+        cell_height,
+        padding.0,
+        padding.1,
+        false,
+    )
+}
+
+/// Feed bytes through the parser into the terminal.
+fn advance<T: EventListener>(parser: &mut ansi::Processor, terminal: &mut Term<T>, bytes: &[u8]) {
+    for byte in bytes {
+        parser.advance(terminal, *byte, &mut io::sink());
+    }
+}
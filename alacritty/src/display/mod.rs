@@ -4,13 +4,14 @@
 use std::cmp::min;
 use std::f64;
 use std::fmt::{self, Formatter};
+use std::mem;
 #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
 use std::sync::atomic::Ordering;
 use std::time::Instant;
 
 use glutin::dpi::{PhysicalPosition, PhysicalSize};
 use glutin::event::ModifiersState;
-use glutin::event_loop::EventLoop;
+use glutin::event_loop::EventLoopWindowTarget;
 #[cfg(not(any(target_os = "macos", windows)))]
 use glutin::platform::unix::EventLoopWindowTargetExtUnix;
 use glutin::window::CursorIcon;
@@ -18,7 +19,7 @@ use log::{debug, info};
 use parking_lot::MutexGuard;
 use unicode_width::UnicodeWidthChar;
 #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
-use wayland_client::{Display as WaylandDisplay, EventQueue};
+use wayland_client::EventQueue;
 
 use crossfont::{self, Rasterize, Rasterizer};
 
@@ -27,6 +28,8 @@ use alacritty_terminal::event::{EventListener, OnResize};
 use alacritty_terminal::grid::Dimensions as _;
 use alacritty_terminal::index::{Column, Direction, Line, Point};
 use alacritty_terminal::selection::Selection;
+use alacritty_terminal::term::color::Rgb;
+use alacritty_terminal::term::search::{SearchCase, SearchOptions};
 use alacritty_terminal::term::{SizeInfo, Term, TermMode, MIN_COLS, MIN_SCREEN_LINES};
 
 use crate::config::font::Font;
@@ -36,18 +39,24 @@ use crate::config::window::StartupMode;
 use crate::config::Config;
 use crate::display::bell::VisualBell;
 use crate::display::color::List;
-use crate::display::content::RenderableContent;
+use crate::display::content::{RenderableCell, RenderableContent, RenderableCursor};
 use crate::display::cursor::IntoRects;
+use crate::display::damage::DamageTracker;
+use crate::display::hint::HintState;
 use crate::display::meter::Meter;
 use crate::display::window::Window;
-use crate::event::{Mouse, SearchState};
+use crate::event::{Mouse, SearchResult, SearchState};
 use crate::message_bar::{MessageBuffer, MessageType};
+use crate::renderer::graphics::RenderGraphic;
 use crate::renderer::rects::{RenderLines, RenderRect};
-use crate::renderer::{self, GlyphCache, QuadRenderer};
+use crate::renderer::{self, GlyphCache, QuadRenderer, Renderer, TextRenderApi};
 use crate::url::{Url, Urls};
 
 pub mod content;
 pub mod cursor;
+pub mod damage;
+pub mod headless;
+pub mod hint;
 pub mod window;
 
 mod bell;
@@ -56,8 +65,11 @@ mod meter;
 #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
 mod wayland_theme;
 
-const FORWARD_SEARCH_LABEL: &str = "Search: ";
-const BACKWARD_SEARCH_LABEL: &str = "Backward Search: ";
+const FORWARD_SEARCH_LABEL: &str = "Search";
+const BACKWARD_SEARCH_LABEL: &str = "Backward Search";
+
+/// Maximum number of lines used by the search results list.
+const MAX_SEARCH_RESULTS: usize = 10;
 
 #[derive(Debug)]
 pub enum Error {
@@ -161,15 +173,11 @@ impl DisplayUpdate {
 /// The display wraps a window, font rasterizer, and GPU renderer.
 pub struct Display {
     pub size_info: SizeInfo,
-    pub window: Window,
     pub urls: Urls,
 
     /// Currently highlighted URL.
     pub highlighted_url: Option<Url>,
 
-    #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
-    pub wayland_event_queue: Option<EventQueue>,
-
     #[cfg(not(any(target_os = "macos", windows)))]
     pub is_x11: bool,
 
@@ -181,13 +189,29 @@ pub struct Display {
     /// Mapped RGB values for each terminal color.
     pub colors: List,
 
+    /// State of the keyboard hints.
+    pub hint_state: HintState,
+
+    /// Lines which need to be redrawn with the next frame.
+    damage: DamageTracker,
+
     renderer: QuadRenderer,
     glyph_cache: GlyphCache,
     meter: Meter,
+
+    /// Window owning the OpenGL context.
+    ///
+    /// NOTE: This must be dropped after all GPU resources to delete them in the right context.
+    pub window: Window,
 }
 
 impl Display {
-    pub fn new<E>(config: &Config, event_loop: &EventLoop<E>) -> Result<Display, Error> {
+    pub fn new<E>(
+        config: &Config,
+        event_loop: &EventLoopWindowTarget<E>,
+        #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
+        wayland_event_queue: Option<&EventQueue>,
+    ) -> Result<Display, Error> {
         // Guess DPR based on first monitor.
         let estimated_dpr =
             event_loop.available_monitors().next().map(|m| m.scale_factor()).unwrap_or(1.);
@@ -206,23 +230,13 @@ impl Display {
         debug!("Estimated window size: {:?}", estimated_size);
         debug!("Estimated cell size: {} x {}", cell_width, cell_height);
 
-        #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
-        let mut wayland_event_queue = None;
-
-        // Initialize Wayland event queue, to handle Wayland callbacks.
-        #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
-        if let Some(display) = event_loop.wayland_display() {
-            let display = unsafe { WaylandDisplay::from_external_display(display as _) };
-            wayland_event_queue = Some(display.create_event_queue());
-        }
-
         // Spawn the Alacritty window.
         let mut window = Window::new(
             event_loop,
             &config,
             estimated_size,
             #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
-            wayland_event_queue.as_ref(),
+            wayland_event_queue,
         )?;
 
         info!("Device pixel ratio: {}", window.dpr);
@@ -323,11 +337,11 @@ impl Display {
             highlighted_url: None,
             #[cfg(not(any(target_os = "macos", windows)))]
             is_x11,
-            #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
-            wayland_event_queue,
             cursor_hidden: false,
             visual_bell: VisualBell::from(&config.ui_config.bell),
             colors: List::from(&config.ui_config.colors),
+            hint_state: HintState::new(config.ui_config.hints.alphabet()),
+            damage: DamageTracker::default(),
         })
     }
 
@@ -345,7 +359,7 @@ impl Display {
             let init_start = Instant::now();
 
             let cache =
-                renderer.with_loader(|mut api| GlyphCache::new(rasterizer, &font, &mut api))?;
+                renderer.with_loader(|mut api| GlyphCache::new(rasterizer, &font, dpr, &mut api))?;
 
             let stop = init_start.elapsed();
             let stop_f = stop.as_secs() as f64 + f64::from(stop.subsec_nanos()) / 1_000_000_000f64;
@@ -397,6 +411,12 @@ impl Display {
     ) where
         T: EventListener,
     {
+        // Switch to this window's OpenGL context before touching any GPU resources.
+        self.window.make_current();
+
+        // Glyphs and the offscreen framebuffer are recreated, so everything must be redrawn.
+        self.damage.mark_fully_damaged();
+
         let (mut cell_width, mut cell_height) =
             (self.size_info.cell_width(), self.size_info.cell_height());
 
@@ -458,88 +478,98 @@ impl Display {
     /// This call may block if vsync is enabled.
     pub fn draw<T: EventListener>(
         &mut self,
-        terminal: MutexGuard<'_, Term<T>>,
+        mut terminal: MutexGuard<'_, Term<T>>,
         message_buffer: &MessageBuffer,
         config: &Config,
         mouse: &Mouse,
         mods: ModifiersState,
         search_state: &SearchState,
     ) {
-        // Convert search match from viewport to absolute indexing.
+        // Make sure this window's OpenGL context is used for rendering.
+        self.window.make_current();
+
         let search_active = search_state.regex().is_some();
-        let viewport_match = search_state
-            .focused_match()
-            .and_then(|focused_match| terminal.grid().clamp_buffer_range_to_visible(focused_match));
-        let cursor_hidden = self.cursor_hidden || search_state.regex().is_some();
+        let cursor_hidden = self.cursor_hidden || search_active;
+
+        // Update visible hint matches, since the terminal content might have changed.
+        self.hint_state.update_matches(&terminal);
+
+        // Find the lines which changed since the last frame.
+        let highlighted = search_active || self.hint_state.active();
+        self.damage.collect(&mut terminal, &self.size_info, highlighted);
 
         // Collect renderable content before the terminal is dropped.
+        let size_info = self.size_info;
         let dfas = search_state.dfas();
         let colors = &self.colors;
-        let mut content = RenderableContent::new(&terminal, dfas, config, colors, !cursor_hidden);
-        let mut grid_cells = Vec::new();
-        while let Some(cell) = content.next() {
-            grid_cells.push(cell);
-        }
-        let background_color = content.color(NamedColor::Background as usize);
-        let display_offset = content.display_offset();
-        let cursor = content.cursor();
+        let hint_state = &self.hint_state;
+        let content =
+            RenderableContent::new(&terminal, dfas, hint_state, config, colors, !cursor_hidden)
+                .with_damage(&self.damage);
+        let mut frame = Frame::new(&terminal, content, config, search_state, &size_info);
 
         let cursor_point = terminal.grid().cursor.point;
-        let total_lines = terminal.grid().total_lines();
         let metrics = self.glyph_cache.font_metrics();
-        let size_info = self.size_info;
 
         let selection = !terminal.selection.as_ref().map(Selection::is_empty).unwrap_or(true);
         let mouse_mode = terminal.mode().intersects(TermMode::MOUSE_MODE)
             && !terminal.mode().contains(TermMode::VI);
 
-        let vi_mode = terminal.mode().contains(TermMode::VI);
-        let vi_mode_cursor = if vi_mode { Some(terminal.vi_mode_cursor) } else { None };
+        let graphics_updates = terminal.graphics_take_updates();
 
         // Drop terminal as early as possible to free lock.
         drop(terminal);
 
-        self.renderer.with_api(&config.ui_config, &size_info, |api| {
-            api.clear(background_color);
+        // Upload new graphics and release unused textures.
+        if let Some(graphics_updates) = graphics_updates {
+            self.renderer.graphics_run_updates(graphics_updates);
+        }
+
+        // Redraw the damaged lines of the grid, keeping the rest of the last frame.
+        self.renderer.bind_framebuffer();
+        let background_color = frame.background_color;
+        let damage_rects =
+            if self.damage.is_fully_damaged() { None } else { Some(self.damage.rects(&size_info)) };
+        self.renderer.with_api(&config.ui_config, &size_info, |api| match &damage_rects {
+            Some(rects) => api.clear_rects(background_color, rects),
+            None => api.clear(background_color),
         });
 
-        let mut lines = RenderLines::new();
+        // Keep the URLs of all lines which are not redrawn.
         let mut urls = Urls::new();
+        if !self.damage.is_fully_damaged() {
+            urls = mem::take(&mut self.urls);
+            urls.remove_damaged(&self.damage);
+        }
+
+        let mut graphics = Vec::new();
 
         // Draw grid.
-        {
+        let lines = {
             let _sampler = self.meter.sampler();
 
-            let glyph_cache = &mut self.glyph_cache;
-            self.renderer.with_api(&config.ui_config, &size_info, |mut api| {
-                // Iterate over all non-empty cells in the grid.
-                for mut cell in grid_cells {
-                    // Invert the active match during search.
-                    if cell.is_match
-                        && viewport_match
-                            .as_ref()
-                            .map_or(false, |viewport_match| viewport_match.contains(&cell.point))
-                    {
-                        let colors = config.ui_config.colors.search.focused_match;
-                        let match_fg = colors.foreground.color(cell.fg, cell.bg);
-                        cell.bg = colors.background.color(cell.fg, cell.bg);
-                        cell.fg = match_fg;
-                        cell.bg_alpha = 1.0;
-                    }
-
-                    // Update URL underlines.
-                    urls.update(size_info.cols(), &cell);
-
-                    // Update underline/strikeout.
-                    lines.update(&cell);
-
-                    // Draw the cell.
-                    api.render_cell(cell, glyph_cache);
+            let mut frame_renderer =
+                FrameRenderer::new(&mut self.renderer, &mut self.glyph_cache, config, &size_info);
+            frame_renderer.draw_cells(mem::take(&mut frame.grid_cells), |cell| {
+                // Update URL underlines.
+                urls.update(size_info.cols(), cell);
+
+                // Collect graphics to draw them above the text.
+                if let Some(graphic) = &cell.graphic {
+                    graphics.push(RenderGraphic::new(cell.point, graphic));
                 }
-            });
-        }
+            })
+        };
+
+        self.renderer.draw_graphics(&size_info, graphics);
+
+        FrameRenderer::new(&mut self.renderer, &mut self.glyph_cache, config, &size_info)
+            .draw_lines(lines, frame.cursor);
+
+        // Draw everything else on top of the grid, directly into the window.
+        self.renderer.blit_framebuffer();
 
-        let mut rects = lines.rects(&metrics, &size_info);
+        let mut rects = Vec::new();
 
         // Update visible URLs.
         self.urls = urls;
@@ -559,29 +589,17 @@ impl Display {
             }
         }
 
-        if let Some(vi_mode_cursor) = vi_mode_cursor {
-            // Highlight URLs at the vi mode cursor position.
-            let vi_mode_point = vi_mode_cursor.point;
+        // Highlight URLs at the vi mode cursor position.
+        if let Some(vi_mode_point) = frame.vi_mode_point {
             if let Some(url) = self.urls.find_at(vi_mode_point) {
                 rects.append(&mut url.rects(&metrics, &size_info));
             }
-
-            // Indicate vi mode by showing the cursor's position in the top right corner.
-            let line = size_info.screen_lines() + display_offset - vi_mode_point.line - 1;
-            self.draw_line_indicator(config, &size_info, total_lines, Some(vi_mode_point), line.0);
-        } else if search_active {
-            // Show current display offset in vi-less search to indicate match position.
-            self.draw_line_indicator(config, &size_info, total_lines, None, display_offset);
         }
 
-        // Push the cursor rects for rendering.
-        if let Some(cursor) = cursor {
-            for rect in cursor.rects(&size_info, config.cursor.thickness()) {
-                rects.push(rect);
-            }
-        }
+        FrameRenderer::new(&mut self.renderer, &mut self.glyph_cache, config, &size_info)
+            .draw_line_indicator(&frame, search_active);
 
-        // Push visual bell after url/underline/strikeout rects.
+        // Push visual bell after url rects.
         let visual_bell_intensity = self.visual_bell.intensity();
         if visual_bell_intensity != 0. {
             let visual_bell_rect = RenderRect::new(
@@ -595,6 +613,13 @@ impl Display {
             rects.push(visual_bell_rect);
         }
 
+        // Check if anything covers the grid, since it would have to be removed again.
+        let overlay = !rects.is_empty()
+            || frame.vi_mode_point.is_some()
+            || search_active
+            || message_buffer.message().is_some()
+            || config.ui_config.debug.render_timer;
+
         if let Some(message) = message_buffer.message() {
             let search_offset = if search_active { 1 } else { 0 };
             let text = message.text(&size_info);
@@ -606,6 +631,7 @@ impl Display {
             let bg = match message.ty() {
                 MessageType::Error => config.ui_config.colors.normal.red,
                 MessageType::Warning => config.ui_config.colors.normal.yellow,
+                MessageType::Info => config.ui_config.colors.normal.green,
             };
 
             let message_bar_rect =
@@ -636,15 +662,13 @@ impl Display {
         // Handle search and IME positioning.
         let ime_position = match search_state.regex() {
             Some(regex) => {
-                let search_label = match search_state.direction() {
-                    Direction::Right => FORWARD_SEARCH_LABEL,
-                    Direction::Left => BACKWARD_SEARCH_LABEL,
-                };
-
-                let search_text = Self::format_search(&size_info, regex, search_label);
-
-                // Render the search bar.
-                self.draw_search(config, &size_info, &search_text);
+                let mut frame_renderer = FrameRenderer::new(
+                    &mut self.renderer,
+                    &mut self.glyph_cache,
+                    config,
+                    &size_info,
+                );
+                let search_text = frame_renderer.draw_search(&frame, search_state, regex);
 
                 // Compute IME position.
                 Point::new(size_info.screen_lines() + 1, Column(search_text.chars().count() - 1))
@@ -660,7 +684,9 @@ impl Display {
         #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
         self.request_frame(&self.window);
 
-        self.window.swap_buffers();
+        // Present only the lines which changed, where the platform supports it.
+        let damage_rects = self.damage.finish_frame(&size_info, overlay);
+        self.window.swap_buffers_with_damage(&damage_rects);
 
         #[cfg(all(feature = "x11", not(any(target_os = "macos", windows))))]
         if self.is_x11 {
@@ -677,6 +703,43 @@ impl Display {
     pub fn update_config(&mut self, config: &Config) {
         self.visual_bell.update_config(&config.ui_config.bell);
         self.colors = List::from(&config.ui_config.colors);
+        self.hint_state.update_alphabet(config.ui_config.hints.alphabet());
+        self.damage.mark_fully_damaged();
+    }
+
+    /// Window size required for the terminal dimensions using the current font.
+    pub fn window_size(&self, config: &Config, dimensions: Dimensions) -> PhysicalSize<u32> {
+        let size_info = &self.size_info;
+        let (cell_width, cell_height) = (size_info.cell_width(), size_info.cell_height());
+        window_size(config, dimensions, cell_width, cell_height, self.window.dpr)
+    }
+
+    /// Format the search label, including all active search options.
+    fn format_search_label(direction: Direction, options: SearchOptions) -> String {
+        let mut label = String::from(match direction {
+            Direction::Right => FORWARD_SEARCH_LABEL,
+            Direction::Left => BACKWARD_SEARCH_LABEL,
+        });
+
+        let mut toggles = Vec::new();
+        if options.literal {
+            toggles.push("literal");
+        }
+        match options.case {
+            SearchCase::Sensitive => toggles.push("case"),
+            SearchCase::Insensitive => toggles.push("nocase"),
+            SearchCase::Smart => (),
+        }
+        if options.whole_word {
+            toggles.push("word");
+        }
+
+        if !toggles.is_empty() {
+            label.push_str(&format!(" [{}]", toggles.join(" ")));
+        }
+        label.push_str(": ");
+
+        label
     }
 
     /// Format search regex to account for the cursor and fullwidth characters.
@@ -710,21 +773,32 @@ impl Display {
         bar_text
     }
 
-    /// Draw current search regex.
-    fn draw_search(&mut self, config: &Config, size_info: &SizeInfo, text: &str) {
-        let glyph_cache = &mut self.glyph_cache;
+    /// Format a search result, truncating it to the viewport width.
+    fn format_search_result(
+        size_info: &SizeInfo,
+        result: &SearchResult,
+        line_width: usize,
+    ) -> String {
         let num_cols = size_info.cols().0;
+        let mut text = format!("{:>1$}: ", result.line, line_width);
 
-        // Assure text length is at least num_cols.
-        let text = format!("{:<1$}", text, num_cols);
+        // Add spacers for wide chars.
+        let mut width = text.chars().count();
+        for c in result.text.chars() {
+            let c_width = c.width().unwrap_or(1);
+            if width + c_width > num_cols {
+                break;
+            }
 
-        let point = Point::new(size_info.screen_lines(), Column(0));
-        let fg = config.ui_config.colors.search_bar_foreground();
-        let bg = config.ui_config.colors.search_bar_background();
+            text.push(c);
+            if c_width == 2 {
+                text.push(' ');
+            }
+            width += c_width;
+        }
 
-        self.renderer.with_api(&config.ui_config, &size_info, |mut api| {
-            api.render_string(glyph_cache, point, fg, bg, &text);
-        });
+        // Assure text length is at least num_cols.
+        format!("{:<1$}", text, num_cols)
     }
 
     /// Draw render timer.
@@ -745,30 +819,6 @@ impl Display {
         });
     }
 
-    /// Draw an indicator for the position of a line in history.
-    fn draw_line_indicator(
-        &mut self,
-        config: &Config,
-        size_info: &SizeInfo,
-        total_lines: usize,
-        vi_mode_point: Option<Point>,
-        line: usize,
-    ) {
-        let text = format!("[{}/{}]", line, total_lines - 1);
-        let column = Column(size_info.cols().0.saturating_sub(text.len()));
-        let colors = &config.ui_config.colors;
-        let fg = colors.line_indicator.foreground.unwrap_or(colors.primary.background);
-        let bg = colors.line_indicator.background.unwrap_or(colors.primary.foreground);
-
-        // Do not render anything if it would obscure the vi mode cursor.
-        if vi_mode_point.map_or(true, |point| point.line.0 != 0 || point.column < column) {
-            let glyph_cache = &mut self.glyph_cache;
-            self.renderer.with_api(&config.ui_config, &size_info, |mut api| {
-                api.render_string(glyph_cache, Point::new(Line(0), column), fg, bg, &text);
-            });
-        }
-    }
-
     /// Requst a new frame for a window on Wayland.
     #[inline]
     #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
@@ -790,6 +840,204 @@ impl Display {
     }
 }
 
+impl Drop for Display {
+    fn drop(&mut self) {
+        // Switch OpenGL context before dropping, otherwise objects like programs and textures from
+        // another window's context might be deleted.
+        self.window.make_current();
+    }
+}
+
+/// Terminal content of a frame, collected while the terminal is locked.
+struct Frame {
+    grid_cells: Vec<RenderableCell>,
+    background_color: Rgb,
+    display_offset: usize,
+    cursor: Option<RenderableCursor>,
+    total_lines: usize,
+    vi_mode_point: Option<Point>,
+    search_count: Option<String>,
+    search_results: Vec<SearchResult>,
+}
+
+impl Frame {
+    fn new<T>(
+        terminal: &Term<T>,
+        mut content: RenderableContent<'_>,
+        config: &Config,
+        search_state: &SearchState,
+        size_info: &SizeInfo,
+    ) -> Self {
+        let mut grid_cells: Vec<RenderableCell> = content.by_ref().collect();
+        let background_color = content.color(NamedColor::Background as usize);
+        let display_offset = content.display_offset();
+        let cursor = content.cursor();
+
+        // Invert the active match during search.
+        let viewport_match = search_state
+            .focused_match()
+            .and_then(|focused_match| terminal.grid().clamp_buffer_range_to_visible(focused_match));
+        for cell in grid_cells.iter_mut().filter(|cell| cell.is_match) {
+            if viewport_match
+                .as_ref()
+                .map_or(false, |viewport_match| viewport_match.contains(&cell.point))
+            {
+                let colors = config.ui_config.colors.search.focused_match;
+                let match_fg = colors.foreground.color(cell.fg, cell.bg);
+                cell.bg = colors.background.color(cell.fg, cell.bg);
+                cell.fg = match_fg;
+                cell.bg_alpha = 1.0;
+            }
+        }
+
+        let vi_mode = terminal.mode().contains(TermMode::VI);
+        let vi_mode_point = if vi_mode { Some(terminal.vi_mode_cursor.point) } else { None };
+
+        // Collect search match count and results list.
+        let search_count = search_state.match_count(terminal);
+        let search_results = if search_state.regex().is_some() && search_state.results_visible() {
+            let max_results = min(MAX_SEARCH_RESULTS, size_info.screen_lines().0 / 2);
+            search_state.results(terminal, max_results)
+        } else {
+            Vec::new()
+        };
+
+        Self {
+            grid_cells,
+            background_color,
+            display_offset,
+            cursor,
+            total_lines: terminal.grid().total_lines(),
+            vi_mode_point,
+            search_count,
+            search_results,
+        }
+    }
+}
+
+/// Renderer and glyph cache used to draw a frame.
+struct FrameRenderer<'a, R> {
+    renderer: &'a mut R,
+    glyph_cache: &'a mut GlyphCache,
+    config: &'a Config,
+    size_info: &'a SizeInfo,
+}
+
+impl<'a, R: Renderer> FrameRenderer<'a, R> {
+    fn new(
+        renderer: &'a mut R,
+        glyph_cache: &'a mut GlyphCache,
+        config: &'a Config,
+        size_info: &'a SizeInfo,
+    ) -> Self {
+        Self { renderer, glyph_cache, config, size_info }
+    }
+
+    /// Draw the grid cells, calling `f` for every cell before it is drawn.
+    ///
+    /// Returns the underlines and strikeouts of the cells.
+    fn draw_cells<F>(&mut self, mut cells: Vec<RenderableCell>, mut f: F) -> RenderLines
+    where
+        F: FnMut(&RenderableCell),
+    {
+        // Substitute glyphs using the configured OpenType features.
+        self.glyph_cache.shape(&mut cells);
+
+        let mut lines = RenderLines::new();
+        let cells = cells.into_iter().inspect(|cell| {
+            lines.update(cell);
+            f(cell);
+        });
+        self.renderer.draw_cells(&self.config.ui_config, self.size_info, self.glyph_cache, cells);
+
+        lines
+    }
+
+    /// Draw the underlines and strikeouts of the grid, followed by the cursor.
+    fn draw_lines(&mut self, lines: RenderLines, cursor: Option<RenderableCursor>) {
+        let metrics = self.glyph_cache.font_metrics();
+        let mut rects = lines.rects(&metrics, self.size_info);
+
+        if let Some(cursor) = cursor {
+            rects.extend(cursor.rects(self.size_info, self.config.cursor.thickness()));
+        }
+
+        self.renderer.draw_rects(self.size_info, rects);
+    }
+
+    /// Draw an indicator for the position in history.
+    ///
+    /// In vi mode this shows the position of the vi mode cursor, during search the position of
+    /// the viewport to indicate the match position.
+    fn draw_line_indicator(&mut self, frame: &Frame, search_active: bool) {
+        let line = match frame.vi_mode_point {
+            Some(point) => {
+                (self.size_info.screen_lines() + frame.display_offset - point.line - 1).0
+            },
+            None if search_active => frame.display_offset,
+            None => return,
+        };
+
+        let text = format!("[{}/{}]", line, frame.total_lines - 1);
+        let column = Column(self.size_info.cols().0.saturating_sub(text.len()));
+        let colors = &self.config.ui_config.colors;
+        let fg = colors.line_indicator.foreground.unwrap_or(colors.primary.background);
+        let bg = colors.line_indicator.background.unwrap_or(colors.primary.foreground);
+
+        // Do not render anything if it would obscure the vi mode cursor.
+        if frame.vi_mode_point.map_or(true, |point| point.line.0 != 0 || point.column < column) {
+            self.draw_string(Point::new(Line(0), column), fg, bg, &text);
+        }
+    }
+
+    /// Draw the search results list and the search bar with the match count.
+    ///
+    /// Returns the text of the search bar.
+    fn draw_search(&mut self, frame: &Frame, search_state: &SearchState, regex: &str) -> String {
+        let fg = self.config.ui_config.colors.search_bar_foreground();
+        let bg = self.config.ui_config.colors.search_bar_background();
+        let num_cols = self.size_info.cols().0;
+
+        // Render the search results list above the search bar.
+        let results = &frame.search_results;
+        let line_width = results.iter().map(|result| result.line.to_string().len()).max();
+        if let Some(line_width) = line_width {
+            let start_line = self.size_info.screen_lines() - results.len();
+            for (i, result) in results.iter().enumerate() {
+                let text = Display::format_search_result(self.size_info, result, line_width);
+                let point = Point::new(start_line + i, Column(0));
+
+                // Highlight the focused match by inverting its colors.
+                let (fg, bg) = if result.focused { (bg, fg) } else { (fg, bg) };
+
+                self.draw_string(point, fg, bg, &text);
+            }
+        }
+
+        let label = Display::format_search_label(search_state.direction(), search_state.options());
+        let search_text = Display::format_search(self.size_info, regex, &label);
+
+        // Right-align the match count, unless it would overlap the search text.
+        let text = match frame.search_count.as_deref() {
+            Some(count) if search_text.chars().count() + count.len() < num_cols => {
+                format!("{:<2$}{}", search_text, count, num_cols - count.len())
+            },
+            // Assure text length is at least num_cols.
+            _ => format!("{:<1$}", search_text, num_cols),
+        };
+
+        let point = Point::new(self.size_info.screen_lines(), Column(0));
+        self.draw_string(point, fg, bg, &text);
+
+        search_text
+    }
+
+    fn draw_string(&mut self, point: Point, fg: Rgb, bg: Rgb, text: &str) {
+        let cells = renderer::string_cells(point, fg, bg, text);
+        self.renderer.draw_cells(&self.config.ui_config, self.size_info, self.glyph_cache, cells);
+    }
+}
+
 /// Calculate the cell dimensions based on font metrics.
 ///
 /// This will return a tuple of the cell width and height.
@@ -29,11 +29,12 @@ use {
 };
 
 use std::fmt::{self, Display, Formatter};
+use std::ops::Deref;
 
 #[cfg(target_os = "macos")]
 use cocoa::base::{id, NO, YES};
 use glutin::dpi::{PhysicalPosition, PhysicalSize};
-use glutin::event_loop::EventLoop;
+use glutin::event_loop::EventLoopWindowTarget;
 #[cfg(target_os = "macos")]
 use glutin::platform::macos::{WindowBuilderExtMacOS, WindowExtMacOS};
 #[cfg(windows)]
@@ -41,7 +42,7 @@ use glutin::platform::windows::IconExtWindows;
 use glutin::window::{
     CursorIcon, Fullscreen, UserAttentionType, Window as GlutinWindow, WindowBuilder, WindowId,
 };
-use glutin::{self, ContextBuilder, PossiblyCurrent, WindowedContext};
+use glutin::{self, ContextBuilder, PossiblyCurrent, Rect, WindowedContext};
 #[cfg(target_os = "macos")]
 use objc::{msg_send, sel, sel_impl};
 #[cfg(target_os = "macos")]
@@ -120,7 +121,7 @@ impl From<crossfont::Error> for Error {
 
 fn create_gl_window<E>(
     mut window: WindowBuilder,
-    event_loop: &EventLoop<E>,
+    event_loop: &EventLoopWindowTarget<E>,
     srgb: bool,
     vsync: bool,
     dimensions: Option<PhysicalSize<u32>>,
@@ -156,7 +157,7 @@ pub struct Window {
     /// Cached DPR for quickly scaling pixel sizes.
     pub dpr: f64,
 
-    windowed_context: WindowedContext<PossiblyCurrent>,
+    windowed_context: Replaceable<WindowedContext<PossiblyCurrent>>,
     current_mouse_cursor: CursorIcon,
     mouse_visible: bool,
 }
@@ -166,7 +167,7 @@ impl Window {
     ///
     /// This creates a window and fully initializes a window.
     pub fn new<E>(
-        event_loop: &EventLoop<E>,
+        event_loop: &EventLoopWindowTarget<E>,
         config: &Config,
         size: Option<PhysicalSize<u32>>,
         #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
@@ -221,7 +222,7 @@ impl Window {
         Ok(Self {
             current_mouse_cursor,
             mouse_visible: true,
-            windowed_context,
+            windowed_context: Replaceable::new(windowed_context),
             #[cfg(not(any(target_os = "macos", windows)))]
             should_draw: Arc::new(AtomicBool::new(true)),
             #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
@@ -417,10 +418,30 @@ impl Window {
         self.window().set_ime_position(PhysicalPosition::new(nspot_x, nspot_y));
     }
 
+    /// Make the window's OpenGL context the current one.
+    pub fn make_current(&mut self) {
+        if !self.windowed_context.is_current() {
+            self.windowed_context.replace_with(|context| unsafe {
+                context.make_current().map_err(|(_, err)| err).expect("context swap")
+            });
+        }
+    }
+
     pub fn swap_buffers(&self) {
         self.windowed_context.swap_buffers().expect("swap buffers");
     }
 
+    /// Swap buffers, only presenting the damaged regions of the window where supported.
+    ///
+    /// Without any damaged regions, the entire window is presented.
+    pub fn swap_buffers_with_damage(&self, rects: &[Rect]) {
+        if !rects.is_empty() && self.windowed_context.swap_buffers_with_damage_supported() {
+            self.windowed_context.swap_buffers_with_damage(rects).expect("swap buffers");
+        } else {
+            self.swap_buffers();
+        }
+    }
+
     pub fn resize(&self, size: PhysicalSize<u32>) {
         self.windowed_context.resize(size);
     }
@@ -446,6 +467,31 @@ impl Window {
     }
 }
 
+/// Struct for safe in-place replacement.
+///
+/// This allows replacing struct fields which only provide `self -> Self` methods in-place, without
+/// having to deal with unwrapping the underlying [`Option`] everywhere.
+struct Replaceable<T>(Option<T>);
+
+impl<T> Replaceable<T> {
+    fn new(inner: T) -> Self {
+        Self(Some(inner))
+    }
+
+    /// Replace the contents of the container.
+    fn replace_with<F: FnOnce(T) -> T>(&mut self, f: F) {
+        self.0 = self.0.take().map(f);
+    }
+}
+
+impl<T> Deref for Replaceable<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.0.as_ref().unwrap()
+    }
+}
+
 #[cfg(all(feature = "x11", not(any(target_os = "macos", windows))))]
 fn x_embed_window(window: &GlutinWindow, parent_id: std::os::raw::c_ulong) {
     let (xlib_display, xlib_window) = match (window.xlib_display(), window.xlib_window()) {
@@ -2,57 +2,66 @@
 
 use std::borrow::Cow;
 use std::cmp::{max, min};
-use std::collections::VecDeque;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::env;
-use std::f32;
+use std::error::Error;
 use std::fmt::Debug;
-#[cfg(not(any(target_os = "macos", windows)))]
-use std::fs;
-use std::fs::File;
-use std::io::Write;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
 use std::mem;
 use std::ops::RangeInclusive;
+#[cfg(not(windows))]
+use std::os::unix::io::RawFd;
 use std::path::{Path, PathBuf};
-#[cfg(not(any(target_os = "macos", windows)))]
-use std::sync::atomic::Ordering;
-use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use glutin::dpi::PhysicalSize;
-use glutin::event::{ElementState, Event as GlutinEvent, ModifiersState, MouseButton, WindowEvent};
+use glutin::event::{
+    ElementState, Event as GlutinEvent, ModifiersState, MouseButton, ScanCode, WindowEvent,
+};
 use glutin::event_loop::{ControlFlow, EventLoop, EventLoopProxy, EventLoopWindowTarget};
 use glutin::platform::run_return::EventLoopExtRunReturn;
 #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
 use glutin::platform::unix::EventLoopWindowTargetExtUnix;
-use log::info;
-use serde_json as json;
+use glutin::window::WindowId;
+#[cfg(unix)]
+use log::warn;
+use log::{error, info};
+#[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
+use wayland_client::{Display as WaylandDisplay, EventQueue};
 
 use crossfont::{self, Size};
 
-use alacritty_terminal::config::LOG_TARGET_CONFIG;
-use alacritty_terminal::event::{Event as TerminalEvent, EventListener, Notify, OnResize};
+use alacritty_terminal::event::{Event as TerminalEvent, EventListener, Notify, Record};
 use alacritty_terminal::grid::{Dimensions, Scroll};
 use alacritty_terminal::index::{Boundary, Column, Direction, Line, Point, Side};
 use alacritty_terminal::selection::{Selection, SelectionType};
-use alacritty_terminal::sync::FairMutex;
-use alacritty_terminal::term::search::{Match, RegexSearch};
+use alacritty_terminal::term::export::ExportFormat;
+use alacritty_terminal::term::search::{Match, MatchCounter, RegexSearch, SearchOptions};
 use alacritty_terminal::term::{ClipboardType, SizeInfo, Term, TermMode};
-#[cfg(not(windows))]
-use alacritty_terminal::tty;
+use alacritty_terminal::vi_mode::{ViCommand, ViCommandParser, ViMotion, ViRegister};
 
-use crate::cli::Options as CLIOptions;
+#[cfg(unix)]
+use crate::cli::SocketMessage;
+use crate::cli::{Options as CLIOptions, WindowOptions};
 use crate::clipboard::Clipboard;
 use crate::config;
+use crate::config::ui_config::Hint;
+use crate::config::window::Dimensions as WindowDimensions;
+#[cfg(unix)]
+use crate::config::Action;
 use crate::config::Config;
+#[cfg(not(windows))]
+use crate::daemon::foreground_process_path;
 use crate::daemon::start_daemon;
+use crate::display::hint::HintMatch;
 use crate::display::window::Window;
 use crate::display::{Display, DisplayUpdate};
 use crate::input::{self, ActionContext as _, FONT_SIZE_STEP};
-#[cfg(target_os = "macos")]
-use crate::macos;
-use crate::message_bar::{Message, MessageBuffer};
-use crate::scheduler::{Scheduler, TimerId};
+use crate::message_bar::{Message, MessageBuffer, MessageType};
+use crate::scheduler::{Scheduler, TimerId, Topic};
 use crate::url::{Url, Urls};
+use crate::window_context::WindowContext;
 
 /// Duration after the last user input until an unlimited search is performed.
 pub const TYPING_SEARCH_DELAY: Duration = Duration::from_millis(500);
@@ -63,9 +72,34 @@ const MAX_SEARCH_WHILE_TYPING: Option<usize> = Some(1000);
 /// Maximum number of search terms stored in the history.
 const MAX_HISTORY_SIZE: usize = 255;
 
+/// Maximum number of lines searched for the match count before processing other events.
+const MAX_SEARCH_COUNT_LINES: usize = 1000;
+
 /// Events dispatched through the UI event loop.
 #[derive(Debug, Clone)]
-pub enum Event {
+pub struct Event {
+    /// Window receiving the event, or all windows if `None`.
+    window_id: Option<WindowId>,
+
+    /// Event payload.
+    payload: EventType,
+}
+
+impl Event {
+    pub fn new<I: Into<Option<WindowId>>>(payload: EventType, window_id: I) -> Self {
+        Self { window_id: window_id.into(), payload }
+    }
+}
+
+impl From<Event> for GlutinEvent<'_, Event> {
+    fn from(event: Event) -> Self {
+        GlutinEvent::UserEvent(event)
+    }
+}
+
+/// Payload of the events dispatched through the UI event loop.
+#[derive(Debug, Clone)]
+pub enum EventType {
     TerminalEvent(TerminalEvent),
     DPRChanged(f64, (u32, u32)),
     Scroll(Scroll),
@@ -73,17 +107,15 @@ pub enum Event {
     Message(Message),
     BlinkCursor,
     SearchNext,
+    SearchCount,
+    CreateWindow(WindowOptions),
+    #[cfg(unix)]
+    Ipc(SocketMessage),
 }
 
-impl From<Event> for GlutinEvent<'_, Event> {
-    fn from(event: Event) -> Self {
-        GlutinEvent::UserEvent(event)
-    }
-}
-
-impl From<TerminalEvent> for Event {
+impl From<TerminalEvent> for EventType {
     fn from(event: TerminalEvent) -> Self {
-        Event::TerminalEvent(event)
+        EventType::TerminalEvent(event)
     }
 }
 
@@ -114,10 +146,32 @@ pub struct SearchState {
 
     /// Compiled search automatons.
     dfas: Option<RegexSearch>,
+
+    /// All matches of the active search.
+    match_counter: MatchCounter,
+
+    /// Show the list of all matches above the search bar.
+    results_visible: bool,
+
+    /// Options applied to the search regex.
+    options: SearchOptions,
+}
+
+/// Line containing a search match, shown in the search results list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    /// Buffer line of the match.
+    pub line: usize,
+
+    /// Text of the line.
+    pub text: String,
+
+    /// Whether this is the focused match.
+    pub focused: bool,
 }
 
 impl SearchState {
-    fn new() -> Self {
+    pub fn new() -> Self {
         Self::default()
     }
 
@@ -141,6 +195,61 @@ impl SearchState {
         self.dfas.as_ref()
     }
 
+    /// Position of the focused match and total number of matches, like `3/17`.
+    ///
+    /// A `+` is appended to the total while the count is still in progress.
+    pub fn match_count<T>(&self, term: &Term<T>) -> Option<String> {
+        self.dfas.as_ref()?;
+
+        let index = self
+            .focused_match
+            .as_ref()
+            .and_then(|focused_match| self.match_counter.index_of(term, focused_match))
+            .map_or_else(|| String::from("?"), |index| (index + 1).to_string());
+        let pending = if self.match_counter.is_done() { "" } else { "+" };
+
+        Some(format!("{}/{}{}", index, self.match_counter.len(), pending))
+    }
+
+    /// Options applied to the search regex.
+    pub fn options(&self) -> SearchOptions {
+        self.options
+    }
+
+    /// Discard all matches counted so far.
+    pub fn reset_match_count(&mut self) {
+        self.match_counter.reset();
+    }
+
+    /// Whether the search results list is visible.
+    pub fn results_visible(&self) -> bool {
+        self.results_visible
+    }
+
+    /// Lines of up to `max_results` matches surrounding the focused match.
+    pub fn results<T>(&self, term: &Term<T>, max_results: usize) -> Vec<SearchResult> {
+        let focused_index = self
+            .focused_match
+            .as_ref()
+            .and_then(|focused_match| self.match_counter.index_of(term, focused_match));
+
+        // Keep the focused match centered, while filling all available lines.
+        let len = self.match_counter.len();
+        let first = focused_index.map_or(0, |index| index.saturating_sub(max_results / 2));
+        let first = min(first, len.saturating_sub(max_results));
+
+        let last_column = term.cols() - 1;
+        (first..min(first + max_results, len))
+            .filter_map(|index| {
+                let line = self.match_counter.get(term, index)?.start().line;
+                let text = term
+                    .bounds_to_string(Point::new(line, Column(0)), Point::new(line, last_column));
+                let text = text.trim_end().to_owned();
+                Some(SearchResult { line, text, focused: focused_index == Some(index) })
+            })
+            .collect()
+    }
+
     /// Search regex text if a search is active.
     fn regex_mut(&mut self) -> Option<&mut String> {
         self.history_index.and_then(move |index| self.history.get_mut(index))
@@ -157,6 +266,9 @@ impl Default for SearchState {
             history: Default::default(),
             origin: Default::default(),
             dfas: Default::default(),
+            match_counter: Default::default(),
+            results_visible: Default::default(),
+            options: Default::default(),
         }
     }
 }
@@ -168,20 +280,49 @@ pub struct ActionContext<'a, N, T> {
     pub mouse: &'a mut Mouse,
     pub received_count: &'a mut usize,
     pub suppress_chars: &'a mut bool,
+    pub last_pressed_key: &'a mut Option<ScanCode>,
+    pub reported_keys: &'a mut HashSet<ScanCode>,
     pub modifiers: &'a mut ModifiersState,
     pub display: &'a mut Display,
     pub message_buffer: &'a mut MessageBuffer,
     pub display_update_pending: &'a mut DisplayUpdate,
-    pub config: &'a mut Config,
+    pub config: &'a Config,
     pub event_loop: &'a EventLoopWindowTarget<Event>,
+    pub event_proxy: &'a EventLoopProxy<Event>,
     pub scheduler: &'a mut Scheduler,
     pub search_state: &'a mut SearchState,
-    cli_options: &'a CLIOptions,
-    font_size: &'a mut Size,
-    dirty: &'a mut bool,
+    pub vi_command_parser: &'a mut ViCommandParser,
+    pub font_size: &'a mut Size,
+    pub dirty: &'a mut bool,
+    #[cfg(not(windows))]
+    pub master_fd: RawFd,
+    #[cfg(not(windows))]
+    pub shell_pid: u32,
 }
 
-impl<'a, N: Notify + 'a, T: EventListener> input::ActionContext<T> for ActionContext<'a, N, T> {
+/// Create a file in `directory` which did not exist before.
+///
+/// If a file called `name` already exists, a counter is added to the name.
+fn create_new_file(directory: &Path, name: &str, extension: &str) -> io::Result<(PathBuf, File)> {
+    let mut index = 0;
+    loop {
+        let file_name = match index {
+            0 => format!("{}.{}", name, extension),
+            index => format!("{}-{}.{}", name, index, extension),
+        };
+
+        let path = directory.join(file_name);
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => index += 1,
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+impl<'a, N: Notify + Record + 'a, T: EventListener> input::ActionContext<T>
+    for ActionContext<'a, N, T>
+{
     #[inline]
     fn write_to_pty<B: Into<Cow<'static, [u8]>>>(&mut self, val: B) {
         self.notifier.notify(val);
@@ -233,6 +374,56 @@ impl<'a, N: Notify + 'a, T: EventListener> input::ActionContext<T> for ActionCon
         }
     }
 
+    fn export_selection(&mut self, format: ExportFormat) {
+        let palette = self.display.colors.as_array();
+        if let Some(selected) = self.terminal.export_selection(format, palette) {
+            if !selected.is_empty() {
+                self.clipboard.store(ClipboardType::Clipboard, selected);
+            }
+        }
+    }
+
+    fn save_buffer(&mut self, format: ExportFormat) {
+        let extension = match format {
+            ExportFormat::Text => "txt",
+            ExportFormat::Ansi => "ansi",
+            ExportFormat::Html => "html",
+        };
+
+        // Save next to the files of the foreground process, or fallback to the home directory.
+        #[cfg(not(windows))]
+        let directory = foreground_process_path(self.master_fd, self.shell_pid).ok();
+        #[cfg(windows)]
+        let directory = None;
+        let directory = directory.or_else(dirs::home_dir).unwrap_or_else(env::temp_dir);
+
+        let timestamp =
+            SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs());
+        let name = format!("alacritty-{}", timestamp);
+
+        let buffer = self.terminal.export_buffer(format, self.display.colors.as_array());
+        let path = match create_new_file(&directory, &name, extension) {
+            Ok((path, mut file)) => match file.write_all(buffer.as_bytes()) {
+                Ok(()) => path,
+                Err(err) => {
+                    error!("Unable to save terminal buffer to {:?}: {}", path, err);
+                    return;
+                },
+            },
+            Err(err) => {
+                error!("Unable to save terminal buffer in {:?}: {}", directory, err);
+                return;
+            },
+        };
+
+        info!("Saved terminal buffer to {:?}", path);
+
+        let text = format!("Saved terminal buffer to {}", path.display());
+        self.message_buffer.push(Message::new(text, MessageType::Info));
+        self.display_update_pending.dirty = true;
+        *self.dirty = true;
+    }
+
     fn selection_is_empty(&self) -> bool {
         self.terminal.selection.as_ref().map(Selection::is_empty).unwrap_or(true)
     }
@@ -321,6 +512,16 @@ impl<'a, N: Notify + 'a, T: EventListener> input::ActionContext<T> for ActionCon
         &mut self.suppress_chars
     }
 
+    #[inline]
+    fn last_pressed_key(&mut self) -> &mut Option<ScanCode> {
+        &mut self.last_pressed_key
+    }
+
+    #[inline]
+    fn reported_keys(&mut self) -> &mut HashSet<ScanCode> {
+        &mut self.reported_keys
+    }
+
     #[inline]
     fn modifiers(&mut self) -> &mut ModifiersState {
         &mut self.modifiers
@@ -350,28 +551,16 @@ impl<'a, N: Notify + 'a, T: EventListener> input::ActionContext<T> for ActionCon
         let mut env_args = env::args();
         let alacritty = env_args.next().unwrap();
 
-        #[cfg(unix)]
+        #[cfg(not(windows))]
         let mut args = {
             // Use working directory of controlling process, or fallback to initial shell.
-            let mut pid = unsafe { libc::tcgetpgrp(tty::master_fd()) };
-            if pid < 0 {
-                pid = tty::child_pid();
-            }
-
-            #[cfg(not(any(target_os = "macos", target_os = "freebsd")))]
-            let link_path = format!("/proc/{}/cwd", pid);
-            #[cfg(target_os = "freebsd")]
-            let link_path = format!("/compat/linux/proc/{}/cwd", pid);
-            #[cfg(not(target_os = "macos"))]
-            let cwd = fs::read_link(link_path);
-            #[cfg(target_os = "macos")]
-            let cwd = macos::proc::cwd(pid);
+            let cwd = foreground_process_path(self.master_fd, self.shell_pid);
 
             // Add the current working directory as parameter.
             cwd.map(|path| vec!["--working-directory".into(), path]).unwrap_or_default()
         };
 
-        #[cfg(not(unix))]
+        #[cfg(windows)]
         let mut args: Vec<PathBuf> = Vec::new();
 
         let working_directory_set = !args.is_empty();
@@ -384,12 +573,46 @@ impl<'a, N: Notify + 'a, T: EventListener> input::ActionContext<T> for ActionCon
                 continue;
             }
 
+            // Drop session recording and playback, which only apply to the first instance.
+            if ["--record", "--play", "--speed"].contains(&arg.as_str()) {
+                let _ = env_args.next();
+                continue;
+            }
+
             args.push(arg.into());
         }
 
         start_daemon(&alacritty, &args);
     }
 
+    /// Create a new window inside the current Alacritty process.
+    fn create_new_window(&mut self) {
+        #[cfg(not(windows))]
+        let options = WindowOptions {
+            working_directory: foreground_process_path(self.master_fd, self.shell_pid).ok(),
+            ..WindowOptions::default()
+        };
+        #[cfg(windows)]
+        let options = WindowOptions::default();
+
+        let _ = self.event_proxy.send_event(Event::new(EventType::CreateWindow(options), None));
+    }
+
+    /// Start or stop recording the session to a file in the recording directory.
+    fn toggle_recording(&mut self) {
+        let recording = &self.config.ui_config.recording;
+        let directory = recording.directory();
+        if let Err(err) = fs::create_dir_all(&directory) {
+            error!("Unable to create recording directory {:?}: {}", directory, err);
+            return;
+        }
+
+        let timestamp =
+            SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs());
+        let path = directory.join(format!("alacritty-{}.cast", timestamp));
+        self.notifier.toggle_recording(path, recording.input);
+    }
+
     /// Spawn URL launcher when clicking on URLs.
     fn launch_url(&self, url: Url) {
         if self.mouse.block_url_launcher {
@@ -398,9 +621,14 @@ impl<'a, N: Notify + 'a, T: EventListener> input::ActionContext<T> for ActionCon
 
         if let Some(ref launcher) = self.config.ui_config.mouse.url.launcher {
             let mut args = launcher.args().to_vec();
-            let start = self.terminal.visible_to_buffer(url.start());
-            let end = self.terminal.visible_to_buffer(url.end());
-            args.push(self.terminal.bounds_to_string(start, end));
+            match url.hyperlink() {
+                Some(hyperlink) => args.push(hyperlink.uri().to_owned()),
+                None => {
+                    let start = self.terminal.visible_to_buffer(url.start());
+                    let end = self.terminal.visible_to_buffer(url.end());
+                    args.push(self.terminal.bounds_to_string(start, end));
+                },
+            }
 
             start_daemon(launcher.program(), &args);
         }
@@ -449,6 +677,7 @@ impl<'a, N: Notify + 'a, T: EventListener> input::ActionContext<T> for ActionCon
 
         // Store original search position as origin and reset location.
         if self.terminal.mode().contains(TermMode::VI) {
+            self.terminal.vi_record_jump();
             self.search_state.origin = self.terminal.vi_mode_cursor.point;
             self.search_state.display_offset_delta = 0;
         } else {
@@ -473,7 +702,8 @@ impl<'a, N: Notify + 'a, T: EventListener> input::ActionContext<T> for ActionCon
         }
 
         // Force unlimited search if the previous one was interrupted.
-        if self.scheduler.scheduled(TimerId::DelayedSearch) {
+        let timer_id = TimerId::new(Topic::DelayedSearch, self.display.window.window_id());
+        if self.scheduler.scheduled(timer_id) {
             self.goto_match(None);
         }
 
@@ -622,10 +852,37 @@ impl<'a, N: Notify + 'a, T: EventListener> input::ActionContext<T> for ActionCon
         direction: Direction,
         side: Side,
     ) -> Option<Match> {
-        self.search_state
+        let regex_match = self
+            .search_state
             .dfas
             .as_ref()
-            .and_then(|dfas| self.terminal.search_next(dfas, origin, direction, side, None))
+            .and_then(|dfas| self.terminal.search_next(dfas, origin, direction, side, None));
+
+        // Free the disk history lines decompressed by the search.
+        self.terminal.shrink_disk_cache();
+
+        regex_match
+    }
+
+    #[inline]
+    fn toggle_search_results(&mut self) {
+        self.search_state.results_visible ^= true;
+        *self.dirty = true;
+    }
+
+    #[inline]
+    fn search_options(&self) -> SearchOptions {
+        self.search_state.options
+    }
+
+    #[inline]
+    fn set_search_options(&mut self, options: SearchOptions) {
+        self.search_state.options = options;
+
+        // Rebuild the search dfas with the new options.
+        if self.search_active() {
+            self.update_search();
+        }
     }
 
     #[inline]
@@ -648,7 +905,8 @@ impl<'a, N: Notify + 'a, T: EventListener> input::ActionContext<T> for ActionCon
     fn on_typing_start(&mut self) {
         // Disable cursor blinking.
         let blink_interval = self.config.cursor.blink_interval();
-        if let Some(timer) = self.scheduler.get_mut(TimerId::BlinkCursor) {
+        let timer_id = TimerId::new(Topic::BlinkCursor, self.display.window.window_id());
+        if let Some(timer) = self.scheduler.get_mut(timer_id) {
             timer.deadline = Instant::now() + Duration::from_millis(blink_interval);
             self.display.cursor_hidden = false;
             *self.dirty = true;
@@ -668,11 +926,57 @@ impl<'a, N: Notify + 'a, T: EventListener> input::ActionContext<T> for ActionCon
         }
 
         self.cancel_search();
+        self.vi_command_parser.reset();
         self.terminal.toggle_vi_mode();
 
         *self.dirty = true;
     }
 
+    /// Process the next character of a vi mode command.
+    fn vi_input(&mut self, c: char) {
+        let visual = !self.selection_is_empty();
+        if let Some(command) = self.vi_command_parser.advance(c, visual) {
+            self.vi_command(command);
+        }
+    }
+
+    /// Complete a pending vi mode command with a motion.
+    fn vi_input_motion(&mut self, motion: ViMotion) {
+        if let Some(command) = self.vi_command_parser.motion(motion) {
+            self.vi_command(command);
+        }
+    }
+
+    #[inline]
+    fn vi_input_pending(&self) -> bool {
+        self.terminal.mode().contains(TermMode::VI)
+            && !self.search_active()
+            && self.vi_command_parser.is_pending()
+    }
+
+    #[inline]
+    fn vi_input_expects(&self, c: Option<char>) -> bool {
+        self.vi_command_parser.expects(c)
+    }
+
+    /// Start highlighting the visible matches of a hint.
+    fn start_hint(&mut self, hint: Hint) {
+        self.display.hint_state.start(hint);
+        self.display.hint_state.update_matches(self.terminal);
+        *self.dirty = true;
+    }
+
+    /// Process a key pressed during hint selection.
+    fn hint_input(&mut self, c: char) -> Option<HintMatch> {
+        *self.dirty = true;
+        self.display.hint_state.keyboard_input(self.terminal, c)
+    }
+
+    #[inline]
+    fn hint_active(&self) -> bool {
+        self.display.hint_state.active()
+    }
+
     fn message(&self) -> Option<&Message> {
         self.message_buffer.message()
     }
@@ -698,7 +1002,7 @@ impl<'a, N: Notify + 'a, T: EventListener> input::ActionContext<T> for ActionCon
     }
 }
 
-impl<'a, N: Notify + 'a, T: EventListener> ActionContext<'a, N, T> {
+impl<'a, N: Notify + Record + 'a, T: EventListener> ActionContext<'a, N, T> {
     fn update_search(&mut self) {
         let regex = match self.search_state.regex() {
             Some(regex) => regex,
@@ -714,9 +1018,15 @@ impl<'a, N: Notify + 'a, T: EventListener> ActionContext<'a, N, T> {
             // Stop search if there's nothing to search for.
             self.search_reset_state();
             self.search_state.dfas = None;
+            self.search_state.match_counter.reset();
         } else {
             // Create search dfas for the new regex string.
-            self.search_state.dfas = RegexSearch::new(&regex).ok();
+            self.search_state.dfas =
+                RegexSearch::with_options(&regex, self.search_state.options).ok();
+
+            // Restart the match count for the new regex.
+            self.search_state.match_counter.reset();
+            self.schedule_search_count();
 
             // Update search highlighting.
             self.goto_match(MAX_SEARCH_WHILE_TYPING);
@@ -728,7 +1038,8 @@ impl<'a, N: Notify + 'a, T: EventListener> ActionContext<'a, N, T> {
     /// Reset terminal to the state before search was started.
     fn search_reset_state(&mut self) {
         // Unschedule pending timers.
-        self.scheduler.unschedule(TimerId::DelayedSearch);
+        let timer_id = TimerId::new(Topic::DelayedSearch, self.display.window.window_id());
+        self.scheduler.unschedule(timer_id);
 
         // The viewport reset logic is only needed for vi mode, since without it our origin is
         // always at the current display offset instead of at the vi cursor position which we need
@@ -763,6 +1074,9 @@ impl<'a, N: Notify + 'a, T: EventListener> ActionContext<'a, N, T> {
         // Limit search only when enough lines are available to run into the limit.
         limit = limit.filter(|&limit| limit <= self.terminal.total_lines());
 
+        let window_id = self.display.window.window_id();
+        let timer_id = TimerId::new(Topic::DelayedSearch, window_id);
+
         // Jump to the next match.
         let direction = self.search_state.direction;
         let origin = self.absolute_origin();
@@ -786,19 +1100,15 @@ impl<'a, N: Notify + 'a, T: EventListener> ActionContext<'a, N, T> {
                 self.search_state.display_offset_delta += old_offset - display_offset as isize;
 
                 // Since we found a result, we require no delayed re-search.
-                self.scheduler.unschedule(TimerId::DelayedSearch);
+                self.scheduler.unschedule(timer_id);
             },
             // Reset viewport only when we know there is no match, to prevent unnecessary jumping.
             None if limit.is_none() => self.search_reset_state(),
             None => {
                 // Schedule delayed search if we ran into our search limit.
-                if !self.scheduler.scheduled(TimerId::DelayedSearch) {
-                    self.scheduler.schedule(
-                        Event::SearchNext.into(),
-                        TYPING_SEARCH_DELAY,
-                        false,
-                        TimerId::DelayedSearch,
-                    );
+                if !self.scheduler.scheduled(timer_id) {
+                    let event = Event::new(EventType::SearchNext, window_id);
+                    self.scheduler.schedule(event, TYPING_SEARCH_DELAY, false, timer_id);
                 }
 
                 // Clear focused match.
@@ -806,6 +1116,41 @@ impl<'a, N: Notify + 'a, T: EventListener> ActionContext<'a, N, T> {
             },
         }
 
+        // Free the disk history lines decompressed by the search.
+        self.terminal.shrink_disk_cache();
+
+        *self.dirty = true;
+    }
+
+    /// Schedule the next step of the match count.
+    fn schedule_search_count(&mut self) {
+        if !self.search_active() || self.search_state.dfas.is_none() {
+            return;
+        }
+
+        let window_id = self.display.window.window_id();
+        let timer_id = TimerId::new(Topic::SearchCount, window_id);
+        if !self.scheduler.scheduled(timer_id) {
+            let event = Event::new(EventType::SearchCount, window_id);
+            self.scheduler.schedule(event, Duration::from_millis(0), false, timer_id);
+        }
+    }
+
+    /// Count the matches in the next part of the buffer.
+    fn count_search_matches(&mut self) {
+        let dfas = match &self.search_state.dfas {
+            Some(dfas) if self.search_active() => dfas,
+            _ => return,
+        };
+
+        let match_counter = &mut self.search_state.match_counter;
+        match_counter.step(self.terminal, dfas, MAX_SEARCH_COUNT_LINES);
+
+        // Continue counting after other pending events have been processed.
+        if !match_counter.is_done() {
+            self.schedule_search_count();
+        }
+
         *self.dirty = true;
     }
 
@@ -823,6 +1168,10 @@ impl<'a, N: Notify + 'a, T: EventListener> ActionContext<'a, N, T> {
         self.search_state.history_index = None;
         *self.dirty = true;
 
+        // Stop counting matches.
+        let timer_id = TimerId::new(Topic::SearchCount, self.display.window.window_id());
+        self.scheduler.unschedule(timer_id);
+
         // Clear focused match.
         self.search_state.focused_match = None;
     }
@@ -841,6 +1190,20 @@ impl<'a, N: Notify + 'a, T: EventListener> ActionContext<'a, N, T> {
         origin
     }
 
+    /// Run a vi mode command, copying yanked text to its register.
+    fn vi_command(&mut self, command: ViCommand) {
+        let register = command.register();
+        if let Some(text) = self.terminal.vi_command(command).filter(|text| !text.is_empty()) {
+            let ty = match register {
+                Some(ViRegister::Selection) => ClipboardType::Selection,
+                _ => ClipboardType::Clipboard,
+            };
+            self.clipboard.store(ty, text);
+        }
+
+        *self.dirty = true;
+    }
+
     /// Update the cursor blinking state.
     fn update_cursor_blinking(&mut self) {
         // Get config cursor style.
@@ -854,14 +1217,13 @@ impl<'a, N: Notify + 'a, T: EventListener> ActionContext<'a, N, T> {
         let blinking = cursor_style.blinking_override().unwrap_or(terminal_blinking);
 
         // Update cursor blinking state.
-        self.scheduler.unschedule(TimerId::BlinkCursor);
+        let window_id = self.display.window.window_id();
+        let timer_id = TimerId::new(Topic::BlinkCursor, window_id);
+        self.scheduler.unschedule(timer_id);
         if blinking && self.terminal.is_focused {
-            self.scheduler.schedule(
-                GlutinEvent::UserEvent(Event::BlinkCursor),
-                Duration::from_millis(self.config.cursor.blink_interval()),
-                true,
-                TimerId::BlinkCursor,
-            )
+            let event = Event::new(EventType::BlinkCursor, window_id);
+            let interval = Duration::from_millis(self.config.cursor.blink_interval());
+            self.scheduler.schedule(event, interval, true, timer_id);
         } else {
             self.display.cursor_hidden = false;
             *self.dirty = true;
@@ -921,89 +1283,61 @@ impl Default for Mouse {
 
 /// The event processor.
 ///
-/// Stores some state from received events and dispatches actions when they are
-/// triggered.
-pub struct Processor<N> {
-    notifier: N,
-    mouse: Mouse,
-    received_count: usize,
-    suppress_chars: bool,
-    modifiers: ModifiersState,
-    config: Config,
-    message_buffer: MessageBuffer,
-    display: Display,
-    font_size: Size,
-    event_queue: Vec<GlutinEvent<'static, Event>>,
-    search_state: SearchState,
+/// Owns all terminal windows and dispatches the events of the shared event loop to them.
+pub struct Processor {
+    #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
+    wayland_event_queue: Option<EventQueue>,
+    windows: HashMap<WindowId, WindowContext>,
     cli_options: CLIOptions,
-    dirty: bool,
+    config: Config,
 }
 
-impl<N: Notify + OnResize> Processor<N> {
+impl Processor {
     /// Create a new event processor.
-    ///
-    /// Takes a writer which is expected to be hooked up to the write end of a PTY.
     pub fn new(
-        notifier: N,
-        message_buffer: MessageBuffer,
         config: Config,
-        display: Display,
         cli_options: CLIOptions,
-    ) -> Processor<N> {
+        _event_loop: &EventLoop<Event>,
+    ) -> Processor {
+        // Initialize Wayland event queue, to handle Wayland callbacks.
+        #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
+        let wayland_event_queue = _event_loop.wayland_display().map(|display| {
+            let display = unsafe { WaylandDisplay::from_external_display(display as _) };
+            display.create_event_queue()
+        });
+
         Processor {
-            notifier,
-            mouse: Default::default(),
-            received_count: 0,
-            suppress_chars: false,
-            modifiers: Default::default(),
-            font_size: config.ui_config.font.size(),
-            config,
-            message_buffer,
-            display,
-            event_queue: Vec::new(),
-            search_state: SearchState::new(),
+            #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
+            wayland_event_queue,
+            windows: HashMap::new(),
             cli_options,
-            dirty: false,
+            config,
         }
     }
 
-    /// Return `true` if `event_queue` is empty, `false` otherwise.
-    #[inline]
-    #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
-    fn event_queue_empty(&mut self) -> bool {
-        let wayland_event_queue = match self.display.wayland_event_queue.as_mut() {
-            Some(wayland_event_queue) => wayland_event_queue,
-            // Since frame callbacks do not exist on X11, just check for event queue.
-            None => return self.event_queue.is_empty(),
-        };
-
-        // Check for pending frame callbacks on Wayland.
-        let events_dispatched = wayland_event_queue
-            .dispatch_pending(&mut (), |_, _, _| {})
-            .expect("failed to dispatch event queue");
-
-        self.event_queue.is_empty() && events_dispatched == 0
-    }
-
-    /// Return `true` if `event_queue` is empty, `false` otherwise.
-    #[inline]
-    #[cfg(any(not(feature = "wayland"), target_os = "macos", windows))]
-    fn event_queue_empty(&mut self) -> bool {
-        self.event_queue.is_empty()
+    /// Create a new terminal window.
+    pub fn create_window(
+        &mut self,
+        event_loop: &EventLoopWindowTarget<Event>,
+        proxy: EventLoopProxy<Event>,
+        options: WindowOptions,
+    ) -> Result<(), Box<dyn Error>> {
+        let window_context = WindowContext::new(
+            &self.config,
+            &options,
+            event_loop,
+            proxy,
+            #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
+            self.wayland_event_queue.as_ref(),
+        )?;
+        self.windows.insert(window_context.id(), window_context);
+        Ok(())
     }
 
     /// Run the event loop.
-    pub fn run<T>(&mut self, terminal: Arc<FairMutex<Term<T>>>, mut event_loop: EventLoop<Event>)
-    where
-        T: EventListener,
-    {
-        let mut scheduler = Scheduler::new();
-
-        // Start the initial cursor blinking timer.
-        if self.config.cursor.style().blinking {
-            let event: Event = TerminalEvent::CursorBlinkingChange(true).into();
-            self.event_queue.push(event.into());
-        }
+    pub fn run(&mut self, mut event_loop: EventLoop<Event>) {
+        let proxy = event_loop.create_proxy();
+        let mut scheduler = Scheduler::new(proxy.clone());
 
         // NOTE: Since this takes a pointer to the winit event loop, it MUST be dropped first.
         #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
@@ -1022,198 +1356,308 @@ impl<N: Notify + OnResize> Processor<N> {
             }
 
             match event {
-                // Check for shutdown.
-                GlutinEvent::UserEvent(Event::TerminalEvent(TerminalEvent::Exit)) => {
-                    *control_flow = ControlFlow::Exit;
-                    return;
+                // Check for window shutdown.
+                GlutinEvent::UserEvent(Event {
+                    window_id: Some(window_id),
+                    payload: EventType::TerminalEvent(TerminalEvent::Exit),
+                }) => {
+                    // Remove the closed terminal.
+                    let window_context = match self.windows.remove(&window_id) {
+                        Some(window_context) => window_context,
+                        None => return,
+                    };
+
+                    // Unschedule pending events.
+                    scheduler.unschedule_window(window_id);
+
+                    // Write ref tests to disk.
+                    if self.config.ui_config.debug.ref_test {
+                        window_context.write_ref_test_results();
+                    }
+
+                    // Store scrollback history for the next terminal.
+                    window_context.persist_history();
+
+                    // Shutdown once all windows are closed.
+                    if self.windows.is_empty() {
+                        *control_flow = ControlFlow::Exit;
+                    }
                 },
-                // Process events.
+                // Process all pending events.
                 GlutinEvent::RedrawEventsCleared => {
-                    *control_flow = match scheduler.update(&mut self.event_queue) {
+                    *control_flow = match scheduler.update() {
                         Some(instant) => ControlFlow::WaitUntil(instant),
                         None => ControlFlow::Wait,
                     };
 
-                    if self.event_queue_empty() {
-                        return;
+                    // Check for pending frame callbacks on Wayland.
+                    #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
+                    if let Some(wayland_event_queue) = self.wayland_event_queue.as_mut() {
+                        wayland_event_queue
+                            .dispatch_pending(&mut (), |_, _, _| {})
+                            .expect("failed to dispatch event queue");
+                    }
+
+                    // Dispatch the queued events of every window.
+                    for window_context in self.windows.values_mut() {
+                        window_context.handle_event(
+                            event_loop,
+                            &proxy,
+                            &self.config,
+                            &mut clipboard,
+                            &mut scheduler,
+                            control_flow,
+                            GlutinEvent::RedrawEventsCleared,
+                        );
                     }
                 },
-                // Remap DPR change event to remove lifetime.
-                GlutinEvent::WindowEvent {
-                    event: WindowEvent::ScaleFactorChanged { scale_factor, new_inner_size },
-                    ..
-                } => {
+                // Reload the configuration of all windows.
+                GlutinEvent::UserEvent(Event {
+                    payload: EventType::ConfigReload(path), ..
+                }) => {
+                    self.reload_config(Some(&path));
+                },
+                // Create a new terminal window.
+                GlutinEvent::UserEvent(Event {
+                    payload: EventType::CreateWindow(options), ..
+                }) => {
+                    if let Err(err) = self.create_window(event_loop, proxy.clone(), options) {
+                        error!("Could not create window: {}", err);
+                    }
+                },
+                // Handle messages received through the IPC socket.
+                #[cfg(unix)]
+                GlutinEvent::UserEvent(Event {
+                    window_id: None,
+                    payload: EventType::Ipc(message),
+                }) => {
+                    self.handle_ipc_message(message, event_loop, &proxy);
+                },
+                // Broadcast events without a target window to all windows.
+                GlutinEvent::UserEvent(event @ Event { window_id: None, .. }) => {
                     *control_flow = ControlFlow::Poll;
-                    let size = (new_inner_size.width, new_inner_size.height);
-                    self.event_queue.push(Event::DPRChanged(scale_factor, size).into());
-                    return;
+                    for window_context in self.windows.values_mut() {
+                        window_context.handle_event(
+                            event_loop,
+                            &proxy,
+                            &self.config,
+                            &mut clipboard,
+                            &mut scheduler,
+                            control_flow,
+                            event.clone().into(),
+                        );
+                    }
                 },
-                // Transmute to extend lifetime, which exists only for `ScaleFactorChanged` event.
-                // Since we remap that event to remove the lifetime, this is safe.
-                event => unsafe {
+                // Route window specific events to their window.
+                GlutinEvent::WindowEvent { window_id, .. }
+                | GlutinEvent::UserEvent(Event { window_id: Some(window_id), .. })
+                | GlutinEvent::RedrawRequested(window_id) => {
                     *control_flow = ControlFlow::Poll;
-                    self.event_queue.push(mem::transmute(event));
-                    return;
+                    if let Some(window_context) = self.windows.get_mut(&window_id) {
+                        window_context.handle_event(
+                            event_loop,
+                            &proxy,
+                            &self.config,
+                            &mut clipboard,
+                            &mut scheduler,
+                            control_flow,
+                            event,
+                        );
+                    }
                 },
+                _ => (),
             }
+        });
+    }
 
-            let mut terminal = terminal.lock();
-
-            let mut display_update_pending = DisplayUpdate::default();
-            let old_is_searching = self.search_state.history_index.is_some();
-
-            let context = ActionContext {
-                terminal: &mut terminal,
-                notifier: &mut self.notifier,
-                mouse: &mut self.mouse,
-                clipboard: &mut clipboard,
-                received_count: &mut self.received_count,
-                suppress_chars: &mut self.suppress_chars,
-                modifiers: &mut self.modifiers,
-                message_buffer: &mut self.message_buffer,
-                display_update_pending: &mut display_update_pending,
-                display: &mut self.display,
-                font_size: &mut self.font_size,
-                config: &mut self.config,
-                scheduler: &mut scheduler,
-                search_state: &mut self.search_state,
-                cli_options: &self.cli_options,
-                dirty: &mut self.dirty,
-                event_loop,
-            };
-            let mut processor = input::Processor::new(context);
-
-            for event in self.event_queue.drain(..) {
-                Processor::handle_event(event, &mut processor);
-            }
-
-            // Process DisplayUpdate events.
-            if display_update_pending.dirty {
-                self.submit_display_update(&mut terminal, old_is_searching, display_update_pending);
-            }
+    /// Check if an event is irrelevant and can be skipped.
+    fn skip_event(event: &GlutinEvent<'_, Event>) -> bool {
+        match event {
+            GlutinEvent::WindowEvent { event, .. } => matches!(
+                event,
+                WindowEvent::KeyboardInput { is_synthetic: true, .. }
+                    | WindowEvent::TouchpadPressure { .. }
+                    | WindowEvent::CursorEntered { .. }
+                    | WindowEvent::AxisMotion { .. }
+                    | WindowEvent::HoveredFileCancelled
+                    | WindowEvent::Destroyed
+                    | WindowEvent::HoveredFile(_)
+                    | WindowEvent::Touch(_)
+                    | WindowEvent::Moved(_)
+            ),
+            GlutinEvent::Suspended { .. }
+            | GlutinEvent::NewEvents { .. }
+            | GlutinEvent::MainEventsCleared
+            | GlutinEvent::LoopDestroyed => true,
+            _ => false,
+        }
+    }
 
-            // Skip rendering on Wayland until we get frame event from compositor.
-            #[cfg(not(any(target_os = "macos", windows)))]
-            if !self.display.is_x11 && !self.display.window.should_draw.load(Ordering::Relaxed) {
-                return;
-            }
+    /// Handle a message received through the IPC socket.
+    #[cfg(unix)]
+    fn handle_ipc_message(
+        &mut self,
+        message: SocketMessage,
+        event_loop: &EventLoopWindowTarget<Event>,
+        proxy: &EventLoopProxy<Event>,
+    ) {
+        match message {
+            SocketMessage::CreateWindow(options) => {
+                if let Err(err) = self.create_window(event_loop, proxy.clone(), options) {
+                    error!("Could not create window: {}", err);
+                }
+            },
+            SocketMessage::Config(ipc_config) => {
+                self.cli_options.update_ipc_config(&ipc_config);
 
-            if self.dirty {
-                self.dirty = false;
+                let path = self.config.ui_config.config_paths.first().cloned();
+                self.reload_config(path.as_deref());
+            },
+            SocketMessage::Action(name) => {
+                // Run actions in the focused window, or any window if none is focused.
+                let window_id = self
+                    .windows
+                    .values()
+                    .find(|window_context| window_context.is_focused())
+                    .or_else(|| self.windows.values().next())
+                    .map(WindowContext::id);
+
+                if let Some(window_id) = window_id {
+                    let event = Event::new(EventType::Ipc(SocketMessage::Action(name)), window_id);
+                    let _ = proxy.send_event(event);
+                }
+            },
+        }
+    }
 
-                // Request immediate re-draw if visual bell animation is not finished yet.
-                if !self.display.visual_bell.completed() {
-                    let event: Event = TerminalEvent::Wakeup.into();
-                    self.event_queue.push(event.into());
+    /// Reload the configuration files from disk and apply them to all windows.
+    ///
+    /// Without a configuration file, only the config overrides are applied to the defaults.
+    fn reload_config(&mut self, path: Option<&Path>) {
+        // Clear config logs from the message bar of all windows.
+        for window_context in self.windows.values_mut() {
+            window_context.clear_config_logs();
+        }
 
-                    *control_flow = ControlFlow::Poll;
-                }
+        let config = match path {
+            Some(path) => match config::reload(path, &self.cli_options) {
+                Ok(config) => config,
+                Err(_) => return,
+            },
+            None => config::load(&self.cli_options),
+        };
 
-                // Redraw screen.
-                self.display.draw(
-                    terminal,
-                    &self.message_buffer,
-                    &self.config,
-                    &self.mouse,
-                    self.modifiers,
-                    &self.search_state,
-                );
-            }
-        });
+        // Set subpixel anti-aliasing.
+        #[cfg(target_os = "macos")]
+        crossfont::set_font_smoothing(config.ui_config.font.use_thin_strokes);
 
-        // Write ref tests to disk.
-        if self.config.ui_config.debug.ref_test {
-            self.write_ref_test_results(&terminal.lock());
+        for window_context in self.windows.values_mut() {
+            window_context.update_config(&self.config, &config);
         }
+
+        self.config = config;
     }
+}
 
+impl<'a, N: Notify + Record + 'a, T: EventListener> input::Processor<T, ActionContext<'a, N, T>> {
     /// Handle events from glutin.
-    ///
-    /// Doesn't take self mutably due to borrow checking.
-    fn handle_event<T>(
-        event: GlutinEvent<'_, Event>,
-        processor: &mut input::Processor<T, ActionContext<'_, N, T>>,
-    ) where
-        T: EventListener,
-    {
+    pub fn handle_event(&mut self, event: GlutinEvent<'_, Event>) {
         match event {
-            GlutinEvent::UserEvent(event) => match event {
-                Event::DPRChanged(scale_factor, (width, height)) => {
-                    let display_update_pending = &mut processor.ctx.display_update_pending;
+            GlutinEvent::UserEvent(Event { payload, .. }) => match payload {
+                EventType::DPRChanged(scale_factor, (width, height)) => {
+                    let display_update_pending = &mut self.ctx.display_update_pending;
 
                     // Push current font to update its DPR.
-                    let font = processor.ctx.config.ui_config.font.clone();
-                    display_update_pending.set_font(font.with_size(*processor.ctx.font_size));
+                    let font = self.ctx.config.ui_config.font.clone();
+                    display_update_pending.set_font(font.with_size(*self.ctx.font_size));
 
                     // Resize to event's dimensions, since no resize event is emitted on Wayland.
                     display_update_pending.set_dimensions(PhysicalSize::new(width, height));
 
-                    processor.ctx.window_mut().dpr = scale_factor;
-                    *processor.ctx.dirty = true;
+                    self.ctx.window_mut().dpr = scale_factor;
+                    *self.ctx.dirty = true;
                 },
-                Event::Message(message) => {
-                    processor.ctx.message_buffer.push(message);
-                    processor.ctx.display_update_pending.dirty = true;
-                    *processor.ctx.dirty = true;
+                EventType::Message(message) => {
+                    self.ctx.message_buffer.push(message);
+                    self.ctx.display_update_pending.dirty = true;
+                    *self.ctx.dirty = true;
                 },
-                Event::SearchNext => processor.ctx.goto_match(None),
-                Event::ConfigReload(path) => Self::reload_config(&path, processor),
-                Event::Scroll(scroll) => processor.ctx.scroll(scroll),
-                Event::BlinkCursor => {
-                    processor.ctx.display.cursor_hidden ^= true;
-                    *processor.ctx.dirty = true;
+                EventType::SearchNext => self.ctx.goto_match(None),
+                EventType::SearchCount => self.ctx.count_search_matches(),
+                #[cfg(unix)]
+                EventType::Ipc(SocketMessage::Action(name)) => match Action::from_name(&name) {
+                    Some(action) => self.execute_action(&action),
+                    None => warn!("Invalid IPC action: {:?}", name),
                 },
-                Event::TerminalEvent(event) => match event {
+                EventType::Scroll(scroll) => self.ctx.scroll(scroll),
+                EventType::BlinkCursor => {
+                    self.ctx.display.cursor_hidden ^= true;
+                    *self.ctx.dirty = true;
+                },
+                EventType::TerminalEvent(event) => match event {
                     TerminalEvent::Title(title) => {
-                        let ui_config = &processor.ctx.config.ui_config;
+                        let ui_config = &self.ctx.config.ui_config;
                         if ui_config.window.dynamic_title {
-                            processor.ctx.window_mut().set_title(&title);
+                            self.ctx.window_mut().set_title(&title);
                         }
                     },
                     TerminalEvent::ResetTitle => {
-                        let ui_config = &processor.ctx.config.ui_config;
+                        let ui_config = &self.ctx.config.ui_config;
                         if ui_config.window.dynamic_title {
-                            processor.ctx.display.window.set_title(&ui_config.window.title);
+                            self.ctx.display.window.set_title(&ui_config.window.title);
                         }
                     },
-                    TerminalEvent::Wakeup => *processor.ctx.dirty = true,
+                    TerminalEvent::Wakeup => {
+                        // Count matches in the new content.
+                        self.ctx.schedule_search_count();
+                        *self.ctx.dirty = true;
+                    },
                     TerminalEvent::Bell => {
                         // Set window urgency.
-                        if processor.ctx.terminal.mode().contains(TermMode::URGENCY_HINTS) {
-                            let focused = processor.ctx.terminal.is_focused;
-                            processor.ctx.window_mut().set_urgent(!focused);
+                        if self.ctx.terminal.mode().contains(TermMode::URGENCY_HINTS) {
+                            let focused = self.ctx.terminal.is_focused;
+                            self.ctx.window_mut().set_urgent(!focused);
                         }
 
                         // Ring visual bell.
-                        processor.ctx.display.visual_bell.ring();
+                        self.ctx.display.visual_bell.ring();
 
                         // Execute bell command.
-                        if let Some(bell_command) = &processor.ctx.config.ui_config.bell.command {
+                        if let Some(bell_command) = &self.ctx.config.ui_config.bell.command {
                             start_daemon(bell_command.program(), bell_command.args());
                         }
                     },
                     TerminalEvent::ClipboardStore(clipboard_type, content) => {
-                        processor.ctx.clipboard.store(clipboard_type, content);
+                        self.ctx.clipboard.store(clipboard_type, content);
                     },
                     TerminalEvent::ClipboardLoad(clipboard_type, format) => {
-                        let text = format(processor.ctx.clipboard.load(clipboard_type).as_str());
-                        processor.ctx.write_to_pty(text.into_bytes());
+                        let text = format(self.ctx.clipboard.load(clipboard_type).as_str());
+                        self.ctx.write_to_pty(text.into_bytes());
                     },
                     TerminalEvent::ColorRequest(index, format) => {
-                        let text = format(processor.ctx.display.colors[index]);
-                        processor.ctx.write_to_pty(text.into_bytes());
+                        let text = format(self.ctx.display.colors[index]);
+                        self.ctx.write_to_pty(text.into_bytes());
                     },
-                    TerminalEvent::MouseCursorDirty => processor.reset_mouse_cursor(),
+                    TerminalEvent::MouseCursorDirty => self.reset_mouse_cursor(),
                     TerminalEvent::Exit => (),
                     TerminalEvent::CursorBlinkingChange(_) => {
-                        processor.ctx.update_cursor_blinking();
+                        self.ctx.update_cursor_blinking();
+                    },
+                    TerminalEvent::ResizeRequest(columns, lines) => {
+                        let dimensions = WindowDimensions { columns, lines };
+                        let size = self.ctx.display.window_size(self.ctx.config, dimensions);
+                        self.ctx.window_mut().set_inner_size(size);
                     },
                 },
+                // Handled by the event processor for all windows.
+                EventType::ConfigReload(_) | EventType::CreateWindow(_) => (),
+                #[cfg(unix)]
+                EventType::Ipc(_) => (),
             },
-            GlutinEvent::RedrawRequested(_) => *processor.ctx.dirty = true,
+            GlutinEvent::RedrawRequested(_) => *self.ctx.dirty = true,
             GlutinEvent::WindowEvent { event, window_id, .. } => {
                 match event {
-                    WindowEvent::CloseRequested => processor.ctx.terminal.exit(),
+                    WindowEvent::CloseRequested => self.ctx.terminal.exit(),
                     WindowEvent::Resized(size) => {
                         // Minimizing the window sends a Resize event with zero width and
                         // height. But there's no need to ever actually resize to this.
@@ -1223,53 +1667,55 @@ impl<N: Notify + OnResize> Processor<N> {
                             return;
                         }
 
-                        processor.ctx.display_update_pending.set_dimensions(size);
-                        *processor.ctx.dirty = true;
+                        self.ctx.display_update_pending.set_dimensions(size);
+                        *self.ctx.dirty = true;
                     },
                     WindowEvent::KeyboardInput { input, is_synthetic: false, .. } => {
-                        processor.key_input(input);
+                        self.key_input(input);
                     },
-                    WindowEvent::ModifiersChanged(modifiers) => {
-                        processor.modifiers_input(modifiers)
-                    },
-                    WindowEvent::ReceivedCharacter(c) => processor.received_char(c),
+                    WindowEvent::ModifiersChanged(modifiers) => self.modifiers_input(modifiers),
+                    WindowEvent::ReceivedCharacter(c) => self.received_char(c),
                     WindowEvent::MouseInput { state, button, .. } => {
-                        processor.ctx.window_mut().set_mouse_visible(true);
-                        processor.mouse_input(state, button);
-                        *processor.ctx.dirty = true;
+                        self.ctx.window_mut().set_mouse_visible(true);
+                        self.mouse_input(state, button);
+                        *self.ctx.dirty = true;
                     },
                     WindowEvent::CursorMoved { position, .. } => {
-                        processor.ctx.window_mut().set_mouse_visible(true);
-                        processor.mouse_moved(position);
+                        self.ctx.window_mut().set_mouse_visible(true);
+                        self.mouse_moved(position);
                     },
                     WindowEvent::MouseWheel { delta, phase, .. } => {
-                        processor.ctx.window_mut().set_mouse_visible(true);
-                        processor.mouse_wheel_input(delta, phase);
+                        self.ctx.window_mut().set_mouse_visible(true);
+                        self.mouse_wheel_input(delta, phase);
                     },
                     WindowEvent::Focused(is_focused) => {
-                        if window_id == processor.ctx.window().window_id() {
-                            processor.ctx.terminal.is_focused = is_focused;
-                            *processor.ctx.dirty = true;
+                        if window_id == self.ctx.window().window_id() {
+                            self.ctx.terminal.is_focused = is_focused;
+                            *self.ctx.dirty = true;
+
+                            // Releases are not received while the window is unfocused.
+                            *self.ctx.last_pressed_key = None;
+                            self.ctx.reported_keys.clear();
 
                             if is_focused {
-                                processor.ctx.window_mut().set_urgent(false);
+                                self.ctx.window_mut().set_urgent(false);
                             } else {
-                                processor.ctx.window_mut().set_mouse_visible(true);
+                                self.ctx.window_mut().set_mouse_visible(true);
                             }
 
-                            processor.ctx.update_cursor_blinking();
-                            processor.on_focus_change(is_focused);
+                            self.ctx.update_cursor_blinking();
+                            self.on_focus_change(is_focused);
                         }
                     },
                     WindowEvent::DroppedFile(path) => {
                         let path: String = path.to_string_lossy().into();
-                        processor.ctx.write_to_pty((path + " ").into_bytes());
+                        self.ctx.write_to_pty((path + " ").into_bytes());
                     },
                     WindowEvent::CursorLeft { .. } => {
-                        processor.ctx.mouse.inside_text_area = false;
+                        self.ctx.mouse.inside_text_area = false;
 
-                        if processor.ctx.highlighted_url().is_some() {
-                            *processor.ctx.dirty = true;
+                        if self.ctx.highlighted_url().is_some() {
+                            *self.ctx.dirty = true;
                         }
                     },
                     WindowEvent::KeyboardInput { is_synthetic: true, .. }
@@ -1294,182 +1740,22 @@ impl<N: Notify + OnResize> Processor<N> {
             | GlutinEvent::LoopDestroyed => (),
         }
     }
-
-    /// Check if an event is irrelevant and can be skipped.
-    fn skip_event(event: &GlutinEvent<'_, Event>) -> bool {
-        match event {
-            GlutinEvent::WindowEvent { event, .. } => matches!(
-                event,
-                WindowEvent::KeyboardInput { is_synthetic: true, .. }
-                    | WindowEvent::TouchpadPressure { .. }
-                    | WindowEvent::CursorEntered { .. }
-                    | WindowEvent::AxisMotion { .. }
-                    | WindowEvent::HoveredFileCancelled
-                    | WindowEvent::Destroyed
-                    | WindowEvent::HoveredFile(_)
-                    | WindowEvent::Touch(_)
-                    | WindowEvent::Moved(_)
-            ),
-            GlutinEvent::Suspended { .. }
-            | GlutinEvent::NewEvents { .. }
-            | GlutinEvent::MainEventsCleared
-            | GlutinEvent::LoopDestroyed => true,
-            _ => false,
-        }
-    }
-
-    /// Reload the configuration files from disk.
-    fn reload_config<T>(path: &Path, processor: &mut input::Processor<T, ActionContext<'_, N, T>>)
-    where
-        T: EventListener,
-    {
-        if !processor.ctx.message_buffer.is_empty() {
-            processor.ctx.message_buffer.remove_target(LOG_TARGET_CONFIG);
-            processor.ctx.display_update_pending.dirty = true;
-        }
-
-        let config = match config::reload(&path, &processor.ctx.cli_options) {
-            Ok(config) => config,
-            Err(_) => return,
-        };
-
-        processor.ctx.display.update_config(&config);
-        processor.ctx.terminal.update_config(&config);
-
-        // Reload cursor if its thickness has changed.
-        if (processor.ctx.config.cursor.thickness() - config.cursor.thickness()).abs()
-            > f32::EPSILON
-        {
-            processor.ctx.display_update_pending.set_cursor_dirty();
-        }
-
-        if processor.ctx.config.ui_config.font != config.ui_config.font {
-            // Do not update font size if it has been changed at runtime.
-            if *processor.ctx.font_size == processor.ctx.config.ui_config.font.size() {
-                *processor.ctx.font_size = config.ui_config.font.size();
-            }
-
-            let font = config.ui_config.font.clone().with_size(*processor.ctx.font_size);
-            processor.ctx.display_update_pending.set_font(font);
-        }
-
-        // Update display if padding options were changed.
-        let window_config = &processor.ctx.config.ui_config.window;
-        if window_config.padding(1.) != config.ui_config.window.padding(1.)
-            || window_config.dynamic_padding != config.ui_config.window.dynamic_padding
-        {
-            processor.ctx.display_update_pending.dirty = true;
-        }
-
-        // Live title reload.
-        if !config.ui_config.window.dynamic_title
-            || processor.ctx.config.ui_config.window.title != config.ui_config.window.title
-        {
-            processor.ctx.window_mut().set_title(&config.ui_config.window.title);
-        }
-
-        #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
-        if processor.ctx.event_loop.is_wayland() {
-            processor.ctx.window_mut().set_wayland_theme(&config.ui_config.colors);
-        }
-
-        // Set subpixel anti-aliasing.
-        #[cfg(target_os = "macos")]
-        crossfont::set_font_smoothing(config.ui_config.font.use_thin_strokes);
-
-        // Disable shadows for transparent windows on macOS.
-        #[cfg(target_os = "macos")]
-        processor.ctx.window_mut().set_has_shadow(config.ui_config.background_opacity() >= 1.0);
-
-        *processor.ctx.config = config;
-
-        // Update cursor blinking.
-        processor.ctx.update_cursor_blinking();
-
-        *processor.ctx.dirty = true;
-    }
-
-    /// Submit the pending changes to the `Display`.
-    fn submit_display_update<T>(
-        &mut self,
-        terminal: &mut Term<T>,
-        old_is_searching: bool,
-        display_update_pending: DisplayUpdate,
-    ) where
-        T: EventListener,
-    {
-        // Compute cursor positions before resize.
-        let num_lines = terminal.screen_lines();
-        let cursor_at_bottom = terminal.grid().cursor.point.line + 1 == num_lines;
-        let origin_at_bottom = if terminal.mode().contains(TermMode::VI) {
-            terminal.vi_mode_cursor.point.line == num_lines - 1
-        } else {
-            self.search_state.direction == Direction::Left
-        };
-
-        self.display.handle_update(
-            terminal,
-            &mut self.notifier,
-            &self.message_buffer,
-            self.search_state.history_index.is_some(),
-            &self.config,
-            display_update_pending,
-        );
-
-        // Scroll to make sure search origin is visible and content moves as little as possible.
-        if !old_is_searching && self.search_state.history_index.is_some() {
-            let display_offset = terminal.grid().display_offset();
-            if display_offset == 0 && cursor_at_bottom && !origin_at_bottom {
-                terminal.scroll_display(Scroll::Delta(1));
-            } else if display_offset != 0 && origin_at_bottom {
-                terminal.scroll_display(Scroll::Delta(-1));
-            }
-        }
-    }
-
-    /// Write the ref test results to the disk.
-    fn write_ref_test_results<T>(&self, terminal: &Term<T>) {
-        // Dump grid state.
-        let mut grid = terminal.grid().clone();
-        grid.initialize_all();
-        grid.truncate();
-
-        let serialized_grid = json::to_string(&grid).expect("serialize grid");
-
-        let serialized_size = json::to_string(&self.display.size_info).expect("serialize size");
-
-        let serialized_config = format!("{{\"history_size\":{}}}", grid.history_size());
-
-        File::create("./grid.json")
-            .and_then(|mut f| f.write_all(serialized_grid.as_bytes()))
-            .expect("write grid.json");
-
-        File::create("./size.json")
-            .and_then(|mut f| f.write_all(serialized_size.as_bytes()))
-            .expect("write size.json");
-
-        File::create("./config.json")
-            .and_then(|mut f| f.write_all(serialized_config.as_bytes()))
-            .expect("write config.json");
-    }
 }
 
 #[derive(Debug, Clone)]
-pub struct EventProxy(EventLoopProxy<Event>);
+pub struct EventProxy {
+    proxy: EventLoopProxy<Event>,
+    window_id: WindowId,
+}
 
 impl EventProxy {
-    pub fn new(proxy: EventLoopProxy<Event>) -> Self {
-        EventProxy(proxy)
-    }
-
-    /// Send an event to the event loop.
-    pub fn send_event(&self, event: Event) {
-        let _ = self.0.send_event(event);
+    pub fn new(proxy: EventLoopProxy<Event>, window_id: WindowId) -> Self {
+        Self { proxy, window_id }
     }
 }
 
 impl EventListener for EventProxy {
     fn send_event(&self, event: TerminalEvent) {
-        let _ = self.0.send_event(Event::TerminalEvent(event));
+        let _ = self.proxy.send_event(Event::new(event.into(), self.window_id));
     }
 }
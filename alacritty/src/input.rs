@@ -7,6 +7,7 @@
 
 use std::borrow::Cow;
 use std::cmp::{max, min, Ordering};
+use std::collections::HashSet;
 use std::marker::PhantomData;
 use std::time::{Duration, Instant};
 
@@ -14,29 +15,33 @@ use log::trace;
 
 use glutin::dpi::PhysicalPosition;
 use glutin::event::{
-    ElementState, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta, TouchPhase,
+    ElementState, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta, ScanCode,
+    TouchPhase, VirtualKeyCode,
 };
 use glutin::event_loop::EventLoopWindowTarget;
 #[cfg(target_os = "macos")]
 use glutin::platform::macos::EventLoopWindowTargetExtMacOS;
 use glutin::window::CursorIcon;
 
-use alacritty_terminal::ansi::{ClearMode, Handler};
+use alacritty_terminal::ansi::{ClearMode, Handler, KeyboardModes};
 use alacritty_terminal::event::EventListener;
 use alacritty_terminal::grid::{Dimensions, Scroll};
 use alacritty_terminal::index::{Boundary, Column, Direction, Line, Point, Side};
-use alacritty_terminal::selection::SelectionType;
-use alacritty_terminal::term::search::Match;
+use alacritty_terminal::selection::{Selection, SelectionMotion, SelectionType};
+use alacritty_terminal::term::export::ExportFormat;
+use alacritty_terminal::term::search::{Match, SearchCase, SearchOptions};
 use alacritty_terminal::term::{ClipboardType, SizeInfo, Term, TermMode};
 use alacritty_terminal::vi_mode::ViMotion;
 
 use crate::clipboard::Clipboard;
+use crate::config::ui_config::{Hint, HintAction, HintInternalAction};
 use crate::config::{Action, Binding, BindingMode, Config, Key, SearchAction, ViAction};
 use crate::daemon::start_daemon;
+use crate::display::hint::HintMatch;
 use crate::display::window::Window;
-use crate::event::{ClickState, Event, Mouse, TYPING_SEARCH_DELAY};
+use crate::event::{ClickState, Event, EventType, Mouse, TYPING_SEARCH_DELAY};
 use crate::message_bar::{self, Message};
-use crate::scheduler::{Scheduler, TimerId};
+use crate::scheduler::{Scheduler, TimerId, Topic};
 use crate::url::{Url, Urls};
 
 /// Font size change interval.
@@ -65,6 +70,8 @@ pub trait ActionContext<T: EventListener> {
     fn mark_dirty(&mut self) {}
     fn size_info(&self) -> SizeInfo;
     fn copy_selection(&mut self, _ty: ClipboardType) {}
+    fn export_selection(&mut self, _format: ExportFormat) {}
+    fn save_buffer(&mut self, _format: ExportFormat) {}
     fn start_selection(&mut self, _ty: SelectionType, _point: Point, _side: Side) {}
     fn toggle_selection(&mut self, _ty: SelectionType, _point: Point, _side: Side) {}
     fn update_selection(&mut self, _point: Point, _side: Side) {}
@@ -75,6 +82,8 @@ pub trait ActionContext<T: EventListener> {
     fn mouse_coords(&self) -> Option<Point>;
     fn received_count(&mut self) -> &mut usize;
     fn suppress_chars(&mut self) -> &mut bool;
+    fn last_pressed_key(&mut self) -> &mut Option<ScanCode>;
+    fn reported_keys(&mut self) -> &mut HashSet<ScanCode>;
     fn modifiers(&mut self) -> &mut ModifiersState;
     fn scroll(&mut self, _scroll: Scroll) {}
     fn window(&self) -> &Window;
@@ -82,6 +91,8 @@ pub trait ActionContext<T: EventListener> {
     fn terminal(&self) -> &Term<T>;
     fn terminal_mut(&mut self) -> &mut Term<T>;
     fn spawn_new_instance(&mut self) {}
+    fn create_new_window(&mut self) {}
+    fn toggle_recording(&mut self) {}
     fn change_font_size(&mut self, _delta: f32) {}
     fn reset_font_size(&mut self) {}
     fn pop_message(&mut self) {}
@@ -108,10 +119,26 @@ pub trait ActionContext<T: EventListener> {
         side: Side,
     ) -> Option<Match>;
     fn advance_search_origin(&mut self, _direction: Direction) {}
+    fn toggle_search_results(&mut self) {}
+    fn search_options(&self) -> SearchOptions;
+    fn set_search_options(&mut self, _options: SearchOptions) {}
     fn search_direction(&self) -> Direction;
     fn search_active(&self) -> bool;
     fn on_typing_start(&mut self) {}
     fn toggle_vi_mode(&mut self) {}
+    fn vi_input(&mut self, _c: char) {}
+    fn vi_input_motion(&mut self, _motion: ViMotion) {}
+    fn vi_input_pending(&self) -> bool {
+        false
+    }
+    fn vi_input_expects(&self, _c: Option<char>) -> bool {
+        false
+    }
+    fn start_hint(&mut self, _hint: Hint) {}
+    fn hint_input(&mut self, _c: char) -> Option<HintMatch> {
+        None
+    }
+    fn hint_active(&self) -> bool;
 }
 
 trait Execute<T: EventListener> {
@@ -160,10 +187,15 @@ impl<T: EventListener> Execute<T> for Action {
 
                 start_daemon(program, args);
             },
+            Action::Hint(ref hint) => ctx.start_hint(hint.clone()),
             Action::ToggleViMode => ctx.toggle_vi_mode(),
             Action::ViMotion(motion) => {
                 ctx.on_typing_start();
-                ctx.terminal_mut().vi_motion(motion);
+                if ctx.vi_input_pending() {
+                    ctx.vi_input_motion(motion);
+                } else {
+                    ctx.terminal_mut().vi_motion(motion);
+                }
                 ctx.mark_dirty();
             },
             Action::ViAction(ViAction::ToggleNormalSelection) => {
@@ -178,6 +210,18 @@ impl<T: EventListener> Execute<T> for Action {
             Action::ViAction(ViAction::ToggleSemanticSelection) => {
                 Self::toggle_selection(ctx, SelectionType::Semantic)
             },
+            Action::ViAction(ViAction::Yank) => {
+                ctx.on_typing_start();
+                ctx.vi_input('y');
+            },
+            Action::ViAction(ViAction::JumpOlder) => {
+                ctx.terminal_mut().vi_jump_older();
+                ctx.mark_dirty();
+            },
+            Action::ViAction(ViAction::JumpNewer) => {
+                ctx.terminal_mut().vi_jump_newer();
+                ctx.mark_dirty();
+            },
             Action::ViAction(ViAction::Open) => {
                 ctx.mouse_mut().block_url_launcher = false;
                 if let Some(url) = ctx.urls().find_at(ctx.terminal().vi_mode_cursor.point) {
@@ -194,7 +238,7 @@ impl<T: EventListener> Execute<T> for Action {
                 };
 
                 if let Some(regex_match) = ctx.search_next(origin, direction, Side::Left) {
-                    ctx.terminal_mut().vi_goto_point(*regex_match.start());
+                    ctx.terminal_mut().vi_jump(*regex_match.start());
                     ctx.mark_dirty();
                 }
             },
@@ -208,7 +252,7 @@ impl<T: EventListener> Execute<T> for Action {
                 };
 
                 if let Some(regex_match) = ctx.search_next(origin, direction, Side::Left) {
-                    ctx.terminal_mut().vi_goto_point(*regex_match.start());
+                    ctx.terminal_mut().vi_jump(*regex_match.start());
                     ctx.mark_dirty();
                 }
             },
@@ -219,7 +263,7 @@ impl<T: EventListener> Execute<T> for Action {
                     .sub_absolute(terminal, Boundary::Wrap, 1);
 
                 if let Some(regex_match) = ctx.search_next(origin, Direction::Left, Side::Left) {
-                    ctx.terminal_mut().vi_goto_point(*regex_match.start());
+                    ctx.terminal_mut().vi_jump(*regex_match.start());
                     ctx.mark_dirty();
                 }
             },
@@ -230,7 +274,7 @@ impl<T: EventListener> Execute<T> for Action {
                     .add_absolute(terminal, Boundary::Wrap, 1);
 
                 if let Some(regex_match) = ctx.search_next(origin, Direction::Right, Side::Right) {
-                    ctx.terminal_mut().vi_goto_point(*regex_match.end());
+                    ctx.terminal_mut().vi_jump(*regex_match.end());
                     ctx.mark_dirty();
                 }
             },
@@ -253,12 +297,59 @@ impl<T: EventListener> Execute<T> for Action {
                 ctx.search_history_previous()
             },
             Action::SearchAction(SearchAction::SearchHistoryNext) => ctx.search_history_next(),
+            Action::SearchAction(SearchAction::SearchToggleResults) => ctx.toggle_search_results(),
+            Action::SearchAction(SearchAction::SearchToggleLiteral) => {
+                let mut options = ctx.search_options();
+                options.literal ^= true;
+                ctx.set_search_options(options);
+            },
+            Action::SearchAction(SearchAction::SearchToggleCaseSensitive) => {
+                toggle_search_case(ctx, SearchCase::Sensitive)
+            },
+            Action::SearchAction(SearchAction::SearchToggleCaseInsensitive) => {
+                toggle_search_case(ctx, SearchCase::Insensitive)
+            },
+            Action::SearchAction(SearchAction::SearchToggleWholeWord) => {
+                let mut options = ctx.search_options();
+                options.whole_word ^= true;
+                ctx.set_search_options(options);
+            },
             Action::SearchForward => ctx.start_search(Direction::Right),
             Action::SearchBackward => ctx.start_search(Direction::Left),
             Action::Copy => ctx.copy_selection(ClipboardType::Clipboard),
+            Action::CopyAsHtml => ctx.export_selection(ExportFormat::Html),
+            Action::CopyAsAnsi => ctx.export_selection(ExportFormat::Ansi),
             #[cfg(not(any(target_os = "macos", windows)))]
             Action::CopySelection => ctx.copy_selection(ClipboardType::Selection),
             Action::ClearSelection => ctx.clear_selection(),
+            Action::SelectLeft => {
+                extend_selection(ctx, SelectionType::Simple, SelectionMotion::Left)
+            },
+            Action::SelectRight => {
+                extend_selection(ctx, SelectionType::Simple, SelectionMotion::Right)
+            },
+            Action::SelectUp => extend_selection(ctx, SelectionType::Simple, SelectionMotion::Up),
+            Action::SelectDown => {
+                extend_selection(ctx, SelectionType::Simple, SelectionMotion::Down)
+            },
+            Action::SelectSemanticLeft => {
+                extend_selection(ctx, SelectionType::Simple, SelectionMotion::SemanticLeft)
+            },
+            Action::SelectSemanticRight => {
+                extend_selection(ctx, SelectionType::Simple, SelectionMotion::SemanticRight)
+            },
+            Action::SelectBlockLeft => {
+                extend_selection(ctx, SelectionType::Block, SelectionMotion::Left)
+            },
+            Action::SelectBlockRight => {
+                extend_selection(ctx, SelectionType::Block, SelectionMotion::Right)
+            },
+            Action::SelectBlockUp => {
+                extend_selection(ctx, SelectionType::Block, SelectionMotion::Up)
+            },
+            Action::SelectBlockDown => {
+                extend_selection(ctx, SelectionType::Block, SelectionMotion::Down)
+            },
             Action::Paste => {
                 let text = ctx.clipboard_mut().load(ClipboardType::Clipboard);
                 paste(ctx, &text);
@@ -333,6 +424,7 @@ impl<T: EventListener> Execute<T> for Action {
                 ctx.scroll(Scroll::Delta(-1));
             },
             Action::ScrollToTop => {
+                ctx.terminal_mut().vi_record_jump();
                 ctx.scroll(Scroll::Top);
 
                 // Move vi mode cursor.
@@ -341,6 +433,7 @@ impl<T: EventListener> Execute<T> for Action {
                 ctx.mark_dirty();
             },
             Action::ScrollToBottom => {
+                ctx.terminal_mut().vi_record_jump();
                 ctx.scroll(Scroll::Bottom);
 
                 // Move vi mode cursor.
@@ -352,14 +445,81 @@ impl<T: EventListener> Execute<T> for Action {
                 term.vi_motion(ViMotion::FirstOccupied);
                 ctx.mark_dirty();
             },
+            Action::ScrollToPreviousPrompt => {
+                let term = ctx.terminal();
+                let top = term.grid().display_offset() + term.screen_lines().0 - 1;
+                if let Some(line) = term.prompt_search(top, Direction::Left) {
+                    ctx.scroll(Scroll::Delta((line - top) as isize));
+                }
+            },
+            Action::ScrollToNextPrompt => {
+                let term = ctx.terminal();
+                let top = term.grid().display_offset() + term.screen_lines().0 - 1;
+                match term.prompt_search(top, Direction::Right) {
+                    Some(line) => ctx.scroll(Scroll::Delta(-((top - line) as isize))),
+                    None => ctx.scroll(Scroll::Bottom),
+                }
+            },
+            Action::SelectCommandOutput => {
+                let term = ctx.terminal();
+                let output = if term.mode().contains(TermMode::VI) {
+                    let vi_point = term.visible_to_buffer(term.vi_mode_cursor.point);
+                    term.command_output(vi_point.line)
+                } else {
+                    term.last_command_output()
+                };
+
+                if let Some(output) = output {
+                    let mut selection =
+                        Selection::new(SelectionType::Lines, *output.start(), Side::Left);
+                    selection.update(*output.end(), Side::Right);
+                    ctx.terminal_mut().selection = Some(selection);
+                    ctx.mark_dirty();
+                }
+            },
+            Action::CopyLastCommandOutput => {
+                let term = ctx.terminal();
+                if let Some(output) = term.last_command_output() {
+                    let text = term.bounds_to_string(*output.start(), *output.end());
+                    ctx.clipboard_mut().store(ClipboardType::Clipboard, text);
+                }
+            },
             Action::ClearHistory => ctx.terminal_mut().clear_screen(ClearMode::Saved),
+            Action::SaveBufferAsText => ctx.save_buffer(ExportFormat::Text),
+            Action::SaveBufferAsAnsi => ctx.save_buffer(ExportFormat::Ansi),
+            Action::SaveBufferAsHtml => ctx.save_buffer(ExportFormat::Html),
             Action::ClearLogNotice => ctx.pop_message(),
             Action::SpawnNewInstance => ctx.spawn_new_instance(),
+            Action::CreateNewWindow => ctx.create_new_window(),
+            Action::ToggleRecording => ctx.toggle_recording(),
             Action::ReceiveChar | Action::None => (),
         }
     }
 }
 
+/// Extend the selection using a keyboard motion, starting a new one at the cursor if necessary.
+fn extend_selection<T: EventListener, A: ActionContext<T>>(
+    ctx: &mut A,
+    ty: SelectionType,
+    motion: SelectionMotion,
+) {
+    ctx.terminal_mut().extend_selection(ty, motion);
+
+    if ctx.config().selection.save_to_clipboard {
+        ctx.copy_selection(ClipboardType::Clipboard);
+    }
+    ctx.copy_selection(ClipboardType::Selection);
+
+    ctx.mark_dirty();
+}
+
+/// Force the search case sensitivity, returning to smart case if it is already active.
+fn toggle_search_case<T: EventListener, A: ActionContext<T>>(ctx: &mut A, case: SearchCase) {
+    let mut options = ctx.search_options();
+    options.case = if options.case == case { SearchCase::Smart } else { case };
+    ctx.set_search_options(options);
+}
+
 fn paste<T: EventListener, A: ActionContext<T>>(ctx: &mut A, contents: &str) {
     if ctx.search_active() {
         for c in contents.chars() {
@@ -380,6 +540,320 @@ fn paste<T: EventListener, A: ActionContext<T>>(ctx: &mut A, contents: &str) {
     }
 }
 
+/// Type of a key event in the kitty keyboard protocol.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum KittyEvent {
+    Press,
+    Repeat,
+    Release,
+}
+
+/// Encoding of a key in the kitty keyboard protocol.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum KittyKey {
+    /// Key producing text, identified by its unshifted codepoint.
+    Text(u32),
+
+    /// Enter, Tab and Backspace, which keep their legacy encoding without modifiers.
+    Legacy(u32, &'static str),
+
+    /// Key which is always reported with its `CSI u` code.
+    Code(u32),
+
+    /// Modifier key, which is only reported when all keys are reported as escape codes.
+    Modifier(u32),
+
+    /// Functional key using the `CSI number ~` encoding.
+    Tilde(u32),
+
+    /// Functional key using the `CSI 1 letter` encoding.
+    Letter(char),
+}
+
+impl KittyKey {
+    fn new(key: VirtualKeyCode) -> Option<Self> {
+        use glutin::event::VirtualKeyCode::*;
+
+        let key = match key {
+            A => KittyKey::Text('a' as u32),
+            B => KittyKey::Text('b' as u32),
+            C => KittyKey::Text('c' as u32),
+            D => KittyKey::Text('d' as u32),
+            E => KittyKey::Text('e' as u32),
+            F => KittyKey::Text('f' as u32),
+            G => KittyKey::Text('g' as u32),
+            H => KittyKey::Text('h' as u32),
+            I => KittyKey::Text('i' as u32),
+            J => KittyKey::Text('j' as u32),
+            K => KittyKey::Text('k' as u32),
+            L => KittyKey::Text('l' as u32),
+            M => KittyKey::Text('m' as u32),
+            N => KittyKey::Text('n' as u32),
+            O => KittyKey::Text('o' as u32),
+            P => KittyKey::Text('p' as u32),
+            Q => KittyKey::Text('q' as u32),
+            R => KittyKey::Text('r' as u32),
+            S => KittyKey::Text('s' as u32),
+            T => KittyKey::Text('t' as u32),
+            U => KittyKey::Text('u' as u32),
+            V => KittyKey::Text('v' as u32),
+            W => KittyKey::Text('w' as u32),
+            X => KittyKey::Text('x' as u32),
+            Y => KittyKey::Text('y' as u32),
+            Z => KittyKey::Text('z' as u32),
+            Key0 => KittyKey::Text('0' as u32),
+            Key1 => KittyKey::Text('1' as u32),
+            Key2 => KittyKey::Text('2' as u32),
+            Key3 => KittyKey::Text('3' as u32),
+            Key4 => KittyKey::Text('4' as u32),
+            Key5 => KittyKey::Text('5' as u32),
+            Key6 => KittyKey::Text('6' as u32),
+            Key7 => KittyKey::Text('7' as u32),
+            Key8 => KittyKey::Text('8' as u32),
+            Key9 => KittyKey::Text('9' as u32),
+            Space => KittyKey::Text(' ' as u32),
+            Apostrophe => KittyKey::Text('\'' as u32),
+            Asterisk => KittyKey::Text('*' as u32),
+            At => KittyKey::Text('@' as u32),
+            Backslash => KittyKey::Text('\\' as u32),
+            Caret => KittyKey::Text('^' as u32),
+            Colon => KittyKey::Text(':' as u32),
+            Comma => KittyKey::Text(',' as u32),
+            Equals => KittyKey::Text('=' as u32),
+            Grave => KittyKey::Text('`' as u32),
+            LBracket => KittyKey::Text('[' as u32),
+            Minus => KittyKey::Text('-' as u32),
+            Period => KittyKey::Text('.' as u32),
+            Plus => KittyKey::Text('+' as u32),
+            RBracket => KittyKey::Text(']' as u32),
+            Semicolon => KittyKey::Text(';' as u32),
+            Slash => KittyKey::Text('/' as u32),
+            Underline => KittyKey::Text('_' as u32),
+            Numpad0 => KittyKey::Text(57399),
+            Numpad1 => KittyKey::Text(57400),
+            Numpad2 => KittyKey::Text(57401),
+            Numpad3 => KittyKey::Text(57402),
+            Numpad4 => KittyKey::Text(57403),
+            Numpad5 => KittyKey::Text(57404),
+            Numpad6 => KittyKey::Text(57405),
+            Numpad7 => KittyKey::Text(57406),
+            Numpad8 => KittyKey::Text(57407),
+            Numpad9 => KittyKey::Text(57408),
+            NumpadDecimal => KittyKey::Text(57409),
+            NumpadDivide => KittyKey::Text(57410),
+            NumpadMultiply => KittyKey::Text(57411),
+            NumpadSubtract => KittyKey::Text(57412),
+            NumpadAdd => KittyKey::Text(57413),
+            NumpadEquals => KittyKey::Text(57415),
+            NumpadComma => KittyKey::Text(57416),
+            Return => KittyKey::Legacy(13, "\r"),
+            Tab => KittyKey::Legacy(9, "\t"),
+            Back => KittyKey::Legacy(127, "\x7f"),
+            Escape => KittyKey::Code(27),
+            NumpadEnter => KittyKey::Code(57414),
+            Capital => KittyKey::Code(57358),
+            Scroll => KittyKey::Code(57359),
+            Numlock => KittyKey::Code(57360),
+            Snapshot => KittyKey::Code(57361),
+            Pause => KittyKey::Code(57362),
+            Apps => KittyKey::Code(57363),
+            F13 => KittyKey::Code(57376),
+            F14 => KittyKey::Code(57377),
+            F15 => KittyKey::Code(57378),
+            F16 => KittyKey::Code(57379),
+            F17 => KittyKey::Code(57380),
+            F18 => KittyKey::Code(57381),
+            F19 => KittyKey::Code(57382),
+            F20 => KittyKey::Code(57383),
+            F21 => KittyKey::Code(57384),
+            F22 => KittyKey::Code(57385),
+            F23 => KittyKey::Code(57386),
+            F24 => KittyKey::Code(57387),
+            PlayPause => KittyKey::Code(57430),
+            MediaStop => KittyKey::Code(57432),
+            NextTrack => KittyKey::Code(57435),
+            PrevTrack => KittyKey::Code(57436),
+            VolumeDown => KittyKey::Code(57438),
+            VolumeUp => KittyKey::Code(57439),
+            Mute => KittyKey::Code(57440),
+            LShift => KittyKey::Modifier(57441),
+            LControl => KittyKey::Modifier(57442),
+            LAlt => KittyKey::Modifier(57443),
+            LWin => KittyKey::Modifier(57444),
+            RShift => KittyKey::Modifier(57447),
+            RControl => KittyKey::Modifier(57448),
+            RAlt => KittyKey::Modifier(57449),
+            RWin => KittyKey::Modifier(57450),
+            Insert => KittyKey::Tilde(2),
+            Delete => KittyKey::Tilde(3),
+            PageUp => KittyKey::Tilde(5),
+            PageDown => KittyKey::Tilde(6),
+            F3 => KittyKey::Tilde(13),
+            F5 => KittyKey::Tilde(15),
+            F6 => KittyKey::Tilde(17),
+            F7 => KittyKey::Tilde(18),
+            F8 => KittyKey::Tilde(19),
+            F9 => KittyKey::Tilde(20),
+            F10 => KittyKey::Tilde(21),
+            F11 => KittyKey::Tilde(23),
+            F12 => KittyKey::Tilde(24),
+            Up => KittyKey::Letter('A'),
+            Down => KittyKey::Letter('B'),
+            Right => KittyKey::Letter('C'),
+            Left => KittyKey::Letter('D'),
+            End => KittyKey::Letter('F'),
+            Home => KittyKey::Letter('H'),
+            F1 => KittyKey::Letter('P'),
+            F2 => KittyKey::Letter('Q'),
+            F4 => KittyKey::Letter('S'),
+            _ => return None,
+        };
+
+        Some(key)
+    }
+}
+
+/// Build the kitty keyboard protocol escape sequence for a key event.
+///
+/// Returns `None` if the key should be handled using the legacy encoding instead.
+fn kitty_key_sequence(
+    key: VirtualKeyCode,
+    mods: ModifiersState,
+    event: KittyEvent,
+    modes: KeyboardModes,
+) -> Option<String> {
+    let key = KittyKey::new(key)?;
+
+    // Releases and repeats are only distinguished from presses on request.
+    let event = match event {
+        KittyEvent::Release if !modes.contains(KeyboardModes::REPORT_EVENT_TYPES) => return None,
+        KittyEvent::Repeat if !modes.contains(KeyboardModes::REPORT_EVENT_TYPES) => {
+            KittyEvent::Press
+        },
+        event => event,
+    };
+    let pressed = event != KittyEvent::Release;
+
+    let mut modifiers = 1;
+    if mods.shift() {
+        modifiers += 0b0001;
+    }
+    if mods.alt() {
+        modifiers += 0b0010;
+    }
+    if mods.ctrl() {
+        modifiers += 0b0100;
+    }
+    if mods.logo() {
+        modifiers += 0b1000;
+    }
+
+    let report_all = modes.contains(KeyboardModes::REPORT_ALL_KEYS_AS_ESC);
+    let text_mods = mods.ctrl() || mods.alt() || mods.logo();
+
+    let code = match key {
+        // Text without modifiers is sent as-is, releases are reported as escapes.
+        KittyKey::Text(_) if !report_all && !text_mods && pressed => return None,
+        KittyKey::Text(code) => code,
+
+        // Keep the legacy behavior to make sure it's always possible to type `reset`.
+        KittyKey::Legacy(..) if !report_all && !pressed => return None,
+        KittyKey::Legacy(_, text) if !report_all && modifiers == 1 => return Some(text.into()),
+        KittyKey::Legacy(code, _) | KittyKey::Code(code) => code,
+
+        KittyKey::Modifier(_) if !report_all => return None,
+        KittyKey::Modifier(code) => code,
+
+        KittyKey::Tilde(number) => {
+            let modifiers = kitty_modifiers(modifiers, event);
+            return Some(format!("\x1b[{}{}~", number, modifiers));
+        },
+        KittyKey::Letter(letter) => {
+            let modifiers = kitty_modifiers(modifiers, event);
+            let number = if modifiers.is_empty() { "" } else { "1" };
+            return Some(format!("\x1b[{}{}{}", number, modifiers, letter));
+        },
+    };
+
+    // Append the shifted key for text keys.
+    let mut key = code.to_string();
+    if mods.shift() && modes.contains(KeyboardModes::REPORT_ALTERNATE_KEYS) {
+        if let Some(shifted) = std::char::from_u32(code).and_then(shifted_char) {
+            key.push_str(&format!(":{}", shifted as u32));
+        }
+    }
+
+    Some(format!("\x1b[{}{}u", key, kitty_modifiers(modifiers, event)))
+}
+
+/// Character produced by a text key while Shift is held.
+///
+/// Winit does not expose the keyboard layout, so symbols are shifted using the US layout.
+fn shifted_char(c: char) -> Option<char> {
+    let shifted = match c {
+        'a'..='z' => c.to_ascii_uppercase(),
+        '0' => ')',
+        '1' => '!',
+        '2' => '@',
+        '3' => '#',
+        '4' => '$',
+        '5' => '%',
+        '6' => '^',
+        '7' => '&',
+        '8' => '*',
+        '9' => '(',
+        '`' => '~',
+        '-' => '_',
+        '=' => '+',
+        '[' => '{',
+        ']' => '}',
+        '\\' => '|',
+        ';' => ':',
+        '\'' => '"',
+        ',' => '<',
+        '.' => '>',
+        '/' => '?',
+        _ => return None,
+    };
+
+    Some(shifted)
+}
+
+/// Digit typed by a key, which extends the count of pending vi mode commands.
+fn key_digit(key: Option<VirtualKeyCode>, mods: ModifiersState) -> Option<char> {
+    if mods.shift() {
+        return None;
+    }
+
+    let digit = match key? {
+        VirtualKeyCode::Key0 | VirtualKeyCode::Numpad0 => '0',
+        VirtualKeyCode::Key1 | VirtualKeyCode::Numpad1 => '1',
+        VirtualKeyCode::Key2 | VirtualKeyCode::Numpad2 => '2',
+        VirtualKeyCode::Key3 | VirtualKeyCode::Numpad3 => '3',
+        VirtualKeyCode::Key4 | VirtualKeyCode::Numpad4 => '4',
+        VirtualKeyCode::Key5 | VirtualKeyCode::Numpad5 => '5',
+        VirtualKeyCode::Key6 | VirtualKeyCode::Numpad6 => '6',
+        VirtualKeyCode::Key7 | VirtualKeyCode::Numpad7 => '7',
+        VirtualKeyCode::Key8 | VirtualKeyCode::Numpad8 => '8',
+        VirtualKeyCode::Key9 | VirtualKeyCode::Numpad9 => '9',
+        _ => return None,
+    };
+
+    Some(digit)
+}
+
+/// Modifier and event type parameters of a kitty keyboard protocol escape sequence.
+///
+/// Parameters are omitted for unmodified key presses.
+fn kitty_modifiers(modifiers: u8, event: KittyEvent) -> String {
+    match (modifiers, event) {
+        (1, KittyEvent::Press) => String::new(),
+        (_, KittyEvent::Press) => format!(";{}", modifiers),
+        (_, KittyEvent::Repeat) => format!(";{}:2", modifiers),
+        (_, KittyEvent::Release) => format!(";{}:3", modifiers),
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum MouseState {
     Url(Url),
@@ -404,6 +878,13 @@ impl<T: EventListener, A: ActionContext<T>> Processor<T, A> {
         Self { ctx, _phantom: Default::default() }
     }
 
+    /// Execute an action which was not triggered by a binding.
+    #[cfg(unix)]
+    pub fn execute_action(&mut self, action: &Action) {
+        action.execute(&mut self.ctx);
+        self.ctx.mark_dirty();
+    }
+
     #[inline]
     pub fn mouse_moved(&mut self, position: PhysicalPosition<f64>) {
         let size_info = self.ctx.size_info();
@@ -697,7 +1178,8 @@ impl<T: EventListener, A: ActionContext<T>> Processor<T, A> {
             self.ctx.launch_url(url);
         }
 
-        self.ctx.scheduler_mut().unschedule(TimerId::SelectionScrolling);
+        let timer_id = TimerId::new(Topic::SelectionScrolling, self.ctx.window().window_id());
+        self.ctx.scheduler_mut().unschedule(timer_id);
         self.copy_selection();
     }
 
@@ -842,18 +1324,109 @@ impl<T: EventListener, A: ActionContext<T>> Processor<T, A> {
     pub fn key_input(&mut self, input: KeyboardInput) {
         // Reset search delay when the user is still typing.
         if self.ctx.search_active() {
-            if let Some(timer) = self.ctx.scheduler_mut().get_mut(TimerId::DelayedSearch) {
+            let timer_id = TimerId::new(Topic::DelayedSearch, self.ctx.window().window_id());
+            if let Some(timer) = self.ctx.scheduler_mut().get_mut(timer_id) {
                 timer.deadline = Instant::now() + TYPING_SEARCH_DELAY;
             }
         }
 
+        // Keys pressed again without being released in between are repeats.
+        let last_pressed_key = self.ctx.last_pressed_key();
+        let event = match input.state {
+            ElementState::Pressed => match last_pressed_key.replace(input.scancode) {
+                Some(scancode) if scancode == input.scancode => KittyEvent::Repeat,
+                _ => KittyEvent::Press,
+            },
+            ElementState::Released => {
+                if *last_pressed_key == Some(input.scancode) {
+                    *last_pressed_key = None;
+                }
+
+                KittyEvent::Release
+            },
+        };
+
         match input.state {
             ElementState::Pressed => {
                 *self.ctx.received_count() = 0;
-                self.process_key_bindings(input);
+
+                // Keys of pending vi mode commands are passed to the command parser, unless they
+                // are bound to a motion completing the command.
+                if self.ctx.vi_input_pending() {
+                    let digit = key_digit(input.virtual_keycode, *self.ctx.modifiers());
+                    if !self.ctx.vi_input_expects(digit) && self.process_key_bindings(input, true) {
+                        self.ctx.reported_keys().remove(&input.scancode);
+                        return;
+                    }
+
+                    *self.ctx.suppress_chars() = false;
+                } else if !self.ctx.hint_active() && self.process_key_bindings(input, false) {
+                    self.ctx.reported_keys().remove(&input.scancode);
+                    return;
+                }
             },
             ElementState::Released => *self.ctx.suppress_chars() = false,
         }
+
+        // Keys are only reported while the kitty keyboard protocol is active.
+        let modes = self.keyboard_modes();
+        if modes.is_empty() {
+            self.ctx.reported_keys().remove(&input.scancode);
+            return;
+        }
+
+        // Releases and repeats are only reported for keys whose press reached the application.
+        let reported_keys = self.ctx.reported_keys();
+        let reported = match event {
+            KittyEvent::Press => {
+                reported_keys.insert(input.scancode);
+                true
+            },
+            KittyEvent::Repeat => reported_keys.contains(&input.scancode),
+            KittyEvent::Release => reported_keys.remove(&input.scancode),
+        };
+
+        if !reported {
+            // Drop the text of repeats too.
+            if event == KittyEvent::Repeat {
+                *self.ctx.suppress_chars() = true;
+            }
+
+            return;
+        }
+
+        // Encode the key using the kitty keyboard protocol.
+        let sequence = input
+            .virtual_keycode
+            .and_then(|key| kitty_key_sequence(key, *self.ctx.modifiers(), event, modes));
+
+        if let Some(sequence) = sequence {
+            if input.state == ElementState::Pressed {
+                self.ctx.on_typing_start();
+
+                self.ctx.scroll(Scroll::Bottom);
+                self.ctx.clear_selection();
+
+                // Text was already sent as part of the escape sequence.
+                *self.ctx.suppress_chars() = true;
+            }
+
+            self.ctx.write_to_pty(sequence.into_bytes());
+        }
+    }
+
+    /// Active kitty keyboard protocol modes.
+    ///
+    /// Keys are only reported to the application while it is receiving regular input.
+    fn keyboard_modes(&self) -> KeyboardModes {
+        if self.ctx.search_active()
+            || self.ctx.hint_active()
+            || self.ctx.terminal().mode().contains(TermMode::VI)
+        {
+            KeyboardModes::empty()
+        } else {
+            self.ctx.terminal().keyboard_modes()
+        }
     }
 
     /// Modifier state change.
@@ -876,10 +1449,24 @@ impl<T: EventListener, A: ActionContext<T>> Processor<T, A> {
     /// Process a received character.
     pub fn received_char(&mut self, c: char) {
         let suppress_chars = *self.ctx.suppress_chars();
+
+        // Handle hint selection over terminal content.
+        if self.ctx.hint_active() {
+            if !suppress_chars {
+                if let Some(hint_match) = self.ctx.hint_input(c) {
+                    self.hint_action(hint_match);
+                }
+            }
+
+            return;
+        }
+
         let search_active = self.ctx.search_active();
         if suppress_chars || self.ctx.terminal().mode().contains(TermMode::VI) || search_active {
             if search_active && !suppress_chars {
                 self.ctx.search_input(c);
+            } else if !suppress_chars {
+                self.ctx.vi_input(c);
             }
 
             return;
@@ -910,18 +1497,55 @@ impl<T: EventListener, A: ActionContext<T>> Processor<T, A> {
         *self.ctx.received_count() += 1;
     }
 
+    /// Run the action of a selected hint on its text.
+    fn hint_action(&mut self, hint_match: HintMatch) {
+        let start = *hint_match.bounds.start();
+        let end = *hint_match.bounds.end();
+        let text = self.ctx.terminal().bounds_to_string(start, end);
+
+        match hint_match.action {
+            // Launch an external program.
+            HintAction::Command(command) => {
+                let mut args = command.args().to_vec();
+                args.push(text);
+                start_daemon(command.program(), &args);
+            },
+            // Copy the text to the clipboard.
+            HintAction::Action(HintInternalAction::Copy) => {
+                self.ctx.clipboard_mut().store(ClipboardType::Clipboard, text);
+            },
+            // Write the text to the PTY/search.
+            HintAction::Action(HintInternalAction::Paste) => paste(&mut self.ctx, &text),
+        }
+    }
+
     /// Attempt to find a binding and execute its action.
     ///
     /// The provided mode, mods, and key must match what is allowed by a binding
     /// for its action to be executed.
-    fn process_key_bindings(&mut self, input: KeyboardInput) {
+    ///
+    /// Returns `true` if any binding was triggered.
+    fn process_key_bindings(&mut self, input: KeyboardInput, motions_only: bool) -> bool {
         let mode = BindingMode::new(self.ctx.terminal().mode(), self.ctx.search_active());
         let mods = *self.ctx.modifiers();
         let mut suppress_chars = None;
 
+        // Escape sequence bindings are replaced by the kitty keyboard protocol encoding.
+        let kitty_keyboard = !self.keyboard_modes().is_empty();
+
         for i in 0..self.ctx.config().ui_config.key_bindings().len() {
             let binding = &self.ctx.config().ui_config.key_bindings()[i];
 
+            if kitty_keyboard {
+                if let Action::Esc(_) = binding.action {
+                    continue;
+                }
+            }
+
+            if motions_only && !matches!(binding.action, Action::ViMotion(_)) {
+                continue;
+            }
+
             let key = match (binding.trigger, input.virtual_keycode) {
                 (Key::Scancode(_), _) => Key::Scancode(input.scancode),
                 (_, Some(key)) => Key::Keycode(key),
@@ -940,6 +1564,8 @@ impl<T: EventListener, A: ActionContext<T>> Processor<T, A> {
 
         // Don't suppress char if no bindings were triggered.
         *self.ctx.suppress_chars() = suppress_chars.unwrap_or(false);
+
+        suppress_chars.is_some()
     }
 
     /// Attempt to find a binding and execute its action.
@@ -1043,6 +1669,8 @@ impl<T: EventListener, A: ActionContext<T>> Processor<T, A> {
     fn update_selection_scrolling(&mut self, mouse_y: i32) {
         let dpr = self.ctx.window().dpr;
         let size = self.ctx.size_info();
+        let window_id = self.ctx.window().window_id();
+        let timer_id = TimerId::new(Topic::SelectionScrolling, window_id);
         let scheduler = self.ctx.scheduler_mut();
 
         // Scale constants by DPI.
@@ -1060,25 +1688,18 @@ impl<T: EventListener, A: ActionContext<T>> Processor<T, A> {
         } else if mouse_y >= start_bottom {
             start_bottom - mouse_y - step
         } else {
-            scheduler.unschedule(TimerId::SelectionScrolling);
+            scheduler.unschedule(timer_id);
             return;
         };
 
         // Scale number of lines scrolled based on distance to boundary.
         let delta = delta as isize / step as isize;
-        let event = Event::Scroll(Scroll::Delta(delta));
+        let event = Event::new(EventType::Scroll(Scroll::Delta(delta)), window_id);
 
         // Schedule event.
-        match scheduler.get_mut(TimerId::SelectionScrolling) {
-            Some(timer) => timer.event = event.into(),
-            None => {
-                scheduler.schedule(
-                    event.into(),
-                    SELECTION_SCROLLING_INTERVAL,
-                    true,
-                    TimerId::SelectionScrolling,
-                );
-            },
+        match scheduler.get_mut(timer_id) {
+            Some(timer) => timer.event = event,
+            None => scheduler.schedule(event, SELECTION_SCROLLING_INTERVAL, true, timer_id),
         }
     }
 }
@@ -1108,6 +1729,8 @@ mod tests {
         pub message_buffer: &'a mut MessageBuffer,
         pub received_count: usize,
         pub suppress_chars: bool,
+        pub last_pressed_key: Option<ScanCode>,
+        pub reported_keys: HashSet<ScanCode>,
         pub modifiers: ModifiersState,
         config: &'a Config,
     }
@@ -1122,6 +1745,10 @@ mod tests {
             None
         }
 
+        fn search_options(&self) -> SearchOptions {
+            SearchOptions::default()
+        }
+
         fn search_direction(&self) -> Direction {
             Direction::Right
         }
@@ -1130,6 +1757,10 @@ mod tests {
             false
         }
 
+        fn hint_active(&self) -> bool {
+            false
+        }
+
         fn terminal(&self) -> &Term<T> {
             &self.terminal
         }
@@ -1183,6 +1814,14 @@ mod tests {
             &mut self.suppress_chars
         }
 
+        fn last_pressed_key(&mut self) -> &mut Option<ScanCode> {
+            &mut self.last_pressed_key
+        }
+
+        fn reported_keys(&mut self) -> &mut HashSet<ScanCode> {
+            &mut self.reported_keys
+        }
+
         fn modifiers(&mut self) -> &mut ModifiersState {
             &mut self.modifiers
         }
@@ -1269,6 +1908,8 @@ mod tests {
                     clipboard: &mut clipboard,
                     received_count: 0,
                     suppress_chars: false,
+                    last_pressed_key: None,
+                    reported_keys: Default::default(),
                     modifiers: Default::default(),
                     message_buffer: &mut message_buffer,
                     config: &cfg,
@@ -1472,4 +2113,56 @@ mod tests {
         mode: BindingMode::empty(),
         mods: ModifiersState::ALT | ModifiersState::LOGO,
     }
+
+    #[test]
+    fn kitty_keyboard_disambiguate() {
+        let modes = KeyboardModes::DISAMBIGUATE_ESC_CODES;
+        let sequence = |key, mods, event| kitty_key_sequence(key, mods, event, modes);
+        let pressed = KittyEvent::Press;
+        let none = ModifiersState::empty();
+
+        let ctrl_i = sequence(VirtualKeyCode::I, ModifiersState::CTRL, pressed);
+        assert_eq!(ctrl_i, Some(String::from("\x1b[105;5u")));
+        assert_eq!(sequence(VirtualKeyCode::Tab, none, pressed), Some(String::from("\t")));
+        assert_eq!(sequence(VirtualKeyCode::Escape, none, pressed), Some(String::from("\x1b[27u")));
+        assert_eq!(
+            sequence(VirtualKeyCode::Up, ModifiersState::SHIFT, pressed).unwrap(),
+            "\x1b[1;2A"
+        );
+        assert_eq!(sequence(VirtualKeyCode::A, ModifiersState::SHIFT, pressed), None);
+        assert_eq!(sequence(VirtualKeyCode::A, ModifiersState::CTRL, KittyEvent::Release), None);
+
+        // Repeats are reported as presses without event types.
+        let ctrl_i = sequence(VirtualKeyCode::I, ModifiersState::CTRL, KittyEvent::Repeat);
+        assert_eq!(ctrl_i, Some(String::from("\x1b[105;5u")));
+        let tab = sequence(VirtualKeyCode::Tab, none, KittyEvent::Repeat);
+        assert_eq!(tab, Some(String::from("\t")));
+    }
+
+    #[test]
+    fn kitty_keyboard_report_all() {
+        let modes = KeyboardModes::all();
+        let sequence = |key, mods, event| kitty_key_sequence(key, mods, event, modes).unwrap();
+        let pressed = KittyEvent::Press;
+        let released = KittyEvent::Release;
+        let none = ModifiersState::empty();
+
+        let ctrl_shift = ModifiersState::CTRL | ModifiersState::SHIFT;
+        assert_eq!(sequence(VirtualKeyCode::A, ctrl_shift, pressed), "\x1b[97:65;6u");
+        assert_eq!(sequence(VirtualKeyCode::A, none, released), "\x1b[97;1:3u");
+        assert_eq!(sequence(VirtualKeyCode::Return, none, pressed), "\x1b[13u");
+        assert_eq!(
+            sequence(VirtualKeyCode::LShift, ModifiersState::SHIFT, pressed),
+            "\x1b[57441;2u"
+        );
+        assert_eq!(sequence(VirtualKeyCode::Delete, none, released), "\x1b[3;1:3~");
+        assert_eq!(sequence(VirtualKeyCode::A, none, KittyEvent::Repeat), "\x1b[97;1:2u");
+        assert_eq!(sequence(VirtualKeyCode::Up, none, KittyEvent::Repeat), "\x1b[1;1:2A");
+
+        // Alternate keys are reported for symbols.
+        let shift = ModifiersState::SHIFT;
+        assert_eq!(sequence(VirtualKeyCode::Key1, shift, pressed), "\x1b[49:33;2u");
+        assert_eq!(sequence(VirtualKeyCode::Slash, shift, pressed), "\x1b[47:63;2u");
+        assert_eq!(sequence(VirtualKeyCode::Numpad1, shift, pressed), "\x1b[57400;2u");
+    }
 }
@@ -16,7 +16,7 @@ use glutin::event_loop::EventLoopProxy;
 use log::{self, Level};
 
 use crate::cli::Options;
-use crate::event::Event;
+use crate::event::{Event, EventType};
 use crate::message_bar::{Message, MessageType};
 
 /// Name for the environment variable containing the log file's path.
@@ -27,7 +27,7 @@ const ALLOWED_TARGETS: [&str; 4] =
 
 pub fn initialize(
     options: &Options,
-    event_proxy: EventLoopProxy<Event>,
+    event_proxy: Option<EventLoopProxy<Event>>,
 ) -> Result<Option<PathBuf>, log::SetLoggerError> {
     log::set_max_level(options.log_level);
 
@@ -41,11 +41,11 @@ pub fn initialize(
 pub struct Logger {
     logfile: Mutex<OnDemandLogFile>,
     stdout: Mutex<LineWriter<Stdout>>,
-    event_proxy: Mutex<EventLoopProxy<Event>>,
+    event_proxy: Mutex<Option<EventLoopProxy<Event>>>,
 }
 
 impl Logger {
-    fn new(event_proxy: EventLoopProxy<Event>) -> Self {
+    fn new(event_proxy: Option<EventLoopProxy<Event>>) -> Self {
         let logfile = Mutex::new(OnDemandLogFile::new());
         let stdout = Mutex::new(LineWriter::new(io::stdout()));
 
@@ -67,6 +67,12 @@ impl Logger {
             Err(_) => return,
         };
 
+        // Without a window there is no message bar to log to.
+        let event_proxy = match event_proxy.as_ref() {
+            Some(event_proxy) => event_proxy,
+            None => return,
+        };
+
         #[cfg(not(windows))]
         let env_var = format!("${}", ALACRITTY_LOG_ENV);
         #[cfg(windows)]
@@ -88,7 +94,7 @@ impl Logger {
         let mut message = Message::new(message, message_type);
         message.set_target(record.target().to_owned());
 
-        let _ = event_proxy.send_event(Event::Message(message));
+        let _ = event_proxy.send_event(Event::new(EventType::Message(message), None));
     }
 }
 
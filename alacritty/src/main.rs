@@ -17,16 +17,14 @@ use std::env;
 use std::error::Error;
 use std::fs;
 use std::io::{self, Write};
-use std::sync::Arc;
+#[cfg(not(unix))]
+use std::path::PathBuf;
 
 use glutin::event_loop::EventLoop as GlutinEventLoop;
 use log::{error, info};
 #[cfg(windows)]
 use winapi::um::wincon::{AttachConsole, FreeConsole, ATTACH_PARENT_PROCESS};
 
-use alacritty_terminal::event_loop::{self, EventLoop, Msg};
-use alacritty_terminal::sync::FairMutex;
-use alacritty_terminal::term::Term;
 use alacritty_terminal::tty;
 
 mod cli;
@@ -36,6 +34,8 @@ mod daemon;
 mod display;
 mod event;
 mod input;
+#[cfg(unix)]
+mod ipc;
 mod logging;
 #[cfg(target_os = "macos")]
 mod macos;
@@ -45,6 +45,7 @@ mod panic;
 mod renderer;
 mod scheduler;
 mod url;
+mod window_context;
 
 mod gl {
     #![allow(clippy::all)]
@@ -54,11 +55,9 @@ mod gl {
 use crate::cli::Options;
 use crate::config::monitor;
 use crate::config::Config;
-use crate::display::Display;
-use crate::event::{Event, EventProxy, Processor};
+use crate::event::{Event, Processor};
 #[cfg(target_os = "macos")]
 use crate::macos::locale;
-use crate::message_bar::MessageBuffer;
 
 fn main() {
     #[cfg(windows)]
@@ -75,11 +74,45 @@ fn main() {
     // Load command line options.
     let options = Options::new();
 
+    // Send the message to a running instance instead of starting a new one.
+    #[cfg(unix)]
+    if let Some(message_options) = &options.message {
+        let socket = message_options.socket.clone();
+        if let Err(err) = ipc::send_message(socket, message_options.message.clone()) {
+            let _ = writeln!(io::stderr(), "Error sending message: {}", err);
+            std::process::exit(1);
+        }
+
+        return;
+    }
+
+    // Render a screenshot without opening any windows.
+    if let Some(path) = options.screenshot.clone() {
+        let log_file = logging::initialize(&options, None).expect("Unable to initialize logger");
+
+        let config = config::load(&options);
+        log::set_max_level(config.ui_config.debug.log_level);
+        tty::setup_env(&config, None);
+
+        if let Err(err) = display::headless::run(&config, &options, &path) {
+            error!("Unable to render screenshot: {}", err);
+            std::process::exit(1);
+        }
+
+        if let Some(log_file) = log_file {
+            if !config.ui_config.debug.persistent_logging {
+                let _ = fs::remove_file(&log_file);
+            }
+        }
+
+        return;
+    }
+
     // Setup glutin event loop.
     let window_event_loop = GlutinEventLoop::<Event>::with_user_event();
 
     // Initialize the logger as soon as possible as to capture output from other subsystems.
-    let log_file = logging::initialize(&options, window_event_loop.create_proxy())
+    let log_file = logging::initialize(&options, Some(window_event_loop.create_proxy()))
         .expect("Unable to initialize logger");
 
     // Load configuration file.
@@ -114,8 +147,8 @@ fn main() {
 
 /// Run Alacritty.
 ///
-/// Creates a window, the terminal state, PTY, I/O event loop, input processor,
-/// config change monitor, and runs the main display loop.
+/// Creates the initial window, config change monitor, and runs the main event loop which
+/// dispatches events to all windows.
 fn run(
     window_event_loop: GlutinEventLoop<Event>,
     config: Config,
@@ -126,103 +159,66 @@ fn run(
     // Log the configuration paths.
     log_config_path(&config);
 
-    // Set environment variables.
-    tty::setup_env(&config);
-
-    let event_proxy = EventProxy::new(window_event_loop.create_proxy());
+    // Create the IPC socket listener.
+    #[cfg(unix)]
+    let socket_path = if config.ui_config.ipc_socket {
+        ipc::spawn_ipc_socket(&options, window_event_loop.create_proxy())
+    } else {
+        None
+    };
+    #[cfg(not(unix))]
+    let socket_path: Option<PathBuf> = None;
 
-    // Create a display.
-    //
-    // The display manages a window and can draw the terminal.
-    let display = Display::new(&config, &window_event_loop)?;
-
-    info!(
-        "PTY dimensions: {:?} x {:?}",
-        display.size_info.screen_lines(),
-        display.size_info.cols()
-    );
-
-    // Create the terminal.
-    //
-    // This object contains all of the state about what's being displayed. It's
-    // wrapped in a clonable mutex since both the I/O loop and display need to
-    // access it.
-    let terminal = Term::new(&config, display.size_info, event_proxy.clone());
-    let terminal = Arc::new(FairMutex::new(terminal));
-
-    // Create the PTY.
-    //
-    // The PTY forks a process to run the shell on the slave side of the
-    // pseudoterminal. A file descriptor for the master side is retained for
-    // reading/writing to the shell.
-    let pty = tty::new(&config, &display.size_info, display.window.x11_window_id());
-
-    // Create the pseudoterminal I/O loop.
-    //
-    // PTY I/O is ran on another thread as to not occupy cycles used by the
-    // renderer and input processing. Note that access to the terminal state is
-    // synchronized since the I/O loop updates the state, and the display
-    // consumes it periodically.
-    let event_loop = EventLoop::new(
-        Arc::clone(&terminal),
-        event_proxy.clone(),
-        pty,
-        config.hold,
-        config.ui_config.debug.ref_test,
-    );
-
-    // The event loop channel allows write requests from the event processor
-    // to be sent to the pty loop and ultimately written to the pty.
-    let loop_tx = event_loop.channel();
+    // Set environment variables.
+    tty::setup_env(&config, socket_path.as_deref());
 
     // Create a config monitor when config was loaded from path.
     //
     // The monitor watches the config file for changes and reloads it. Pending
     // config changes are processed in the main loop.
     if config.ui_config.live_config_reload {
-        monitor::watch(config.ui_config.config_paths.clone(), event_proxy);
+        monitor::watch(config.ui_config.config_paths.clone(), window_event_loop.create_proxy());
     }
 
-    // Setup storage for message UI.
-    let message_buffer = MessageBuffer::new();
+    // Terminal options of the initial window.
+    let window_options = options.window_options();
 
     // Event processor.
-    let mut processor = Processor::new(
-        event_loop::Notifier(loop_tx.clone()),
-        message_buffer,
-        config,
-        display,
-        options,
-    );
+    let mut processor = Processor::new(config, options, &window_event_loop);
 
-    // Kick off the I/O thread.
-    let io_thread = event_loop.spawn();
+    // Create the first Alacritty window.
+    let proxy = window_event_loop.create_proxy();
+    processor.create_window(&window_event_loop, proxy, window_options)?;
 
     info!("Initialisation complete");
 
     // Start event loop and block until shutdown.
-    processor.run(terminal, window_event_loop);
+    processor.run(window_event_loop);
 
     // This explicit drop is needed for Windows, ConPTY backend. Otherwise a deadlock can occur.
     // The cause:
     //   - Drop for ConPTY will deadlock if the conout pipe has already been dropped.
-    //   - The conout pipe is dropped when the io_thread is joined below (io_thread owns PTY).
+    //   - The conout pipe is dropped when the io_thread is joined (io_thread owns PTY).
     //   - ConPTY is dropped when the last of processor and io_thread are dropped, because both of
     //     them own an Arc<ConPTY>.
     //
     // The fix is to ensure that processor is dropped first. That way, when io_thread (i.e. PTY)
     // is dropped, it can ensure ConPTY is dropped before the conout pipe in the PTY drop order.
     //
+    // Dropping the processor drops all remaining windows, which shut down and join the I/O
+    // threads of their PTYs.
+    //
     // FIXME: Change PTY API to enforce the correct drop order with the typesystem.
     drop(processor);
 
-    // Shutdown PTY parser event loop.
-    loop_tx.send(Msg::Shutdown).expect("Error sending shutdown to PTY event loop");
-    io_thread.join().expect("join io thread");
-
     // FIXME patch notify library to have a shutdown method.
     // config_reloader.join().ok();
 
+    // Clean up the IPC socket file.
+    if let Some(socket_path) = socket_path {
+        let _ = fs::remove_file(socket_path);
+    }
+
     // Without explicitly detaching the console cmd won't redraw it's prompt.
     #[cfg(windows)]
     unsafe {
@@ -24,6 +24,9 @@ pub enum MessageType {
 
     /// A message represents a warning.
     Warning,
+
+    /// A message represents information about a completed action.
+    Info,
 }
 
 impl Message {
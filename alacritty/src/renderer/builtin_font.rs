@@ -0,0 +1,524 @@
+//! Built-in glyphs for box drawing, block elements and Powerline symbols.
+//!
+//! These characters are supposed to connect seamlessly with their neighbors, which font glyphs
+//! often fail to do once the cell size no longer matches the font metrics. Drawing them directly
+//! at the size of a cell guarantees that lines and blocks always line up with the grid.
+
+use crossfont::{BitmapBuffer, Metrics, RasterizedGlyph};
+
+use crate::config::ui_config::Delta;
+
+/// Number of samples per pixel axis used for anti-aliasing.
+const SAMPLES: usize = 4;
+
+/// Weight of double lines in the box drawing range.
+const DOUBLE: u8 = 3;
+
+/// Lines of the box drawing characters from U+2500 to U+257F.
+///
+/// Every entry contains the weight of the up, right, down and left arms. Characters which are
+/// not composed of straight lines are drawn separately and have no arms.
+#[rustfmt::skip]
+const BOX_LINES: [[u8; 4]; 128] = [
+    // U+2500
+    [0, 1, 0, 1], [0, 2, 0, 2], [1, 0, 1, 0], [2, 0, 2, 0],
+    [0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0],
+    [0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0],
+    [0, 1, 1, 0], [0, 2, 1, 0], [0, 1, 2, 0], [0, 2, 2, 0],
+    // U+2510
+    [0, 0, 1, 1], [0, 0, 1, 2], [0, 0, 2, 1], [0, 0, 2, 2],
+    [1, 1, 0, 0], [1, 2, 0, 0], [2, 1, 0, 0], [2, 2, 0, 0],
+    [1, 0, 0, 1], [1, 0, 0, 2], [2, 0, 0, 1], [2, 0, 0, 2],
+    [1, 1, 1, 0], [1, 2, 1, 0], [2, 1, 1, 0], [1, 1, 2, 0],
+    // U+2520
+    [2, 1, 2, 0], [2, 2, 1, 0], [1, 2, 2, 0], [2, 2, 2, 0],
+    [1, 0, 1, 1], [1, 0, 1, 2], [2, 0, 1, 1], [1, 0, 2, 1],
+    [2, 0, 2, 1], [2, 0, 1, 2], [1, 0, 2, 2], [2, 0, 2, 2],
+    [0, 1, 1, 1], [0, 1, 1, 2], [0, 2, 1, 1], [0, 2, 1, 2],
+    // U+2530
+    [0, 1, 2, 1], [0, 1, 2, 2], [0, 2, 2, 1], [0, 2, 2, 2],
+    [1, 1, 0, 1], [1, 1, 0, 2], [1, 2, 0, 1], [1, 2, 0, 2],
+    [2, 1, 0, 1], [2, 1, 0, 2], [2, 2, 0, 1], [2, 2, 0, 2],
+    [1, 1, 1, 1], [1, 1, 1, 2], [1, 2, 1, 1], [1, 2, 1, 2],
+    // U+2540
+    [2, 1, 1, 1], [1, 1, 2, 1], [2, 1, 2, 1], [2, 1, 1, 2],
+    [2, 2, 1, 1], [1, 1, 2, 2], [1, 2, 2, 1], [2, 2, 1, 2],
+    [1, 2, 2, 2], [2, 1, 2, 2], [2, 2, 2, 1], [2, 2, 2, 2],
+    [0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0],
+    // U+2550
+    [0, 3, 0, 3], [3, 0, 3, 0], [0, 3, 1, 0], [0, 1, 3, 0],
+    [0, 3, 3, 0], [0, 0, 1, 3], [0, 0, 3, 1], [0, 0, 3, 3],
+    [1, 3, 0, 0], [3, 1, 0, 0], [3, 3, 0, 0], [1, 0, 0, 3],
+    [3, 0, 0, 1], [3, 0, 0, 3], [1, 3, 1, 0], [3, 1, 3, 0],
+    // U+2560
+    [3, 3, 3, 0], [1, 0, 1, 3], [3, 0, 3, 1], [3, 0, 3, 3],
+    [0, 3, 1, 3], [0, 1, 3, 1], [0, 3, 3, 3], [1, 3, 0, 3],
+    [3, 1, 0, 1], [3, 3, 0, 3], [1, 3, 1, 3], [3, 1, 3, 1],
+    [3, 3, 3, 3], [0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0],
+    // U+2570
+    [0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0],
+    [0, 0, 0, 1], [1, 0, 0, 0], [0, 1, 0, 0], [0, 0, 1, 0],
+    [0, 0, 0, 2], [2, 0, 0, 0], [0, 2, 0, 0], [0, 0, 2, 0],
+    [0, 2, 0, 1], [1, 0, 2, 0], [0, 1, 0, 2], [2, 0, 1, 0],
+];
+
+/// Check if a character is drawn by the built-in font.
+pub fn is_builtin(character: char) -> bool {
+    matches!(character, '\u{2500}'..='\u{259f}' | '\u{e0b0}'..='\u{e0b3}')
+}
+
+/// Rasterize a built-in glyph.
+///
+/// The glyph covers the entire cell, which is computed from the font metrics and `font.offset`.
+/// Returns `None` if the character is not part of the built-in font.
+pub fn builtin_glyph(
+    character: char,
+    metrics: &Metrics,
+    offset: &Delta<i8>,
+    glyph_offset: &Delta<i8>,
+) -> Option<RasterizedGlyph> {
+    if !is_builtin(character) {
+        return None;
+    }
+
+    let width = (metrics.average_advance + f64::from(offset.x)).floor().max(1.) as usize;
+    let height = (metrics.line_height + f64::from(offset.y)).floor().max(1.) as usize;
+    let mut canvas = Canvas::new(width, height);
+
+    // Light lines use the font's underline thickness, to match the weight of the text.
+    let stroke = (metrics.underline_thickness.round() as usize).max(1);
+
+    match character {
+        '\u{2500}'..='\u{257f}' => draw_box(&mut canvas, character, stroke),
+        '\u{2580}'..='\u{259f}' => draw_block(&mut canvas, character),
+        _ => draw_powerline(&mut canvas, character, stroke),
+    }
+
+    // Compensate for the offsets applied to all glyphs once they are loaded, so the glyph's top
+    // edge ends up at the top of the cell.
+    let top = height as i32 + metrics.descent as i32 - i32::from(glyph_offset.y);
+    let left = -i32::from(glyph_offset.x);
+
+    let glyph = RasterizedGlyph {
+        character,
+        width: width as i32,
+        height: height as i32,
+        top,
+        left,
+        buffer: BitmapBuffer::RGB(canvas.into_rgb()),
+    };
+
+    Some(glyph)
+}
+
+/// Draw characters from the box drawing range.
+fn draw_box(canvas: &mut Canvas, character: char, stroke: usize) {
+    let (width, height) = (canvas.width, canvas.height);
+    let index = character as usize - 0x2500;
+
+    match character {
+        // Dashed lines.
+        '\u{2504}'..='\u{250b}' | '\u{254c}'..='\u{254f}' => {
+            let (dashes, weight) = match character {
+                '\u{2504}'..='\u{2507}' => (3, index - 0x04),
+                '\u{2508}'..='\u{250b}' => (4, index - 0x08),
+                _ => (2, index - 0x4c),
+            };
+            let thickness = if weight % 2 == 0 { stroke } else { stroke * 2 };
+
+            if weight < 2 {
+                let (y0, y1) = band(height, thickness);
+                for (x0, x1) in dash_segments(width, dashes) {
+                    canvas.fill_rect(x0, y0, x1, y1);
+                }
+            } else {
+                let (x0, x1) = band(width, thickness);
+                for (y0, y1) in dash_segments(height, dashes) {
+                    canvas.fill_rect(x0, y0, x1, y1);
+                }
+            }
+        },
+        // Arcs.
+        '\u{256d}' => draw_arc(canvas, stroke, 1., 1.),
+        '\u{256e}' => draw_arc(canvas, stroke, -1., 1.),
+        '\u{256f}' => draw_arc(canvas, stroke, -1., -1.),
+        '\u{2570}' => draw_arc(canvas, stroke, 1., -1.),
+        // Diagonals.
+        '\u{2571}'..='\u{2573}' => {
+            let (w, h) = (width as f32, height as f32);
+            let length = (w * w + h * h).sqrt();
+            let half_stroke = stroke as f32 / 2.;
+
+            if character != '\u{2572}' {
+                canvas.fill_shape(|x, y| ((w - x) * h - y * w).abs() / length <= half_stroke);
+            }
+            if character != '\u{2571}' {
+                canvas.fill_shape(|x, y| (x * h - y * w).abs() / length <= half_stroke);
+            }
+        },
+        _ => draw_lines(canvas, BOX_LINES[index], stroke),
+    }
+}
+
+/// Draw the straight arms of a box drawing character.
+///
+/// Every arm reaches from the edge of the cell to the far side of the lines crossing it, so all
+/// arms are connected in the center of the cell.
+fn draw_lines(canvas: &mut Canvas, arms: [u8; 4], stroke: usize) {
+    let (width, height) = (canvas.width, canvas.height);
+    let [up, right, down, left] = arms;
+    let thickness = |weight: u8| usize::from(weight) * stroke;
+
+    // Thickness of the lines crossing the center, if there are none use the other direction.
+    let mut vertical = thickness(up.max(down));
+    let mut horizontal = thickness(left.max(right));
+    if vertical == 0 {
+        vertical = horizontal;
+    } else if horizontal == 0 {
+        horizontal = vertical;
+    }
+
+    let (x_start, x_end) = band(width, vertical);
+    let (y_start, y_end) = band(height, horizontal);
+
+    // Draw all arms as solid lines.
+    if up != 0 {
+        let (x0, x1) = band(width, thickness(up));
+        canvas.fill_rect(x0, 0, x1, y_end);
+    }
+    if down != 0 {
+        let (x0, x1) = band(width, thickness(down));
+        canvas.fill_rect(x0, y_start, x1, height);
+    }
+    if left != 0 {
+        let (y0, y1) = band(height, thickness(left));
+        canvas.fill_rect(0, y0, x_end, y1);
+    }
+    if right != 0 {
+        let (y0, y1) = band(height, thickness(right));
+        canvas.fill_rect(x_start, y0, width, y1);
+    }
+
+    // Cut the gaps into double lines, stopping at the stroke on the opposite side of the center.
+    if up == DOUBLE {
+        let (x0, _) = band(width, thickness(DOUBLE));
+        canvas.clear_rect(x0 + stroke, 0, x0 + 2 * stroke, y_end.saturating_sub(stroke));
+    }
+    if down == DOUBLE {
+        let (x0, _) = band(width, thickness(DOUBLE));
+        canvas.clear_rect(x0 + stroke, y_start + stroke, x0 + 2 * stroke, height);
+    }
+    if left == DOUBLE {
+        let (y0, _) = band(height, thickness(DOUBLE));
+        canvas.clear_rect(0, y0 + stroke, x_end.saturating_sub(stroke), y0 + 2 * stroke);
+    }
+    if right == DOUBLE {
+        let (y0, _) = band(height, thickness(DOUBLE));
+        canvas.clear_rect(x_start + stroke, y0 + stroke, width, y0 + 2 * stroke);
+    }
+}
+
+/// Draw a rounded corner connecting the center of the cell with two of its edges.
+///
+/// The horizontal and vertical directions point towards the edges the arc is connected to.
+fn draw_arc(canvas: &mut Canvas, stroke: usize, horizontal: f32, vertical: f32) {
+    let (width, height) = (canvas.width, canvas.height);
+    let (x0, x1) = band(width, stroke);
+    let (y0, y1) = band(height, stroke);
+
+    // Center of the lines going through the cell.
+    let mid_x = (x0 + x1) as f32 / 2.;
+    let mid_y = (y0 + y1) as f32 / 2.;
+
+    // Use the largest radius which still fits into the cell.
+    let radius = mid_x.min(width as f32 - mid_x).min(mid_y).min(height as f32 - mid_y);
+    let center_x = mid_x + horizontal * radius;
+    let center_y = mid_y + vertical * radius;
+
+    // Connect the arc to the edges of the cell.
+    if horizontal > 0. {
+        canvas.fill_rect(center_x as usize, y0, width, y1);
+    } else {
+        canvas.fill_rect(0, y0, center_x.ceil() as usize, y1);
+    }
+    if vertical > 0. {
+        canvas.fill_rect(x0, center_y as usize, x1, height);
+    } else {
+        canvas.fill_rect(x0, 0, x1, center_y.ceil() as usize);
+    }
+
+    let half_stroke = stroke as f32 / 2.;
+    canvas.fill_shape(|x, y| {
+        let (dx, dy) = (x - center_x, y - center_y);
+        let in_quadrant = dx * horizontal <= 0. && dy * vertical <= 0.;
+        in_quadrant && ((dx * dx + dy * dy).sqrt() - radius).abs() <= half_stroke
+    });
+}
+
+/// Draw characters from the block elements range.
+fn draw_block(canvas: &mut Canvas, character: char) {
+    let (width, height) = (canvas.width, canvas.height);
+    let eighth_x = |eighths: usize| (width * eighths + 4) / 8;
+    let eighth_y = |eighths: usize| (height * eighths + 4) / 8;
+    let (mid_x, mid_y) = (eighth_x(4), eighth_y(4));
+
+    match character {
+        // Upper half block.
+        '\u{2580}' => canvas.fill_rect(0, 0, width, mid_y),
+        // Lower eighths and full block.
+        '\u{2581}'..='\u{2588}' => {
+            let eighths = character as usize - 0x2580;
+            canvas.fill_rect(0, height - eighth_y(eighths), width, height);
+        },
+        // Left eighths.
+        '\u{2589}'..='\u{258f}' => {
+            let eighths = 0x2590 - character as usize;
+            canvas.fill_rect(0, 0, eighth_x(eighths), height);
+        },
+        // Right half block.
+        '\u{2590}' => canvas.fill_rect(mid_x, 0, width, height),
+        // Shades.
+        '\u{2591}'..='\u{2593}' => {
+            let alpha = (character as usize - 0x2590) as f32 / 4.;
+            canvas.fill_rect_alpha(0, 0, width, height, alpha);
+        },
+        // Upper one eighth block.
+        '\u{2594}' => canvas.fill_rect(0, 0, width, eighth_y(1)),
+        // Right one eighth block.
+        '\u{2595}' => canvas.fill_rect(width - eighth_x(1), 0, width, height),
+        // Quadrants.
+        _ => {
+            let (upper_left, upper_right, lower_left, lower_right) = match character {
+                '\u{2596}' => (false, false, true, false),
+                '\u{2597}' => (false, false, false, true),
+                '\u{2598}' => (true, false, false, false),
+                '\u{2599}' => (true, false, true, true),
+                '\u{259a}' => (true, false, false, true),
+                '\u{259b}' => (true, true, true, false),
+                '\u{259c}' => (true, true, false, true),
+                '\u{259d}' => (false, true, false, false),
+                '\u{259e}' => (false, true, true, false),
+                _ => (false, true, true, true),
+            };
+
+            if upper_left {
+                canvas.fill_rect(0, 0, mid_x, mid_y);
+            }
+            if upper_right {
+                canvas.fill_rect(mid_x, 0, width, mid_y);
+            }
+            if lower_left {
+                canvas.fill_rect(0, mid_y, mid_x, height);
+            }
+            if lower_right {
+                canvas.fill_rect(mid_x, mid_y, width, height);
+            }
+        },
+    }
+}
+
+/// Draw Powerline separators.
+fn draw_powerline(canvas: &mut Canvas, character: char, stroke: usize) {
+    let (w, h) = (canvas.width as f32, canvas.height as f32);
+
+    // Separators pointing left are mirrored horizontally.
+    let mirror = character == '\u{e0b2}' || character == '\u{e0b3}';
+    let column = move |x: f32| if mirror { w - x } else { x };
+
+    if character == '\u{e0b0}' || character == '\u{e0b2}' {
+        // Solid triangle with the tip in the center of the cell's edge.
+        canvas.fill_shape(|x, y| {
+            let x = column(x);
+            let half_height = h / 2. * (1. - x / w);
+            (y - h / 2.).abs() <= half_height
+        });
+    } else {
+        // Outline of the triangle's tip, folding the lower half onto the upper one.
+        let length = (w * w + h * h / 4.).sqrt();
+        let half_stroke = stroke as f32 / 2.;
+        canvas.fill_shape(|x, y| {
+            let y = h / 2. - (y - h / 2.).abs();
+            (y * w - column(x) * h / 2.).abs() / length <= half_stroke
+        });
+    }
+}
+
+/// Start and end of a line with the specified thickness, centered within `size` pixels.
+fn band(size: usize, thickness: usize) -> (usize, usize) {
+    let thickness = thickness.min(size);
+    let start = (size - thickness) / 2;
+    (start, start + thickness)
+}
+
+/// Split a line into dashes, with half a gap at each end to keep spacing across cells uniform.
+fn dash_segments(size: usize, dashes: usize) -> Vec<(usize, usize)> {
+    let gap = (size / dashes / 3).max(1);
+    (0..dashes)
+        .map(|i| {
+            let (start, end) = (size * i / dashes, size * (i + 1) / dashes);
+            (start + gap / 2, end.saturating_sub(gap - gap / 2).max(start + gap / 2))
+        })
+        .collect()
+}
+
+/// Coverage buffer with the size of a single cell.
+struct Canvas {
+    width: usize,
+    height: usize,
+    buffer: Vec<f32>,
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize) -> Self {
+        Self { width, height, buffer: vec![0.; width * height] }
+    }
+
+    fn fill_rect(&mut self, x0: usize, y0: usize, x1: usize, y1: usize) {
+        self.fill_rect_alpha(x0, y0, x1, y1, 1.);
+    }
+
+    fn clear_rect(&mut self, x0: usize, y0: usize, x1: usize, y1: usize) {
+        self.set_rect(x0, y0, x1, y1, |_| 0.);
+    }
+
+    fn fill_rect_alpha(&mut self, x0: usize, y0: usize, x1: usize, y1: usize, alpha: f32) {
+        self.set_rect(x0, y0, x1, y1, |coverage| coverage.max(alpha));
+    }
+
+    fn set_rect<F: Fn(f32) -> f32>(&mut self, x0: usize, y0: usize, x1: usize, y1: usize, f: F) {
+        let (x1, y1) = (x1.min(self.width), y1.min(self.height));
+        for y in y0..y1 {
+            for coverage in &mut self.buffer[y * self.width + x0.min(x1)..y * self.width + x1] {
+                *coverage = f(*coverage);
+            }
+        }
+    }
+
+    /// Fill all pixels covered by a shape, using supersampling for anti-aliasing.
+    fn fill_shape<F: Fn(f32, f32) -> bool>(&mut self, contains: F) {
+        let step = 1. / SAMPLES as f32;
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let mut samples = 0;
+                for sample_y in 0..SAMPLES {
+                    for sample_x in 0..SAMPLES {
+                        let sample_x = x as f32 + (sample_x as f32 + 0.5) * step;
+                        let sample_y = y as f32 + (sample_y as f32 + 0.5) * step;
+                        if contains(sample_x, sample_y) {
+                            samples += 1;
+                        }
+                    }
+                }
+
+                let coverage = &mut self.buffer[y * self.width + x];
+                *coverage = coverage.max(samples as f32 / (SAMPLES * SAMPLES) as f32);
+            }
+        }
+    }
+
+    fn into_rgb(self) -> Vec<u8> {
+        self.buffer
+            .into_iter()
+            .flat_map(|coverage| {
+                let alpha = (coverage.min(1.) * 255.).round() as u8;
+                vec![alpha, alpha, alpha]
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metrics() -> Metrics {
+        Metrics {
+            average_advance: 8.6,
+            line_height: 17.3,
+            descent: -4.,
+            underline_position: -2.,
+            underline_thickness: 1.,
+            strikeout_position: 5.,
+            strikeout_thickness: 1.,
+        }
+    }
+
+    fn glyph(character: char) -> RasterizedGlyph {
+        let offset = Delta { x: 2, y: 3 };
+        builtin_glyph(character, &metrics(), &offset, &Delta::default()).unwrap()
+    }
+
+    fn alpha(glyph: &RasterizedGlyph, x: usize, y: usize) -> u8 {
+        match &glyph.buffer {
+            BitmapBuffer::RGB(buffer) => buffer[(y * glyph.width as usize + x) * 3],
+            BitmapBuffer::RGBA(_) => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn builtin_glyph_size() {
+        for character in ('\u{2500}'..='\u{259f}').chain('\u{e0b0}'..='\u{e0b3}') {
+            let glyph = glyph(character);
+            assert_eq!((glyph.width, glyph.height), (10, 20));
+            assert_eq!((glyph.left, glyph.top), (0, 16));
+        }
+
+        let offset = Delta::default();
+        assert!(builtin_glyph('a', &metrics(), &offset, &offset).is_none());
+    }
+
+    #[test]
+    fn builtin_glyph_offset() {
+        let glyph_offset = Delta { x: 1, y: -2 };
+        let glyph = builtin_glyph('█', &metrics(), &Delta::default(), &glyph_offset).unwrap();
+        assert_eq!((glyph.left, glyph.top), (-1, 15));
+    }
+
+    #[test]
+    fn lines_reach_cell_edges() {
+        let horizontal = glyph('─');
+        let row = (0..20).find(|&y| alpha(&horizontal, 0, y) == 255).unwrap();
+        assert!((0..10).all(|x| alpha(&horizontal, x, row) == 255));
+
+        let vertical = glyph('│');
+        let column = (0..10).find(|&x| alpha(&vertical, x, 0) == 255).unwrap();
+        assert!((0..20).all(|y| alpha(&vertical, column, y) == 255));
+
+        // Corners connect with straight lines in the neighboring cells.
+        let corner = glyph('┌');
+        assert_eq!(alpha(&corner, 9, row), 255);
+        assert_eq!(alpha(&corner, column, 19), 255);
+        assert_eq!(alpha(&corner, 0, row), 0);
+        assert_eq!(alpha(&corner, column, 0), 0);
+    }
+
+    #[test]
+    fn double_lines() {
+        let glyph = glyph('═');
+        let rows: Vec<usize> = (0..20).filter(|&y| alpha(&glyph, 0, y) == 255).collect();
+        assert_eq!(rows, vec![8, 10]);
+        assert!(rows.iter().all(|&y| (0..10).all(|x| alpha(&glyph, x, y) == 255)));
+    }
+
+    #[test]
+    fn blocks() {
+        let full = glyph('█');
+        assert!((0..20).all(|y| (0..10).all(|x| alpha(&full, x, y) == 255)));
+
+        let lower = glyph('▄');
+        assert_eq!(alpha(&lower, 0, 9), 0);
+        assert_eq!(alpha(&lower, 9, 10), 255);
+
+        let shade = glyph('▒');
+        assert_eq!(alpha(&shade, 5, 5), 128);
+    }
+
+    #[test]
+    fn powerline_triangle() {
+        let glyph = glyph('\u{e0b0}');
+        assert!((1..19).all(|y| alpha(&glyph, 0, y) == 255));
+        assert!(alpha(&glyph, 0, 0) > 0 && alpha(&glyph, 0, 19) > 0);
+        assert_eq!(alpha(&glyph, 9, 0), 0);
+        assert!(alpha(&glyph, 9, 10) > 0);
+    }
+}
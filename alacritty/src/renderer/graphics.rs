@@ -0,0 +1,331 @@
+//! Rendering of graphics which are part of the terminal grid.
+
+use std::collections::HashMap;
+use std::mem;
+
+use alacritty_terminal::graphics::{GraphicCell, GraphicData, GraphicId, UpdateQueues};
+use alacritty_terminal::index::Point;
+use alacritty_terminal::term::SizeInfo;
+
+use crate::gl;
+use crate::gl::types::*;
+use crate::renderer;
+
+/// Shader sources for graphics rendering program.
+static GRAPHICS_SHADER_F: &str = include_str!("../../res/graphics.f.glsl");
+static GRAPHICS_SHADER_V: &str = include_str!("../../res/graphics.v.glsl");
+
+/// Fragment of a graphic which should be drawn in a cell.
+#[derive(Debug, Copy, Clone)]
+pub struct RenderGraphic {
+    pub point: Point,
+    pub id: GraphicId,
+    pub offset_x: u16,
+    pub offset_y: u16,
+}
+
+impl RenderGraphic {
+    pub fn new(point: Point, graphic: &GraphicCell) -> Self {
+        RenderGraphic {
+            point,
+            id: graphic.texture.id,
+            offset_x: graphic.offset_x,
+            offset_y: graphic.offset_y,
+        }
+    }
+}
+
+/// Texture holding the pixels of a graphic.
+#[derive(Debug)]
+struct GraphicTexture {
+    /// OpenGL texture id.
+    id: GLuint,
+
+    /// Width in pixels.
+    width: usize,
+
+    /// Height in pixels.
+    height: usize,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct Vertex {
+    // Normalized screen coordinates.
+    x: f32,
+    y: f32,
+
+    // Texture coordinates.
+    u: f32,
+    v: f32,
+}
+
+#[derive(Debug)]
+pub struct GraphicsRenderer {
+    // GL buffer objects.
+    vao: GLuint,
+    vbo: GLuint,
+
+    program: GraphicsShaderProgram,
+
+    /// Uploaded textures of all graphics in the grid.
+    textures: HashMap<GraphicId, GraphicTexture>,
+
+    vertices: Vec<Vertex>,
+}
+
+impl GraphicsRenderer {
+    pub fn new() -> Result<Self, renderer::Error> {
+        let mut vao: GLuint = 0;
+        let mut vbo: GLuint = 0;
+        let program = GraphicsShaderProgram::new()?;
+
+        unsafe {
+            // Allocate buffers.
+            gl::GenVertexArrays(1, &mut vao);
+            gl::GenBuffers(1, &mut vbo);
+
+            gl::BindVertexArray(vao);
+
+            // VBO binding is not part of VAO itself, but VBO binding is stored in attributes.
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+
+            let mut attribute_offset = 0;
+
+            // Position.
+            gl::VertexAttribPointer(
+                0,
+                2,
+                gl::FLOAT,
+                gl::FALSE,
+                mem::size_of::<Vertex>() as i32,
+                attribute_offset as *const _,
+            );
+            gl::EnableVertexAttribArray(0);
+            attribute_offset += mem::size_of::<f32>() * 2;
+
+            // Texture coordinates.
+            gl::VertexAttribPointer(
+                1,
+                2,
+                gl::FLOAT,
+                gl::FALSE,
+                mem::size_of::<Vertex>() as i32,
+                attribute_offset as *const _,
+            );
+            gl::EnableVertexAttribArray(1);
+
+            // Reset buffer bindings.
+            gl::BindVertexArray(0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
+
+        Ok(Self { vao, vbo, program, textures: HashMap::new(), vertices: Vec::new() })
+    }
+
+    /// Upload new graphics and delete the textures which are no longer used.
+    pub fn run_updates(&mut self, updates: UpdateQueues) {
+        for graphic in updates.pending {
+            self.upload(graphic);
+        }
+
+        for id in updates.remove_queue {
+            if let Some(texture) = self.textures.remove(&id) {
+                unsafe { gl::DeleteTextures(1, &texture.id) };
+            }
+        }
+    }
+
+    fn upload(&mut self, graphic: GraphicData) {
+        let mut id: GLuint = 0;
+
+        unsafe {
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::GenTextures(1, &mut id);
+            gl::BindTexture(gl::TEXTURE_2D, id);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGBA as i32,
+                graphic.width as i32,
+                graphic.height as i32,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                graphic.pixels.as_ptr() as *const _,
+            );
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+
+        let texture = GraphicTexture { id, width: graphic.width, height: graphic.height };
+        self.textures.insert(graphic.id, texture);
+    }
+
+    pub fn draw(&mut self, size_info: &SizeInfo, mut graphics: Vec<RenderGraphic>) {
+        // Group cells by their texture, to bind every texture only once.
+        graphics.sort_unstable_by_key(|graphic| graphic.id);
+
+        unsafe {
+            // Bind VAO to enable vertex attribute slots.
+            gl::BindVertexArray(self.vao);
+
+            // Bind VBO only once for buffer data upload only.
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+
+            gl::UseProgram(self.program.id);
+            gl::ActiveTexture(gl::TEXTURE0);
+        }
+
+        let half_width = size_info.width() / 2.;
+        let half_height = size_info.height() / 2.;
+
+        let mut start = 0;
+        while start < graphics.len() {
+            let id = graphics[start].id;
+            let end = graphics[start..]
+                .iter()
+                .position(|graphic| graphic.id != id)
+                .map_or(graphics.len(), |len| start + len);
+            let chunk = &graphics[start..end];
+            start = end;
+
+            let texture = match self.textures.get(&id) {
+                Some(texture) => texture,
+                None => continue,
+            };
+
+            // Build vertices for all cells showing this texture.
+            self.vertices.clear();
+            for graphic in chunk {
+                add_graphic(
+                    &mut self.vertices,
+                    size_info,
+                    half_width,
+                    half_height,
+                    texture,
+                    graphic,
+                );
+            }
+
+            unsafe {
+                gl::BindTexture(gl::TEXTURE_2D, texture.id);
+
+                // Upload accumulated vertices.
+                gl::BufferData(
+                    gl::ARRAY_BUFFER,
+                    (self.vertices.len() * mem::size_of::<Vertex>()) as isize,
+                    self.vertices.as_ptr() as *const _,
+                    gl::STREAM_DRAW,
+                );
+
+                // Draw all vertices as list of triangles.
+                gl::DrawArrays(gl::TRIANGLES, 0, self.vertices.len() as i32);
+            }
+        }
+
+        unsafe {
+            // Disable program.
+            gl::UseProgram(0);
+
+            // Reset buffer and texture bindings to nothing.
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+            gl::BindVertexArray(0);
+        }
+    }
+}
+
+impl Drop for GraphicsRenderer {
+    fn drop(&mut self) {
+        for texture in self.textures.values() {
+            unsafe { gl::DeleteTextures(1, &texture.id) };
+        }
+    }
+}
+
+/// Add the vertices for a single cell of a graphic.
+fn add_graphic(
+    vertices: &mut Vec<Vertex>,
+    size_info: &SizeInfo,
+    half_width: f32,
+    half_height: f32,
+    texture: &GraphicTexture,
+    graphic: &RenderGraphic,
+) {
+    let offset_x = graphic.offset_x as f32;
+    let offset_y = graphic.offset_y as f32;
+    let texture_width = texture.width as f32;
+    let texture_height = texture.height as f32;
+
+    // Clip the cell to the edges of the graphic.
+    let width = size_info.cell_width().min(texture_width - offset_x);
+    let height = size_info.cell_height().min(texture_height - offset_y);
+    if width <= 0. || height <= 0. {
+        return;
+    }
+
+    // Calculate cell position in normalized device coordinates.
+    // NDC range from -1 to +1, with Y pointing up.
+    let column = graphic.point.column.0 as f32;
+    let line = graphic.point.line.0 as f32;
+    let x = size_info.cell_width().mul_add(column, size_info.padding_x()) / half_width - 1.;
+    let y = -size_info.cell_height().mul_add(line, size_info.padding_y()) / half_height + 1.;
+    let ndc_width = width / half_width;
+    let ndc_height = height / half_height;
+
+    // Texture rows are stored top to bottom.
+    let u = offset_x / texture_width;
+    let v = offset_y / texture_height;
+    let uv_width = width / texture_width;
+    let uv_height = height / texture_height;
+
+    // Make quad vertices.
+    let quad = [
+        Vertex { x, y, u, v },
+        Vertex { x, y: y - ndc_height, u, v: v + uv_height },
+        Vertex { x: x + ndc_width, y, u: u + uv_width, v },
+        Vertex { x: x + ndc_width, y: y - ndc_height, u: u + uv_width, v: v + uv_height },
+    ];
+
+    // Append the vertices to form two triangles.
+    vertices.push(quad[0]);
+    vertices.push(quad[1]);
+    vertices.push(quad[2]);
+    vertices.push(quad[2]);
+    vertices.push(quad[3]);
+    vertices.push(quad[1]);
+}
+
+/// Graphics drawing program.
+#[derive(Debug)]
+pub struct GraphicsShaderProgram {
+    /// Program id.
+    id: GLuint,
+}
+
+impl GraphicsShaderProgram {
+    pub fn new() -> Result<Self, renderer::ShaderCreationError> {
+        let vertex_shader = renderer::create_shader(gl::VERTEX_SHADER, GRAPHICS_SHADER_V)?;
+        let fragment_shader = renderer::create_shader(gl::FRAGMENT_SHADER, GRAPHICS_SHADER_F)?;
+        let program = renderer::create_program(vertex_shader, fragment_shader)?;
+
+        unsafe {
+            gl::DeleteShader(fragment_shader);
+            gl::DeleteShader(vertex_shader);
+        }
+
+        Ok(Self { id: program })
+    }
+}
+
+impl Drop for GraphicsShaderProgram {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteProgram(self.id);
+        }
+    }
+}
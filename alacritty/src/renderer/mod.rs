@@ -11,9 +11,11 @@ use crossfont::{
     RasterizedGlyph, Rasterizer, Size, Slant, Style, Weight,
 };
 use fnv::FnvHasher;
+use glutin::Rect;
 use log::{error, info};
 use unicode_width::UnicodeWidthChar;
 
+use alacritty_terminal::graphics::UpdateQueues;
 use alacritty_terminal::index::Point;
 use alacritty_terminal::term::cell::Flags;
 use alacritty_terminal::term::color::Rgb;
@@ -24,9 +26,17 @@ use crate::config::ui_config::{Delta, UIConfig};
 use crate::display::content::RenderableCell;
 use crate::gl;
 use crate::gl::types::*;
+use crate::renderer::framebuffer::Framebuffer;
+use crate::renderer::graphics::{GraphicsRenderer, RenderGraphic};
 use crate::renderer::rects::{RectRenderer, RenderRect};
+use crate::renderer::shaping::{ShapedGlyph, Shaper, SubstitutedGlyph};
 
+mod builtin_font;
+mod framebuffer;
+pub mod graphics;
 pub mod rects;
+pub mod shaping;
+pub mod software;
 
 // Shader source.
 static TEXT_SHADER_F: &str = include_str!("../../res/text.f.glsl");
@@ -43,6 +53,101 @@ pub trait LoadGlyph {
     fn clear(&mut self);
 }
 
+/// Text drawing shared between the OpenGL and the software renderer.
+pub trait TextRenderApi: LoadGlyph + Sized {
+    /// Queue a glyph for drawing in the cell's position.
+    fn add_render_item(&mut self, cell: &RenderableCell, glyph: &Glyph);
+
+    /// Render a string in a variable location. Used for printing the render timer, warnings and
+    /// errors.
+    fn render_string(
+        &mut self,
+        glyph_cache: &mut GlyphCache,
+        point: Point,
+        fg: Rgb,
+        bg: Rgb,
+        string: &str,
+    ) {
+        for cell in string_cells(point, fg, bg, string) {
+            self.render_cell(cell, glyph_cache);
+        }
+    }
+
+    fn render_cell(&mut self, mut cell: RenderableCell, glyph_cache: &mut GlyphCache) {
+        // Get font key for cell.
+        let font_key = glyph_cache.font_key(cell.flags);
+
+        // Ignore hidden cells and render tabs as spaces to prevent font issues.
+        let hidden = cell.flags.contains(Flags::HIDDEN);
+        if cell.character == '\t' || hidden {
+            cell.character = ' ';
+        }
+
+        // Cells covered by a ligature only render their background.
+        if cell.shaped == ShapedGlyph::Continuation {
+            cell.character = ' ';
+        }
+
+        let mut glyph_key =
+            GlyphKey { font_key, size: glyph_cache.font_size, character: cell.character };
+
+        // Add cell to batch.
+        let glyph = match cell.shaped {
+            ShapedGlyph::Substituted(glyph) => glyph_cache.get_shaped(font_key, glyph, self),
+            _ => glyph_cache.get(glyph_key, self, true),
+        };
+        self.add_render_item(&cell, &glyph);
+
+        // Render visible zero-width characters.
+        if let Some(zerowidth) = cell.zerowidth.take().filter(|_| !hidden) {
+            for character in zerowidth {
+                glyph_key.character = character;
+                let glyph = glyph_cache.get(glyph_key, self, false);
+                self.add_render_item(&cell, &glyph);
+            }
+        }
+    }
+}
+
+/// Drawing of frames shared between the OpenGL and the software renderer.
+pub trait Renderer {
+    /// Draw cells on top of the current frame.
+    fn draw_cells<I>(
+        &mut self,
+        config: &UIConfig,
+        size_info: &SizeInfo,
+        glyph_cache: &mut GlyphCache,
+        cells: I,
+    ) where
+        I: IntoIterator<Item = RenderableCell>;
+
+    /// Draw rectangles on top of the current frame.
+    fn draw_rects(&mut self, size_info: &SizeInfo, rects: Vec<RenderRect>);
+}
+
+/// Cells for drawing a string starting at a point.
+pub fn string_cells(
+    point: Point,
+    fg: Rgb,
+    bg: Rgb,
+    string: &str,
+) -> impl Iterator<Item = RenderableCell> + '_ {
+    string.chars().enumerate().map(move |(i, character)| RenderableCell {
+        point: Point::new(point.line, point.column + i),
+        character,
+        zerowidth: None,
+        flags: Flags::empty(),
+        bg_alpha: 1.0,
+        fg,
+        bg,
+        underline: fg,
+        is_match: false,
+        hyperlink: None,
+        graphic: None,
+        shaped: ShapedGlyph::Character,
+    })
+}
+
 #[derive(Debug)]
 pub enum Error {
     ShaderCreation(ShaderCreationError),
@@ -114,6 +219,12 @@ pub struct GlyphCache {
     /// Cache of buffered glyphs.
     cache: HashMap<GlyphKey, Glyph, BuildHasherDefault<FnvHasher>>,
 
+    /// Cache of glyphs substituted by the shaper.
+    shaped_cache: HashMap<(FontKey, SubstitutedGlyph), Glyph, BuildHasherDefault<FnvHasher>>,
+
+    /// Text shaper, if any OpenType features are enabled.
+    shaper: Option<Shaper>,
+
     /// Rasterizer for loading new glyphs.
     rasterizer: Rasterizer,
 
@@ -132,9 +243,15 @@ pub struct GlyphCache {
     /// Font size.
     font_size: crossfont::Size,
 
+    /// Font offset.
+    font_offset: Delta<i8>,
+
     /// Glyph offset.
     glyph_offset: Delta<i8>,
 
+    /// Draw box drawing and block characters without using the font.
+    builtin_box_drawing: bool,
+
     /// Font metrics.
     metrics: crossfont::Metrics,
 }
@@ -143,6 +260,7 @@ impl GlyphCache {
     pub fn new<L>(
         mut rasterizer: Rasterizer,
         font: &Font,
+        dpr: f64,
         loader: &mut L,
     ) -> Result<GlyphCache, crossfont::Error>
     where
@@ -157,15 +275,22 @@ impl GlyphCache {
 
         let metrics = rasterizer.metrics(regular, font.size())?;
 
+        let keys = [regular, bold, italic, bold_italic];
+        let shaper = Shaper::new(font, keys, dpr);
+
         let mut cache = Self {
             cache: HashMap::default(),
+            shaped_cache: HashMap::default(),
+            shaper,
             rasterizer,
             font_size: font.size(),
             font_key: regular,
             bold_key: bold,
             italic_key: italic,
             bold_italic_key: bold_italic,
+            font_offset: font.offset,
             glyph_offset: font.glyph_offset,
+            builtin_box_drawing: font.builtin_box_drawing,
             metrics,
         };
 
@@ -266,8 +391,21 @@ impl GlyphCache {
             return *glyph;
         };
 
-        // Rasterize glyph.
-        let glyph = match self.rasterizer.get_glyph(glyph_key) {
+        // Rasterize the glyph using the built-in font for special characters or the user's font
+        // for everything else.
+        let builtin = if self.builtin_box_drawing {
+            builtin_font::builtin_glyph(
+                glyph_key.character,
+                &self.metrics,
+                &self.font_offset,
+                &self.glyph_offset,
+            )
+        } else {
+            None
+        };
+        let rasterized = builtin.map(Ok).unwrap_or_else(|| self.rasterizer.get_glyph(glyph_key));
+
+        let glyph = match rasterized {
             Ok(rasterized) => self.load_glyph(loader, rasterized),
             // Load fallback glyph.
             Err(RasterizerError::MissingGlyph(rasterized)) if show_missing => {
@@ -290,6 +428,88 @@ impl GlyphCache {
         *self.cache.entry(glyph_key).or_insert(glyph)
     }
 
+    /// Get a glyph picked by the shaper.
+    fn get_shaped<L>(
+        &mut self,
+        font_key: FontKey,
+        shaped: SubstitutedGlyph,
+        loader: &mut L,
+    ) -> Glyph
+    where
+        L: LoadGlyph,
+    {
+        if let Some(glyph) = self.shaped_cache.get(&(font_key, shaped)) {
+            return *glyph;
+        }
+
+        let shaper = self.shaper.as_ref();
+        let rasterized = shaper.and_then(|shaper| shaper.rasterize(font_key, shaped));
+        let glyph = self.load_glyph(loader, rasterized.unwrap_or_default());
+
+        *self.shaped_cache.entry((font_key, shaped)).or_insert(glyph)
+    }
+
+    /// Shape runs of cells with the same style.
+    ///
+    /// This does nothing unless OpenType features are enabled in the font configuration.
+    pub fn shape(&self, cells: &mut [RenderableCell]) {
+        let shaper = match &self.shaper {
+            Some(shaper) => shaper,
+            None => return,
+        };
+
+        let mut start = 0;
+        while start < cells.len() {
+            let mut end = start + 1;
+            while end < cells.len() && self.continues_run(&cells[end - 1], &cells[end]) {
+                end += 1;
+            }
+
+            if end - start > 1 {
+                let text: Vec<char> = cells[start..end].iter().map(|cell| cell.character).collect();
+                let font_key = self.font_key(cells[start].flags);
+                let shaped = shaper.shape(font_key, &text);
+                for (cell, shaped) in cells[start..end].iter_mut().zip(shaped) {
+                    cell.shaped = shaped;
+                }
+            }
+
+            start = end;
+        }
+    }
+
+    /// Check if two cells are part of the same shaping run.
+    fn continues_run(&self, previous: &RenderableCell, cell: &RenderableCell) -> bool {
+        let shapeable = |cell: &RenderableCell| {
+            cell.zerowidth.is_none()
+                && cell.character != '\t'
+                && !(self.builtin_box_drawing && builtin_font::is_builtin(cell.character))
+                && !cell.flags.intersects(
+                    Flags::HIDDEN
+                        | Flags::WIDE_CHAR
+                        | Flags::WIDE_CHAR_SPACER
+                        | Flags::LEADING_WIDE_CHAR_SPACER,
+                )
+        };
+
+        shapeable(previous)
+            && shapeable(cell)
+            && previous.point.line == cell.point.line
+            && previous.point.column + 1 == cell.point.column
+            && previous.fg == cell.fg
+            && previous.flags & Flags::BOLD_ITALIC == cell.flags & Flags::BOLD_ITALIC
+    }
+
+    /// Get the font key for a cell's style.
+    fn font_key(&self, flags: Flags) -> FontKey {
+        match flags & Flags::BOLD_ITALIC {
+            Flags::BOLD_ITALIC => self.bold_italic_key,
+            Flags::ITALIC => self.italic_key,
+            Flags::BOLD => self.bold_key,
+            _ => self.font_key,
+        }
+    }
+
     /// Load glyph into the atlas.
     ///
     /// This will apply all transforms defined for the glyph cache to the rasterized glyph before
@@ -319,6 +539,7 @@ impl GlyphCache {
     pub fn clear_glyph_cache<L: LoadGlyph>(&mut self, loader: &mut L) {
         loader.clear();
         self.cache = HashMap::default();
+        self.shaped_cache = HashMap::default();
 
         self.load_common_glyphs(loader);
     }
@@ -345,11 +566,19 @@ impl GlyphCache {
 
         info!("Font size changed to {:?} with DPR of {}", font.size(), dpr);
 
+        let keys = [regular, bold, italic, bold_italic];
+        self.shaper = match self.shaper.take() {
+            Some(shaper) => shaper.update(font, keys, dpr),
+            None => Shaper::new(font, keys, dpr),
+        };
+
         self.font_size = font.size();
         self.font_key = regular;
         self.bold_key = bold;
         self.italic_key = italic;
         self.bold_italic_key = bold_italic;
+        self.font_offset = font.offset;
+        self.builtin_box_drawing = font.builtin_box_drawing;
         self.metrics = metrics;
 
         self.clear_glyph_cache(loader);
@@ -439,6 +668,10 @@ pub struct QuadRenderer {
     batch: Batch,
 
     rect_renderer: RectRenderer,
+    graphics_renderer: GraphicsRenderer,
+
+    /// Offscreen framebuffer preserving the grid between frames.
+    framebuffer: Option<Framebuffer>,
 }
 
 #[derive(Debug)]
@@ -638,6 +871,7 @@ impl QuadRenderer {
         let mut renderer = Self {
             program,
             rect_renderer: RectRenderer::new()?,
+            graphics_renderer: GraphicsRenderer::new()?,
             vao,
             ebo,
             vbo_instance,
@@ -645,6 +879,7 @@ impl QuadRenderer {
             current_atlas: 0,
             active_tex: 0,
             batch: Batch::new(),
+            framebuffer: None,
         };
 
         let atlas = Atlas::new(ATLAS_SIZE);
@@ -682,6 +917,58 @@ impl QuadRenderer {
         }
     }
 
+    /// Upload new graphics and release the ones which are no longer visible.
+    pub fn graphics_run_updates(&mut self, updates: UpdateQueues) {
+        self.graphics_renderer.run_updates(updates);
+
+        // Texture uploads change the bound texture.
+        self.active_tex = 0;
+    }
+
+    /// Draw all graphics fragments in the grid.
+    pub fn draw_graphics(&mut self, size_info: &SizeInfo, graphics: Vec<RenderGraphic>) {
+        if graphics.is_empty() {
+            return;
+        }
+
+        // Prepare graphics rendering state.
+        unsafe {
+            // Remove padding from viewport.
+            gl::Viewport(0, 0, size_info.width() as i32, size_info.height() as i32);
+            gl::BlendFuncSeparate(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA, gl::SRC_ALPHA, gl::ONE);
+        }
+
+        self.graphics_renderer.draw(size_info, graphics);
+        self.active_tex = 0;
+
+        // Activate regular state again.
+        unsafe {
+            // Reset blending strategy.
+            gl::BlendFunc(gl::SRC1_COLOR, gl::ONE_MINUS_SRC1_COLOR);
+
+            // Restore viewport with padding.
+            let padding_x = size_info.padding_x() as i32;
+            let padding_y = size_info.padding_y() as i32;
+            let width = size_info.width() as i32;
+            let height = size_info.height() as i32;
+            gl::Viewport(padding_x, padding_y, width - 2 * padding_x, height - 2 * padding_y);
+        }
+    }
+
+    /// Draw the grid into the offscreen framebuffer, which keeps its content between frames.
+    pub fn bind_framebuffer(&self) {
+        if let Some(framebuffer) = &self.framebuffer {
+            framebuffer.bind();
+        }
+    }
+
+    /// Copy the grid into the window, to draw everything else on top of it.
+    pub fn blit_framebuffer(&self) {
+        if let Some(framebuffer) = &self.framebuffer {
+            framebuffer.blit();
+        }
+    }
+
     pub fn with_api<F, T>(&mut self, config: &UIConfig, props: &SizeInfo, func: F) -> T
     where
         F: FnOnce(RenderApi<'_>) -> T,
@@ -751,20 +1038,45 @@ impl QuadRenderer {
             );
             gl::UseProgram(0);
         }
+
+        // Recreate the framebuffer with the new size, discarding its content.
+        self.framebuffer = Some(Framebuffer::new(size));
+        self.active_tex = 0;
     }
 }
 
 impl<'a> RenderApi<'a> {
     pub fn clear(&self, color: Rgb) {
+        self.set_clear_color(color);
+        unsafe {
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+        }
+    }
+
+    /// Clear only the specified regions of the window.
+    pub fn clear_rects(&self, color: Rgb, rects: &[Rect]) {
+        self.set_clear_color(color);
+        unsafe {
+            gl::Enable(gl::SCISSOR_TEST);
+
+            for rect in rects {
+                gl::Scissor(rect.x as i32, rect.y as i32, rect.width as i32, rect.height as i32);
+                gl::Clear(gl::COLOR_BUFFER_BIT);
+            }
+
+            gl::Disable(gl::SCISSOR_TEST);
+        }
+    }
+
+    fn set_clear_color(&self, color: Rgb) {
+        let alpha = self.config.background_opacity();
         unsafe {
-            let alpha = self.config.background_opacity();
             gl::ClearColor(
                 (f32::from(color.r) / 255.0).min(1.0) * alpha,
                 (f32::from(color.g) / 255.0).min(1.0) * alpha,
                 (f32::from(color.b) / 255.0).min(1.0) * alpha,
                 alpha,
             );
-            gl::Clear(gl::COLOR_BUFFER_BIT);
         }
     }
 
@@ -814,37 +1126,31 @@ impl<'a> RenderApi<'a> {
 
         self.batch.clear();
     }
+}
 
-    /// Render a string in a variable location. Used for printing the render timer, warnings and
-    /// errors.
-    pub fn render_string(
+impl Renderer for QuadRenderer {
+    fn draw_cells<I>(
         &mut self,
+        config: &UIConfig,
+        size_info: &SizeInfo,
         glyph_cache: &mut GlyphCache,
-        point: Point,
-        fg: Rgb,
-        bg: Rgb,
-        string: &str,
-    ) {
-        let cells = string
-            .chars()
-            .enumerate()
-            .map(|(i, character)| RenderableCell {
-                point: Point::new(point.line, point.column + i),
-                character,
-                zerowidth: None,
-                flags: Flags::empty(),
-                bg_alpha: 1.0,
-                fg,
-                bg,
-                is_match: false,
-            })
-            .collect::<Vec<_>>();
-
-        for cell in cells {
-            self.render_cell(cell, glyph_cache);
-        }
+        cells: I,
+    ) where
+        I: IntoIterator<Item = RenderableCell>,
+    {
+        self.with_api(config, size_info, |mut api| {
+            for cell in cells {
+                api.render_cell(cell, glyph_cache);
+            }
+        });
     }
 
+    fn draw_rects(&mut self, size_info: &SizeInfo, rects: Vec<RenderRect>) {
+        QuadRenderer::draw_rects(self, size_info, rects);
+    }
+}
+
+impl<'a> TextRenderApi for RenderApi<'a> {
     #[inline]
     fn add_render_item(&mut self, cell: &RenderableCell, glyph: &Glyph) {
         // Flush batch if tex changing.
@@ -859,38 +1165,6 @@ impl<'a> RenderApi<'a> {
             self.render_batch();
         }
     }
-
-    pub fn render_cell(&mut self, mut cell: RenderableCell, glyph_cache: &mut GlyphCache) {
-        // Get font key for cell.
-        let font_key = match cell.flags & Flags::BOLD_ITALIC {
-            Flags::BOLD_ITALIC => glyph_cache.bold_italic_key,
-            Flags::ITALIC => glyph_cache.italic_key,
-            Flags::BOLD => glyph_cache.bold_key,
-            _ => glyph_cache.font_key,
-        };
-
-        // Ignore hidden cells and render tabs as spaces to prevent font issues.
-        let hidden = cell.flags.contains(Flags::HIDDEN);
-        if cell.character == '\t' || hidden {
-            cell.character = ' ';
-        }
-
-        let mut glyph_key =
-            GlyphKey { font_key, size: glyph_cache.font_size, character: cell.character };
-
-        // Add cell to batch.
-        let glyph = glyph_cache.get(glyph_key, self, true);
-        self.add_render_item(&cell, &glyph);
-
-        // Render visible zero-width characters.
-        if let Some(zerowidth) = cell.zerowidth.take().filter(|_| !hidden) {
-            for character in zerowidth {
-                glyph_key.character = character;
-                let glyph = glyph_cache.get(glyph_key, self, false);
-                self.add_render_item(&cell, &glyph);
-            }
-        }
-    }
 }
 
 /// Load a glyph into a texture atlas.
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::f32::consts::PI;
 use std::mem;
 
 use crossfont::Metrics;
@@ -79,6 +80,45 @@ impl RenderLine {
 
                 (bottom_pos, metrics.underline_thickness)
             },
+            Flags::UNDERCURL => {
+                // Center the curl in the descent, leaving room for its amplitude.
+                let position = metrics.descent / 2.;
+                let rect = Self::create_rect(
+                    size,
+                    metrics.descent,
+                    start,
+                    end,
+                    position,
+                    metrics.underline_thickness,
+                    color,
+                );
+
+                let amplitude = (-metrics.descent / 4.).max(1.);
+                Self::push_undercurl(rects, size, rect, amplitude);
+                return;
+            },
+            Flags::DOTTED_UNDERLINE | Flags::DASHED_UNDERLINE => {
+                let rect = Self::create_rect(
+                    size,
+                    metrics.descent,
+                    start,
+                    end,
+                    metrics.underline_position,
+                    metrics.underline_thickness,
+                    color,
+                );
+
+                // Dots are square, while every cell contains a single dash.
+                let (on, off) = if flag == Flags::DOTTED_UNDERLINE {
+                    (rect.height, rect.height)
+                } else {
+                    let dash = (size.cell_width() * 0.6).round().max(1.);
+                    (dash, size.cell_width() - dash)
+                };
+
+                Self::push_segments(rects, rect, on, off);
+                return;
+            },
             Flags::UNDERLINE => (metrics.underline_position, metrics.underline_thickness),
             Flags::STRIKEOUT => (metrics.strikeout_position, metrics.strikeout_thickness),
             _ => unimplemented!("Invalid flag for cell line drawing specified"),
@@ -95,6 +135,43 @@ impl RenderLine {
         ));
     }
 
+    /// Split a line's rect into segments of `on` pixels, separated by `off` pixels.
+    fn push_segments(rects: &mut Vec<RenderRect>, rect: RenderRect, on: f32, off: f32) {
+        let mut offset = 0.;
+        while offset < rect.width {
+            let width = on.min(rect.width - offset);
+            rects.push(RenderRect { x: rect.x + offset, width, ..rect });
+            offset += on + off;
+        }
+    }
+
+    /// Approximate a wave along a line's rect, with one period per cell.
+    fn push_undercurl(
+        rects: &mut Vec<RenderRect>,
+        size: &SizeInfo,
+        rect: RenderRect,
+        amplitude: f32,
+    ) {
+        let step = rect.height;
+        let mut previous_y = None;
+
+        let mut offset = 0.;
+        while offset < rect.width {
+            let width = step.min(rect.width - offset);
+            let phase = (offset + width / 2.) / size.cell_width() * 2. * PI;
+            let y = (rect.y - amplitude * phase.sin()).round();
+
+            // Stretch each segment vertically to connect it to the previous one.
+            let top = previous_y.map_or(y, |previous_y: f32| previous_y.min(y));
+            let bottom = previous_y.map_or(y, |previous_y: f32| previous_y.max(y));
+            let height = bottom - top + rect.height;
+            rects.push(RenderRect { x: rect.x + offset, y: top, width, height, ..rect });
+
+            previous_y = Some(y);
+            offset += step;
+        }
+    }
+
     /// Create a line's rect at a position relative to the baseline.
     fn create_rect(
         size: &SizeInfo,
@@ -159,6 +236,9 @@ impl RenderLines {
     pub fn update(&mut self, cell: &RenderableCell) {
         self.update_flag(&cell, Flags::UNDERLINE);
         self.update_flag(&cell, Flags::DOUBLE_UNDERLINE);
+        self.update_flag(&cell, Flags::UNDERCURL);
+        self.update_flag(&cell, Flags::DOTTED_UNDERLINE);
+        self.update_flag(&cell, Flags::DASHED_UNDERLINE);
         self.update_flag(&cell, Flags::STRIKEOUT);
     }
 
@@ -174,9 +254,12 @@ impl RenderLines {
             end.column += 1;
         }
 
+        // Underlines use their own color, which defaults to the foreground.
+        let color = if flag == Flags::STRIKEOUT { cell.fg } else { cell.underline };
+
         // Check if there's an active line.
         if let Some(line) = self.inner.get_mut(&flag).and_then(|lines| lines.last_mut()) {
-            if cell.fg == line.color
+            if color == line.color
                 && cell.point.column == line.end.column + 1
                 && cell.point.line == line.end.line
             {
@@ -187,7 +270,7 @@ impl RenderLines {
         }
 
         // Start new line if there currently is none.
-        let line = RenderLine { start: cell.point, end, color: cell.fg };
+        let line = RenderLine { start: cell.point, end, color };
         match self.inner.get_mut(&flag) {
             Some(lines) => lines.push(line),
             None => {
@@ -378,3 +461,51 @@ impl Drop for RectShaderProgram {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alacritty_terminal::index::Line;
+
+    fn rects(flag: Flags) -> Vec<RenderRect> {
+        let metrics = Metrics {
+            average_advance: 10.,
+            line_height: 20.,
+            descent: -8.,
+            underline_position: -2.,
+            underline_thickness: 1.,
+            strikeout_position: 5.,
+            strikeout_thickness: 1.,
+        };
+        let size = SizeInfo::new(100., 100., 10., 20., 0., 0., false);
+        let line = RenderLine {
+            start: Point::new(Line(0), Column(0)),
+            end: Point::new(Line(0), Column(1)),
+            color: Rgb::default(),
+        };
+
+        line.rects(flag, &metrics, &size)
+    }
+
+    #[test]
+    fn underline_styles() {
+        let dotted = rects(Flags::DOTTED_UNDERLINE);
+        assert_eq!(dotted.len(), 10);
+        assert!(dotted.iter().all(|rect| rect.width == 1. && rect.height == 1.));
+
+        let dashed = rects(Flags::DASHED_UNDERLINE);
+        assert_eq!(
+            dashed.iter().map(|rect| (rect.x, rect.width)).collect::<Vec<_>>(),
+            vec![(0., 6.), (10., 6.)]
+        );
+
+        // Undercurl segments are connected and stay within the cell.
+        let undercurl = rects(Flags::UNDERCURL);
+        assert_eq!(undercurl.len(), 20);
+        for (previous, rect) in undercurl.iter().zip(&undercurl[1..]) {
+            assert!(rect.y <= previous.y + previous.height && previous.y <= rect.y + rect.height);
+            assert!(rect.y + rect.height <= 20.);
+        }
+    }
+}
@@ -0,0 +1,360 @@
+//! Font faces loaded through Fontconfig and FreeType.
+//!
+//! Faces are resolved with the same Fontconfig patterns crossfont uses for the primary fonts and
+//! glyphs are rendered with the same FreeType settings, so substituted glyphs match the glyphs
+//! rasterized by crossfont.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crossfont::ft::fc::{self, Pattern, PatternRef, Rgba};
+use crossfont::{BitmapBuffer, RasterizedGlyph, Size, Slant, Weight};
+use freetype::bitmap::{Bitmap, PixelMode};
+use freetype::face::LoadFlag;
+use freetype::{ffi, LcdFilter, Library, Matrix, RenderMode};
+use log::{info, warn};
+use rustybuzz::Face;
+use ttf_parser::GlyphId;
+
+use crate::config::font::FontDescription;
+
+/// Loader for the font files of all font styles.
+pub struct FontLoader {
+    library: Library,
+
+    /// Font files which have already been loaded, with their path and face index.
+    files: Vec<(PathBuf, isize, Rc<FontFile>)>,
+}
+
+impl FontLoader {
+    pub fn new() -> Option<Self> {
+        match Library::init() {
+            Ok(library) => Some(Self { library, files: Vec::new() }),
+            Err(err) => {
+                warn!("Unable to initialize FreeType, text shaping disabled: {}", err);
+                None
+            },
+        }
+    }
+
+    /// Load the primary font for a font description.
+    ///
+    /// Returns `None` if the font has no layout tables.
+    pub fn load(
+        &mut self,
+        desc: &FontDescription,
+        slant: Slant,
+        weight: Weight,
+        size: Size,
+        dpr: f32,
+    ) -> Option<ShapingFont> {
+        // Resolve the font exactly like crossfont, so both use the same file and settings.
+        let pixelsize = size.as_f32_pts() * dpr * 96. / 72.;
+
+        let config = fc::Config::get_current();
+        let mut pattern = Pattern::new();
+        pattern.add_family(&desc.family);
+        pattern.add_pixelsize(f64::from(pixelsize));
+
+        match &desc.style {
+            Some(style) => {
+                pattern.add_style(style);
+            },
+            None => {
+                pattern.set_weight(match weight {
+                    Weight::Normal => fc::Weight::Regular,
+                    Weight::Bold => fc::Weight::Bold,
+                });
+                pattern.set_slant(match slant {
+                    Slant::Normal => fc::Slant::Roman,
+                    Slant::Italic => fc::Slant::Italic,
+                    Slant::Oblique => fc::Slant::Oblique,
+                });
+            },
+        }
+
+        pattern.config_substitute(config, fc::MatchKind::Pattern);
+        pattern.default_substitute();
+
+        let fonts = fc::font_sort(config, &pattern)?;
+        let primary_font = pattern.render_prepare(config, fonts.into_iter().next()?);
+        let location = primary_font.ft_face_location(0)?;
+
+        let existing = self
+            .files
+            .iter()
+            .find(|(path, index, _)| *path == location.path && *index == location.index);
+        let file = match existing {
+            Some((.., file)) => file.clone(),
+            None => {
+                let file = match FontFile::new(&self.library, &location.path, location.index) {
+                    Some(file) => Rc::new(file),
+                    None => {
+                        warn!("Unable to load {} for text shaping", location.path.display());
+                        return None;
+                    },
+                };
+
+                info!("Shaping {:?} using {}", desc.family, location.path.display());
+                self.files.push((location.path, location.index, file.clone()));
+
+                file
+            },
+        };
+
+        // Fonts without layout tables have nothing to shape, colored fonts only contain bitmaps.
+        let tables = file.face.tables();
+        if (tables.gsub.is_none() && tables.gpos.is_none()) || file.ft_face.has_color() {
+            return None;
+        }
+
+        let non_scalable = if primary_font.scalable().next().unwrap_or(true) {
+            None
+        } else {
+            primary_font.pixelsize().next().map(|pixelsize| pixelsize as f32)
+        };
+
+        let matrix = primary_font.get_matrix().map(|matrix| Matrix {
+            xx: (matrix.xx * 65536.0) as ffi::FT_Fixed,
+            xy: (matrix.xy * 65536.0) as ffi::FT_Fixed,
+            yx: (matrix.yx * 65536.0) as ffi::FT_Fixed,
+            yy: (matrix.yy * 65536.0) as ffi::FT_Fixed,
+        });
+
+        Some(ShapingFont {
+            file,
+            load_flags: load_flags(&primary_font),
+            render_mode: render_mode(&primary_font),
+            lcd_filter: lcd_filter(&primary_font),
+            embolden: primary_font.embolden().next().unwrap_or(false),
+            matrix,
+            rgba: primary_font.rgba().next().unwrap_or(Rgba::Unknown),
+            pixelsize: non_scalable.unwrap_or(pixelsize),
+        })
+    }
+
+    /// Rasterize a glyph by its glyph ID.
+    pub fn rasterize(&self, font: &ShapingFont, glyph_id: GlyphId) -> Option<RasterizedGlyph> {
+        let ft_face = &font.file.ft_face;
+        ft_face.set_char_size((font.pixelsize * 64.).round() as isize, 0, 0, 0).ok()?;
+
+        self.library.set_lcd_filter(font.lcd_filter).ok()?;
+        ft_face.load_glyph(u32::from(glyph_id.0), font.load_flags).ok()?;
+
+        let glyph = ft_face.glyph();
+
+        // Generate synthetic bold.
+        if font.embolden {
+            unsafe {
+                let slot = glyph.raw() as *const ffi::FT_GlyphSlotRec as *mut ffi::FT_GlyphSlotRec;
+                ffi::FT_GlyphSlot_Embolden(slot);
+            }
+        }
+
+        // Transform glyphs with the matrix from Fontconfig, which is used to generate italics.
+        if let Some(matrix) = &font.matrix {
+            let slot = glyph.raw();
+            if slot.format == ffi::FT_GLYPH_FORMAT_OUTLINE {
+                unsafe { ffi::FT_Outline_Transform(&slot.outline, matrix) };
+            }
+        }
+
+        glyph.render_glyph(font.render_mode).ok()?;
+
+        let (height, width, buffer) = normalize_buffer(&glyph.bitmap(), &font.rgba)?;
+
+        Some(RasterizedGlyph {
+            top: glyph.bitmap_top(),
+            left: glyph.bitmap_left(),
+            width,
+            height,
+            buffer,
+            ..Default::default()
+        })
+    }
+}
+
+/// Font file parsed for shaping and rasterization.
+pub struct FontFile {
+    /// Face used for shaping, borrowing from `data`.
+    face: Face<'static>,
+
+    ft_face: freetype::Face,
+
+    /// Font data shared with the FreeType face.
+    ///
+    /// This has to be declared after `face`, so it outlives the borrowed tables.
+    _data: Rc<Vec<u8>>,
+}
+
+impl FontFile {
+    fn new(library: &Library, path: &Path, index: isize) -> Option<Self> {
+        let data = Rc::new(fs::read(path).ok()?);
+        let ft_face = library.new_memory_face(data.clone(), index).ok()?;
+
+        // SAFETY: The data is never modified and lives as long as the parsed face, since both
+        // are stored in the same struct and the face is dropped first.
+        let bytes: &'static [u8] = unsafe { &*(data.as_slice() as *const [u8]) };
+        let face = Face::from_slice(bytes, index as u32)?;
+
+        Some(Self { face, ft_face, _data: data })
+    }
+}
+
+/// Primary font of a font style.
+pub struct ShapingFont {
+    file: Rc<FontFile>,
+    load_flags: LoadFlag,
+    render_mode: RenderMode,
+    lcd_filter: LcdFilter,
+    embolden: bool,
+    matrix: Option<Matrix>,
+    rgba: Rgba,
+
+    /// Size of the font in pixels.
+    pixelsize: f32,
+}
+
+impl ShapingFont {
+    /// Glyph ID of a character, using the same character map as the rasterizer.
+    pub fn glyph_index(&self, c: char) -> Option<GlyphId> {
+        match self.file.ft_face.get_char_index(c as usize) {
+            0 => None,
+            index => Some(GlyphId(index as u16)),
+        }
+    }
+
+    /// Face used for shaping.
+    pub fn face(&self) -> &Face<'_> {
+        &self.file.face
+    }
+
+    /// Pixels per font unit.
+    pub fn scale(&self) -> f32 {
+        self.pixelsize / self.file.face.units_per_em() as f32
+    }
+}
+
+/// FreeType load flags, matching crossfont's settings for a Fontconfig pattern.
+fn load_flags(pattern: &PatternRef) -> LoadFlag {
+    let antialias = pattern.antialias().next().unwrap_or(true);
+    let autohint = pattern.autohint().next().unwrap_or(false);
+    let hinting = pattern.hinting().next().unwrap_or(true);
+    let rgba = pattern.rgba().next().unwrap_or(Rgba::Unknown);
+    let embedded_bitmaps = pattern.embeddedbitmap().next().unwrap_or(true);
+    let scalable = pattern.scalable().next().unwrap_or(true);
+
+    let hintstyle = if hinting {
+        pattern.hintstyle().next().unwrap_or(fc::HintStyle::Full)
+    } else {
+        fc::HintStyle::None
+    };
+
+    let mut flags = match (antialias, hintstyle, rgba) {
+        (false, fc::HintStyle::None, _) => LoadFlag::NO_HINTING | LoadFlag::MONOCHROME,
+        (false, ..) => LoadFlag::TARGET_MONO | LoadFlag::MONOCHROME,
+        (true, fc::HintStyle::None, _) => LoadFlag::NO_HINTING,
+        (true, fc::HintStyle::Slight, _) => LoadFlag::TARGET_LIGHT,
+        (true, fc::HintStyle::Medium, _) => LoadFlag::TARGET_NORMAL,
+        (true, fc::HintStyle::Full, Rgba::Rgb) | (true, fc::HintStyle::Full, Rgba::Bgr) => {
+            LoadFlag::TARGET_LCD
+        },
+        (true, fc::HintStyle::Full, Rgba::Vrgb) | (true, fc::HintStyle::Full, Rgba::Vbgr) => {
+            LoadFlag::TARGET_LCD_V
+        },
+        (true, fc::HintStyle::Full, Rgba::Unknown) | (true, fc::HintStyle::Full, Rgba::None) => {
+            LoadFlag::TARGET_NORMAL
+        },
+    };
+
+    if !embedded_bitmaps && scalable {
+        flags |= LoadFlag::NO_BITMAP;
+    }
+
+    if autohint {
+        flags |= LoadFlag::FORCE_AUTOHINT;
+    }
+
+    flags
+}
+
+fn render_mode(pattern: &PatternRef) -> RenderMode {
+    let antialias = pattern.antialias().next().unwrap_or(true);
+    let rgba = pattern.rgba().next().unwrap_or(Rgba::Unknown);
+
+    match (antialias, rgba) {
+        (false, _) => RenderMode::Mono,
+        (_, Rgba::Rgb) | (_, Rgba::Bgr) => RenderMode::Lcd,
+        (_, Rgba::Vrgb) | (_, Rgba::Vbgr) => RenderMode::LcdV,
+        (true, _) => RenderMode::Normal,
+    }
+}
+
+fn lcd_filter(pattern: &PatternRef) -> LcdFilter {
+    match pattern.lcdfilter().next().unwrap_or(fc::LcdFilter::Default) {
+        fc::LcdFilter::None => LcdFilter::LcdFilterNone,
+        fc::LcdFilter::Default => LcdFilter::LcdFilterDefault,
+        fc::LcdFilter::Light => LcdFilter::LcdFilterLight,
+        fc::LcdFilter::Legacy => LcdFilter::LcdFilterLegacy,
+    }
+}
+
+/// Convert a FreeType bitmap to a buffer with one byte per LCD channel.
+///
+/// Returns the height and width of the glyph in pixels along with the buffer.
+fn normalize_buffer(bitmap: &Bitmap, rgba: &Rgba) -> Option<(i32, i32, BitmapBuffer)> {
+    let buf = bitmap.buffer();
+    let pitch = bitmap.pitch().abs() as usize;
+    let (rows, width) = (bitmap.rows() as usize, bitmap.width() as usize);
+    let mut packed = Vec::with_capacity(rows * width * 3);
+
+    match bitmap.pixel_mode().ok()? {
+        PixelMode::Lcd => {
+            for row in 0..rows {
+                let start = row * pitch;
+                match rgba {
+                    Rgba::Bgr => {
+                        for rgb in buf[start..start + width].chunks(3) {
+                            packed.extend_from_slice(&[rgb[2], rgb[1], rgb[0]]);
+                        }
+                    },
+                    _ => packed.extend_from_slice(&buf[start..start + width]),
+                }
+            }
+            Some((rows as i32, (width / 3) as i32, BitmapBuffer::RGB(packed)))
+        },
+        PixelMode::LcdV => {
+            for row in 0..rows / 3 {
+                for column in 0..width {
+                    for channel in 0..3 {
+                        let channel = if let Rgba::Vbgr = rgba { 2 - channel } else { channel };
+                        packed.push(buf[(row * 3 + channel) * pitch + column]);
+                    }
+                }
+            }
+            Some(((rows / 3) as i32, width as i32, BitmapBuffer::RGB(packed)))
+        },
+        // Mono data is stored using 1 bit per pixel, starting with the most significant bit.
+        PixelMode::Mono => {
+            for row in 0..rows {
+                for column in 0..width {
+                    let byte = buf[row * pitch + column / 8];
+                    let value = ((byte >> (7 - column % 8)) & 1) * 255;
+                    packed.extend_from_slice(&[value; 3]);
+                }
+            }
+            Some((rows as i32, width as i32, BitmapBuffer::RGB(packed)))
+        },
+        PixelMode::Gray => {
+            for row in 0..rows {
+                let start = row * pitch;
+                for &value in &buf[start..start + width] {
+                    packed.extend_from_slice(&[value; 3]);
+                }
+            }
+            Some((rows as i32, width as i32, BitmapBuffer::RGB(packed)))
+        },
+        _ => None,
+    }
+}
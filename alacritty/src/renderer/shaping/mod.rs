@@ -0,0 +1,468 @@
+//! OpenType text shaping.
+//!
+//! Runs of cells with the same style are shaped with rustybuzz, which applies the GSUB and GPOS
+//! tables of a font just like HarfBuzz does. This enables programming ligatures and contextual
+//! alternates, along with the substitutions required by other scripts. To keep the grid intact,
+//! every glyph is drawn in the cell of the first character it replaces, while the remaining cells
+//! of a ligature only draw their background.
+
+use std::ops::Range;
+
+use crossfont::{FontKey, RasterizedGlyph, Size, Slant, Weight};
+use rustybuzz::{Face, Feature, UnicodeBuffer};
+use ttf_parser::GlyphId;
+use unicode_script::{Script, UnicodeScript};
+
+use crate::config::font::{Font, FontDescription};
+
+#[cfg(not(any(target_os = "macos", windows)))]
+mod ft;
+
+use ft::{FontLoader, ShapingFont};
+
+/// Glyph picked for a single grid cell.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ShapedGlyph {
+    /// Glyph of the cell's character.
+    Character,
+
+    /// Glyph substituted or positioned by the shaper.
+    Substituted(SubstitutedGlyph),
+
+    /// Cell is covered by a ligature drawn in a previous cell.
+    Continuation,
+}
+
+impl Default for ShapedGlyph {
+    fn default() -> Self {
+        ShapedGlyph::Character
+    }
+}
+
+/// Glyph ID and position picked by the shaper.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SubstitutedGlyph {
+    pub id: u16,
+
+    /// Offset from the regular glyph position in pixels, pointing right and up.
+    pub x_offset: i32,
+    pub y_offset: i32,
+}
+
+/// Font faces used for text shaping.
+pub struct Shaper {
+    /// Font configuration used to load the font faces.
+    features: Vec<Feature>,
+    descriptions: Vec<FontDescription>,
+
+    /// Font keys and faces of the regular, bold, italic and bold italic styles.
+    keys: [FontKey; 4],
+    fonts: Vec<Option<ShapingFont>>,
+
+    loader: FontLoader,
+}
+
+impl Shaper {
+    /// Load the fonts of all font styles.
+    ///
+    /// Returns `None` if no features are configured or none of the fonts have layout tables.
+    pub fn new(font: &Font, keys: [FontKey; 4], dpr: f64) -> Option<Self> {
+        if font.features.is_empty() {
+            return None;
+        }
+
+        let loader = FontLoader::new()?;
+        let mut shaper = Self {
+            features: Self::features(font),
+            descriptions: Self::descriptions(font),
+            keys,
+            fonts: Vec::new(),
+            loader,
+        };
+        shaper.load_fonts(font.size(), dpr);
+
+        if shaper.fonts.iter().all(Option::is_none) {
+            None
+        } else {
+            Some(shaper)
+        }
+    }
+
+    /// Update the shaper after the font configuration changed.
+    ///
+    /// Font files are only reloaded if the font faces or features have changed.
+    pub fn update(mut self, font: &Font, keys: [FontKey; 4], dpr: f64) -> Option<Self> {
+        if self.features != Self::features(font) || self.descriptions != Self::descriptions(font) {
+            return Self::new(font, keys, dpr);
+        }
+
+        // Font keys and rendering settings depend on the font size, so they have to be updated.
+        self.keys = keys;
+        self.load_fonts(font.size(), dpr);
+
+        Some(self)
+    }
+
+    /// Shape a run of characters with the same style.
+    ///
+    /// Returns one glyph for every character.
+    pub fn shape(&self, font_key: FontKey, text: &[char]) -> Vec<ShapedGlyph> {
+        let mut shaped = vec![ShapedGlyph::Character; text.len()];
+        let font = match self.font(font_key) {
+            Some(font) => font,
+            None => return shaped,
+        };
+
+        // Characters missing from the font are drawn using fallback fonts, so they split runs.
+        let mut start = 0;
+        while start < text.len() {
+            let len = text[start..].iter().take_while(|&&c| font.glyph_index(c).is_some()).count();
+            if len == 0 {
+                start += 1;
+                continue;
+            }
+
+            let end = start + len;
+            for run in script_runs(&text[start..end]) {
+                let run = start + run.start..start + run.end;
+                let glyphs =
+                    shape_run(font.face(), &self.features, &text[run.clone()], font.scale());
+                shaped[run].copy_from_slice(&glyphs);
+            }
+            start = end;
+        }
+
+        shaped
+    }
+
+    /// Rasterize a glyph picked by the shaper.
+    pub fn rasterize(&self, font_key: FontKey, glyph: SubstitutedGlyph) -> Option<RasterizedGlyph> {
+        let mut rasterized = self.loader.rasterize(self.font(font_key)?, GlyphId(glyph.id))?;
+        rasterized.left += glyph.x_offset;
+        rasterized.top += glyph.y_offset;
+        Some(rasterized)
+    }
+
+    fn font(&self, font_key: FontKey) -> Option<&ShapingFont> {
+        let index = self.keys.iter().position(|&key| key == font_key)?;
+        self.fonts[index].as_ref()
+    }
+
+    /// Resolve the fonts of all styles, like the rasterizer does for their font keys.
+    fn load_fonts(&mut self, size: Size, dpr: f64) {
+        let styles = [
+            (Slant::Normal, Weight::Normal),
+            (Slant::Normal, Weight::Bold),
+            (Slant::Italic, Weight::Normal),
+            (Slant::Italic, Weight::Bold),
+        ];
+
+        let loader = &mut self.loader;
+        self.fonts = self
+            .descriptions
+            .iter()
+            .zip(&styles)
+            .map(|(desc, &(slant, weight))| loader.load(desc, slant, weight, size, dpr as f32))
+            .collect();
+    }
+
+    fn features(font: &Font) -> Vec<Feature> {
+        font.features.iter().map(|feature| feature.feature()).collect()
+    }
+
+    fn descriptions(font: &Font) -> Vec<FontDescription> {
+        vec![font.normal().clone(), font.bold(), font.italic(), font.bold_italic()]
+    }
+}
+
+/// Fonts are only loaded through FreeType, so shaping is unavailable on other platforms.
+#[cfg(any(target_os = "macos", windows))]
+mod ft {
+    use log::warn;
+
+    use super::*;
+
+    pub enum FontLoader {}
+
+    impl FontLoader {
+        pub fn new() -> Option<Self> {
+            warn!("Text shaping requires FreeType and Fontconfig, features are ignored");
+            None
+        }
+
+        pub fn load(
+            &mut self,
+            _: &FontDescription,
+            _: Slant,
+            _: Weight,
+            _: Size,
+            _: f32,
+        ) -> Option<ShapingFont> {
+            match *self {}
+        }
+
+        pub fn rasterize(&self, _: &ShapingFont, _: GlyphId) -> Option<RasterizedGlyph> {
+            match *self {}
+        }
+    }
+
+    pub enum ShapingFont {}
+
+    impl ShapingFont {
+        pub fn glyph_index(&self, _: char) -> Option<GlyphId> {
+            match *self {}
+        }
+
+        pub fn face(&self) -> &Face<'_> {
+            match *self {}
+        }
+
+        pub fn scale(&self) -> f32 {
+            match *self {}
+        }
+    }
+}
+
+/// Split text into runs of a single script.
+///
+/// Characters shared between scripts, like punctuation and combining marks, are added to the
+/// surrounding run.
+fn script_runs(text: &[char]) -> Vec<Range<usize>> {
+    let mut runs = Vec::new();
+    let mut run_script = None;
+    let mut start = 0;
+
+    for (index, c) in text.iter().enumerate() {
+        let script = match c.script() {
+            Script::Common | Script::Inherited | Script::Unknown => continue,
+            script => script,
+        };
+
+        if run_script.map_or(false, |run_script| run_script != script) {
+            runs.push(start..index);
+            start = index;
+        }
+        run_script = Some(script);
+    }
+    runs.push(start..text.len());
+
+    runs
+}
+
+/// Shape a run of characters with a single script, which are all present in the font.
+///
+/// Glyph offsets are converted from font units to pixels using `scale`.
+fn shape_run(face: &Face<'_>, features: &[Feature], text: &[char], scale: f32) -> Vec<ShapedGlyph> {
+    let string: String = text.iter().collect();
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str(&string);
+    buffer.guess_segment_properties();
+
+    let output = rustybuzz::shape(face, features, buffer);
+
+    // Clusters are byte offsets into the string, so they have to be mapped back to the cells.
+    let offsets: Vec<usize> = string.char_indices().map(|(offset, _)| offset).collect();
+    let glyphs: Vec<BufferGlyph> = output
+        .glyph_infos()
+        .iter()
+        .zip(output.glyph_positions())
+        .map(|(info, position)| BufferGlyph {
+            glyph: SubstitutedGlyph {
+                id: info.glyph_id as u16,
+                x_offset: (position.x_offset as f32 * scale).round() as i32,
+                y_offset: (position.y_offset as f32 * scale).round() as i32,
+            },
+            cluster: offsets.binary_search(&(info.cluster as usize)).unwrap_or_else(|i| i),
+        })
+        .collect();
+
+    let original: Vec<u16> =
+        text.iter().map(|&c| face.glyph_index(c).map_or(0, |glyph| glyph.0)).collect();
+
+    map_clusters(&original, glyphs)
+}
+
+/// Glyph returned by the shaper.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct BufferGlyph {
+    glyph: SubstitutedGlyph,
+
+    /// Index of the first cell covered by this glyph.
+    cluster: usize,
+}
+
+/// Map shaped glyphs back onto the cells of their clusters.
+///
+/// Every cluster covers all cells up to the start of the next cluster. Since cells can only draw a
+/// single glyph, clusters which were shaped into multiple glyphs keep their original characters.
+fn map_clusters(original: &[u16], mut glyphs: Vec<BufferGlyph>) -> Vec<ShapedGlyph> {
+    let mut shaped = vec![ShapedGlyph::Character; original.len()];
+
+    // Glyphs of right-to-left scripts are returned in visual order.
+    glyphs.sort_by_key(|glyph| glyph.cluster);
+
+    let mut index = 0;
+    while index < glyphs.len() {
+        let cluster = glyphs[index].cluster;
+        let count = glyphs[index..].iter().take_while(|glyph| glyph.cluster == cluster).count();
+        let end = glyphs.get(index + count).map_or(original.len(), |glyph| glyph.cluster);
+        let glyph = glyphs[index].glyph;
+        index += count;
+
+        let modified = end - cluster > 1
+            || glyph.id != original[cluster]
+            || glyph.x_offset != 0
+            || glyph.y_offset != 0;
+        if count > 1 || !modified {
+            continue;
+        }
+
+        shaped[cluster] = ShapedGlyph::Substituted(glyph);
+        for cell in &mut shaped[cluster + 1..end] {
+            *cell = ShapedGlyph::Continuation;
+        }
+    }
+
+    shaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glyph(id: u16, cluster: usize) -> BufferGlyph {
+        BufferGlyph { glyph: SubstitutedGlyph { id, x_offset: 0, y_offset: 0 }, cluster }
+    }
+
+    fn substituted(id: u16) -> ShapedGlyph {
+        ShapedGlyph::Substituted(SubstitutedGlyph { id, x_offset: 0, y_offset: 0 })
+    }
+
+    #[test]
+    fn map_unchanged_clusters() {
+        let shaped = map_clusters(&[1, 2], vec![glyph(1, 0), glyph(2, 1)]);
+        assert_eq!(shaped, vec![ShapedGlyph::Character, ShapedGlyph::Character]);
+    }
+
+    #[test]
+    fn map_contextual_alternates() {
+        // Contextual ligatures replace every character with a glyph of the same width.
+        let shaped = map_clusters(&[1, 2, 3], vec![glyph(1, 0), glyph(20, 1), glyph(30, 2)]);
+        assert_eq!(shaped, vec![ShapedGlyph::Character, substituted(20), substituted(30)]);
+    }
+
+    #[test]
+    fn map_ligature_clusters() {
+        let shaped = map_clusters(&[1, 2, 3, 4], vec![glyph(10, 0), glyph(4, 3)]);
+        assert_eq!(shaped, vec![
+            substituted(10),
+            ShapedGlyph::Continuation,
+            ShapedGlyph::Continuation,
+            ShapedGlyph::Character,
+        ]);
+    }
+
+    #[test]
+    fn map_right_to_left_clusters() {
+        let shaped = map_clusters(&[1, 2, 3], vec![glyph(3, 2), glyph(10, 0)]);
+        assert_eq!(shaped, vec![substituted(10), ShapedGlyph::Continuation, ShapedGlyph::Character]);
+    }
+
+    #[test]
+    fn map_multiple_glyphs() {
+        // Clusters with multiple glyphs do not fit into their cells.
+        let shaped = map_clusters(&[1, 2], vec![glyph(1, 0), glyph(20, 1), glyph(21, 1)]);
+        assert_eq!(shaped, vec![ShapedGlyph::Character, ShapedGlyph::Character]);
+    }
+
+    #[test]
+    fn map_positioned_glyphs() {
+        let mut positioned = glyph(2, 1);
+        positioned.glyph.y_offset = 3;
+
+        let shaped = map_clusters(&[1, 2], vec![glyph(1, 0), positioned]);
+        let expected = ShapedGlyph::Substituted(positioned.glyph);
+        assert_eq!(shaped, vec![ShapedGlyph::Character, expected]);
+    }
+
+    #[test]
+    fn split_script_runs() {
+        let text: Vec<char> = "a->b αβ! a".chars().collect();
+        assert_eq!(script_runs(&text), vec![0..5, 5..9, 9..10]);
+
+        let text: Vec<char> = "!=".chars().collect();
+        assert_eq!(script_runs(&text), vec![0..2]);
+    }
+
+    /// Build a font with the tables required by ttf-parser, mapping `a` to `c` to glyphs 1 to 3.
+    fn font_with_gsub(gsub: &[u16]) -> Vec<u8> {
+        #[rustfmt::skip]
+        let cmap: &[u16] = &[
+            // Header with a single Unicode subtable.
+            0, 1, 3, 10, 0, 12,
+            // Segmented coverage of a single group.
+            12, 0, 0, 28, 0, 0, 0, 1, 0, 0x61, 0, 0x63, 0, 1,
+        ];
+        let mut head = vec![0; 54];
+        head[18..20].copy_from_slice(&1000u16.to_be_bytes());
+        let hhea = vec![0; 36];
+        let maxp = vec![0, 0, 0x50, 0, 0, 16];
+        let words = |words: &[u16]| -> Vec<u8> {
+            words.iter().flat_map(|word| word.to_be_bytes().to_vec()).collect()
+        };
+        let tables = [
+            (b"GSUB", words(gsub)),
+            (b"cmap", words(cmap)),
+            (b"head", head),
+            (b"hhea", hhea),
+            (b"maxp", maxp),
+        ];
+
+        let mut font = vec![0, 1, 0, 0, 0, tables.len() as u8, 0, 0, 0, 0, 0, 0];
+        let mut offset = font.len() + tables.len() * 16;
+        for (tag, table) in &tables {
+            font.extend_from_slice(*tag);
+            font.extend_from_slice(&[0; 4]);
+            font.extend_from_slice(&(offset as u32).to_be_bytes());
+            font.extend_from_slice(&(table.len() as u32).to_be_bytes());
+            offset += table.len();
+        }
+        for (_, table) in &tables {
+            font.extend_from_slice(table);
+        }
+
+        font
+    }
+
+    #[test]
+    fn shape_ligatures() {
+        // The `liga` feature of the default script replaces glyphs 1 and 2 with glyph 10.
+        #[rustfmt::skip]
+        let data = font_with_gsub(&[
+            // Header with the offsets of the script, feature and lookup lists.
+            1, 0, 10, 30, 44,
+            // Script list with the default script and its default language.
+            1, 0x4446, 0x4c54, 8, 4, 0, 0, 0xffff, 1, 0,
+            // Feature list with the `liga` feature.
+            1, 0x6c69, 0x6761, 8, 0, 1, 0,
+            // Lookup list with a single ligature substitution.
+            1, 4, 4, 0, 1, 8,
+            // Ligature substitution with the coverage of glyph 1 and its ligature set.
+            1, 8, 1, 14, 1, 1, 1, 1, 4, 10, 2, 2,
+        ]);
+        let face = Face::from_slice(&data, 0).unwrap();
+        let text: Vec<char> = "abcba".chars().collect();
+
+        // Ligatures are enabled by default.
+        assert_eq!(shape_run(&face, &[], &text, 1.), vec![
+            substituted(10),
+            ShapedGlyph::Continuation,
+            ShapedGlyph::Character,
+            ShapedGlyph::Character,
+            ShapedGlyph::Character,
+        ]);
+
+        let features = ["-liga".parse().unwrap()];
+        assert_eq!(shape_run(&face, &features, &text, 1.), vec![ShapedGlyph::Character; 5]);
+    }
+}
@@ -0,0 +1,440 @@
+//! CPU rendering backend.
+//!
+//! This draws the same glyphs and rectangles as the OpenGL renderer into an RGBA buffer in
+//! memory, which allows rendering frames on machines without a GPU.
+
+use std::io::Write;
+
+use crossfont::{BitmapBuffer, RasterizedGlyph};
+
+use alacritty_terminal::term::cell::Flags;
+use alacritty_terminal::term::color::Rgb;
+use alacritty_terminal::term::SizeInfo;
+
+use crate::config::ui_config::UIConfig;
+use crate::display::content::RenderableCell;
+use crate::renderer::rects::RenderRect;
+use crate::renderer::{Glyph, GlyphCache, LoadGlyph, Renderer, TextRenderApi};
+
+/// Pixels of a glyph loaded into the software renderer.
+#[derive(Debug)]
+struct GlyphBitmap {
+    width: usize,
+    height: usize,
+
+    /// RGB coverage mask for regular glyphs and premultiplied RGBA for colored ones.
+    buffer: Vec<u8>,
+}
+
+/// Glyph queued for drawing.
+#[derive(Debug)]
+struct RenderItem {
+    column: usize,
+    line: usize,
+    glyph: Glyph,
+    fg: Rgb,
+    bg: Rgb,
+    bg_alpha: f32,
+    wide: bool,
+}
+
+/// Renderer drawing into a premultiplied RGBA buffer.
+#[derive(Debug, Default)]
+pub struct SoftwareRenderer {
+    width: usize,
+    height: usize,
+    buffer: Vec<u8>,
+    glyphs: Vec<GlyphBitmap>,
+}
+
+#[derive(Debug)]
+pub struct SoftwareApi<'a> {
+    renderer: &'a mut SoftwareRenderer,
+    items: Vec<RenderItem>,
+    size_info: &'a SizeInfo,
+    config: &'a UIConfig,
+}
+
+#[derive(Debug)]
+pub struct SoftwareLoaderApi<'a> {
+    glyphs: &'a mut Vec<GlyphBitmap>,
+}
+
+impl SoftwareRenderer {
+    /// Resize the frame buffer, discarding its content.
+    pub fn resize(&mut self, size: &SizeInfo) {
+        self.width = size.width() as usize;
+        self.height = size.height() as usize;
+        self.buffer = vec![0; self.width * self.height * 4];
+    }
+
+    pub fn with_api<F, T>(&mut self, config: &UIConfig, size_info: &SizeInfo, func: F) -> T
+    where
+        F: FnOnce(SoftwareApi<'_>) -> T,
+    {
+        func(SoftwareApi { renderer: self, items: Vec::new(), size_info, config })
+    }
+
+    pub fn with_loader<F, T>(&mut self, func: F) -> T
+    where
+        F: FnOnce(SoftwareLoaderApi<'_>) -> T,
+    {
+        func(SoftwareLoaderApi { glyphs: &mut self.glyphs })
+    }
+
+    /// Draw rectangles on top of the current frame.
+    pub fn draw_rects(&mut self, size_info: &SizeInfo, rects: Vec<RenderRect>) {
+        let (width, height) = (size_info.width(), size_info.height());
+        for rect in rects {
+            let alpha = f32::from((rect.alpha * 255.) as u8) / 255.;
+            let color = [rect.color.r, rect.color.g, rect.color.b];
+
+            let (x0, x1) =
+                (pixel_edge(rect.x.max(0.)), pixel_edge((rect.x + rect.width).min(width)));
+            let (y0, y1) =
+                (pixel_edge(rect.y.max(0.)), pixel_edge((rect.y + rect.height).min(height)));
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    let pixel = self.pixel_mut(x, y);
+                    for (channel, &color) in pixel.iter_mut().zip(&color) {
+                        *channel = blend(f32::from(color) / 255. * alpha, *channel, alpha);
+                    }
+                    let dst_alpha = f32::from(pixel[3]) / 255.;
+                    pixel[3] = to_u8(alpha * alpha + dst_alpha);
+                }
+            }
+        }
+    }
+
+    /// Encode the frame as PNG.
+    pub fn write_png<W: Write>(&self, writer: W) -> Result<(), png::EncodingError> {
+        let mut encoder = png::Encoder::new(writer, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::RGBA);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        // PNG expects straight alpha.
+        let mut data = self.buffer.clone();
+        for pixel in data.chunks_mut(4) {
+            let alpha = u32::from(pixel[3]);
+            if alpha != 0 && alpha != 255 {
+                for channel in &mut pixel[..3] {
+                    *channel = (u32::from(*channel) * 255 / alpha).min(255) as u8;
+                }
+            }
+        }
+
+        encoder.write_header()?.write_image_data(&data)
+    }
+
+    fn pixel_mut(&mut self, x: usize, y: usize) -> &mut [u8] {
+        let index = (y * self.width + x) * 4;
+        &mut self.buffer[index..index + 4]
+    }
+}
+
+impl Renderer for SoftwareRenderer {
+    fn draw_cells<I>(
+        &mut self,
+        config: &UIConfig,
+        size_info: &SizeInfo,
+        glyph_cache: &mut GlyphCache,
+        cells: I,
+    ) where
+        I: IntoIterator<Item = RenderableCell>,
+    {
+        self.with_api(config, size_info, |mut api| {
+            for cell in cells {
+                api.render_cell(cell, glyph_cache);
+            }
+        });
+    }
+
+    fn draw_rects(&mut self, size_info: &SizeInfo, rects: Vec<RenderRect>) {
+        SoftwareRenderer::draw_rects(self, size_info, rects);
+    }
+}
+
+impl<'a> SoftwareApi<'a> {
+    pub fn clear(&mut self, color: Rgb) {
+        let alpha = self.config.background_opacity();
+        let pixel = [
+            to_u8(f32::from(color.r) / 255. * alpha),
+            to_u8(f32::from(color.g) / 255. * alpha),
+            to_u8(f32::from(color.b) / 255. * alpha),
+            to_u8(alpha),
+        ];
+
+        for chunk in self.renderer.buffer.chunks_mut(4) {
+            chunk.copy_from_slice(&pixel);
+        }
+    }
+
+    /// Draw all queued glyphs, backgrounds first.
+    fn flush(&mut self) {
+        let size = self.size_info;
+        let (cell_width, cell_height) = (size.cell_width() as i32, size.cell_height() as i32);
+        let (padding_x, padding_y) = (size.padding_x() as i32, size.padding_y() as i32);
+
+        // Everything outside of the padding is clipped, like with the OpenGL viewport.
+        let clip_x = padding_x..(size.width() as i32 - padding_x).min(self.renderer.width as i32);
+        let clip_y = padding_y..(size.height() as i32 - padding_y).min(self.renderer.height as i32);
+
+        let SoftwareRenderer { width: buffer_width, buffer, glyphs, .. } = &mut *self.renderer;
+        let pixel_index = |x: i32, y: i32| (y as usize * *buffer_width + x as usize) * 4;
+
+        for item in self.items.iter().filter(|item| item.bg_alpha != 0.) {
+            let x = padding_x + item.column as i32 * cell_width;
+            let y = padding_y + item.line as i32 * cell_height;
+            let width = if item.wide { cell_width * 2 } else { cell_width };
+
+            let bg = [item.bg.r, item.bg.g, item.bg.b, 255];
+            for y in y.max(clip_y.start)..(y + cell_height).min(clip_y.end) {
+                for x in x.max(clip_x.start)..(x + width).min(clip_x.end) {
+                    let index = pixel_index(x, y);
+                    buffer[index..index + 4].copy_from_slice(&bg);
+                }
+            }
+        }
+
+        for item in self.items.drain(..) {
+            let glyph = &item.glyph;
+            let bitmap = match glyphs.get(glyph.tex_id as usize) {
+                Some(bitmap) if bitmap.width != 0 && bitmap.height != 0 => bitmap,
+                _ => continue,
+            };
+
+            let x = padding_x + item.column as i32 * cell_width + i32::from(glyph.left);
+            let y = padding_y + item.line as i32 * cell_height + cell_height - i32::from(glyph.top);
+
+            for row in 0..bitmap.height {
+                let pixel_y = y + row as i32;
+                if !clip_y.contains(&pixel_y) {
+                    continue;
+                }
+
+                for column in 0..bitmap.width {
+                    let pixel_x = x + column as i32;
+                    if !clip_x.contains(&pixel_x) {
+                        continue;
+                    }
+
+                    let index = pixel_index(pixel_x, pixel_y);
+                    let pixel = &mut buffer[index..index + 4];
+                    if glyph.multicolor {
+                        let index = (row * bitmap.width + column) * 4;
+                        let src = &bitmap.buffer[index..index + 4];
+                        let alpha = f32::from(src[3]) / 255.;
+                        for (channel, &color) in pixel.iter_mut().zip(src) {
+                            *channel = blend(f32::from(color) / 255., *channel, alpha);
+                        }
+                    } else {
+                        let index = (row * bitmap.width + column) * 3;
+                        let mask = &bitmap.buffer[index..index + 3];
+                        let fg = [item.fg.r, item.fg.g, item.fg.b];
+                        for ((channel, &color), &mask) in pixel.iter_mut().zip(&fg).zip(mask) {
+                            let mask = f32::from(mask) / 255.;
+                            *channel = blend(f32::from(color) / 255. * mask, *channel, mask);
+                        }
+                        let mask = f32::from(mask[0]) / 255.;
+                        pixel[3] = blend(mask, pixel[3], mask);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<'a> TextRenderApi for SoftwareApi<'a> {
+    fn add_render_item(&mut self, cell: &RenderableCell, glyph: &Glyph) {
+        self.items.push(RenderItem {
+            column: cell.point.column.0,
+            line: cell.point.line.0,
+            glyph: *glyph,
+            fg: cell.fg,
+            bg: cell.bg,
+            bg_alpha: cell.bg_alpha,
+            wide: cell.flags.contains(Flags::WIDE_CHAR),
+        });
+    }
+}
+
+impl<'a> Drop for SoftwareApi<'a> {
+    fn drop(&mut self) {
+        if !self.items.is_empty() {
+            self.flush();
+        }
+    }
+}
+
+impl<'a> LoadGlyph for SoftwareApi<'a> {
+    fn load_glyph(&mut self, rasterized: &RasterizedGlyph) -> Glyph {
+        load_glyph(&mut self.renderer.glyphs, rasterized)
+    }
+
+    fn clear(&mut self) {
+        self.renderer.glyphs.clear();
+    }
+}
+
+impl<'a> LoadGlyph for SoftwareLoaderApi<'a> {
+    fn load_glyph(&mut self, rasterized: &RasterizedGlyph) -> Glyph {
+        load_glyph(self.glyphs, rasterized)
+    }
+
+    fn clear(&mut self) {
+        self.glyphs.clear();
+    }
+}
+
+/// Store a glyph's pixels, using its index in place of the texture ID.
+fn load_glyph(glyphs: &mut Vec<GlyphBitmap>, rasterized: &RasterizedGlyph) -> Glyph {
+    let (multicolor, buffer) = match &rasterized.buffer {
+        BitmapBuffer::RGB(buffer) => (false, buffer.clone()),
+        BitmapBuffer::RGBA(buffer) => (true, buffer.clone()),
+    };
+
+    let width = rasterized.width.max(0) as usize;
+    let height = rasterized.height.max(0) as usize;
+    glyphs.push(GlyphBitmap { width, height, buffer });
+
+    Glyph {
+        tex_id: (glyphs.len() - 1) as u32,
+        multicolor,
+        top: rasterized.top as i16,
+        left: rasterized.left as i16,
+        width: width as i16,
+        height: height as i16,
+        uv_bot: 0.,
+        uv_left: 0.,
+        uv_width: 0.,
+        uv_height: 0.,
+    }
+}
+
+/// Index of the first pixel whose center lies beyond the edge.
+fn pixel_edge(position: f32) -> usize {
+    (position - 0.5).ceil().max(0.) as usize
+}
+
+/// Blend a premultiplied source with the destination.
+fn blend(src: f32, dst: u8, alpha: f32) -> u8 {
+    to_u8(src + f32::from(dst) / 255. * (1. - alpha))
+}
+
+fn to_u8(value: f32) -> u8 {
+    (value.max(0.).min(1.) * 255.).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alacritty_terminal::index::{Column, Line, Point};
+
+    const RED: Rgb = Rgb { r: 255, g: 0, b: 0 };
+    const WHITE: Rgb = Rgb { r: 255, g: 255, b: 255 };
+
+    fn size_info() -> SizeInfo {
+        SizeInfo::new(24., 24., 10., 20., 2., 2., false)
+    }
+
+    fn renderer(size: &SizeInfo) -> SoftwareRenderer {
+        let mut renderer = SoftwareRenderer::default();
+        renderer.resize(size);
+        renderer
+    }
+
+    fn pixel(renderer: &SoftwareRenderer, x: usize, y: usize) -> [u8; 4] {
+        let index = (y * renderer.width + x) * 4;
+        let mut pixel = [0; 4];
+        pixel.copy_from_slice(&renderer.buffer[index..index + 4]);
+        pixel
+    }
+
+    fn render_item(glyph: Glyph, point: Point, bg_alpha: f32) -> RenderItem {
+        RenderItem {
+            column: point.column.0,
+            line: point.line.0,
+            glyph,
+            fg: WHITE,
+            bg: RED,
+            bg_alpha,
+            wide: false,
+        }
+    }
+
+    #[test]
+    fn clear_and_rects() {
+        let size = size_info();
+        let config = UIConfig::default();
+        let mut renderer = renderer(&size);
+
+        renderer.with_api(&config, &size, |mut api| api.clear(Rgb { r: 0, g: 0, b: 255 }));
+        assert_eq!(pixel(&renderer, 0, 0), [0, 0, 255, 255]);
+
+        let rects = vec![
+            RenderRect::new(0., 0., 12., 24., RED, 1.),
+            RenderRect::new(12., 0., 12., 24., WHITE, 0.5),
+        ];
+        renderer.draw_rects(&size, rects);
+
+        assert_eq!(pixel(&renderer, 11, 5), [255, 0, 0, 255]);
+        assert_eq!(pixel(&renderer, 12, 5), [127, 127, 255, 255]);
+    }
+
+    #[test]
+    fn glyph_blending() {
+        let size = size_info();
+        let config = UIConfig::default();
+        let mut renderer = renderer(&size);
+
+        // Glyph with a fully covered top row and a half covered bottom row.
+        let rasterized = RasterizedGlyph {
+            character: 'x',
+            width: 2,
+            height: 2,
+            top: 20,
+            left: 1,
+            buffer: BitmapBuffer::RGB(vec![255; 6].into_iter().chain(vec![128; 6]).collect()),
+        };
+        let glyph = renderer.with_loader(|mut api| api.load_glyph(&rasterized));
+
+        renderer.with_api(&config, &size, |mut api| {
+            api.clear(Rgb { r: 0, g: 0, b: 0 });
+            api.items.push(render_item(glyph, Point::new(Line(0), Column(0)), 1.));
+            api.items.push(render_item(glyph, Point::new(Line(0), Column(1)), 0.));
+        });
+
+        // Cell backgrounds are opaque and clipped to the padding.
+        assert_eq!(pixel(&renderer, 2, 10), [255, 0, 0, 255]);
+        assert_eq!(pixel(&renderer, 12, 10), [0, 0, 0, 255]);
+        assert_eq!(pixel(&renderer, 1, 10), [0, 0, 0, 255]);
+
+        // Glyphs are blended using their coverage.
+        assert_eq!(pixel(&renderer, 3, 2), [255, 255, 255, 255]);
+        assert_eq!(pixel(&renderer, 3, 3), [255, 128, 128, 255]);
+        assert_eq!(pixel(&renderer, 13, 3), [128, 128, 128, 255]);
+    }
+
+    #[test]
+    fn png_output() {
+        let size = size_info();
+        let config = UIConfig::default();
+        let mut renderer = renderer(&size);
+
+        // Translucent white, like the background with an opacity of 50%.
+        renderer.with_api(&config, &size, |mut api| api.clear(WHITE));
+        renderer.buffer[..4].copy_from_slice(&[128, 128, 128, 128]);
+
+        let mut png = Vec::new();
+        renderer.write_png(&mut png).unwrap();
+
+        let decoder = png::Decoder::new(png.as_slice());
+        let (info, mut reader) = decoder.read_info().unwrap();
+        assert_eq!((info.width, info.height), (24, 24));
+
+        let mut data = vec![0; info.buffer_size()];
+        reader.next_frame(&mut data).unwrap();
+        assert_eq!(&data[..4], &[255, 255, 255, 128]);
+    }
+}
@@ -3,17 +3,30 @@
 use std::collections::VecDeque;
 use std::time::{Duration, Instant};
 
-use glutin::event::Event as GlutinEvent;
+use glutin::event_loop::EventLoopProxy;
+use glutin::window::WindowId;
 
-use crate::event::Event as AlacrittyEvent;
-
-type Event = GlutinEvent<'static, AlacrittyEvent>;
+use crate::event::Event;
 
 /// ID uniquely identifying a timer.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
-pub enum TimerId {
+pub struct TimerId {
+    topic: Topic,
+    window_id: WindowId,
+}
+
+impl TimerId {
+    pub fn new(topic: Topic, window_id: WindowId) -> Self {
+        Self { topic, window_id }
+    }
+}
+
+/// Available timer topics.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Topic {
     SelectionScrolling,
     DelayedSearch,
+    SearchCount,
     BlinkCursor,
 }
 
@@ -29,24 +42,19 @@ pub struct Timer {
 /// Scheduler tracking all pending timers.
 pub struct Scheduler {
     timers: VecDeque<Timer>,
-}
-
-impl Default for Scheduler {
-    fn default() -> Self {
-        Self { timers: VecDeque::new() }
-    }
+    event_proxy: EventLoopProxy<Event>,
 }
 
 impl Scheduler {
-    pub fn new() -> Self {
-        Self::default()
+    pub fn new(event_proxy: EventLoopProxy<Event>) -> Self {
+        Self { timers: VecDeque::new(), event_proxy }
     }
 
     /// Process all pending timers.
     ///
-    /// If there are still timers pending after all ready events have been processed, the closest
-    /// pending deadline will be returned.
-    pub fn update(&mut self, event_queue: &mut Vec<Event>) -> Option<Instant> {
+    /// Events of expired timers are sent to the event loop. If there are still timers pending
+    /// after all ready events have been processed, the closest pending deadline will be returned.
+    pub fn update(&mut self) -> Option<Instant> {
         let now = Instant::now();
         while !self.timers.is_empty() && self.timers[0].deadline <= now {
             if let Some(timer) = self.timers.pop_front() {
@@ -55,7 +63,7 @@ impl Scheduler {
                     self.schedule(timer.event.clone(), interval, true, timer.id);
                 }
 
-                event_queue.push(timer.event);
+                let _ = self.event_proxy.send_event(timer.event);
             }
         }
 
@@ -96,6 +104,11 @@ impl Scheduler {
         self.timers.iter().any(|timer| timer.id == id)
     }
 
+    /// Remove all timers scheduled for a window.
+    pub fn unschedule_window(&mut self, window_id: WindowId) {
+        self.timers.retain(|timer| timer.id.window_id != window_id);
+    }
+
     /// Access a staged event by ID.
     pub fn get_mut(&mut self, id: TimerId) -> Option<&mut Timer> {
         self.timers.iter_mut().find(|timer| timer.id == id)
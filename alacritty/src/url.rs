@@ -6,12 +6,13 @@ use glutin::event::{ElementState, ModifiersState};
 use urlocator::{UrlLocation, UrlLocator};
 
 use alacritty_terminal::index::{Column, Point};
-use alacritty_terminal::term::cell::Flags;
+use alacritty_terminal::term::cell::{Flags, Hyperlink};
 use alacritty_terminal::term::color::Rgb;
 use alacritty_terminal::term::SizeInfo;
 
 use crate::config::Config;
 use crate::display::content::RenderableCell;
+use crate::display::damage::DamageTracker;
 use crate::event::Mouse;
 use crate::renderer::rects::{RenderLine, RenderRect};
 
@@ -20,6 +21,7 @@ pub struct Url {
     lines: Vec<RenderLine>,
     end_offset: u16,
     num_cols: Column,
+    hyperlink: Option<Hyperlink>,
 }
 
 impl Url {
@@ -44,6 +46,11 @@ impl Url {
     pub fn end(&self) -> Point {
         self.lines[self.lines.len() - 1].end.sub(self.num_cols, self.end_offset as usize)
     }
+
+    /// Explicit hyperlink target set through OSC 8.
+    pub fn hyperlink(&self) -> Option<&Hyperlink> {
+        self.hyperlink.as_ref()
+    }
 }
 
 pub struct Urls {
@@ -51,6 +58,7 @@ pub struct Urls {
     urls: Vec<Url>,
     scheme_buffer: Vec<(Point, Rgb)>,
     last_point: Option<Point>,
+    last_hyperlink: Option<Hyperlink>,
     state: UrlLocation,
 }
 
@@ -62,6 +70,7 @@ impl Default for Urls {
             urls: Vec::new(),
             state: UrlLocation::Reset,
             last_point: None,
+            last_hyperlink: None,
         }
     }
 }
@@ -82,12 +91,34 @@ impl Urls {
         }
 
         // Reset URL when empty cells have been skipped.
-        if point != Point::default() && Some(point.sub(num_cols, 1)) != self.last_point {
+        let contiguous =
+            point == Point::default() || Some(point.sub(num_cols, 1)) == self.last_point;
+        if !contiguous {
             self.reset();
         }
 
         self.last_point = Some(end);
 
+        // Explicit hyperlinks take precedence over URLs detected in the cell content.
+        let last_hyperlink = self.last_hyperlink.take();
+        if let Some(hyperlink) = &cell.hyperlink {
+            self.reset();
+
+            if !contiguous || last_hyperlink.as_ref() != Some(hyperlink) {
+                self.urls.push(Url {
+                    lines: Vec::new(),
+                    end_offset: 0,
+                    num_cols,
+                    hyperlink: Some(hyperlink.clone()),
+                });
+            }
+
+            self.extend_url(point, end, cell.fg, 0);
+            self.last_hyperlink = Some(hyperlink.clone());
+
+            return;
+        }
+
         // Extend current state if a leading wide char spacer is encountered.
         if cell.flags.intersects(Flags::LEADING_WIDE_CHAR_SPACER) {
             if let UrlLocation::Url(_, mut end_offset) = self.state {
@@ -106,7 +137,7 @@ impl Urls {
         match (self.state, last_state) {
             (UrlLocation::Url(_length, end_offset), UrlLocation::Scheme) => {
                 // Create empty URL.
-                self.urls.push(Url { lines: Vec::new(), end_offset, num_cols });
+                self.urls.push(Url { lines: Vec::new(), end_offset, num_cols, hyperlink: None });
 
                 // Push schemes into URL.
                 for (scheme_point, scheme_fg) in self.scheme_buffer.split_off(0) {
@@ -174,13 +205,35 @@ impl Urls {
     }
 
     /// Find URL at location.
+    ///
+    /// Hyperlinks are merged with all other visible parts sharing the same ID and URI.
     pub fn find_at(&self, point: Point) -> Option<Url> {
-        for url in &self.urls {
-            if (url.start()..=url.end()).contains(&point) {
-                return Some(url.clone());
-            }
+        let mut url =
+            self.urls.iter().find(|url| (url.start()..=url.end()).contains(&point))?.clone();
+
+        if let Some(hyperlink) = url.hyperlink.clone() {
+            url.lines = self
+                .urls
+                .iter()
+                .filter(|other| other.hyperlink.as_ref() == Some(&hyperlink))
+                .flat_map(|other| other.lines.iter().copied())
+                .collect();
         }
-        None
+
+        Some(url)
+    }
+
+    /// Remove all URLs in damaged lines, so they can be detected again.
+    pub fn remove_damaged(&mut self, damage: &DamageTracker) {
+        self.urls.retain(|url| {
+            url.lines.iter().all(|line| {
+                !damage.is_damaged(line.start.line) && !damage.is_damaged(line.end.line)
+            })
+        });
+
+        self.last_point = None;
+        self.last_hyperlink = None;
+        self.reset();
     }
 
     fn reset(&mut self) {
@@ -205,9 +258,13 @@ mod tests {
                 point: Point::new(Line(0), Column(i)),
                 fg: Default::default(),
                 bg: Default::default(),
+                underline: Default::default(),
                 bg_alpha: 0.,
                 flags: Flags::empty(),
                 is_match: false,
+                hyperlink: None,
+                graphic: None,
+                shaped: Default::default(),
             })
             .collect()
     }
@@ -272,4 +329,59 @@ mod tests {
         assert_eq!(urls.urls[1].start().column, Column(20));
         assert_eq!(urls.urls[1].end().column, Column(28));
     }
+
+    #[test]
+    fn hyperlinks() {
+        let mut input = text_to_cells("link other link");
+        let num_cols = input.len();
+
+        let hyperlink = Hyperlink::new(Some("id"), "https://example.org");
+        let (head, tail) = input.split_at_mut(11);
+        for cell in head[..4].iter_mut().chain(tail.iter_mut()) {
+            cell.hyperlink = Some(hyperlink.clone());
+        }
+
+        let mut urls = Urls::new();
+
+        for cell in input {
+            urls.update(Column(num_cols), &cell);
+        }
+
+        assert_eq!(urls.urls.len(), 2);
+
+        let url = urls.find_at(Point::new(Line(0), Column(1))).unwrap();
+        assert_eq!(url.hyperlink(), Some(&hyperlink));
+        assert_eq!(url.start().column, Column(0));
+        assert_eq!(url.end().column, Column(14));
+        assert_eq!(url.lines.len(), 2);
+
+        assert_eq!(urls.find_at(Point::new(Line(0), Column(7))), None);
+    }
+
+    #[test]
+    fn hyperlinks_reusing_id() {
+        let mut input = text_to_cells("link link");
+        let num_cols = input.len();
+
+        let first = Hyperlink::new(Some("1"), "https://example.org");
+        let second = Hyperlink::new(Some("1"), "https://example.com");
+        let (head, tail) = input.split_at_mut(5);
+        for cell in &mut head[..4] {
+            cell.hyperlink = Some(first.clone());
+        }
+        for cell in tail {
+            cell.hyperlink = Some(second.clone());
+        }
+
+        let mut urls = Urls::new();
+
+        for cell in input {
+            urls.update(Column(num_cols), &cell);
+        }
+
+        let url = urls.find_at(Point::new(Line(0), Column(6))).unwrap();
+        assert_eq!(url.hyperlink(), Some(&second));
+        assert_eq!(url.start().column, Column(5));
+        assert_eq!(url.lines.len(), 1);
+    }
 }
@@ -0,0 +1,491 @@
+//! Terminal window context.
+
+use std::collections::HashSet;
+use std::error::Error;
+use std::f32;
+use std::fs::File;
+use std::io::Write;
+use std::mem;
+#[cfg(not(windows))]
+use std::os::unix::io::{AsRawFd, RawFd};
+#[cfg(not(windows))]
+use std::process;
+#[cfg(not(any(target_os = "macos", windows)))]
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use glutin::event::{Event as GlutinEvent, ModifiersState, ScanCode, WindowEvent};
+use glutin::event_loop::{ControlFlow, EventLoopProxy, EventLoopWindowTarget};
+use glutin::window::WindowId;
+use log::info;
+use serde_json as json;
+#[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
+use wayland_client::EventQueue;
+
+use crossfont::Size;
+
+use alacritty_terminal::asciicast::{Cast, Player};
+use alacritty_terminal::config::{Config as TermConfig, LOG_TARGET_CONFIG};
+use alacritty_terminal::event::{Event as TerminalEvent, Record};
+use alacritty_terminal::event_loop::{self, EventLoop as PtyEventLoop, Msg, Notifier};
+use alacritty_terminal::grid::{Dimensions, Scroll};
+use alacritty_terminal::index::Direction;
+use alacritty_terminal::sync::FairMutex;
+use alacritty_terminal::term::{Term, TermMode};
+use alacritty_terminal::tty;
+use alacritty_terminal::vi_mode::ViCommandParser;
+
+use crate::cli::WindowOptions;
+use crate::clipboard::Clipboard;
+use crate::config::Config;
+use crate::display::{Display, DisplayUpdate};
+use crate::event::{ActionContext, Event, EventProxy, EventType, Mouse, SearchState};
+use crate::input;
+use crate::message_bar::MessageBuffer;
+use crate::scheduler::{Scheduler, TimerId, Topic};
+
+/// Event context for one individual Alacritty window.
+pub struct WindowContext {
+    message_buffer: MessageBuffer,
+    display: Display,
+    display_update_pending: DisplayUpdate,
+    event_queue: Vec<GlutinEvent<'static, Event>>,
+    terminal: Arc<FairMutex<Term<EventProxy>>>,
+    modifiers: ModifiersState,
+    search_state: SearchState,
+    vi_command_parser: ViCommandParser,
+    received_count: usize,
+    suppress_chars: bool,
+    last_pressed_key: Option<ScanCode>,
+    reported_keys: HashSet<ScanCode>,
+    notifier: Notifier,
+    io_thread: Option<JoinHandle<(PtyEventLoop<tty::Pty, EventProxy>, event_loop::State)>>,
+    font_size: Size,
+    mouse: Mouse,
+    dirty: bool,
+    #[cfg(not(windows))]
+    master_fd: RawFd,
+    #[cfg(not(windows))]
+    shell_pid: u32,
+}
+
+impl WindowContext {
+    /// Create a new terminal window context.
+    pub fn new(
+        config: &Config,
+        options: &WindowOptions,
+        window_event_loop: &EventLoopWindowTarget<Event>,
+        proxy: EventLoopProxy<Event>,
+        #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
+        wayland_event_queue: Option<&EventQueue>,
+    ) -> Result<Self, Box<dyn Error>> {
+        // Create a display.
+        //
+        // The display manages a window and can draw the terminal.
+        let display = Display::new(
+            config,
+            window_event_loop,
+            #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
+            wayland_event_queue,
+        )?;
+
+        info!(
+            "PTY dimensions: {:?} x {:?}",
+            display.size_info.screen_lines(),
+            display.size_info.cols()
+        );
+
+        let event_proxy = EventProxy::new(proxy, display.window.window_id());
+
+        // Create the terminal.
+        //
+        // This object contains all of the state about what's being displayed. It's
+        // wrapped in a clonable mutex since both the I/O loop and display need to
+        // access it.
+        let terminal = Term::new(config, display.size_info, event_proxy.clone());
+        let terminal = Arc::new(FairMutex::new(terminal));
+
+        // Without a shell, new windows inherit Alacritty's working directory.
+        #[cfg(not(windows))]
+        let (mut master_fd, mut shell_pid) = (-1, process::id());
+
+        let mut io_thread = None;
+        let mut notifier = match &options.play {
+            // Replay the recording in place of the PTY I/O loop.
+            Some(playback) => {
+                let cast = Cast::open(&playback.path)?;
+                let player = Player::new(Arc::clone(&terminal), event_proxy, cast, playback.speed);
+                let notifier = Notifier(player.channel());
+                player.spawn();
+                notifier
+            },
+            None => {
+                // Apply the window specific options to the shared PTY configuration.
+                let mut pty_config = TermConfig::<()> {
+                    shell: config.shell.clone(),
+                    working_directory: config.working_directory.clone(),
+                    ..TermConfig::default()
+                };
+                options.override_pty_config(&mut pty_config);
+
+                // Create the PTY.
+                //
+                // The PTY forks a process to run the shell on the slave side of the
+                // pseudoterminal. A file descriptor for the master side is retained for
+                // reading/writing to the shell.
+                let pty = tty::new(&pty_config, &display.size_info, display.window.x11_window_id());
+
+                #[cfg(not(windows))]
+                {
+                    master_fd = pty.file().as_raw_fd();
+                    shell_pid = pty.child().id();
+                }
+
+                // Create the pseudoterminal I/O loop.
+                //
+                // PTY I/O is ran on another thread as to not occupy cycles used by the
+                // renderer and input processing. Note that access to the terminal state is
+                // synchronized since the I/O loop updates the state, and the display
+                // consumes it periodically.
+                let event_loop = PtyEventLoop::new(
+                    Arc::clone(&terminal),
+                    event_proxy,
+                    pty,
+                    pty_config.hold,
+                    config.ui_config.debug.ref_test,
+                );
+
+                // The event loop channel allows write requests from the event processor
+                // to be sent to the pty loop and ultimately written to the pty.
+                let loop_tx = event_loop.channel();
+
+                // Kick off the I/O thread.
+                io_thread = Some(event_loop.spawn());
+
+                Notifier(loop_tx)
+            },
+        };
+
+        // Start recording the session.
+        if let Some(path) = &options.record {
+            notifier.toggle_recording(path.clone(), config.ui_config.recording.input);
+        }
+
+        // Start the initial cursor blinking timer.
+        let mut event_queue = Vec::new();
+        if config.cursor.style().blinking {
+            let event = TerminalEvent::CursorBlinkingChange(true).into();
+            event_queue.push(Event::new(event, display.window.window_id()).into());
+        }
+
+        Ok(WindowContext {
+            font_size: config.ui_config.font.size(),
+            notifier,
+            io_thread,
+            terminal,
+            display,
+            event_queue,
+            display_update_pending: Default::default(),
+            message_buffer: MessageBuffer::new(),
+            search_state: SearchState::new(),
+            vi_command_parser: Default::default(),
+            modifiers: Default::default(),
+            received_count: Default::default(),
+            suppress_chars: Default::default(),
+            last_pressed_key: Default::default(),
+            reported_keys: Default::default(),
+            mouse: Default::default(),
+            dirty: Default::default(),
+            #[cfg(not(windows))]
+            master_fd,
+            #[cfg(not(windows))]
+            shell_pid,
+        })
+    }
+
+    /// ID of this terminal context.
+    pub fn id(&self) -> WindowId {
+        self.display.window.window_id()
+    }
+
+    /// Check if the terminal window currently has keyboard focus.
+    pub fn is_focused(&self) -> bool {
+        self.terminal.lock().is_focused
+    }
+
+    /// Remove all configuration errors from the message bar.
+    pub fn clear_config_logs(&mut self) {
+        if !self.message_buffer.is_empty() {
+            self.message_buffer.remove_target(LOG_TARGET_CONFIG);
+            self.display_update_pending.dirty = true;
+            self.dirty = true;
+        }
+    }
+
+    /// Update the terminal window to the latest config.
+    pub fn update_config(&mut self, old_config: &Config, config: &Config) {
+        self.display.update_config(config);
+        self.terminal.lock().update_config(config);
+
+        // Reload cursor if its thickness has changed.
+        if (old_config.cursor.thickness() - config.cursor.thickness()).abs() > f32::EPSILON {
+            self.display_update_pending.set_cursor_dirty();
+        }
+
+        if old_config.ui_config.font != config.ui_config.font {
+            // Do not update font size if it has been changed at runtime.
+            if self.font_size == old_config.ui_config.font.size() {
+                self.font_size = config.ui_config.font.size();
+            }
+
+            let font = config.ui_config.font.clone().with_size(self.font_size);
+            self.display_update_pending.set_font(font);
+        }
+
+        // Update display if padding options were changed.
+        let window_config = &old_config.ui_config.window;
+        if window_config.padding(1.) != config.ui_config.window.padding(1.)
+            || window_config.dynamic_padding != config.ui_config.window.dynamic_padding
+        {
+            self.display_update_pending.dirty = true;
+        }
+
+        // Live title reload.
+        if !config.ui_config.window.dynamic_title
+            || old_config.ui_config.window.title != config.ui_config.window.title
+        {
+            self.display.window.set_title(&config.ui_config.window.title);
+        }
+
+        #[cfg(all(feature = "wayland", not(any(target_os = "macos", windows))))]
+        if !self.display.is_x11 {
+            self.display.window.set_wayland_theme(&config.ui_config.colors);
+        }
+
+        // Disable shadows for transparent windows on macOS.
+        #[cfg(target_os = "macos")]
+        self.display.window.set_has_shadow(config.ui_config.background_opacity() >= 1.0);
+
+        // Update cursor blinking.
+        let event = TerminalEvent::CursorBlinkingChange(config.cursor.style().blinking).into();
+        self.event_queue.push(Event::new(event, self.id()).into());
+
+        self.dirty = true;
+    }
+
+    /// Process events for this terminal window.
+    #[allow(clippy::too_many_arguments)]
+    pub fn handle_event(
+        &mut self,
+        event_loop: &EventLoopWindowTarget<Event>,
+        event_proxy: &EventLoopProxy<Event>,
+        config: &Config,
+        clipboard: &mut Clipboard,
+        scheduler: &mut Scheduler,
+        control_flow: &mut ControlFlow,
+        event: GlutinEvent<'_, Event>,
+    ) {
+        match event {
+            // Skip further event handling with no staged updates.
+            GlutinEvent::RedrawEventsCleared if self.event_queue.is_empty() && !self.dirty => {
+                return;
+            },
+            // Continue to process all pending events.
+            GlutinEvent::RedrawEventsCleared => (),
+            // Remap DPR change event to remove lifetime.
+            GlutinEvent::WindowEvent {
+                event: WindowEvent::ScaleFactorChanged { scale_factor, new_inner_size },
+                window_id,
+            } => {
+                let size = (new_inner_size.width, new_inner_size.height);
+                let event = Event::new(EventType::DPRChanged(scale_factor, size), window_id);
+                self.event_queue.push(event.into());
+                return;
+            },
+            // Transmute to extend lifetime, which exists only for `ScaleFactorChanged` event.
+            // Since we remap that event to remove the lifetime, this is safe.
+            event => unsafe {
+                self.event_queue.push(mem::transmute(event));
+                return;
+            },
+        }
+
+        let mut terminal = self.terminal.lock();
+
+        let old_is_searching = self.search_state.regex().is_some();
+
+        let context = ActionContext {
+            terminal: &mut terminal,
+            notifier: &mut self.notifier,
+            mouse: &mut self.mouse,
+            clipboard,
+            received_count: &mut self.received_count,
+            suppress_chars: &mut self.suppress_chars,
+            last_pressed_key: &mut self.last_pressed_key,
+            reported_keys: &mut self.reported_keys,
+            modifiers: &mut self.modifiers,
+            message_buffer: &mut self.message_buffer,
+            display_update_pending: &mut self.display_update_pending,
+            display: &mut self.display,
+            font_size: &mut self.font_size,
+            config,
+            scheduler,
+            search_state: &mut self.search_state,
+            vi_command_parser: &mut self.vi_command_parser,
+            dirty: &mut self.dirty,
+            event_loop,
+            event_proxy,
+            #[cfg(not(windows))]
+            master_fd: self.master_fd,
+            #[cfg(not(windows))]
+            shell_pid: self.shell_pid,
+        };
+        let mut processor = input::Processor::new(context);
+
+        for event in self.event_queue.drain(..) {
+            processor.handle_event(event);
+        }
+
+        // Process DisplayUpdate events.
+        if self.display_update_pending.dirty {
+            let old_dimensions = (terminal.cols(), terminal.screen_lines());
+
+            Self::submit_display_update(
+                &mut terminal,
+                &mut self.display,
+                &mut self.notifier,
+                &self.message_buffer,
+                &self.search_state,
+                old_is_searching,
+                config,
+                mem::take(&mut self.display_update_pending),
+            );
+
+            // Count all matches again, since resizing moves content between lines.
+            let dimensions = (terminal.cols(), terminal.screen_lines());
+            if dimensions != old_dimensions && self.search_state.regex().is_some() {
+                self.search_state.reset_match_count();
+
+                let timer_id = TimerId::new(Topic::SearchCount, self.id());
+                if !scheduler.scheduled(timer_id) {
+                    let event = Event::new(EventType::SearchCount, self.id());
+                    scheduler.schedule(event, Duration::from_millis(0), false, timer_id);
+                }
+            }
+        }
+
+        // Skip rendering on Wayland until we get frame event from compositor.
+        #[cfg(not(any(target_os = "macos", windows)))]
+        if !self.display.is_x11 && !self.display.window.should_draw.load(Ordering::Relaxed) {
+            return;
+        }
+
+        if self.dirty {
+            self.dirty = false;
+
+            // Request immediate re-draw if visual bell animation is not finished yet.
+            if !self.display.visual_bell.completed() {
+                let event = Event::new(TerminalEvent::Wakeup.into(), self.id());
+                self.event_queue.push(event.into());
+
+                *control_flow = ControlFlow::Poll;
+            }
+
+            // Redraw screen.
+            self.display.draw(
+                terminal,
+                &self.message_buffer,
+                config,
+                &self.mouse,
+                self.modifiers,
+                &self.search_state,
+            );
+        }
+    }
+
+    /// Store the scrollback history, so it can be restored by the next terminal.
+    pub fn persist_history(&self) {
+        self.terminal.lock().persist_history();
+    }
+
+    /// Write the ref test results to the disk.
+    pub fn write_ref_test_results(&self) {
+        // Dump grid state.
+        let mut grid = self.terminal.lock().grid().clone();
+        grid.initialize_all();
+        grid.truncate();
+
+        let serialized_grid = json::to_string(&grid).expect("serialize grid");
+
+        let serialized_size = json::to_string(&self.display.size_info).expect("serialize size");
+
+        let serialized_config = format!("{{\"history_size\":{}}}", grid.history_size());
+
+        File::create("./grid.json")
+            .and_then(|mut f| f.write_all(serialized_grid.as_bytes()))
+            .expect("write grid.json");
+
+        File::create("./size.json")
+            .and_then(|mut f| f.write_all(serialized_size.as_bytes()))
+            .expect("write size.json");
+
+        File::create("./config.json")
+            .and_then(|mut f| f.write_all(serialized_config.as_bytes()))
+            .expect("write config.json");
+    }
+
+    /// Submit the pending changes to the `Display`.
+    #[allow(clippy::too_many_arguments)]
+    fn submit_display_update(
+        terminal: &mut Term<EventProxy>,
+        display: &mut Display,
+        notifier: &mut Notifier,
+        message_buffer: &MessageBuffer,
+        search_state: &SearchState,
+        old_is_searching: bool,
+        config: &Config,
+        display_update_pending: DisplayUpdate,
+    ) {
+        // Compute cursor positions before resize.
+        let num_lines = terminal.screen_lines();
+        let cursor_at_bottom = terminal.grid().cursor.point.line + 1 == num_lines;
+        let origin_at_bottom = if terminal.mode().contains(TermMode::VI) {
+            terminal.vi_mode_cursor.point.line == num_lines - 1
+        } else {
+            search_state.direction() == Direction::Left
+        };
+
+        display.handle_update(
+            terminal,
+            notifier,
+            message_buffer,
+            search_state.regex().is_some(),
+            config,
+            display_update_pending,
+        );
+
+        // Scroll to make sure search origin is visible and content moves as little as possible.
+        if !old_is_searching && search_state.regex().is_some() {
+            let display_offset = terminal.grid().display_offset();
+            if display_offset == 0 && cursor_at_bottom && !origin_at_bottom {
+                terminal.scroll_display(Scroll::Delta(1));
+            } else if display_offset != 0 && origin_at_bottom {
+                terminal.scroll_display(Scroll::Delta(-1));
+            }
+        }
+    }
+}
+
+impl Drop for WindowContext {
+    fn drop(&mut self) {
+        // Shutdown the terminal's PTY.
+        let _ = self.notifier.0.send(Msg::Shutdown);
+
+        // Wait for the PTY event loop to exit, dropping the PTY it owns.
+        if let Some(io_thread) = self.io_thread.take() {
+            let _ = io_thread.join();
+        }
+    }
+}
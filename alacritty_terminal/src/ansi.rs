@@ -3,13 +3,16 @@
 use std::convert::TryFrom;
 use std::{io, iter, str};
 
+use bitflags::bitflags;
 use log::{debug, trace};
 use serde::{Deserialize, Serialize};
 use vte::{Params, ParamsIter};
 
 use alacritty_config_derive::ConfigDeserialize;
 
+use crate::graphics::{sixel, GraphicData};
 use crate::index::{Column, Line};
+use crate::term::cell::Hyperlink;
 use crate::term::color::Rgb;
 
 /// Parse colors in XParseColor format.
@@ -81,15 +84,49 @@ fn parse_number(input: &[u8]) -> Option<u8> {
     Some(num)
 }
 
+/// Parse a string encoded as pairs of hexadecimal digits.
+fn parse_hex_string(hex: &[u8]) -> Option<String> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+
+    let bytes = hex
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(str::from_utf8(pair).ok()?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+
+    String::from_utf8(bytes).ok()
+}
+
 /// The processor wraps a `vte::Parser` to ultimately call methods on a Handler.
 pub struct Processor {
     state: ProcessorState,
     parser: vte::Parser,
 }
 
+/// Maximum number of bytes accepted in a DCS string.
+const MAX_DCS_LENGTH: usize = 1024;
+
 /// Internal state for VTE processor.
+#[derive(Default)]
 struct ProcessorState {
     preceding_char: Option<char>,
+
+    /// Active DCS sequence.
+    dcs: Option<Dcs>,
+}
+
+/// DCS sequences understood by the processor.
+#[derive(Debug)]
+enum Dcs {
+    /// DECRQSS - Request selection or setting.
+    RequestStatus(Vec<u8>),
+
+    /// XTGETTCAP - Request termcap/terminfo string.
+    RequestCapability(Vec<u8>),
+
+    /// Sixel graphic.
+    Sixel(Box<sixel::Parser>),
 }
 
 /// Helper type that implements `vte::Perform`.
@@ -116,7 +153,7 @@ impl<'a, H: Handler + 'a, W: io::Write> Performer<'a, H, W> {
 
 impl Default for Processor {
     fn default() -> Processor {
-        Processor { state: ProcessorState { preceding_char: None }, parser: vte::Parser::new() }
+        Processor { state: ProcessorState::default(), parser: vte::Parser::new() }
     }
 }
 
@@ -179,6 +216,15 @@ pub trait Handler {
     /// Report device status.
     fn device_status<W: io::Write>(&mut self, _: &mut W, _: usize) {}
 
+    /// Report whether a mode is set (DECRQM).
+    ///
+    /// Private modes are requested with the `?` intermediate, unknown modes should be reported as
+    /// not recognized.
+    fn report_mode<W: io::Write>(&mut self, _: &mut W, _private: bool, _mode: u16) {}
+
+    /// Report the name and version of the terminal (XTVERSION).
+    fn report_version<W: io::Write>(&mut self, _: &mut W) {}
+
     /// Move cursor forward `cols`.
     fn move_forward(&mut self, _: Column) {}
 
@@ -284,6 +330,14 @@ pub trait Handler {
     /// DECSTBM - Set the terminal scrolling region.
     fn set_scrolling_region(&mut self, _top: usize, _bottom: Option<usize>) {}
 
+    /// DECSLRM - Set the left and right margins.
+    ///
+    /// This shares its escape with SCOSC, so the cursor position is saved instead unless the left
+    /// and right margin mode (DECLRMM) is enabled.
+    fn set_left_right_margins(&mut self, _left: usize, _right: Option<usize>) {
+        self.save_cursor_position();
+    }
+
     /// DECKPAM - Set keypad to applications mode (ESCape instead of digits).
     fn set_keypad_application_mode(&mut self) {}
 
@@ -317,6 +371,9 @@ pub trait Handler {
     /// Load data from clipboard.
     fn clipboard_load(&mut self, _: u8, _: &str) {}
 
+    /// Set hyperlink for the following characters, `None` terminates the active hyperlink.
+    fn set_hyperlink(&mut self, _: Option<Hyperlink>) {}
+
     /// Run the decaln routine.
     fn decaln(&mut self) {}
 
@@ -331,6 +388,92 @@ pub trait Handler {
 
     /// Report text area size in characters.
     fn text_area_size_chars<W: io::Write>(&mut self, _: &mut W) {}
+
+    /// Report the state of a setting, `None` for unsupported requests.
+    fn report_status<W: io::Write>(&mut self, _: &mut W, _: Option<StatusRequest>) {}
+
+    /// Report the value of a terminfo capability.
+    fn report_capability<W: io::Write>(&mut self, _: &mut W, _: &str) {}
+
+    /// Insert a graphic at the cursor position.
+    fn insert_graphic(&mut self, _: GraphicData) {}
+
+    /// Push keyboard protocol modes onto the stack.
+    fn push_keyboard_modes(&mut self, _: KeyboardModes) {}
+
+    /// Pop the given number of keyboard protocol modes from the stack.
+    fn pop_keyboard_modes(&mut self, _: u16) {}
+
+    /// Modify the active keyboard protocol modes.
+    fn set_keyboard_modes(&mut self, _: KeyboardModes, _: KeyboardModesApplyBehavior) {}
+
+    /// Report the active keyboard protocol modes.
+    fn report_keyboard_modes<W: io::Write>(&mut self, _: &mut W) {}
+
+    /// Mark a semantic section of the shell prompt at the cursor position.
+    fn prompt_mark(&mut self, _: PromptMark) {}
+}
+
+bitflags! {
+    /// Progressive enhancement flags of the kitty keyboard protocol.
+    #[derive(Default)]
+    pub struct KeyboardModes: u8 {
+        /// Report ambiguous keys like Escape or Ctrl+I using `CSI u` sequences.
+        const DISAMBIGUATE_ESC_CODES = 0b0000_0001;
+        /// Report key repeat and release events.
+        const REPORT_EVENT_TYPES     = 0b0000_0010;
+        /// Report the shifted version of keys.
+        const REPORT_ALTERNATE_KEYS  = 0b0000_0100;
+        /// Report all keys, including text, using escape sequences.
+        ///
+        /// Reporting the text associated with a key (`0b0001_0000`) is not supported.
+        const REPORT_ALL_KEYS_AS_ESC = 0b0000_1000;
+    }
+}
+
+/// How new keyboard protocol modes are combined with the active ones.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum KeyboardModesApplyBehavior {
+    /// Replace the active modes.
+    Replace,
+
+    /// Add to the active modes.
+    Union,
+
+    /// Remove from the active modes.
+    Difference,
+}
+
+/// Semantic shell prompt marks (`OSC 133`).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PromptMark {
+    /// Start of the prompt (`A`).
+    PromptStart,
+
+    /// End of the prompt and start of the user's command (`B`).
+    CommandStart,
+
+    /// Start of the command output (`C`).
+    OutputStart,
+
+    /// End of the command, with its exit code if reported (`D`).
+    CommandEnd(Option<i32>),
+}
+
+/// Settings which can be requested using DECRQSS.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StatusRequest {
+    /// Graphic rendition of the cursor (SGR).
+    Sgr,
+
+    /// Top and bottom margins (DECSTBM).
+    ScrollingRegion,
+
+    /// Left and right margins (DECSLRM).
+    LeftRightMargins,
+
+    /// Cursor style (DECSCUSR).
+    CursorStyle,
 }
 
 /// Terminal cursor configuration.
@@ -404,6 +547,11 @@ pub enum Mode {
     LineFeedNewLine = 20,
     /// ?25
     ShowCursor = 25,
+    /// DECLRMM Left Right Margin Mode.
+    ///
+    /// * `CSI ? 69 h` enable setting left and right margins with DECSLRM
+    /// * `CSI ? 69 l` disable margins and reset them to the full width
+    LeftRightMargin = 69,
     /// ?1000
     ReportMouseClicks = 1000,
     /// ?1002
@@ -424,6 +572,8 @@ pub enum Mode {
     SwapScreenAndSetRestoreCursor = 1049,
     /// ?2004
     BracketedPaste = 2004,
+    /// ?2026
+    SyncUpdate = 2026,
 }
 
 impl Mode {
@@ -445,6 +595,7 @@ impl Mode {
                 7 => Mode::LineWrap,
                 12 => Mode::BlinkingCursor,
                 25 => Mode::ShowCursor,
+                69 => Mode::LeftRightMargin,
                 1000 => Mode::ReportMouseClicks,
                 1002 => Mode::ReportCellMouseMotion,
                 1003 => Mode::ReportAllMouseMotion,
@@ -455,6 +606,7 @@ impl Mode {
                 1042 => Mode::UrgencyHints,
                 1049 => Mode::SwapScreenAndSetRestoreCursor,
                 2004 => Mode::BracketedPaste,
+                2026 => Mode::SyncUpdate,
                 _ => {
                     trace!("[unimplemented] primitive mode: {}", num);
                     return None;
@@ -645,6 +797,12 @@ pub enum Attr {
     Underline,
     /// Underlined twice.
     DoubleUnderline,
+    /// Undercurled text.
+    Undercurl,
+    /// Dotted underlined text.
+    DottedUnderline,
+    /// Dashed underlined text.
+    DashedUnderline,
     /// Blink cursor slowly.
     BlinkSlow,
     /// Blink cursor fast.
@@ -675,6 +833,8 @@ pub enum Attr {
     Foreground(Color),
     /// Set indexed background color.
     Background(Color),
+    /// Set underline color, `None` to use the foreground color.
+    UnderlineColor(Option<Color>),
 }
 
 /// Identifiers which can be assigned to a graphic character set.
@@ -779,20 +939,86 @@ where
 
     #[inline]
     fn hook(&mut self, params: &Params, intermediates: &[u8], ignore: bool, action: char) {
-        debug!(
-            "[unhandled hook] params={:?}, ints: {:?}, ignore: {:?}, action: {:?}",
-            params, intermediates, ignore, action
-        );
+        self.state.dcs = match (action, intermediates) {
+            _ if ignore => None,
+            ('q', [b'$']) => Some(Dcs::RequestStatus(Vec::new())),
+            ('q', [b'+']) => Some(Dcs::RequestCapability(Vec::new())),
+            ('q', []) => {
+                // Pixels which are not drawn are transparent when the second parameter is `1`.
+                let transparent = params.iter().nth(1).map_or(false, |param| param[0] == 1);
+                Some(Dcs::Sixel(Box::new(sixel::Parser::new(transparent))))
+            },
+            _ => None,
+        };
+
+        if self.state.dcs.is_none() {
+            debug!(
+                "[unhandled hook] params={:?}, ints: {:?}, ignore: {:?}, action: {:?}",
+                params, intermediates, ignore, action
+            );
+        }
     }
 
     #[inline]
     fn put(&mut self, byte: u8) {
-        debug!("[unhandled put] byte={:?}", byte);
+        match &mut self.state.dcs {
+            Some(Dcs::Sixel(parser)) => {
+                if let Err(err) = parser.put(byte) {
+                    debug!("Failed to parse Sixel data: {}", err);
+                    self.state.dcs = None;
+                }
+            },
+            Some(Dcs::RequestStatus(data)) | Some(Dcs::RequestCapability(data))
+                if data.len() < MAX_DCS_LENGTH =>
+            {
+                data.push(byte)
+            },
+            Some(_) => {
+                debug!("[unhandled put] DCS exceeded maximum length");
+                self.state.dcs = None;
+            },
+            None => debug!("[unhandled put] byte={:?}", byte),
+        }
     }
 
     #[inline]
     fn unhook(&mut self) {
-        debug!("[unhandled unhook]");
+        let dcs = match self.state.dcs.take() {
+            Some(dcs) => dcs,
+            None => {
+                debug!("[unhandled unhook]");
+                return;
+            },
+        };
+
+        match dcs {
+            Dcs::RequestStatus(data) => {
+                let request = match data.as_slice() {
+                    b"m" => Some(StatusRequest::Sgr),
+                    b"r" => Some(StatusRequest::ScrollingRegion),
+                    b"s" => Some(StatusRequest::LeftRightMargins),
+                    b" q" => Some(StatusRequest::CursorStyle),
+                    _ => {
+                        debug!("[unhandled DECRQSS] request={:?}", String::from_utf8_lossy(&data));
+                        None
+                    },
+                };
+
+                self.handler.report_status(self.writer, request);
+            },
+            Dcs::RequestCapability(data) => {
+                for name in data.split(|&b| b == b';') {
+                    match parse_hex_string(name) {
+                        Some(name) => self.handler.report_capability(self.writer, &name),
+                        None => debug!("[unhandled XTGETTCAP] name={:?}", name),
+                    }
+                }
+            },
+            Dcs::Sixel(parser) => match parser.finish() {
+                Ok(graphic) => self.handler.insert_graphic(graphic),
+                Err(err) => debug!("Failed to parse Sixel data: {}", err),
+            },
+        }
     }
 
     // TODO replace OSC parsing with parser combinators.
@@ -848,6 +1074,51 @@ where
                 unhandled(params);
             },
 
+            // Hyperlink.
+            b"8" if params.len() > 2 => {
+                let link_params = params[1];
+
+                // The URI itself may contain `;`, so it has to be rebuilt from all remaining
+                // parameters.
+                let uri = params[2..]
+                    .iter()
+                    .flat_map(|x| str::from_utf8(x))
+                    .collect::<Vec<&str>>()
+                    .join(";");
+
+                // An empty URI terminates the hyperlink.
+                if uri.is_empty() {
+                    self.handler.set_hyperlink(None);
+                    return;
+                }
+
+                // Link parameters are `key=value` pairs separated by `:`, only `id` is defined.
+                let id = link_params
+                    .split(|&b| b == b':')
+                    .find(|kv| kv.starts_with(b"id="))
+                    .and_then(|kv| str::from_utf8(&kv[3..]).ok());
+
+                self.handler.set_hyperlink(Some(Hyperlink::new(id, uri.as_str())));
+            },
+
+            // Shell integration prompt marks.
+            b"133" if params.len() >= 2 => {
+                let mark = match params[1] {
+                    b"A" => PromptMark::PromptStart,
+                    b"B" => PromptMark::CommandStart,
+                    b"C" => PromptMark::OutputStart,
+                    b"D" => {
+                        let exit_code = params
+                            .get(2)
+                            .and_then(|code| str::from_utf8(code).ok())
+                            .and_then(|code| code.parse().ok());
+                        PromptMark::CommandEnd(exit_code)
+                    },
+                    _ => return unhandled(params),
+                };
+                self.handler.prompt_mark(mark);
+            },
+
             // Get/set Foreground, Background, Cursor colors.
             b"10" | b"11" | b"12" => {
                 if params.len() >= 2 {
@@ -963,7 +1234,10 @@ where
             }};
         }
 
-        if has_ignored_intermediates || intermediates.len() > 1 {
+        // DECRQM is the only sequence combining a private marker with an intermediate.
+        let mode_request = action == 'p' && (intermediates == b"$" || intermediates == b"?$");
+
+        if has_ignored_intermediates || (intermediates.len() > 1 && !mode_request) {
             unhandled!();
             return;
         }
@@ -1076,7 +1350,11 @@ where
                 }
             },
             ('n', None) => handler.device_status(writer, next_param_or(0) as usize),
+            ('p', intermediate) if mode_request => {
+                handler.report_mode(writer, intermediate == Some(&b'?'), next_param_or(0))
+            },
             ('P', None) => handler.delete_chars(Column(next_param_or(1) as usize)),
+            ('q', Some(b'>')) if next_param_or(0) == 0 => handler.report_version(writer),
             ('q', Some(b' ')) => {
                 // DECSCUSR (CSI Ps SP q) -- Set Cursor Style.
                 let cursor_style_id = next_param_or(0);
@@ -1103,7 +1381,13 @@ where
                 handler.set_scrolling_region(top, bottom);
             },
             ('S', None) => handler.scroll_up(Line(next_param_or(1) as usize)),
-            ('s', None) => handler.save_cursor_position(),
+            ('s', None) => {
+                let left = next_param_or(1) as usize;
+                let right =
+                    params_iter.next().map(|param| param[0] as usize).filter(|&param| param != 0);
+
+                handler.set_left_right_margins(left, right);
+            },
             ('T', None) => handler.scroll_down(Line(next_param_or(1) as usize)),
             ('t', None) => match next_param_or(1) as usize {
                 14 => handler.text_area_size_pixels(writer),
@@ -1113,6 +1397,25 @@ where
                 _ => unhandled!(),
             },
             ('u', None) => handler.restore_cursor_position(),
+            ('u', Some(b'?')) => handler.report_keyboard_modes(writer),
+            ('u', Some(b'>')) => {
+                let modes = KeyboardModes::from_bits_truncate(next_param_or(0) as u8);
+                handler.push_keyboard_modes(modes);
+            },
+            ('u', Some(b'<')) => handler.pop_keyboard_modes(next_param_or(1)),
+            ('u', Some(b'=')) => {
+                let modes = KeyboardModes::from_bits_truncate(next_param_or(0) as u8);
+                let behavior = match next_param_or(1) {
+                    1 => KeyboardModesApplyBehavior::Replace,
+                    2 => KeyboardModesApplyBehavior::Union,
+                    3 => KeyboardModesApplyBehavior::Difference,
+                    _ => {
+                        unhandled!();
+                        return;
+                    },
+                };
+                handler.set_keyboard_modes(modes, behavior);
+            },
             ('X', None) => handler.erase_chars(Column(next_param_or(1) as usize)),
             ('Z', None) => handler.move_backward_tabs(next_param_or(1)),
             _ => unhandled!(),
@@ -1183,6 +1486,9 @@ fn attrs_from_sgr_parameters(params: &mut ParamsIter<'_>) -> Vec<Option<Attr>> {
             [3] => Some(Attr::Italic),
             [4, 0] => Some(Attr::CancelUnderline),
             [4, 2] => Some(Attr::DoubleUnderline),
+            [4, 3] => Some(Attr::Undercurl),
+            [4, 4] => Some(Attr::DottedUnderline),
+            [4, 5] => Some(Attr::DashedUnderline),
             [4, ..] => Some(Attr::Underline),
             [5] => Some(Attr::BlinkSlow),
             [6] => Some(Attr::BlinkFast),
@@ -1237,6 +1543,18 @@ fn attrs_from_sgr_parameters(params: &mut ParamsIter<'_>) -> Vec<Option<Attr>> {
                 parse_sgr_color(&mut iter).map(Attr::Background)
             },
             [49] => Some(Attr::Background(Color::Named(NamedColor::Background))),
+            [58] => {
+                let mut iter = params.map(|param| param[0]);
+                parse_sgr_color(&mut iter).map(|color| Attr::UnderlineColor(Some(color)))
+            },
+            [58, params @ ..] => {
+                let rgb_start = if params.len() > 4 { 2 } else { 1 };
+                let rgb_iter = params[rgb_start..].iter().copied();
+                let mut iter = iter::once(params[0]).chain(rgb_iter);
+
+                parse_sgr_color(&mut iter).map(|color| Attr::UnderlineColor(Some(color)))
+            },
+            [59] => Some(Attr::UnderlineColor(None)),
             [90] => Some(Attr::Foreground(Color::Named(NamedColor::BrightBlack))),
             [91] => Some(Attr::Foreground(Color::Named(NamedColor::BrightRed))),
             [92] => Some(Attr::Foreground(Color::Named(NamedColor::BrightGreen))),
@@ -1351,8 +1669,10 @@ pub mod C0 {
 #[cfg(test)]
 mod tests {
     use super::{
-        parse_number, xparse_color, Attr, CharsetIndex, Color, Handler, Processor, StandardCharset,
+        parse_number, xparse_color, Attr, CharsetIndex, Color, Handler, KeyboardModes,
+        KeyboardModesApplyBehavior, Processor, PromptMark, StandardCharset, StatusRequest,
     };
+    use crate::term::cell::Hyperlink;
     use crate::term::color::Rgb;
     use std::io;
 
@@ -1361,6 +1681,15 @@ mod tests {
         charset: StandardCharset,
         attr: Option<Attr>,
         identity_reported: bool,
+        hyperlink: Option<Hyperlink>,
+        status_requests: Vec<Option<StatusRequest>>,
+        capabilities: Vec<String>,
+        keyboard_modes: Vec<KeyboardModes>,
+        keyboard_mode_changes: Vec<(KeyboardModes, KeyboardModesApplyBehavior)>,
+        prompt_marks: Vec<PromptMark>,
+        left_right_margins: Vec<(usize, Option<usize>)>,
+        mode_requests: Vec<(bool, u16)>,
+        version_reported: bool,
     }
 
     impl Handler for MockHandler {
@@ -1381,6 +1710,51 @@ mod tests {
             self.identity_reported = true;
         }
 
+        fn set_hyperlink(&mut self, hyperlink: Option<Hyperlink>) {
+            self.hyperlink = hyperlink;
+        }
+
+        fn report_status<W: io::Write>(&mut self, _: &mut W, request: Option<StatusRequest>) {
+            self.status_requests.push(request);
+        }
+
+        fn report_capability<W: io::Write>(&mut self, _: &mut W, name: &str) {
+            self.capabilities.push(name.to_owned());
+        }
+
+        fn push_keyboard_modes(&mut self, modes: KeyboardModes) {
+            self.keyboard_modes.push(modes);
+        }
+
+        fn pop_keyboard_modes(&mut self, to_pop: u16) {
+            let new_len = self.keyboard_modes.len().saturating_sub(to_pop as usize);
+            self.keyboard_modes.truncate(new_len);
+        }
+
+        fn set_keyboard_modes(
+            &mut self,
+            modes: KeyboardModes,
+            behavior: KeyboardModesApplyBehavior,
+        ) {
+            self.keyboard_mode_changes.push((modes, behavior));
+        }
+
+        fn prompt_mark(&mut self, mark: PromptMark) {
+            self.prompt_marks.push(mark);
+        }
+
+        fn set_left_right_margins(&mut self, left: usize, right: Option<usize>) {
+            self.left_right_margins.push((left, right));
+        }
+
+        fn report_mode<W: io::Write>(&mut self, _: &mut W, private: bool, mode: u16) {
+            self.mode_requests.push((private, mode));
+        }
+
+        fn report_version<W: io::Write>(&mut self, _: &mut W) {
+            self.version_reported = true;
+        }
+
         fn reset_state(&mut self) {
             *self = Self::default();
         }
@@ -1393,6 +1767,15 @@ mod tests {
                 charset: StandardCharset::Ascii,
                 attr: None,
                 identity_reported: false,
+                hyperlink: None,
+                status_requests: Vec::new(),
+                capabilities: Vec::new(),
+                keyboard_modes: Vec::new(),
+                keyboard_mode_changes: Vec::new(),
+                prompt_marks: Vec::new(),
+                left_right_margins: Vec::new(),
+                mode_requests: Vec::new(),
+                version_reported: false,
             }
         }
     }
@@ -1411,6 +1794,160 @@ mod tests {
         assert_eq!(handler.attr, Some(Attr::Bold));
     }
 
+    #[test]
+    fn parse_prompt_marks() {
+        static BYTES: &[u8] = b"\x1b]133;A\x07$ \x1b]133;B\x07ls\r\n\x1b]133;C\x07\x1b]133;D;1\x07";
+
+        let mut parser = Processor::new();
+        let mut handler = MockHandler::default();
+
+        for byte in &BYTES[..] {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+
+        assert_eq!(handler.prompt_marks, vec![
+            PromptMark::PromptStart,
+            PromptMark::CommandStart,
+            PromptMark::OutputStart,
+            PromptMark::CommandEnd(Some(1)),
+        ]);
+    }
+
+    #[test]
+    fn parse_hyperlink() {
+        static BYTES: &[u8] = b"\x1b]8;foo=bar:id=link;https://example.org/a;b\x1b\\";
+
+        let mut parser = Processor::new();
+        let mut handler = MockHandler::default();
+
+        for byte in &BYTES[..] {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+
+        let hyperlink = handler.hyperlink.take().unwrap();
+        assert_eq!(hyperlink.id(), "link");
+        assert_eq!(hyperlink.uri(), "https://example.org/a;b");
+
+        // Links without explicit ID get a unique one.
+        static ANONYMOUS: &[u8] = b"\x1b]8;;https://example.org\x1b\\";
+        let mut links = Vec::new();
+        for _ in 0..2 {
+            for byte in &ANONYMOUS[..] {
+                parser.advance(&mut handler, *byte, &mut io::sink());
+            }
+            links.push(handler.hyperlink.clone().unwrap());
+        }
+
+        assert_eq!(links[0].uri(), "https://example.org");
+        assert_eq!(links[0].uri(), links[1].uri());
+        assert_ne!(links[0].id(), links[1].id());
+
+        static CLOSE: &[u8] = b"\x1b]8;;\x1b\\";
+        for byte in &CLOSE[..] {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+
+        assert_eq!(handler.hyperlink, None);
+    }
+
+    #[test]
+    fn parse_status_request() {
+        static BYTES: &[u8] =
+            b"\x1bP$qm\x1b\\\x1bP$qr\x1b\\\x1bP$q q\x1b\\\x1bP$qs\x1b\\\x1bP$qx\x1b\\";
+
+        let mut parser = Processor::new();
+        let mut handler = MockHandler::default();
+
+        for byte in &BYTES[..] {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+
+        assert_eq!(handler.status_requests, vec![
+            Some(StatusRequest::Sgr),
+            Some(StatusRequest::ScrollingRegion),
+            Some(StatusRequest::CursorStyle),
+            Some(StatusRequest::LeftRightMargins),
+            None,
+        ]);
+    }
+
+    #[test]
+    fn parse_left_right_margins() {
+        static BYTES: &[u8] = b"\x1b[s\x1b[3s\x1b[2;5s\x1b[;0s";
+
+        let mut parser = Processor::new();
+        let mut handler = MockHandler::default();
+
+        for byte in &BYTES[..] {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+
+        assert_eq!(handler.left_right_margins, vec![(1, None), (3, None), (2, Some(5)), (1, None)]);
+    }
+
+    #[test]
+    fn parse_mode_request() {
+        static BYTES: &[u8] = b"\x1b[?2026$p\x1b[4$p\x1b[?9999$p\x1b[?1!p";
+
+        let mut parser = Processor::new();
+        let mut handler = MockHandler::default();
+
+        for byte in &BYTES[..] {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+
+        assert_eq!(handler.mode_requests, vec![(true, 2026), (false, 4), (true, 9999)]);
+    }
+
+    #[test]
+    fn parse_version_request() {
+        let mut parser = Processor::new();
+        let mut handler = MockHandler::default();
+
+        for byte in b"\x1b[>1q" {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+        assert!(!handler.version_reported);
+
+        for byte in b"\x1b[>q" {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+        assert!(handler.version_reported);
+    }
+
+    #[test]
+    fn parse_keyboard_modes() {
+        static BYTES: &[u8] = b"\x1b[>1u\x1b[>10u\x1b[>u\x1b[<2u\x1b[=4;2u\x1b[=1u\x1b[=1;4u";
+
+        let mut parser = Processor::new();
+        let mut handler = MockHandler::default();
+
+        for byte in &BYTES[..] {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+
+        assert_eq!(handler.keyboard_modes, vec![KeyboardModes::DISAMBIGUATE_ESC_CODES]);
+        assert_eq!(handler.keyboard_mode_changes, vec![
+            (KeyboardModes::REPORT_ALTERNATE_KEYS, KeyboardModesApplyBehavior::Union),
+            (KeyboardModes::DISAMBIGUATE_ESC_CODES, KeyboardModesApplyBehavior::Replace),
+        ]);
+    }
+
+    #[test]
+    fn parse_capability_request() {
+        // Request `TN`, `colors` and an invalid hex string.
+        static BYTES: &[u8] = b"\x1bP+q544E;636F6C6F7273;5\x1b\\";
+
+        let mut parser = Processor::new();
+        let mut handler = MockHandler::default();
+
+        for byte in &BYTES[..] {
+            parser.advance(&mut handler, *byte, &mut io::sink());
+        }
+
+        assert_eq!(handler.capabilities, vec![String::from("TN"), String::from("colors")]);
+    }
+
     #[test]
     fn parse_terminal_identity_csi() {
         let bytes: &[u8] = &[0x1b, b'[', b'1', b'c'];
@@ -1489,6 +2026,31 @@ mod tests {
         assert_eq!(handler.attr, Some(Attr::Foreground(Color::Spec(spec))));
     }
 
+    #[test]
+    fn parse_underline_attrs() {
+        let spec = Rgb { r: 255, g: 0, b: 128 };
+        let expected: &[(&[u8], Attr)] = &[
+            (b"\x1b[4:3m", Attr::Undercurl),
+            (b"\x1b[4:4m", Attr::DottedUnderline),
+            (b"\x1b[4:5m", Attr::DashedUnderline),
+            (b"\x1b[58;5;1m", Attr::UnderlineColor(Some(Color::Indexed(1)))),
+            (b"\x1b[58:2::255:0:128m", Attr::UnderlineColor(Some(Color::Spec(spec)))),
+            (b"\x1b[58;2;255;0;128m", Attr::UnderlineColor(Some(Color::Spec(spec)))),
+            (b"\x1b[59m", Attr::UnderlineColor(None)),
+        ];
+
+        for (bytes, attr) in expected {
+            let mut parser = Processor::new();
+            let mut handler = MockHandler::default();
+
+            for byte in bytes.iter() {
+                parser.advance(&mut handler, *byte, &mut io::sink());
+            }
+
+            assert_eq!(handler.attr.as_ref(), Some(attr));
+        }
+    }
+
     /// No exactly a test; useful for debugging.
     #[test]
     fn parse_zsh_startup() {
@@ -0,0 +1,609 @@
+//! Session recording and playback in the asciicast v2 format.
+//!
+//! An asciicast v2 file starts with a JSON header line, followed by one JSON array per line for
+//! every event in the format `[time, code, data]`. See
+//! <https://github.com/asciinema/asciinema/blob/develop/doc/asciicast-v2.md> for details.
+
+use std::collections::HashMap;
+use std::env;
+use std::fmt::{self, Display, Formatter};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::path::Path;
+use std::str;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use mio::{self, Events, PollOpt, Ready};
+use mio_extras::channel::{self, Receiver, Sender};
+use serde::{Deserialize, Serialize};
+
+use crate::ansi;
+use crate::event::{Event, EventListener};
+use crate::event_loop::Msg;
+use crate::grid::Dimensions;
+use crate::index::{Column, Line};
+use crate::sync::FairMutex;
+use crate::term::Term;
+use crate::thread;
+
+/// Supported asciicast format version.
+const VERSION: u64 = 2;
+
+/// Maximum time playback waits for the window to follow a recorded resize.
+const RESIZE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Result from loading an asciicast recording.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Errors occurring while loading an asciicast recording.
+#[derive(Debug)]
+pub enum Error {
+    /// Recording does not contain a header.
+    MissingHeader,
+
+    /// Recording uses an unsupported format version.
+    Version(u64),
+
+    /// io error reading the recording.
+    Io(io::Error),
+
+    /// Header or event is not valid JSON.
+    Json(serde_json::Error),
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::MissingHeader | Error::Version(_) => None,
+            Error::Io(err) => err.source(),
+            Error::Json(err) => err.source(),
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::MissingHeader => write!(f, "Recording has no asciicast header"),
+            Error::Version(version) => write!(f, "Unsupported asciicast version {}", version),
+            Error::Io(err) => write!(f, "Error reading recording: {}", err),
+            Error::Json(err) => write!(f, "Invalid asciicast recording: {}", err),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(val: io::Error) -> Self {
+        Error::Io(val)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(val: serde_json::Error) -> Self {
+        Error::Json(val)
+    }
+}
+
+/// Header of an asciicast recording.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Header {
+    /// Format version.
+    pub version: u64,
+
+    /// Initial terminal width in columns.
+    pub width: usize,
+
+    /// Initial terminal height in lines.
+    pub height: usize,
+
+    /// Unix timestamp of the start of the recording.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+
+    /// Maximum time between two events during playback, in seconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_time_limit: Option<f64>,
+
+    /// Environment variables of the recorded session.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub env: HashMap<String, String>,
+}
+
+impl Header {
+    /// Read only the header of an asciicast recording.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        Self::from_lines(&mut reader.lines())
+    }
+
+    fn from_lines<I>(lines: &mut I) -> Result<Self>
+    where
+        I: Iterator<Item = io::Result<String>>,
+    {
+        for line in lines {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let header: Header = serde_json::from_str(&line)?;
+            if header.version != VERSION {
+                return Err(Error::Version(header.version));
+            }
+
+            return Ok(header);
+        }
+
+        Err(Error::MissingHeader)
+    }
+}
+
+/// Type of a recorded event.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EventKind {
+    /// Data written by the application to the terminal.
+    Output,
+
+    /// Data written by the user to the application.
+    Input,
+
+    /// Terminal resize, with the data in the format `COLUMNSxLINES`.
+    Resize,
+}
+
+impl EventKind {
+    fn code(self) -> &'static str {
+        match self {
+            EventKind::Output => "o",
+            EventKind::Input => "i",
+            EventKind::Resize => "r",
+        }
+    }
+
+    fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "o" => Some(EventKind::Output),
+            "i" => Some(EventKind::Input),
+            "r" => Some(EventKind::Resize),
+            _ => None,
+        }
+    }
+}
+
+/// Single event of an asciicast recording.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CastEvent {
+    /// Time since the start of the recording, in seconds.
+    pub time: f64,
+
+    /// Type of the event.
+    pub kind: EventKind,
+
+    /// Event payload.
+    pub data: String,
+}
+
+impl CastEvent {
+    /// Terminal dimensions of a resize event as columns and lines.
+    pub fn dimensions(&self) -> Option<(usize, usize)> {
+        if self.kind != EventKind::Resize {
+            return None;
+        }
+
+        let mut dimensions = self.data.splitn(2, 'x').map(|value| value.trim().parse().ok());
+        Some((dimensions.next()??, dimensions.next()??))
+    }
+}
+
+/// Parsed asciicast recording.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cast {
+    pub header: Header,
+    pub events: Vec<CastEvent>,
+}
+
+impl Cast {
+    /// Load an asciicast recording from a file.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+
+    /// Load an asciicast recording from a reader.
+    ///
+    /// Events with unknown event codes are ignored.
+    pub fn read<R: BufRead>(reader: R) -> Result<Self> {
+        let mut lines = reader.lines();
+        let header = Header::from_lines(&mut lines)?;
+
+        let mut events = Vec::new();
+        for line in lines {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let (time, code, data): (f64, String, String) = serde_json::from_str(&line)?;
+            if let Some(kind) = EventKind::from_code(&code) {
+                events.push(CastEvent { time, kind, data });
+            }
+        }
+
+        Ok(Self { header, events })
+    }
+}
+
+/// Writer for asciicast recordings.
+pub struct Recorder<W: Write> {
+    writer: W,
+    start: Instant,
+
+    /// Current terminal dimensions as columns and lines.
+    size: (usize, usize),
+
+    /// Output bytes of an incomplete UTF-8 sequence.
+    pending: Vec<u8>,
+}
+
+impl Recorder<File> {
+    /// Create a new recording file, replacing any existing file.
+    pub fn create<P: AsRef<Path>>(path: P, columns: usize, lines: usize) -> io::Result<Self> {
+        Self::new(File::create(path)?, columns, lines)
+    }
+}
+
+impl<W: Write> Recorder<W> {
+    /// Start a new recording by writing its header.
+    pub fn new(mut writer: W, columns: usize, lines: usize) -> io::Result<Self> {
+        let timestamp =
+            SystemTime::now().duration_since(UNIX_EPOCH).ok().map(|time| time.as_secs());
+
+        let env = ["TERM", "SHELL"]
+            .iter()
+            .filter_map(|key| Some((String::from(*key), env::var(key).ok()?)))
+            .collect();
+
+        let header = Header {
+            version: VERSION,
+            width: columns,
+            height: lines,
+            idle_time_limit: None,
+            timestamp,
+            env,
+        };
+        writeln!(writer, "{}", serde_json::to_string(&header)?)?;
+
+        Ok(Self { writer, start: Instant::now(), size: (columns, lines), pending: Vec::new() })
+    }
+
+    /// Record data written to the terminal.
+    ///
+    /// Since events must be valid UTF-8, incomplete sequences are held back until the next write.
+    pub fn output(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.pending.extend_from_slice(bytes);
+
+        let valid = match str::from_utf8(&self.pending) {
+            Err(err) if err.error_len().is_none() => err.valid_up_to(),
+            _ => self.pending.len(),
+        };
+
+        if valid == 0 {
+            return Ok(());
+        }
+
+        let data = String::from_utf8_lossy(&self.pending[..valid]).into_owned();
+        self.pending.drain(..valid);
+
+        self.write_event(EventKind::Output, &data)
+    }
+
+    /// Record data written by the user.
+    pub fn input(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.write_event(EventKind::Input, &String::from_utf8_lossy(bytes))
+    }
+
+    /// Record a change of the terminal dimensions.
+    pub fn resize(&mut self, columns: usize, lines: usize) -> io::Result<()> {
+        if self.size == (columns, lines) {
+            return Ok(());
+        }
+
+        self.size = (columns, lines);
+        self.write_event(EventKind::Resize, &format!("{}x{}", columns, lines))
+    }
+
+    fn write_event(&mut self, kind: EventKind, data: &str) -> io::Result<()> {
+        // Limit timestamps to microsecond precision to keep the recording compact.
+        let time = (self.start.elapsed().as_secs_f64() * 1e6).round() / 1e6;
+
+        let event = serde_json::to_string(&(time, kind.code(), data))?;
+        writeln!(self.writer, "{}", event)
+    }
+}
+
+/// Playback of an asciicast recording.
+///
+/// Takes the place of the PTY event loop, feeding the recorded output through the parser with
+/// the original timing. User input is ignored.
+///
+/// Recorded resizes are requested from the window, pausing playback until the terminal has been
+/// resized.
+pub struct Player<U: EventListener> {
+    poll: mio::Poll,
+    rx: Receiver<Msg>,
+    tx: Sender<Msg>,
+    terminal: Arc<FairMutex<Term<U>>>,
+    event_proxy: U,
+    cast: Cast,
+    speed: f64,
+}
+
+impl<U> Player<U>
+where
+    U: EventListener + Send + 'static,
+{
+    /// Create a new player.
+    ///
+    /// The `speed` is the playback rate, with `1.` replaying the recording in real time.
+    pub fn new(terminal: Arc<FairMutex<Term<U>>>, event_proxy: U, cast: Cast, speed: f64) -> Self {
+        let (tx, rx) = channel::channel();
+        Self {
+            poll: mio::Poll::new().expect("create mio Poll"),
+            tx,
+            rx,
+            terminal,
+            event_proxy,
+            cast,
+            speed,
+        }
+    }
+
+    pub fn channel(&self) -> Sender<Msg> {
+        self.tx.clone()
+    }
+
+    pub fn spawn(self) -> JoinHandle<()> {
+        thread::spawn_named("asciicast player", move || {
+            let mut parser = ansi::Processor::new();
+            let mut events = Events::with_capacity(16);
+
+            let channel_token = mio::Token(0);
+            let poll_opts = PollOpt::edge() | PollOpt::oneshot();
+            self.poll.register(&self.rx, channel_token, Ready::readable(), poll_opts).unwrap();
+
+            let mut playback = playback_times(&self.cast, self.speed)
+                .into_iter()
+                .zip(self.cast.events.iter())
+                .filter(|(_, event)| event.kind != EventKind::Input)
+                .peekable();
+
+            // Time at which playback was paused for a resize.
+            let mut resize_start: Option<Instant> = None;
+
+            let mut start = Instant::now();
+            loop {
+                let deadline = match resize_start {
+                    Some(resize_start) => Some(resize_start + RESIZE_TIMEOUT),
+                    None => playback.peek().map(|(time, _)| start + *time),
+                };
+                let timeout =
+                    deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+                if let Err(err) = self.poll.poll(&mut events, timeout) {
+                    match err.kind() {
+                        ErrorKind::Interrupted => continue,
+                        _ => panic!("Player polling error: {:?}", err),
+                    }
+                }
+
+                let mut resized = false;
+                if !events.is_empty() {
+                    while let Ok(msg) = self.rx.try_recv() {
+                        match msg {
+                            Msg::Shutdown => return,
+                            Msg::Resize(_) => resized = true,
+                            _ => (),
+                        }
+                    }
+
+                    self.poll
+                        .reregister(&self.rx, channel_token, Ready::readable(), poll_opts)
+                        .unwrap();
+                }
+
+                // Resume playback once the window was resized, delaying all remaining events.
+                let now = Instant::now();
+                if let Some(resize_start) = resize_start {
+                    if !resized && now < resize_start + RESIZE_TIMEOUT {
+                        continue;
+                    }
+
+                    start += now - resize_start;
+                }
+                resize_start = None;
+
+                // Play all events which are due.
+                let mut terminal = None;
+                while let Some((time, event)) = playback.peek() {
+                    if start + *time > now {
+                        break;
+                    }
+
+                    let terminal = terminal.get_or_insert_with(|| self.terminal.lock());
+                    match event.dimensions() {
+                        Some((columns, lines)) => {
+                            let (columns, lines) = (Column(columns), Line(lines));
+                            if terminal.cols() != columns || terminal.screen_lines() != lines {
+                                self.event_proxy.send_event(Event::ResizeRequest(columns, lines));
+                                resize_start = Some(now);
+                            }
+                        },
+                        None if event.kind == EventKind::Output => {
+                            for byte in event.data.bytes() {
+                                parser.advance(&mut **terminal, byte, &mut io::sink());
+                            }
+                        },
+                        None => (),
+                    }
+
+                    playback.next();
+
+                    if resize_start.is_some() {
+                        break;
+                    }
+                }
+
+                if terminal.is_some() {
+                    self.event_proxy.send_event(Event::Wakeup);
+                }
+            }
+        })
+    }
+}
+
+/// Playback offset of every event from the start of the recording.
+///
+/// Pauses between events are capped to the header's `idle_time_limit`.
+fn playback_times(cast: &Cast, speed: f64) -> Vec<Duration> {
+    let idle_time_limit = cast.header.idle_time_limit.unwrap_or(f64::INFINITY);
+
+    let mut last_time = 0.;
+    let mut elapsed = 0.;
+    cast.events
+        .iter()
+        .map(|event| {
+            elapsed += (event.time - last_time).max(0.).min(idle_time_limit);
+            last_time = event.time;
+            Duration::from_secs_f64(elapsed / speed)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::{mpsc, Mutex};
+
+    use crate::config::MockConfig;
+    use crate::term::SizeInfo;
+
+    /// Listener forwarding all terminal events to a channel.
+    #[derive(Clone)]
+    struct MockListener(Arc<Mutex<mpsc::Sender<Event>>>);
+
+    impl EventListener for MockListener {
+        fn send_event(&self, event: Event) {
+            let _ = self.0.lock().unwrap().send(event);
+        }
+    }
+
+    #[test]
+    fn record_and_read() {
+        let mut buffer = Vec::new();
+        let mut recorder = Recorder::new(&mut buffer, 80, 24).unwrap();
+        recorder.output(b"\x1b[1mbold\r\n").unwrap();
+        recorder.input(b"ls\r").unwrap();
+        recorder.resize(80, 24).unwrap();
+        recorder.resize(100, 30).unwrap();
+        drop(recorder);
+
+        let cast = Cast::read(&buffer[..]).unwrap();
+        assert_eq!(cast.header.version, 2);
+        assert_eq!((cast.header.width, cast.header.height), (80, 24));
+
+        let events: Vec<_> = cast.events.iter().map(|e| (e.kind, e.data.as_str())).collect();
+        assert_eq!(events, vec![
+            (EventKind::Output, "\x1b[1mbold\r\n"),
+            (EventKind::Input, "ls\r"),
+            (EventKind::Resize, "100x30"),
+        ]);
+    }
+
+    #[test]
+    fn split_utf8_output() {
+        let mut buffer = Vec::new();
+        let mut recorder = Recorder::new(&mut buffer, 80, 24).unwrap();
+        let bytes = "a😀b".as_bytes();
+        recorder.output(&bytes[..3]).unwrap();
+        recorder.output(&bytes[3..]).unwrap();
+        drop(recorder);
+
+        let cast = Cast::read(&buffer[..]).unwrap();
+        let data: Vec<_> = cast.events.iter().map(|e| e.data.as_str()).collect();
+        assert_eq!(data, vec!["a", "😀b"]);
+    }
+
+    #[test]
+    fn read_cast() {
+        let recording = r#"{"version": 2, "width": 10, "height": 5, "idle_time_limit": 1.0}
+[0.5, "o", "x"]
+[0.75, "m", "marker"]
+[4.5, "o", "y"]
+"#;
+
+        let cast = Cast::read(recording.as_bytes()).unwrap();
+        assert_eq!(cast.events.len(), 2);
+
+        let times = playback_times(&cast, 2.);
+        assert_eq!(times, vec![Duration::from_millis(250), Duration::from_millis(750)]);
+    }
+
+    #[test]
+    fn resize_dimensions() {
+        let event = |kind, data: &str| CastEvent { time: 0., kind, data: data.into() };
+        assert_eq!(event(EventKind::Resize, "100x30").dimensions(), Some((100, 30)));
+        assert_eq!(event(EventKind::Resize, "100").dimensions(), None);
+        assert_eq!(event(EventKind::Output, "100x30").dimensions(), None);
+    }
+
+    #[test]
+    fn replay_resize() {
+        let recording = r#"{"version": 2, "width": 10, "height": 5}
+[0.0, "o", "a"]
+[0.0, "r", "20x8"]
+[0.0, "o", "b"]
+"#;
+        let cast = Cast::read(recording.as_bytes()).unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let listener = MockListener(Arc::new(Mutex::new(tx)));
+        let size = SizeInfo::new(10., 5., 1., 1., 0., 0., false);
+        let terminal = Term::new(&MockConfig::default(), size, listener.clone());
+        let terminal = Arc::new(FairMutex::new(terminal));
+
+        let player = Player::new(Arc::clone(&terminal), listener, cast, 1.);
+        let channel = player.channel();
+        let player = player.spawn();
+
+        // Playback is paused until the window has been resized.
+        let timeout = Duration::from_secs(5);
+        loop {
+            if let Event::ResizeRequest(columns, lines) = rx.recv_timeout(timeout).unwrap() {
+                assert_eq!((columns, lines), (Column(20), Line(8)));
+                break;
+            }
+        }
+        assert_eq!(terminal.lock().grid()[Line(0)][Column(0)].c, 'a');
+        assert_eq!(terminal.lock().grid()[Line(0)][Column(1)].c, ' ');
+
+        let size = SizeInfo::new(20., 8., 1., 1., 0., 0., false);
+        terminal.lock().resize(size);
+        channel.send(Msg::Resize(size)).unwrap();
+
+        let deadline = Instant::now() + timeout;
+        while terminal.lock().grid()[Line(0)][Column(1)].c != 'b' {
+            assert!(Instant::now() < deadline);
+            let _ = rx.recv_timeout(timeout);
+        }
+
+        channel.send(Msg::Shutdown).unwrap();
+        player.join().unwrap();
+    }
+
+    #[test]
+    fn unsupported_version() {
+        let recording = r#"{"version": 1, "width": 10, "height": 5}"#;
+        assert!(matches!(Cast::read(recording.as_bytes()), Err(Error::Version(1))));
+    }
+}
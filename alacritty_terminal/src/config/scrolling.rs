@@ -11,12 +11,15 @@ const MAX_SCROLLBACK_LINES: u32 = 100_000;
 pub struct Scrolling {
     pub multiplier: u8,
 
+    /// Scrollback history stored on disk once `history` is exhausted.
+    pub disk: DiskScrolling,
+
     history: ScrollingHistory,
 }
 
 impl Default for Scrolling {
     fn default() -> Self {
-        Self { multiplier: 3, history: Default::default() }
+        Self { multiplier: 3, disk: Default::default(), history: Default::default() }
     }
 }
 
@@ -31,6 +34,16 @@ impl Scrolling {
     }
 }
 
+/// Disk backed scrollback history.
+#[derive(ConfigDeserialize, Default, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DiskScrolling {
+    /// Maximum number of lines stored on disk.
+    pub history: u32,
+
+    /// Restore the history of the last closed terminal on startup.
+    pub restore: bool,
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 struct ScrollingHistory(u32);
 
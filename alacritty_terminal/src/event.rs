@@ -1,7 +1,9 @@
 use std::borrow::Cow;
 use std::fmt::{self, Debug, Formatter};
+use std::path::PathBuf;
 use std::sync::Arc;
 
+use crate::index::{Column, Line};
 use crate::term::color::Rgb;
 use crate::term::{ClipboardType, SizeInfo};
 
@@ -38,6 +40,9 @@ pub enum Event {
     /// Cursor blinking state has changed.
     CursorBlinkingChange(bool),
 
+    /// Request to resize the window to fit the terminal dimensions.
+    ResizeRequest(Column, Line),
+
     /// New terminal content available.
     Wakeup,
 
@@ -61,6 +66,9 @@ impl Debug for Event {
             Event::Bell => write!(f, "Bell"),
             Event::Exit => write!(f, "Exit"),
             Event::CursorBlinkingChange(blinking) => write!(f, "CursorBlinking({})", blinking),
+            Event::ResizeRequest(columns, lines) => {
+                write!(f, "ResizeRequest({}, {})", columns, lines)
+            },
         }
     }
 }
@@ -78,6 +86,14 @@ pub trait OnResize {
     fn on_resize(&mut self, size: &SizeInfo);
 }
 
+/// Types which can record the terminal session.
+pub trait Record {
+    /// Stop the active recording, or start recording the session to `path` if there is none.
+    ///
+    /// User input is only recorded if `input` is set, since it contains secrets like passwords.
+    fn toggle_recording(&mut self, path: PathBuf, input: bool);
+}
+
 /// Event Loop for notifying the renderer about terminal events.
 pub trait EventListener {
     fn send_event(&self, _event: Event) {}
@@ -5,25 +5,37 @@ use std::collections::VecDeque;
 use std::fs::File;
 use std::io::{self, ErrorKind, Read, Write};
 use std::marker::Send;
+use std::mem;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
-use log::error;
+use log::{error, info};
 #[cfg(not(windows))]
 use mio::unix::UnixReady;
 use mio::{self, Events, PollOpt, Ready};
 use mio_extras::channel::{self, Receiver, Sender};
+use serde::Serialize;
 
-use crate::ansi;
+use crate::ansi::{self, Handler};
+use crate::asciicast::Recorder;
 use crate::event::{self, Event, EventListener};
+use crate::grid::Dimensions;
 use crate::sync::FairMutex;
-use crate::term::{SizeInfo, Term};
+use crate::term::{SizeInfo, Term, TermMode};
 use crate::thread;
 use crate::tty;
 
 /// Max bytes to read from the PTY.
 const MAX_READ: usize = u16::max_value() as usize;
 
+/// Maximum time a synchronized update may hold back rendering.
+const SYNC_UPDATE_TIMEOUT: Duration = Duration::from_millis(150);
+
+/// Maximum number of bytes buffered during a synchronized update.
+const SYNC_BUFFER_SIZE: usize = 0x20_0000;
+
 /// Messages that may be sent to the `EventLoop`.
 #[derive(Debug)]
 pub enum Msg {
@@ -35,6 +47,11 @@ pub enum Msg {
 
     /// Instruction to resize the PTY.
     Resize(SizeInfo),
+
+    /// Stop the active session recording, or start recording to the path if there is none.
+    ///
+    /// User input is only recorded if the flag is set.
+    ToggleRecording(PathBuf, bool),
 }
 
 /// The main event!.. loop.
@@ -50,6 +67,38 @@ pub struct EventLoop<T: tty::EventedPty, U: EventListener> {
     event_proxy: U,
     hold: bool,
     ref_test: bool,
+    ref_test_resizes: Vec<RefTestResize>,
+
+    /// Number of bytes written to the ref test recording.
+    ref_test_offset: u64,
+}
+
+/// Terminal dimensions starting at a position of the ref test recording.
+#[derive(Serialize, Debug, Copy, Clone)]
+struct RefTestResize {
+    /// Number of recorded bytes before the resize.
+    offset: u64,
+    cols: usize,
+    screen_lines: usize,
+}
+
+/// Handler detecting the escapes which terminate a synchronized update.
+///
+/// This parses the buffered bytes without applying them, so the update is also terminated by
+/// combined mode parameters or a full reset.
+#[derive(Default)]
+struct SyncEnd {
+    done: bool,
+}
+
+impl Handler for SyncEnd {
+    fn unset_mode(&mut self, mode: ansi::Mode) {
+        self.done |= mode == ansi::Mode::SyncUpdate;
+    }
+
+    fn reset_state(&mut self) {
+        self.done = true;
+    }
 }
 
 /// Helper type which tracks how much of a buffer has been written.
@@ -66,6 +115,24 @@ pub struct State {
     write_list: VecDeque<Cow<'static, [u8]>>,
     writing: Option<Writing>,
     parser: ansi::Processor,
+
+    /// Bytes received while a synchronized update is in progress.
+    sync_bytes: Vec<u8>,
+
+    /// Parser looking for the end of the synchronized update in the buffered bytes.
+    sync_parser: ansi::Processor,
+
+    /// Escapes of the buffered bytes which terminate the synchronized update.
+    sync_end: SyncEnd,
+
+    /// Deadline of the active synchronized update.
+    sync_timeout: Option<Instant>,
+
+    /// Active asciicast session recording.
+    recorder: Option<Recorder<File>>,
+
+    /// Record user input in addition to the terminal output.
+    record_input: bool,
 }
 
 pub struct Notifier(pub Sender<Msg>);
@@ -91,9 +158,25 @@ impl event::OnResize for Notifier {
     }
 }
 
+impl event::Record for Notifier {
+    fn toggle_recording(&mut self, path: PathBuf, input: bool) {
+        self.0.send(Msg::ToggleRecording(path, input)).expect("expected send event loop msg");
+    }
+}
+
 impl Default for State {
     fn default() -> State {
-        State { write_list: VecDeque::new(), parser: ansi::Processor::new(), writing: None }
+        State {
+            write_list: VecDeque::new(),
+            parser: ansi::Processor::new(),
+            writing: None,
+            sync_bytes: Vec::new(),
+            sync_parser: ansi::Processor::new(),
+            sync_end: SyncEnd::default(),
+            sync_timeout: None,
+            recorder: None,
+            record_input: false,
+        }
     }
 }
 
@@ -124,6 +207,93 @@ impl State {
     fn set_current(&mut self, new: Option<Writing>) {
         self.writing = new;
     }
+
+    /// Parse a byte, buffering it while a synchronized update is active.
+    #[inline]
+    fn advance<U, W>(&mut self, terminal: &mut Term<U>, byte: u8, writer: &mut W)
+    where
+        U: EventListener,
+        W: Write,
+    {
+        if self.sync_timeout.is_none() {
+            self.parser.advance(terminal, byte, writer);
+
+            if terminal.mode().contains(TermMode::SYNC_UPDATE) {
+                self.sync_timeout = Some(Instant::now() + SYNC_UPDATE_TIMEOUT);
+            }
+
+            return;
+        }
+
+        self.sync_bytes.push(byte);
+        self.sync_parser.advance(&mut self.sync_end, byte, &mut io::sink());
+
+        if self.sync_end.done || self.sync_bytes.len() >= SYNC_BUFFER_SIZE {
+            self.stop_sync(terminal, writer);
+        }
+    }
+
+    /// Check if the active synchronized update exceeded its deadline.
+    #[inline]
+    fn sync_expired(&self, now: Instant) -> bool {
+        self.sync_timeout.map_or(false, |timeout| timeout <= now)
+    }
+
+    /// Apply all bytes buffered during the synchronized update.
+    fn stop_sync<U, W>(&mut self, terminal: &mut Term<U>, writer: &mut W)
+    where
+        U: EventListener,
+        W: Write,
+    {
+        self.sync_timeout = None;
+        self.sync_parser = ansi::Processor::new();
+        self.sync_end = SyncEnd::default();
+
+        // Make sure a new update is only started by another escape after a timeout.
+        terminal.unset_mode(ansi::Mode::SyncUpdate);
+
+        let mut bytes = mem::take(&mut self.sync_bytes);
+        for byte in &bytes {
+            self.parser.advance(terminal, *byte, writer);
+        }
+
+        // Keep buffering if the applied bytes started the next update.
+        if terminal.mode().contains(TermMode::SYNC_UPDATE) {
+            self.sync_timeout = Some(Instant::now() + SYNC_UPDATE_TIMEOUT);
+        }
+
+        bytes.clear();
+        self.sync_bytes = bytes;
+    }
+
+    /// Apply any synchronized update which is still being buffered.
+    ///
+    /// This should be called before the terminal content is presented one last time, since the
+    /// buffered bytes would otherwise never be applied after the child process exited.
+    pub fn flush_sync<U: EventListener>(&mut self, terminal: &mut Term<U>) {
+        if self.sync_timeout.is_none() {
+            return;
+        }
+
+        // There is no PTY to answer requests anymore.
+        self.stop_sync(terminal, &mut io::sink());
+
+        self.sync_timeout = None;
+        terminal.unset_mode(ansi::Mode::SyncUpdate);
+    }
+
+    /// Pass data to the session recording.
+    ///
+    /// The recording is stopped if writing to it fails.
+    fn record<F>(&mut self, f: F)
+    where
+        F: FnOnce(&mut Recorder<File>) -> io::Result<()>,
+    {
+        if let Some(Err(err)) = self.recorder.as_mut().map(f) {
+            error!("Stopped session recording: {}", err);
+            self.recorder = None;
+        }
+    }
 }
 
 impl Writing {
@@ -171,6 +341,8 @@ where
             event_proxy,
             hold,
             ref_test,
+            ref_test_resizes: Vec::new(),
+            ref_test_offset: 0,
         }
     }
 
@@ -184,15 +356,47 @@ where
     fn drain_recv_channel(&mut self, state: &mut State) -> bool {
         while let Ok(msg) = self.rx.try_recv() {
             match msg {
-                Msg::Input(input) => state.write_list.push_back(input),
+                Msg::Input(input) => {
+                    if state.record_input {
+                        state.record(|recorder| recorder.input(&input));
+                    }
+                    state.write_list.push_back(input);
+                },
                 Msg::Shutdown => return false,
-                Msg::Resize(size) => self.pty.on_resize(&size),
+                Msg::Resize(size) => {
+                    state.record(|recorder| recorder.resize(size.cols().0, size.screen_lines().0));
+                    self.record_ref_test_resize(size.cols().0, size.screen_lines().0);
+                    self.pty.on_resize(&size);
+                },
+                Msg::ToggleRecording(path, input) => self.toggle_recording(state, path, input),
             }
         }
 
         true
     }
 
+    /// Stop the active session recording, or start a new one.
+    fn toggle_recording(&mut self, state: &mut State, path: PathBuf, input: bool) {
+        if state.recorder.take().is_some() {
+            info!("Stopped session recording");
+            return;
+        }
+
+        let (columns, lines) = {
+            let terminal = self.terminal.lock();
+            (terminal.cols().0, terminal.screen_lines().0)
+        };
+
+        match Recorder::create(&path, columns, lines) {
+            Ok(recorder) => {
+                info!("Recording session to {:?}", path);
+                state.recorder = Some(recorder);
+                state.record_input = input;
+            },
+            Err(err) => error!("Unable to record session to {:?}: {}", path, err),
+        }
+    }
+
     /// Returns a `bool` indicating whether or not the event loop should continue running.
     #[inline]
     fn channel_event(&mut self, token: mio::Token, state: &mut State) -> bool {
@@ -207,6 +411,26 @@ where
         true
     }
 
+    /// Store the terminal dimensions for the current position of the ref test recording.
+    fn record_ref_test_resize(&mut self, cols: usize, screen_lines: usize) {
+        if !self.ref_test {
+            return;
+        }
+
+        let offset = self.ref_test_offset;
+        self.ref_test_resizes.push(RefTestResize { offset, cols, screen_lines });
+
+        // Only the initial dimensions are known, so there's nothing to replay yet.
+        if self.ref_test_resizes.len() == 1 {
+            return;
+        }
+
+        let serialized_resizes = serde_json::to_string(&self.ref_test_resizes).expect("serialize");
+        File::create("./resize.json")
+            .and_then(|mut f| f.write_all(serialized_resizes.as_bytes()))
+            .expect("write resize.json");
+    }
+
     #[inline]
     fn pty_read<X>(
         &mut self,
@@ -233,6 +457,11 @@ where
                         w.write_all(&buf[..got]).unwrap();
                         w
                     });
+                    if writer.is_some() {
+                        self.ref_test_offset += got as u64;
+                    }
+
+                    state.record(|recorder| recorder.output(&buf[..got]));
 
                     // Get reference to terminal. Lock is acquired on initial
                     // iteration and held until there's no bytes left to parse
@@ -244,7 +473,7 @@ where
 
                     // Run the parser.
                     for byte in &buf[..got] {
-                        state.parser.advance(&mut **terminal, *byte, &mut self.pty.writer());
+                        state.advance(&mut **terminal, *byte, &mut self.pty.writer());
                     }
 
                     // Exit if we've processed enough bytes.
@@ -261,7 +490,8 @@ where
             }
         }
 
-        if processed > 0 {
+        // Hold back redraws until the synchronized update is completed.
+        if processed > 0 && state.sync_timeout.is_none() {
             // Queue terminal redraw.
             self.event_proxy.send_event(Event::Wakeup);
         }
@@ -324,14 +554,31 @@ where
                 None
             };
 
+            // Store the initial dimensions, to replay resizes during the ref test.
+            if self.ref_test {
+                let terminal = self.terminal.lock();
+                let (cols, screen_lines) = (terminal.cols().0, terminal.screen_lines().0);
+                drop(terminal);
+                self.record_ref_test_resize(cols, screen_lines);
+            }
+
             'event_loop: loop {
-                if let Err(err) = self.poll.poll(&mut events, None) {
+                let timeout = state
+                    .sync_timeout
+                    .map(|timeout| timeout.saturating_duration_since(Instant::now()));
+                if let Err(err) = self.poll.poll(&mut events, timeout) {
                     match err.kind() {
                         ErrorKind::Interrupted => continue,
                         _ => panic!("EventLoop polling error: {:?}", err),
                     }
                 }
 
+                // Present the buffered update once the application took too long to finish it.
+                if state.sync_expired(Instant::now()) {
+                    state.stop_sync(&mut *self.terminal.lock(), &mut self.pty.writer());
+                    self.event_proxy.send_event(Event::Wakeup);
+                }
+
                 for event in events.iter() {
                     match event.token() {
                         token if token == channel_token => {
@@ -411,3 +658,135 @@ where
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::config::MockConfig;
+    use crate::index::{Column, Line};
+
+    fn mock_term() -> Term<()> {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        Term::new(&MockConfig::default(), size, ())
+    }
+
+    fn advance(state: &mut State, terminal: &mut Term<()>, bytes: &[u8]) {
+        for byte in bytes {
+            state.advance(terminal, *byte, &mut io::sink());
+        }
+    }
+
+    #[test]
+    fn sync_update_buffering() {
+        let mut term = mock_term();
+        let mut state = State::default();
+
+        // Content is held back until the update is terminated.
+        advance(&mut state, &mut term, b"\x1b[?2026ha");
+        assert!(state.sync_timeout.is_some());
+        assert_eq!(term.grid()[Line(0)][Column(0)].c, ' ');
+
+        // Combined mode parameters terminate the update.
+        advance(&mut state, &mut term, b"\x1b[?2026;1l");
+        assert!(state.sync_timeout.is_none());
+        assert_eq!(term.grid()[Line(0)][Column(0)].c, 'a');
+
+        // Content after the update is applied immediately.
+        advance(&mut state, &mut term, b"b");
+        assert_eq!(term.grid()[Line(0)][Column(1)].c, 'b');
+    }
+
+    #[test]
+    fn sync_update_restart() {
+        let mut term = mock_term();
+        let mut state = State::default();
+
+        // The next update is started right after the end of the previous one.
+        advance(&mut state, &mut term, b"\x1b[?2026ha");
+        advance(&mut state, &mut term, b"\x1b[?2026l\x1b[?2026hb");
+        assert!(state.sync_timeout.is_some());
+        assert!(term.mode().contains(TermMode::SYNC_UPDATE));
+        assert_eq!(term.grid()[Line(0)][Column(0)].c, 'a');
+        assert_eq!(term.grid()[Line(0)][Column(1)].c, ' ');
+
+        // Updates started by the applied bytes of an expired update are still buffered.
+        advance(&mut state, &mut term, b"\x1b[?2026hc");
+        state.stop_sync(&mut term, &mut io::sink());
+        assert!(state.sync_timeout.is_some());
+        assert!(term.mode().contains(TermMode::SYNC_UPDATE));
+        assert_eq!(term.grid()[Line(0)][Column(2)].c, 'c');
+
+        advance(&mut state, &mut term, b"d");
+        assert_eq!(term.grid()[Line(0)][Column(3)].c, ' ');
+        advance(&mut state, &mut term, b"\x1b[?2026l");
+        assert!(state.sync_timeout.is_none());
+        assert!(!term.mode().contains(TermMode::SYNC_UPDATE));
+        assert_eq!(term.grid()[Line(0)][Column(3)].c, 'd');
+    }
+
+    #[test]
+    fn sync_update_flush() {
+        let mut term = mock_term();
+        let mut state = State::default();
+
+        advance(&mut state, &mut term, b"\x1b[?2026ha\x1b[?2026hb");
+        assert_eq!(term.grid()[Line(0)][Column(0)].c, ' ');
+
+        state.flush_sync(&mut term);
+        assert!(state.sync_timeout.is_none());
+        assert!(state.sync_bytes.is_empty());
+        assert!(!term.mode().contains(TermMode::SYNC_UPDATE));
+        assert_eq!(term.grid()[Line(0)][Column(0)].c, 'a');
+        assert_eq!(term.grid()[Line(0)][Column(1)].c, 'b');
+    }
+
+    #[test]
+    fn sync_update_reset() {
+        let mut term = mock_term();
+        let mut state = State::default();
+
+        advance(&mut state, &mut term, b"\x1b[?2026ha\x1bcb");
+        assert!(state.sync_timeout.is_none());
+        assert!(!term.mode().contains(TermMode::SYNC_UPDATE));
+        assert_eq!(term.grid()[Line(0)][Column(0)].c, 'b');
+    }
+
+    #[test]
+    fn sync_update_timeout() {
+        let mut term = mock_term();
+        let mut state = State::default();
+
+        advance(&mut state, &mut term, b"\x1b[?2026ha");
+        assert!(!state.sync_expired(Instant::now()));
+        assert!(state.sync_expired(Instant::now() + SYNC_UPDATE_TIMEOUT));
+
+        // Expired updates are applied without waiting for the end.
+        state.stop_sync(&mut term, &mut io::sink());
+        assert!(!term.mode().contains(TermMode::SYNC_UPDATE));
+        assert_eq!(term.grid()[Line(0)][Column(0)].c, 'a');
+
+        // Escapes terminating the expired update are ignored.
+        advance(&mut state, &mut term, b"\x1b[?2026lb");
+        assert!(state.sync_timeout.is_none());
+        assert_eq!(term.grid()[Line(0)][Column(1)].c, 'b');
+    }
+
+    #[test]
+    fn sync_update_overflow() {
+        let mut term = mock_term();
+        let mut state = State::default();
+
+        advance(&mut state, &mut term, b"\x1b[?2026h");
+        let bytes = vec![b'a'; SYNC_BUFFER_SIZE - 1];
+        advance(&mut state, &mut term, &bytes);
+        assert!(state.sync_timeout.is_some());
+        assert_eq!(term.grid()[Line(0)][Column(0)].c, ' ');
+
+        // The update is applied once the buffer is full.
+        advance(&mut state, &mut term, b"a");
+        assert!(state.sync_timeout.is_none());
+        assert!(state.sync_bytes.is_empty());
+        assert_eq!(term.grid()[Line(0)][Column(0)].c, 'a');
+    }
+}
@@ -0,0 +1,150 @@
+//! Graphics which are drawn as part of the terminal grid.
+
+use std::mem;
+use std::sync::{Arc, Mutex, Weak};
+
+pub mod sixel;
+
+/// Unique identifier for every graphic added to the grid.
+#[derive(Eq, PartialEq, Clone, Copy, Debug, Hash, PartialOrd, Ord)]
+pub struct GraphicId(pub u64);
+
+/// Pixel data of a graphic.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GraphicData {
+    /// Graphics identifier.
+    pub id: GraphicId,
+
+    /// Width in pixels.
+    pub width: usize,
+
+    /// Height in pixels.
+    pub height: usize,
+
+    /// RGBA pixels, stored row by row.
+    pub pixels: Vec<u8>,
+}
+
+/// Reference to the texture of a graphic.
+///
+/// Once all cells referencing a texture have been dropped, it is queued for removal.
+#[derive(Debug)]
+pub struct TextureRef {
+    /// Graphic identifier.
+    pub id: GraphicId,
+
+    /// Queue for textures which are no longer used.
+    remove_queue: Weak<Mutex<Vec<GraphicId>>>,
+}
+
+impl Drop for TextureRef {
+    fn drop(&mut self) {
+        if let Some(remove_queue) = self.remove_queue.upgrade() {
+            if let Ok(mut remove_queue) = remove_queue.lock() {
+                remove_queue.push(self.id);
+            }
+        }
+    }
+}
+
+/// Fragment of a graphic shown in a single cell.
+#[derive(Clone, Debug)]
+pub struct GraphicCell {
+    /// Texture of the graphic.
+    pub texture: Arc<TextureRef>,
+
+    /// Horizontal offset of the cell within the graphic, in pixels.
+    pub offset_x: u16,
+
+    /// Vertical offset of the cell within the graphic, in pixels.
+    pub offset_y: u16,
+}
+
+impl PartialEq for GraphicCell {
+    fn eq(&self, other: &Self) -> bool {
+        self.texture.id == other.texture.id
+            && self.offset_x == other.offset_x
+            && self.offset_y == other.offset_y
+    }
+}
+
+impl Eq for GraphicCell {}
+
+/// Graphics which have been added to or removed from the grid since the last update.
+#[derive(Debug, Default)]
+pub struct UpdateQueues {
+    /// Graphics which need to be uploaded.
+    pub pending: Vec<GraphicData>,
+
+    /// Graphics which are no longer referenced by any cell.
+    pub remove_queue: Vec<GraphicId>,
+}
+
+/// Graphics state of the terminal.
+#[derive(Debug, Default)]
+pub struct Graphics {
+    /// Last generated identifier.
+    last_id: u64,
+
+    /// Graphics waiting to be uploaded by the renderer.
+    pending: Vec<GraphicData>,
+
+    /// Graphics which are no longer referenced by any cell.
+    remove_queue: Arc<Mutex<Vec<GraphicId>>>,
+}
+
+impl Graphics {
+    /// Add a new graphic, returning the texture reference for its cells.
+    pub fn insert(&mut self, mut graphic: GraphicData) -> Arc<TextureRef> {
+        self.last_id += 1;
+        graphic.id = GraphicId(self.last_id);
+
+        let texture =
+            TextureRef { id: graphic.id, remove_queue: Arc::downgrade(&self.remove_queue) };
+        self.pending.push(graphic);
+
+        Arc::new(texture)
+    }
+
+    /// Take all graphics updates since the last call.
+    pub fn take_updates(&mut self) -> Option<UpdateQueues> {
+        let remove_queue = match self.remove_queue.lock() {
+            Ok(mut remove_queue) if !remove_queue.is_empty() => mem::take(&mut *remove_queue),
+            _ => Vec::new(),
+        };
+
+        if self.pending.is_empty() && remove_queue.is_empty() {
+            return None;
+        }
+
+        Some(UpdateQueues { pending: mem::take(&mut self.pending), remove_queue })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remove_unused_textures() {
+        let mut graphics = Graphics::default();
+
+        let graphic = GraphicData { id: GraphicId(0), width: 1, height: 1, pixels: vec![0; 4] };
+        let texture = graphics.insert(graphic);
+        let id = texture.id;
+
+        let updates = graphics.take_updates().unwrap();
+        assert_eq!(updates.pending.len(), 1);
+        assert_eq!(updates.pending[0].id, id);
+        assert!(updates.remove_queue.is_empty());
+        assert!(graphics.take_updates().is_none());
+
+        let cell = GraphicCell { texture: texture.clone(), offset_x: 0, offset_y: 0 };
+        drop(texture);
+        assert!(graphics.take_updates().is_none());
+
+        drop(cell);
+        let updates = graphics.take_updates().unwrap();
+        assert_eq!(updates.remove_queue, vec![id]);
+    }
+}
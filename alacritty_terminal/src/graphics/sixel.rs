@@ -0,0 +1,407 @@
+//! Decoder for the Sixel graphics format.
+//!
+//! The format is described in the VT330/VT340 programmer reference manual:
+//! <https://vt100.net/docs/vt3xx-gp/chapter14.html>.
+
+use std::fmt::{self, Display, Formatter};
+
+use log::trace;
+
+use crate::graphics::{GraphicData, GraphicId};
+
+/// Maximum width and height of a graphic, in pixels.
+const MAX_GRAPHIC_DIMENSIONS: usize = 4096;
+
+/// Number of available color registers.
+const MAX_COLOR_REGISTERS: usize = 1024;
+
+/// Maximum number of numeric parameters for a single command.
+const MAX_COMMAND_PARAMS: usize = 5;
+
+/// Height of a single sixel, in pixels.
+const SIXEL_HEIGHT: usize = 6;
+
+/// Default color palette of the VT340, as RGB percentages.
+const VT340_PALETTE: [[u16; 3]; 16] = [
+    [0, 0, 0],
+    [20, 20, 80],
+    [80, 13, 13],
+    [20, 80, 20],
+    [80, 20, 80],
+    [20, 80, 80],
+    [80, 80, 20],
+    [53, 53, 53],
+    [26, 26, 26],
+    [33, 33, 60],
+    [60, 26, 26],
+    [33, 60, 33],
+    [60, 33, 60],
+    [33, 60, 60],
+    [60, 60, 33],
+    [80, 80, 80],
+];
+
+/// Errors which can occur while decoding Sixel data.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The graphic does not contain any pixels.
+    Empty,
+
+    /// Color register is outside of the supported range.
+    InvalidColorRegister(u16),
+
+    /// Color coordinate system is neither HLS nor RGB.
+    InvalidColorSpace(u16),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Empty => write!(f, "Sixel graphic is empty"),
+            Error::InvalidColorRegister(register) => {
+                write!(f, "Invalid Sixel color register {}", register)
+            },
+            Error::InvalidColorSpace(space) => write!(f, "Invalid Sixel color space {}", space),
+        }
+    }
+}
+
+/// Command which is waiting for its parameters.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Command {
+    /// No command is active.
+    None,
+
+    /// Raster attributes (`"`).
+    RasterAttributes,
+
+    /// Color introducer (`#`).
+    ColorIntroducer,
+
+    /// Graphics repeat introducer (`!`).
+    RepeatIntroducer,
+}
+
+/// Incremental Sixel decoder.
+#[derive(Debug)]
+pub struct Parser {
+    /// Command waiting for its parameters.
+    command: Command,
+
+    /// Numeric parameters of the active command.
+    params: Vec<u16>,
+
+    /// RGBA color registers.
+    palette: Vec<[u8; 4]>,
+
+    /// Register used for drawing sixels.
+    color_register: usize,
+
+    /// Color of pixels which are not drawn.
+    background: [u8; 4],
+
+    /// Position of the next sixel.
+    x: usize,
+    y: usize,
+
+    /// Size of the graphic.
+    width: usize,
+    height: usize,
+
+    /// RGBA pixel buffer, with a stride of `buffer_width`.
+    pixels: Vec<u8>,
+    buffer_width: usize,
+    buffer_height: usize,
+}
+
+impl Parser {
+    /// Create a new decoder.
+    ///
+    /// With `transparent_background` set, pixels which are not drawn remain transparent instead of
+    /// using the color of the first register.
+    pub fn new(transparent_background: bool) -> Self {
+        let mut palette = vec![[0, 0, 0, 255]; MAX_COLOR_REGISTERS];
+        for (register, [r, g, b]) in VT340_PALETTE.iter().enumerate() {
+            palette[register] = [percent(*r), percent(*g), percent(*b), 255];
+        }
+
+        let background = if transparent_background { [0; 4] } else { palette[0] };
+
+        Self {
+            command: Command::None,
+            params: Vec::new(),
+            palette,
+            color_register: 0,
+            background,
+            x: 0,
+            y: 0,
+            width: 0,
+            height: 0,
+            pixels: Vec::new(),
+            buffer_width: 0,
+            buffer_height: 0,
+        }
+    }
+
+    /// Process the next byte of Sixel data.
+    pub fn put(&mut self, byte: u8) -> Result<(), Error> {
+        match byte {
+            b'0'..=b'9' => {
+                if self.params.is_empty() {
+                    self.params.push(0);
+                }
+
+                let param = self.params.last_mut().unwrap();
+                *param = param.saturating_mul(10).saturating_add(u16::from(byte - b'0'));
+
+                return Ok(());
+            },
+            b';' => {
+                if self.params.is_empty() {
+                    self.params.push(0);
+                }
+
+                if self.params.len() < MAX_COMMAND_PARAMS {
+                    self.params.push(0);
+                }
+
+                return Ok(());
+            },
+            _ => (),
+        }
+
+        let repeat = self.finish_command()?;
+
+        match byte {
+            b'"' => self.command = Command::RasterAttributes,
+            b'#' => self.command = Command::ColorIntroducer,
+            b'!' => self.command = Command::RepeatIntroducer,
+            // Graphics carriage return.
+            b'$' => self.x = 0,
+            // Graphics new line.
+            b'-' => {
+                self.x = 0;
+                self.y += SIXEL_HEIGHT;
+            },
+            b'?'..=b'~' => self.draw_sixel(byte - b'?', repeat),
+            _ => trace!("Ignoring Sixel byte {:?}", byte),
+        }
+
+        Ok(())
+    }
+
+    /// Finish decoding and return the graphic.
+    pub fn finish(mut self) -> Result<GraphicData, Error> {
+        self.finish_command()?;
+
+        if self.width == 0 || self.height == 0 {
+            return Err(Error::Empty);
+        }
+
+        // Crop the buffer to the size of the graphic.
+        let mut pixels = Vec::with_capacity(self.width * self.height * 4);
+        for row in self.pixels.chunks(self.buffer_width * 4).take(self.height) {
+            pixels.extend_from_slice(&row[..self.width * 4]);
+        }
+
+        Ok(GraphicData { id: GraphicId(0), width: self.width, height: self.height, pixels })
+    }
+
+    /// Apply the active command, returning the number of repetitions for the next sixel.
+    fn finish_command(&mut self) -> Result<usize, Error> {
+        let param = |params: &[u16], index: usize| params.get(index).copied().unwrap_or(0);
+
+        let command = self.command;
+        self.command = Command::None;
+
+        let mut repeat = 1;
+        match command {
+            Command::None => (),
+            Command::RasterAttributes => {
+                // Aspect ratio is ignored, pixels are always square.
+                let width = usize::from(param(&self.params, 2));
+                let height = usize::from(param(&self.params, 3));
+                self.resize(width, height);
+            },
+            Command::ColorIntroducer => {
+                let register = param(&self.params, 0);
+                if usize::from(register) >= MAX_COLOR_REGISTERS {
+                    self.params.clear();
+                    return Err(Error::InvalidColorRegister(register));
+                }
+
+                if self.params.len() > 1 {
+                    let (x, y, z) =
+                        (param(&self.params, 2), param(&self.params, 3), param(&self.params, 4));
+                    let [r, g, b] = match param(&self.params, 1) {
+                        1 => hls_to_rgb(x, y, z),
+                        2 => [percent(x), percent(y), percent(z)],
+                        space => {
+                            self.params.clear();
+                            return Err(Error::InvalidColorSpace(space));
+                        },
+                    };
+                    self.palette[usize::from(register)] = [r, g, b, 255];
+                }
+
+                self.color_register = usize::from(register);
+            },
+            Command::RepeatIntroducer => repeat = usize::from(param(&self.params, 0)).max(1),
+        }
+
+        self.params.clear();
+
+        Ok(repeat)
+    }
+
+    /// Draw a sixel at the current position.
+    fn draw_sixel(&mut self, sixel: u8, repeat: usize) {
+        let start = self.x;
+        let end = (start + repeat).min(MAX_GRAPHIC_DIMENSIONS);
+        self.x = end;
+
+        if start >= end || self.y >= MAX_GRAPHIC_DIMENSIONS {
+            return;
+        }
+
+        let sixel_height = SIXEL_HEIGHT.min(MAX_GRAPHIC_DIMENSIONS - self.y);
+        self.resize(end, self.y + sixel_height);
+
+        let color = self.palette[self.color_register];
+        for bit in (0..sixel_height).filter(|bit| sixel & (1 << bit) != 0) {
+            let row_start = ((self.y + bit) * self.buffer_width) * 4;
+            for pixel in self.pixels[row_start + start * 4..row_start + end * 4].chunks_mut(4) {
+                pixel.copy_from_slice(&color);
+            }
+        }
+    }
+
+    /// Grow the graphic to at least the specified size.
+    fn resize(&mut self, width: usize, height: usize) {
+        let width = width.min(MAX_GRAPHIC_DIMENSIONS);
+        let height = height.min(MAX_GRAPHIC_DIMENSIONS);
+
+        self.width = self.width.max(width);
+        self.height = self.height.max(height);
+
+        if self.width <= self.buffer_width && self.height <= self.buffer_height {
+            return;
+        }
+
+        // Grow exponentially to avoid copying the buffer for every new sixel.
+        let buffer_width = if self.width > self.buffer_width {
+            self.width.max(self.buffer_width * 2).min(MAX_GRAPHIC_DIMENSIONS)
+        } else {
+            self.buffer_width
+        };
+        let buffer_height = if self.height > self.buffer_height {
+            self.height.max(self.buffer_height * 2).min(MAX_GRAPHIC_DIMENSIONS)
+        } else {
+            self.buffer_height
+        };
+
+        let mut pixels = Vec::with_capacity(buffer_width * buffer_height * 4);
+        for row in 0..buffer_height {
+            if row < self.buffer_height {
+                let start = row * self.buffer_width * 4;
+                pixels.extend_from_slice(&self.pixels[start..start + self.buffer_width * 4]);
+            }
+
+            while pixels.len() < (row + 1) * buffer_width * 4 {
+                pixels.extend_from_slice(&self.background);
+            }
+        }
+
+        self.pixels = pixels;
+        self.buffer_width = buffer_width;
+        self.buffer_height = buffer_height;
+    }
+}
+
+/// Convert a percentage to a color component.
+fn percent(value: u16) -> u8 {
+    (u32::from(value.min(100)) * 255 / 100) as u8
+}
+
+/// Convert a Sixel HLS color to RGB.
+///
+/// Sixel hue angles start at blue, instead of red.
+fn hls_to_rgb(hue: u16, lightness: u16, saturation: u16) -> [u8; 3] {
+    let hue = f64::from((hue + 240) % 360);
+    let lightness = f64::from(lightness.min(100)) / 100.;
+    let saturation = f64::from(saturation.min(100)) / 100.;
+
+    let chroma = (1. - (2. * lightness - 1.).abs()) * saturation;
+    let x = chroma * (1. - ((hue / 60.) % 2. - 1.).abs());
+    let m = lightness - chroma / 2.;
+
+    let (r, g, b) = match hue as u16 {
+        0..=59 => (chroma, x, 0.),
+        60..=119 => (x, chroma, 0.),
+        120..=179 => (0., chroma, x),
+        180..=239 => (0., x, chroma),
+        240..=299 => (x, 0., chroma),
+        _ => (chroma, 0., x),
+    };
+
+    let component = |value: f64| ((value + m) * 255.).round() as u8;
+    [component(r), component(g), component(b)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(data: &[u8], transparent_background: bool) -> Result<GraphicData, Error> {
+        let mut parser = Parser::new(transparent_background);
+        for byte in data {
+            parser.put(*byte)?;
+        }
+        parser.finish()
+    }
+
+    fn pixel(graphic: &GraphicData, x: usize, y: usize) -> &[u8] {
+        let start = (y * graphic.width + x) * 4;
+        &graphic.pixels[start..start + 4]
+    }
+
+    #[test]
+    fn decode_sixels() {
+        // Two columns of red, with a row of blue pixels on the next line.
+        let graphic = decode(b"#1;2;100;0;0#1~~-#2;2;0;0;100#2!3@", true).unwrap();
+
+        assert_eq!(graphic.width, 3);
+        assert_eq!(graphic.height, 12);
+        assert_eq!(graphic.pixels.len(), 3 * 12 * 4);
+
+        assert_eq!(pixel(&graphic, 0, 0), &[255, 0, 0, 255]);
+        assert_eq!(pixel(&graphic, 1, 5), &[255, 0, 0, 255]);
+        assert_eq!(pixel(&graphic, 2, 0), &[0, 0, 0, 0]);
+        assert_eq!(pixel(&graphic, 0, 6), &[0, 0, 255, 255]);
+        assert_eq!(pixel(&graphic, 2, 6), &[0, 0, 255, 255]);
+        assert_eq!(pixel(&graphic, 2, 7), &[0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn raster_attributes() {
+        let graphic = decode(b"\"1;1;8;4#0~", false).unwrap();
+
+        assert_eq!(graphic.width, 8);
+        assert_eq!(graphic.height, 6);
+        assert_eq!(pixel(&graphic, 7, 3), &[0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn hls_colors() {
+        assert_eq!(hls_to_rgb(120, 50, 100), [255, 0, 0]);
+        assert_eq!(hls_to_rgb(240, 50, 100), [0, 255, 0]);
+        assert_eq!(hls_to_rgb(0, 50, 100), [0, 0, 255]);
+    }
+
+    #[test]
+    fn invalid_data() {
+        assert_eq!(decode(b"", true), Err(Error::Empty));
+        assert_eq!(decode(b"#5000~", true), Err(Error::InvalidColorRegister(5000)));
+        assert_eq!(decode(b"#1;3;0;0;0~", true), Err(Error::InvalidColorSpace(3)));
+    }
+}
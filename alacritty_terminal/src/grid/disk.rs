@@ -0,0 +1,979 @@
+//! Compressed scrollback history stored on disk.
+//!
+//! Rows which exceed the in-memory scrollback limit are collected into chunks of [`CHUNK_SIZE`]
+//! rows, which are compressed and appended to a file. Chunks are decompressed on demand whenever
+//! one of their rows is accessed, and only a limited number of them is kept in memory.
+
+use std::cell::{Cell, UnsafeCell};
+use std::collections::VecDeque;
+use std::fmt::{self, Debug, Formatter};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::mem::ManuallyDrop;
+use std::ops::{Index, IndexMut, Range};
+use std::path::{Path, PathBuf};
+use std::str;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use log::error;
+use miniz_oxide::deflate::compress_to_vec;
+use miniz_oxide::inflate::decompress_to_vec;
+
+use crate::grid::{GridCell, PromptMarks, Row};
+use crate::index::Column;
+
+/// Number of rows compressed together.
+const CHUNK_SIZE: usize = 256;
+
+/// Number of decompressed chunks kept in memory before other chunks are dropped.
+const MAX_LOADED_CHUNKS: usize = 8;
+
+/// Number of history files created by cloning a history.
+static CLONE_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Compression level for new chunks.
+///
+/// Chunks are compressed while the terminal is processing output, so speed is preferred over
+/// size.
+const COMPRESSION_LEVEL: u8 = 1;
+
+/// Identifier at the start of every history file.
+const MAGIC: &[u8; 8] = b"ALACHST1";
+
+/// Size of the header in front of every chunk.
+const CHUNK_HEADER_SIZE: u64 = 8;
+
+/// Minimum number of unused bytes before the history file is compacted.
+const MIN_COMPACTION_SIZE: u64 = 1 << 20;
+
+/// Maximum number of columns accepted when decoding a row.
+const MAX_COLUMNS: usize = u16::MAX as usize;
+
+/// Cells which can be stored in the disk history.
+pub trait DiskCell: Sized {
+    /// Append the binary representation of the cell.
+    fn encode(&self, encoder: &mut Encoder<'_>);
+
+    /// Read the next cell.
+    fn decode(decoder: &mut Decoder<'_>) -> Option<Self>;
+}
+
+/// Binary writer for the disk history.
+pub struct Encoder<'a> {
+    buf: &'a mut Vec<u8>,
+}
+
+impl<'a> Encoder<'a> {
+    #[inline]
+    pub fn write_u8(&mut self, value: u8) {
+        self.buf.push(value);
+    }
+
+    #[inline]
+    pub fn write_u16(&mut self, value: u16) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    #[inline]
+    pub fn write_u32(&mut self, value: u32) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_str(&mut self, value: &str) {
+        self.write_u32(value.len() as u32);
+        self.buf.extend_from_slice(value.as_bytes());
+    }
+}
+
+/// Binary reader for the disk history.
+pub struct Decoder<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    #[inline]
+    fn read_bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.buf.len() < len {
+            return None;
+        }
+
+        let (bytes, remaining) = self.buf.split_at(len);
+        self.buf = remaining;
+
+        Some(bytes)
+    }
+
+    #[inline]
+    pub fn read_u8(&mut self) -> Option<u8> {
+        self.read_bytes(1).map(|bytes| bytes[0])
+    }
+
+    #[inline]
+    pub fn read_u16(&mut self) -> Option<u16> {
+        let bytes = self.read_bytes(2)?;
+        Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    #[inline]
+    pub fn read_u32(&mut self) -> Option<u32> {
+        let bytes = self.read_bytes(4)?;
+        Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    pub fn read_str(&mut self) -> Option<&'a str> {
+        let len = self.read_u32()? as usize;
+        str::from_utf8(self.read_bytes(len)?).ok()
+    }
+}
+
+/// Compressed rows in the history file.
+struct Chunk<T> {
+    /// Start of the compressed rows in the file.
+    offset: u64,
+
+    /// Length of the compressed rows.
+    len: u32,
+
+    /// Decompressed rows, oldest first.
+    rows: UnsafeCell<Option<Vec<Row<T>>>>,
+
+    /// Decompressed rows have been modified.
+    dirty: bool,
+}
+
+impl<T> Chunk<T> {
+    fn new(offset: u64, len: u32) -> Self {
+        Self { offset, len, rows: UnsafeCell::new(None), dirty: false }
+    }
+}
+
+/// Scrollback history stored in a file.
+///
+/// Lines are indexed like the scrollback history of the grid, with `0` being the most recent
+/// line.
+pub struct DiskHistory<T> {
+    /// File storing the compressed chunks.
+    file: ManuallyDrop<File>,
+
+    /// Location of the history file.
+    ///
+    /// On Unix the file is removed right after opening it, so it is cleaned up automatically.
+    path: PathBuf,
+
+    /// Compressed rows, oldest first.
+    chunks: VecDeque<Chunk<T>>,
+
+    /// Rows which do not fill an entire chunk yet, oldest first.
+    pending: Vec<Row<T>>,
+
+    /// Maximum number of lines in the history.
+    max_lines: usize,
+
+    /// Number of columns decompressed rows are resized to.
+    cols: Column,
+
+    /// Length of the history file.
+    file_len: u64,
+
+    /// Bytes in the history file which are no longer used by any chunk.
+    garbage: u64,
+
+    /// Number of chunks which are decompressed.
+    loaded: Cell<usize>,
+
+    /// Decompressed rows dropped from their chunk while the history was borrowed.
+    ///
+    /// These might still be referenced, so they're only freed with the next mutable access.
+    evicted: UnsafeCell<Vec<Vec<Row<T>>>>,
+
+    encode: fn(&[&Row<T>]) -> Vec<u8>,
+    decode: fn(&[u8], usize, Column) -> Vec<Row<T>>,
+}
+
+impl<T> DiskHistory<T> {
+    /// Create an empty history file.
+    pub fn create(path: PathBuf, max_lines: usize, cols: Column) -> io::Result<Self>
+    where
+        T: DiskCell + GridCell + Clone + Default + PartialEq,
+    {
+        let mut file =
+            OpenOptions::new().read(true).write(true).create(true).truncate(true).open(&path)?;
+        file.write_all(MAGIC)?;
+
+        Ok(Self::from_file(file, path, max_lines, cols))
+    }
+
+    /// Load a history file written by [`DiskHistory::persist`].
+    pub fn open(path: PathBuf, max_lines: usize, cols: Column) -> io::Result<Self>
+    where
+        T: DiskCell + GridCell + Clone + Default + PartialEq,
+    {
+        let mut file = OpenOptions::new().read(true).write(true).open(&path)?;
+
+        let mut magic = [0; MAGIC.len()];
+        file.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid history file"));
+        }
+
+        let end = file.metadata()?.len();
+        let mut history = Self::from_file(file, path, max_lines, cols);
+
+        let mut header = [0; CHUNK_HEADER_SIZE as usize];
+        while history.file_len + CHUNK_HEADER_SIZE <= end {
+            history.file.seek(SeekFrom::Start(history.file_len))?;
+            history.file.read_exact(&mut header)?;
+
+            let len = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+            let rows = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+
+            // Stop at incomplete chunks.
+            let offset = history.file_len + CHUNK_HEADER_SIZE;
+            if offset + u64::from(len) > end || rows > CHUNK_SIZE {
+                break;
+            }
+
+            // Only the most recent rows are stored in a partial chunk.
+            if rows < CHUNK_SIZE {
+                let data = history.read(offset, len)?;
+                history.pending = (history.decode)(&data, rows, cols);
+                break;
+            }
+
+            history.chunks.push_back(Chunk::new(offset, len));
+            history.file_len = offset + u64::from(len);
+        }
+
+        // Remove everything which is not part of a complete chunk.
+        history.file.set_len(history.file_len)?;
+
+        history.truncate();
+
+        Ok(history)
+    }
+
+    fn from_file(file: File, path: PathBuf, max_lines: usize, cols: Column) -> Self
+    where
+        T: DiskCell + GridCell + Clone + Default + PartialEq,
+    {
+        #[cfg(unix)]
+        let _ = fs::remove_file(&path);
+
+        Self {
+            file: ManuallyDrop::new(file),
+            path,
+            chunks: VecDeque::new(),
+            pending: Vec::new(),
+            max_lines,
+            cols,
+            file_len: MAGIC.len() as u64,
+            garbage: 0,
+            loaded: Cell::new(0),
+            evicted: UnsafeCell::new(Vec::new()),
+            encode: encode_chunk::<T>,
+            decode: decode_chunk::<T>,
+        }
+    }
+
+    /// Number of lines in the history.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.chunks.len() * CHUNK_SIZE + self.pending.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Add a line to the history, making it the most recent line.
+    pub fn push(&mut self, row: Row<T>) {
+        self.evicted.get_mut().clear();
+
+        self.pending.push(row);
+        if self.pending.len() < CHUNK_SIZE {
+            return;
+        }
+
+        let rows: Vec<&Row<T>> = self.pending.iter().collect();
+        let data = (self.encode)(&rows);
+        match self.append(&data, CHUNK_SIZE) {
+            Ok(offset) => self.chunks.push_back(Chunk::new(offset, data.len() as u32)),
+            Err(err) => error!("Unable to write scrollback history: {}", err),
+        }
+        self.pending.clear();
+
+        self.truncate();
+    }
+
+    /// Update the maximum number of lines in the history.
+    pub fn set_max_lines(&mut self, max_lines: usize) {
+        self.max_lines = max_lines;
+        self.truncate();
+    }
+
+    /// Update the number of columns of all rows.
+    ///
+    /// Lines in the history are not reflowed, instead they are truncated or padded with empty
+    /// cells.
+    pub fn resize(&mut self, cols: Column)
+    where
+        T: GridCell + Clone + Default,
+    {
+        if self.cols == cols {
+            return;
+        }
+
+        self.cols = cols;
+
+        for index in 0..self.chunks.len() {
+            self.unload(index);
+        }
+
+        for row in &mut self.pending {
+            if row.len() < cols.0 {
+                row.grow(cols);
+            } else {
+                row.shrink(cols);
+            }
+        }
+    }
+
+    /// Remove all lines from the history.
+    pub fn clear(&mut self) {
+        self.chunks.clear();
+        self.pending.clear();
+        self.evicted.get_mut().clear();
+        self.loaded.set(0);
+        self.garbage = 0;
+
+        self.file_len = MAGIC.len() as u64;
+        if let Err(err) = self.file.set_len(self.file_len) {
+            error!("Unable to clear scrollback history: {}", err);
+        }
+    }
+
+    /// Drop decompressed chunks which are not part of the `visible` lines.
+    ///
+    /// Chunks are only dropped once more than [`MAX_LOADED_CHUNKS`] have been decompressed. Rows
+    /// dropped while the history was borrowed immutably are always freed, so this should be
+    /// called after reading large parts of the history, like searching through it.
+    pub fn shrink_cache(&mut self, visible: Range<usize>) {
+        self.evicted.get_mut().clear();
+
+        if self.loaded.get() <= MAX_LOADED_CHUNKS {
+            return;
+        }
+
+        let pending = self.pending.len();
+        let keep = if visible.end > pending && visible.start < self.len() {
+            let start = self.chunk_position(self.len().min(visible.end) - 1).0;
+            let end = self.chunk_position(pending.max(visible.start)).0;
+            start..end + 1
+        } else {
+            0..0
+        };
+
+        for index in (0..self.chunks.len()).filter(|index| !keep.contains(index)) {
+            self.unload(index);
+        }
+    }
+
+    /// Write the history, followed by `rows`, to a new file at `path`.
+    ///
+    /// The file can be loaded again using [`DiskHistory::open`].
+    pub fn persist<'a, I>(&'a mut self, path: &Path, rows: I) -> io::Result<()>
+    where
+        I: IntoIterator<Item = &'a Row<T>>,
+        T: 'a,
+    {
+        // Store modified rows before copying the compressed chunks.
+        for index in 0..self.chunks.len() {
+            self.unload(index);
+        }
+
+        let tmp_path = path.with_extension("tmp");
+        let mut output = File::create(&tmp_path)?;
+        output.write_all(MAGIC)?;
+
+        let mut buf = Vec::new();
+        for chunk in &self.chunks {
+            buf.resize((CHUNK_HEADER_SIZE + u64::from(chunk.len)) as usize, 0);
+
+            let mut file = &*self.file;
+            file.seek(SeekFrom::Start(chunk.offset - CHUNK_HEADER_SIZE))?;
+            file.read_exact(&mut buf)?;
+
+            output.write_all(&buf)?;
+        }
+
+        let rows: Vec<&Row<T>> = self.pending.iter().chain(rows).collect();
+        for rows in rows.chunks(CHUNK_SIZE) {
+            write_chunk(&mut output, &(self.encode)(rows), rows.len())?;
+        }
+
+        fs::rename(&tmp_path, path)
+    }
+
+    /// Copy the history to a new file next to the current one.
+    pub fn try_clone(&self) -> io::Result<Self>
+    where
+        T: Clone,
+    {
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        name.push(format!("-{}", CLONE_COUNT.fetch_add(1, Ordering::Relaxed)));
+        let path = self.path.with_file_name(name);
+
+        let file =
+            OpenOptions::new().read(true).write(true).create(true).truncate(true).open(&path)?;
+
+        #[cfg(unix)]
+        let _ = fs::remove_file(&path);
+
+        let mut history = Self {
+            file: ManuallyDrop::new(file),
+            path,
+            chunks: VecDeque::with_capacity(self.chunks.len()),
+            pending: self.pending.clone(),
+            max_lines: self.max_lines,
+            cols: self.cols,
+            file_len: MAGIC.len() as u64,
+            garbage: 0,
+            loaded: Cell::new(0),
+            evicted: UnsafeCell::new(Vec::new()),
+            encode: self.encode,
+            decode: self.decode,
+        };
+        history.file.write_all(MAGIC)?;
+
+        for (index, chunk) in self.chunks.iter().enumerate() {
+            // Modified rows are only written to the file once their chunk is dropped.
+            let data = if chunk.dirty {
+                let rows: Vec<&Row<T>> = self.rows(index).iter().collect();
+                (self.encode)(&rows)
+            } else {
+                self.read(chunk.offset, chunk.len)?
+            };
+
+            let offset = history.append(&data, CHUNK_SIZE)?;
+            history.chunks.push_back(Chunk::new(offset, data.len() as u32));
+        }
+
+        Ok(history)
+    }
+
+    /// Chunk index and row index inside the chunk for a line in the history.
+    #[inline]
+    fn chunk_position(&self, index: usize) -> (usize, usize) {
+        let index = index - self.pending.len();
+        (self.chunks.len() - 1 - index / CHUNK_SIZE, CHUNK_SIZE - 1 - index % CHUNK_SIZE)
+    }
+
+    /// Get the rows of a chunk, decompressing them if necessary.
+    ///
+    /// Once [`MAX_LOADED_CHUNKS`] are decompressed, the unmodified chunk furthest away from
+    /// `index` is dropped to make room for the new one.
+    fn rows(&self, index: usize) -> &[Row<T>] {
+        let chunk = &self.chunks[index];
+
+        // SAFETY: References to decompressed rows only point into the heap allocation of their
+        // vector, which is moved to `evicted` when the chunk is dropped while the history is
+        // borrowed immutably. Vectors are only freed through a mutable borrow, so there can't be
+        // any references to them at that point.
+        unsafe {
+            if let Some(rows) = &*chunk.rows.get() {
+                return rows;
+            }
+
+            if self.loaded.get() >= MAX_LOADED_CHUNKS {
+                let furthest = (0..self.chunks.len())
+                    .filter(|i| !self.chunks[*i].dirty && (*self.chunks[*i].rows.get()).is_some())
+                    .max_by_key(|i| (*i as isize - index as isize).abs());
+
+                if let Some(rows) = furthest.and_then(|i| (*self.chunks[i].rows.get()).take()) {
+                    (*self.evicted.get()).push(rows);
+                    self.loaded.set(self.loaded.get() - 1);
+                }
+            }
+
+            let data = match self.read(chunk.offset, chunk.len) {
+                Ok(data) => data,
+                Err(err) => {
+                    error!("Unable to read scrollback history: {}", err);
+                    Vec::new()
+                },
+            };
+            *chunk.rows.get() = Some((self.decode)(&data, CHUNK_SIZE, self.cols));
+            self.loaded.set(self.loaded.get() + 1);
+
+            match &*chunk.rows.get() {
+                Some(rows) => rows,
+                None => unreachable!(),
+            }
+        }
+    }
+
+    /// Store modified chunks until there's room for `index` to be modified too.
+    ///
+    /// Only unmodified chunks can be dropped by [`DiskHistory::rows`], so at least one of the
+    /// decompressed chunks must always be unmodified.
+    fn limit_dirty(&mut self, index: usize) {
+        loop {
+            let dirty = (0..self.chunks.len()).filter(|i| self.chunks[*i].dirty);
+            if dirty.clone().count() < MAX_LOADED_CHUNKS - 1 {
+                return;
+            }
+
+            match dirty.max_by_key(|i| (*i as isize - index as isize).abs()) {
+                Some(furthest) => self.unload(furthest),
+                None => return,
+            }
+        }
+    }
+
+    /// Drop the decompressed rows of a chunk, storing them if they have been modified.
+    fn unload(&mut self, index: usize) {
+        let chunk = &mut self.chunks[index];
+        let rows = match chunk.rows.get_mut().take() {
+            Some(rows) => rows,
+            None => return,
+        };
+        self.loaded.set(self.loaded.get() - 1);
+
+        if !chunk.dirty {
+            return;
+        }
+        chunk.dirty = false;
+
+        let rows: Vec<&Row<T>> = rows.iter().collect();
+        let data = (self.encode)(&rows);
+        match self.append(&data, CHUNK_SIZE) {
+            Ok(offset) => {
+                let chunk = &mut self.chunks[index];
+                self.garbage += CHUNK_HEADER_SIZE + u64::from(chunk.len);
+                chunk.offset = offset;
+                chunk.len = data.len() as u32;
+            },
+            Err(err) => error!("Unable to write scrollback history: {}", err),
+        }
+    }
+
+    /// Read compressed data from the history file.
+    fn read(&self, offset: u64, len: u32) -> io::Result<Vec<u8>> {
+        let mut data = vec![0; len as usize];
+
+        let mut file = &*self.file;
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut data)?;
+
+        Ok(data)
+    }
+
+    /// Append a compressed chunk to the history file, returning its offset.
+    fn append(&mut self, data: &[u8], rows: usize) -> io::Result<u64> {
+        self.file.seek(SeekFrom::Start(self.file_len))?;
+        write_chunk(&mut *self.file, data, rows)?;
+
+        let offset = self.file_len + CHUNK_HEADER_SIZE;
+        self.file_len = offset + data.len() as u64;
+
+        Ok(offset)
+    }
+
+    /// Remove the oldest chunks exceeding the line limit.
+    fn truncate(&mut self) {
+        while self.chunks.len() * CHUNK_SIZE > self.max_lines {
+            let mut chunk = match self.chunks.pop_front() {
+                Some(chunk) => chunk,
+                None => break,
+            };
+
+            if chunk.rows.get_mut().is_some() {
+                self.loaded.set(self.loaded.get() - 1);
+            }
+            self.garbage += CHUNK_HEADER_SIZE + u64::from(chunk.len);
+        }
+
+        if self.garbage >= MIN_COMPACTION_SIZE && self.garbage * 2 >= self.file_len {
+            if let Err(err) = self.compact() {
+                error!("Unable to compact scrollback history: {}", err);
+            }
+        }
+    }
+
+    /// Remove unused space from the history file.
+    fn compact(&mut self) -> io::Result<()> {
+        // Chunks are moved in file order, since rewritten chunks are appended at the end.
+        let mut order: Vec<usize> = (0..self.chunks.len()).collect();
+        order.sort_unstable_by_key(|index| self.chunks[*index].offset);
+
+        let mut position = MAGIC.len() as u64;
+        let mut buf = Vec::new();
+        for index in order {
+            let (offset, len) = (self.chunks[index].offset, self.chunks[index].len);
+
+            let start = offset - CHUNK_HEADER_SIZE;
+            if start != position {
+                buf.resize((CHUNK_HEADER_SIZE + u64::from(len)) as usize, 0);
+
+                self.file.seek(SeekFrom::Start(start))?;
+                self.file.read_exact(&mut buf)?;
+                self.file.seek(SeekFrom::Start(position))?;
+                self.file.write_all(&buf)?;
+
+                self.chunks[index].offset = position + CHUNK_HEADER_SIZE;
+            }
+
+            position += CHUNK_HEADER_SIZE + u64::from(len);
+        }
+
+        self.file.set_len(position)?;
+        self.file_len = position;
+        self.garbage = 0;
+
+        Ok(())
+    }
+}
+
+impl<T> Index<usize> for DiskHistory<T> {
+    type Output = Row<T>;
+
+    #[inline]
+    fn index(&self, index: usize) -> &Row<T> {
+        let pending = self.pending.len();
+        if index < pending {
+            return &self.pending[pending - 1 - index];
+        }
+
+        let (chunk, row) = self.chunk_position(index);
+        &self.rows(chunk)[row]
+    }
+}
+
+impl<T> IndexMut<usize> for DiskHistory<T> {
+    #[inline]
+    fn index_mut(&mut self, index: usize) -> &mut Row<T> {
+        self.evicted.get_mut().clear();
+
+        let pending = self.pending.len();
+        if index < pending {
+            return &mut self.pending[pending - 1 - index];
+        }
+
+        let (chunk, row) = self.chunk_position(index);
+        if !self.chunks[chunk].dirty {
+            self.limit_dirty(chunk);
+        }
+        self.rows(chunk);
+        self.evicted.get_mut().clear();
+
+        let chunk = &mut self.chunks[chunk];
+        chunk.dirty = true;
+
+        match chunk.rows.get_mut() {
+            Some(rows) => &mut rows[row],
+            None => unreachable!(),
+        }
+    }
+}
+
+impl<T> Debug for DiskHistory<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DiskHistory")
+            .field("path", &self.path)
+            .field("lines", &self.len())
+            .field("max_lines", &self.max_lines)
+            .finish()
+    }
+}
+
+impl<T> Drop for DiskHistory<T> {
+    fn drop(&mut self) {
+        // SAFETY: The file is never accessed again after this.
+        //
+        // Files need to be closed before they can be removed on Windows.
+        unsafe { ManuallyDrop::drop(&mut self.file) };
+
+        #[cfg(not(unix))]
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Write a compressed chunk with its header.
+fn write_chunk<W: Write>(writer: &mut W, data: &[u8], rows: usize) -> io::Result<()> {
+    writer.write_all(&(data.len() as u32).to_le_bytes())?;
+    writer.write_all(&(rows as u32).to_le_bytes())?;
+    writer.write_all(data)
+}
+
+/// Compress rows.
+fn encode_chunk<T: DiskCell + PartialEq>(rows: &[&Row<T>]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    let mut encoder = Encoder { buf: &mut buf };
+
+    for row in rows {
+        let cells = &row[..];
+
+        // Trailing cells identical to the last cell are only stored once.
+        let explicit = match cells.last() {
+            Some(last) => cells.iter().rposition(|cell| cell != last).map_or(0, |i| i + 1),
+            None => 0,
+        };
+
+        encoder.write_u32(cells.len() as u32);
+        encoder.write_u32(explicit as u32);
+        encoder.write_u8(row.marks().bits());
+
+        for cell in &cells[..explicit] {
+            cell.encode(&mut encoder);
+        }
+
+        if let Some(last) = cells.last() {
+            last.encode(&mut encoder);
+        }
+    }
+
+    compress_to_vec(&buf, COMPRESSION_LEVEL)
+}
+
+/// Decompress `rows` rows with `cols` columns.
+///
+/// Data which cannot be decoded is replaced with empty rows.
+fn decode_chunk<T>(data: &[u8], rows: usize, cols: Column) -> Vec<Row<T>>
+where
+    T: DiskCell + GridCell + Clone + Default,
+{
+    let decoded = decompress_to_vec(data).ok().and_then(|data| {
+        let mut decoder = Decoder { buf: &data };
+        (0..rows).map(|_| decode_row(&mut decoder, cols)).collect::<Option<Vec<_>>>()
+    });
+
+    decoded.unwrap_or_else(|| {
+        error!("Unable to decode scrollback history");
+        (0..rows).map(|_| Row::new(cols)).collect()
+    })
+}
+
+fn decode_row<T>(decoder: &mut Decoder<'_>, cols: Column) -> Option<Row<T>>
+where
+    T: DiskCell + Clone + Default,
+{
+    let len = decoder.read_u32()? as usize;
+    let explicit = decoder.read_u32()? as usize;
+    let marks = PromptMarks::from_bits_truncate(decoder.read_u8()?);
+
+    if explicit > len || len > MAX_COLUMNS {
+        return None;
+    }
+
+    let mut cells = Vec::with_capacity(len.max(cols.0));
+    for _ in 0..explicit {
+        cells.push(T::decode(decoder)?);
+    }
+
+    if len > 0 {
+        let last = T::decode(decoder)?;
+        cells.resize(len, last);
+    }
+
+    cells.resize_with(cols.0, T::default);
+
+    let mut row = Row::from_vec(cells, len.min(cols.0));
+    row.insert_marks(marks);
+
+    Some(row)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+    use std::process;
+
+    use crate::ansi::{Color, NamedColor};
+    use crate::term::cell::{Cell, Flags, Hyperlink};
+    use crate::term::color::Rgb;
+
+    /// Unique location for a test's history file.
+    fn history_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("alacritty-history-{}-{}", process::id(), name))
+    }
+
+    fn row(c: char) -> Row<Cell> {
+        let mut row = Row::<Cell>::new(Column(3));
+        row[Column(0)].c = c;
+        row
+    }
+
+    fn history(name: &str, lines: usize) -> DiskHistory<Cell> {
+        let mut history = DiskHistory::create(history_path(name), usize::MAX, Column(3)).unwrap();
+        for i in 0..lines {
+            history.push(row((b'a' + (i % 26) as u8) as char));
+        }
+        history
+    }
+
+    #[test]
+    fn push_lines() {
+        let history = history("push_lines", CHUNK_SIZE * 2 + 3);
+
+        assert_eq!(history.len(), CHUNK_SIZE * 2 + 3);
+        assert_eq!(history.chunks.len(), 2);
+        assert_eq!(history.pending.len(), 3);
+
+        // Most recent line is at index 0.
+        assert_eq!(history[0][Column(0)].c, (b'a' + ((CHUNK_SIZE * 2 + 2) % 26) as u8) as char);
+        assert_eq!(history[3][Column(0)].c, (b'a' + ((CHUNK_SIZE * 2 - 1) % 26) as u8) as char);
+        assert_eq!(history[history.len() - 1][Column(0)].c, 'a');
+        assert_eq!(history[history.len() - 1][Column(1)], Cell::default());
+    }
+
+    #[test]
+    fn cell_attributes() {
+        let mut cell = Cell::default();
+        cell.c = 'x';
+        cell.fg = Color::Spec(Rgb { r: 1, g: 2, b: 3 });
+        cell.bg = Color::Named(NamedColor::DimForeground);
+        cell.flags = Flags::BOLD | Flags::WRAPLINE | Flags::UNDERCURL;
+        cell.push_zerowidth('\u{301}');
+        cell.set_hyperlink(Some(Hyperlink::new(Some("id"), "https://alacritty.org")));
+        cell.set_underline_color(Some(Color::Indexed(5)));
+
+        let mut row = Row::<Cell>::new(Column(3));
+        row[Column(0)].set_underline_color(Some(Color::Named(NamedColor::Red)));
+        row[Column(1)] = cell.clone();
+        row[Column(2)].bg = Color::Indexed(42);
+
+        let rows = decode_chunk::<Cell>(&encode_chunk(&[&row]), 1, Column(3));
+        assert_eq!(rows, vec![row]);
+    }
+
+    #[test]
+    fn max_lines() {
+        let mut history = history("max_lines", CHUNK_SIZE * 3);
+
+        history.set_max_lines(CHUNK_SIZE);
+        assert_eq!(history.len(), CHUNK_SIZE);
+
+        let c = (b'a' + ((CHUNK_SIZE * 2) % 26) as u8) as char;
+        assert_eq!(history[CHUNK_SIZE - 1][Column(0)].c, c);
+    }
+
+    #[test]
+    fn modify_line() {
+        let mut history = history("modify_line", CHUNK_SIZE);
+
+        history[10][Column(2)].c = 'x';
+        history.unload(0);
+
+        assert!(history.chunks[0].rows.get_mut().is_none());
+        assert_eq!(history[10][Column(2)].c, 'x');
+    }
+
+    /// Number of decompressed chunks which are kept in memory.
+    fn retained(history: &mut DiskHistory<Cell>) -> usize {
+        history.loaded.get() + history.evicted.get_mut().len()
+    }
+
+    #[test]
+    fn cache_limit() {
+        let mut history = history("cache_limit", CHUNK_SIZE * (MAX_LOADED_CHUNKS + 2));
+
+        for index in 0..history.len() {
+            let c = (b'a' + ((history.len() - 1 - index) % 26) as u8) as char;
+            assert_eq!(history[index][Column(0)].c, c);
+            assert!(history.loaded.get() <= MAX_LOADED_CHUNKS);
+
+            history.shrink_cache(0..0);
+            assert!(retained(&mut history) <= MAX_LOADED_CHUNKS);
+        }
+    }
+
+    #[test]
+    fn cache_limit_modified() {
+        let mut history = history("cache_limit_modified", CHUNK_SIZE * (MAX_LOADED_CHUNKS + 2));
+
+        for index in (0..history.len()).step_by(CHUNK_SIZE / 2) {
+            history[index][Column(1)].c = 'x';
+            assert!(retained(&mut history) <= MAX_LOADED_CHUNKS);
+
+            // Reading unmodified chunks must still stay within the limit.
+            for index in (0..history.len()).step_by(CHUNK_SIZE) {
+                let _ = &history[index];
+                assert!(history.loaded.get() <= MAX_LOADED_CHUNKS);
+            }
+        }
+
+        history.shrink_cache(0..0);
+        assert!(retained(&mut history) <= MAX_LOADED_CHUNKS);
+        for index in (0..history.len()).step_by(CHUNK_SIZE / 2) {
+            assert_eq!(history[index][Column(1)].c, 'x');
+        }
+    }
+
+    #[test]
+    fn clone_history() {
+        let mut history = history("clone_history", CHUNK_SIZE * 2 + 1);
+        history[CHUNK_SIZE][Column(1)].c = 'x';
+
+        let mut cloned = history.try_clone().unwrap();
+        assert_eq!(cloned.len(), CHUNK_SIZE * 2 + 1);
+        assert_eq!(cloned[CHUNK_SIZE][Column(1)].c, 'x');
+        assert_eq!(cloned[cloned.len() - 1][Column(0)].c, 'a');
+
+        cloned[0][Column(0)].c = 'y';
+        assert_ne!(history[0][Column(0)].c, 'y');
+    }
+
+    #[test]
+    fn resize_lines() {
+        let mut history = history("resize_lines", CHUNK_SIZE + 1);
+
+        history.resize(Column(5));
+        assert_eq!(history[0].len(), 5);
+        assert_eq!(history[CHUNK_SIZE].len(), 5);
+        assert_eq!(history[CHUNK_SIZE][Column(0)].c, 'a');
+
+        history.resize(Column(1));
+        assert_eq!(history[0].len(), 1);
+        assert_eq!(history[CHUNK_SIZE].len(), 1);
+    }
+
+    #[test]
+    fn persist_and_open() {
+        let path = history_path("persist");
+        let mut history = history("persist_and_open", CHUNK_SIZE + 1);
+
+        let mut marked = row('z');
+        marked.insert_marks(PromptMarks::PROMPT_START);
+        history.persist(&path, vec![&marked]).unwrap();
+
+        let mut restored = DiskHistory::<Cell>::open(path, usize::MAX, Column(3)).unwrap();
+        assert_eq!(restored.len(), CHUNK_SIZE + 2);
+        assert_eq!(restored.pending.len(), 2);
+        assert_eq!(restored[0][Column(0)].c, 'z');
+        assert_eq!(restored[0].marks(), PromptMarks::PROMPT_START);
+        assert_eq!(restored[CHUNK_SIZE + 1][Column(0)].c, 'a');
+
+        // Restored history can be extended.
+        for _ in 0..CHUNK_SIZE {
+            restored.push(row('y'));
+        }
+        assert_eq!(restored.chunks.len(), 2);
+        assert_eq!(restored[CHUNK_SIZE][Column(0)].c, 'z');
+    }
+
+    #[test]
+    fn compact() {
+        let mut history = history("compact", CHUNK_SIZE * 3);
+        let len = history.chunks[2].len;
+
+        history.set_max_lines(CHUNK_SIZE);
+        history.compact().unwrap();
+
+        assert_eq!(history.garbage, 0);
+        assert_eq!(history.file_len, MAGIC.len() as u64 + CHUNK_HEADER_SIZE + u64::from(len));
+        assert_eq!(history[0][Column(0)].c, (b'a' + ((CHUNK_SIZE * 3 - 1) % 26) as u8) as char);
+    }
+}
@@ -1,22 +1,29 @@
 //! A specialized 2D grid implementation optimized for use in a terminal.
 
 use std::cmp::{max, min};
+use std::io;
 use std::iter::{Map, TakeWhile};
+use std::mem;
 use std::ops::{Bound, Deref, Index, IndexMut, Range, RangeBounds, RangeInclusive};
+use std::path::Path;
 
+use log::error;
 use serde::{Deserialize, Serialize};
 
 use crate::ansi::{CharsetIndex, StandardCharset};
 use crate::index::{Column, IndexRange, Line, Point};
 use crate::term::cell::{Flags, ResetDiscriminant};
+use crate::vi_mode::ViMarks;
 
+mod disk;
 pub mod resize;
 mod row;
 mod storage;
 #[cfg(test)]
 mod tests;
 
-pub use self::row::Row;
+pub use self::disk::{Decoder, DiskCell, DiskHistory, Encoder};
+pub use self::row::{PromptMarks, Row};
 use self::storage::Storage;
 
 pub trait GridCell: Sized {
@@ -105,7 +112,7 @@ pub enum Scroll {
 ///                           ^
 ///                          cols
 /// ```
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct Grid<T> {
     /// Current cursor for writing data.
     #[serde(skip)]
@@ -134,6 +141,20 @@ pub struct Grid<T> {
 
     /// Maximum number of lines in history.
     max_scroll_limit: usize,
+
+    /// Lines exceeding the history limit, which have been moved to disk.
+    #[serde(skip)]
+    disk: Option<DiskHistory<T>>,
+
+    /// Number of lines all rows have been moved up in the buffer.
+    ///
+    /// This wraps around on overflow, so only the difference between two values is meaningful.
+    #[serde(skip)]
+    rotation: usize,
+
+    /// Vi mode marks and jumps of this grid.
+    #[serde(skip)]
+    vi_marks: ViMarks,
 }
 
 impl<T: GridCell + Default + PartialEq + Clone> Grid<T> {
@@ -146,17 +167,54 @@ impl<T: GridCell + Default + PartialEq + Clone> Grid<T> {
             cursor: Cursor::default(),
             lines,
             cols,
+            disk: None,
+            rotation: 0,
+            vi_marks: ViMarks::default(),
+        }
+    }
+
+    /// Store lines exceeding the scrollback history limit on disk, instead of dropping them.
+    pub fn set_disk_history(&mut self, disk: Option<DiskHistory<T>>) {
+        self.disk = disk;
+        self.display_offset = min(self.display_offset, self.history_size());
+    }
+
+    /// Lines exceeding the scrollback history limit, which have been moved to disk.
+    #[inline]
+    pub fn disk_history(&self) -> Option<&DiskHistory<T>> {
+        self.disk.as_ref()
+    }
+
+    /// Update the maximum number of lines stored on disk.
+    pub fn update_disk_history(&mut self, max_lines: usize) {
+        if let Some(disk) = &mut self.disk {
+            disk.set_max_lines(max_lines);
         }
+        self.display_offset = min(self.display_offset, self.history_size());
+    }
+
+    /// Write the disk history and all lines up to the cursor to a new history file.
+    ///
+    /// Nothing is written without a disk history.
+    pub fn persist_history(&mut self, path: &Path) -> io::Result<()> {
+        let disk = match &mut self.disk {
+            Some(disk) => disk,
+            None => return Ok(()),
+        };
+
+        let raw = &self.raw;
+        let cursor_line = self.lines.0 - 1 - self.cursor.point.line.0;
+        disk.persist(path, (cursor_line..raw.len()).rev().map(|line| &raw[line]))
     }
 
     /// Update the size of the scrollback history.
     pub fn update_history(&mut self, history_size: usize) {
-        let current_history_size = self.history_size();
+        let current_history_size = self.memory_history_size();
         if current_history_size > history_size {
             self.raw.shrink_lines(current_history_size - history_size);
         }
-        self.display_offset = min(self.display_offset, history_size);
         self.max_scroll_limit = history_size;
+        self.display_offset = min(self.display_offset, self.history_size());
     }
 
     pub fn scroll_display(&mut self, scroll: Scroll) {
@@ -170,19 +228,34 @@ impl<T: GridCell + Default + PartialEq + Clone> Grid<T> {
             Scroll::Top => self.history_size(),
             Scroll::Bottom => 0,
         };
+
+        self.shrink_disk_cache();
+    }
+
+    /// Drop decompressed lines of the disk history which are not visible.
+    ///
+    /// Lines read through an immutable borrow of the grid are only freed by this.
+    pub fn shrink_disk_cache(&mut self) {
+        let memory_lines = self.raw.len();
+        let start = self.display_offset.saturating_sub(memory_lines);
+        let end = (self.display_offset + self.lines.0).saturating_sub(memory_lines);
+
+        if let Some(disk) = &mut self.disk {
+            disk.shrink_cache(start..end);
+        }
     }
 
     fn increase_scroll_limit(&mut self, count: usize) {
-        let count = min(count, self.max_scroll_limit - self.history_size());
+        let count = min(count, self.max_scroll_limit - self.memory_history_size());
         if count != 0 {
             self.raw.initialize(count, self.cols);
         }
     }
 
     fn decrease_scroll_limit(&mut self, count: usize) {
-        let count = min(count, self.history_size());
+        let count = min(count, self.memory_history_size());
         if count != 0 {
-            self.raw.shrink_lines(min(count, self.history_size()));
+            self.raw.shrink_lines(count);
             self.display_offset = min(self.display_offset, self.history_size());
         }
     }
@@ -225,6 +298,7 @@ impl<T: GridCell + Default + PartialEq + Clone> Grid<T> {
 
             // Rotate the entire line buffer downward.
             self.raw.rotate_down(*positions);
+            self.rotation = self.rotation.wrapping_sub(positions.0);
 
             // Ensure all new lines are fully cleared.
             for i in 0..positions.0 {
@@ -269,12 +343,8 @@ impl<T: GridCell + Default + PartialEq + Clone> Grid<T> {
             return;
         }
 
-        // Update display offset when not pinned to active area.
-        if self.display_offset != 0 {
-            self.display_offset = min(self.display_offset + *positions, self.max_scroll_limit);
-        }
-
         // Create scrollback for the new lines.
+        let total_lines = self.raw.len();
         self.increase_scroll_limit(*positions);
 
         // Swap the lines fixed at the top to their target positions after rotation.
@@ -290,8 +360,23 @@ impl<T: GridCell + Default + PartialEq + Clone> Grid<T> {
             self.raw.swap(index, index - positions.0);
         }
 
+        // Move lines which are rotated out of the history to disk, oldest first.
+        if let Some(disk) = &mut self.disk {
+            let rotated_out = total_lines + positions.0 - self.raw.len();
+            for line in (total_lines - rotated_out..total_lines).rev() {
+                disk.push(mem::replace(&mut self.raw[line], Row::new(self.cols)));
+            }
+        }
+
+        // Update display offset when not pinned to active area.
+        if self.display_offset != 0 {
+            self.display_offset = min(self.display_offset + *positions, self.history_size());
+            self.shrink_disk_cache();
+        }
+
         // Rotate the entire line buffer upward.
         self.raw.rotate(-(positions.0 as isize));
+        self.rotation = self.rotation.wrapping_add(positions.0);
 
         // Ensure all new lines are fully cleared.
         for i in 0..positions.0 {
@@ -422,10 +507,51 @@ impl<T> Grid<T> {
         Point { line: self.lines.0 + self.display_offset - point.line.0 - 1, column: point.column }
     }
 
+    /// Convert a buffer point to an absolute point, which is not affected by scrolling.
+    #[inline]
+    pub fn buffer_to_absolute(&self, point: Point<usize>) -> Point<usize> {
+        Point::new(point.line.wrapping_sub(self.rotation), point.column)
+    }
+
+    /// Convert an absolute point back to global buffer indexing.
+    ///
+    /// Returns `None` if the line has been removed from the buffer.
+    #[inline]
+    pub fn absolute_to_buffer(&self, point: Point<usize>) -> Option<Point<usize>> {
+        let line = point.line.wrapping_add(self.rotation);
+        if line < self.total_lines() {
+            Some(Point::new(line, min(point.column, self.cols - 1)))
+        } else {
+            None
+        }
+    }
+
+    /// Vi mode marks and jumps, stored as absolute points.
+    #[inline]
+    pub fn vi_marks(&self) -> &ViMarks {
+        &self.vi_marks
+    }
+
+    #[inline]
+    pub fn vi_marks_mut(&mut self) -> &mut ViMarks {
+        &mut self.vi_marks
+    }
+
     #[inline]
     pub fn clear_history(&mut self) {
         // Explicitly purge all lines from history.
-        self.raw.shrink_lines(self.history_size());
+        self.raw.shrink_lines(self.memory_history_size());
+
+        if let Some(disk) = &mut self.disk {
+            disk.clear();
+        }
+
+        // Marks can't point to the history anymore, so they're removed entirely.
+        self.vi_marks.clear();
+
+        // Drop purged lines instead of keeping them cached for reuse, otherwise resources like
+        // graphics referenced by their cells would stay alive until the lines are overwritten.
+        self.raw.truncate();
     }
 
     /// This is used only for initializing after loading ref-tests.
@@ -438,7 +564,13 @@ impl<T> Grid<T> {
         self.truncate();
 
         // Initialize everything with empty new lines.
-        self.raw.initialize(self.max_scroll_limit - self.history_size(), self.cols);
+        self.raw.initialize(self.max_scroll_limit - self.memory_history_size(), self.cols);
+    }
+
+    /// Number of history lines stored in memory.
+    #[inline]
+    fn memory_history_size(&self) -> usize {
+        self.raw.len() - self.lines.0
     }
 
     /// This is used only for truncating before saving ref-tests.
@@ -484,6 +616,33 @@ impl<T> Grid<T> {
     }
 }
 
+impl<T: Clone> Clone for Grid<T> {
+    fn clone(&self) -> Self {
+        // The disk history owns its file, so it's copied to a new one.
+        let disk = self.disk.as_ref().and_then(|disk| match disk.try_clone() {
+            Ok(disk) => Some(disk),
+            Err(err) => {
+                error!("Unable to copy scrollback history: {}", err);
+                None
+            },
+        });
+        let history_size = self.memory_history_size() + disk.as_ref().map_or(0, DiskHistory::len);
+
+        Grid {
+            cursor: self.cursor.clone(),
+            saved_cursor: self.saved_cursor.clone(),
+            raw: self.raw.clone(),
+            cols: self.cols,
+            lines: self.lines,
+            display_offset: min(self.display_offset, history_size),
+            max_scroll_limit: self.max_scroll_limit,
+            disk,
+            rotation: self.rotation,
+            vi_marks: self.vi_marks.clone(),
+        }
+    }
+}
+
 impl<T: PartialEq> PartialEq for Grid<T> {
     fn eq(&self, other: &Self) -> bool {
         // Compare struct fields and check result of grid comparison.
@@ -515,14 +674,21 @@ impl<T> Index<usize> for Grid<T> {
 
     #[inline]
     fn index(&self, index: usize) -> &Row<T> {
-        &self.raw[index]
+        match &self.disk {
+            Some(disk) if index >= self.raw.len() => &disk[index - self.raw.len()],
+            _ => &self.raw[index],
+        }
     }
 }
 
 impl<T> IndexMut<usize> for Grid<T> {
     #[inline]
     fn index_mut(&mut self, index: usize) -> &mut Row<T> {
-        &mut self.raw[index]
+        let memory_lines = self.raw.len();
+        match &mut self.disk {
+            Some(disk) if index >= memory_lines => &mut disk[index - memory_lines],
+            _ => &mut self.raw[index],
+        }
     }
 }
 
@@ -579,7 +745,7 @@ pub trait Dimensions {
 impl<G> Dimensions for Grid<G> {
     #[inline]
     fn total_lines(&self) -> usize {
-        self.raw.len()
+        self.raw.len() + self.disk.as_ref().map_or(0, DiskHistory::len)
     }
 
     #[inline]
@@ -25,12 +25,31 @@ impl<T: GridCell + Default + PartialEq + Clone> Grid<T> {
             Ordering::Equal => (),
         }
 
+        // Lines on disk are not reflowed, they are only truncated or padded.
+        if let Some(disk) = &mut self.disk {
+            disk.resize(cols);
+        }
+
+        // Growing only removes lines at the bottom and shrinking only at the top, so the saved
+        // cursor's logical line is located relative to the unaffected side of the buffer.
+        let from_top = self.cols < cols;
+        let saved_cursor = if reflow && self.cols != cols {
+            Some(self.logical_saved_cursor(from_top))
+        } else {
+            None
+        };
+
         match self.cols.cmp(&cols) {
             Ordering::Less => self.grow_cols(reflow, cols),
             Ordering::Greater => self.shrink_cols(reflow, cols),
             Ordering::Equal => (),
         }
 
+        // Keep the saved cursor attached to its text, since it is not reflowed with the content.
+        if let Some(saved_cursor) = saved_cursor {
+            self.restore_saved_cursor(saved_cursor, from_top);
+        }
+
         // Restore template cell.
         self.cursor.template = template;
     }
@@ -51,7 +70,7 @@ impl<T: GridCell + Default + PartialEq + Clone> Grid<T> {
         self.raw.grow_visible_lines(new_line_count);
         self.lines = new_line_count;
 
-        let history_size = self.history_size();
+        let history_size = self.memory_history_size();
         let from_history = min(history_size, lines_added.0);
 
         // Move existing lines up for every line that couldn't be pulled from history.
@@ -89,10 +108,13 @@ impl<T: GridCell + Default + PartialEq + Clone> Grid<T> {
             self.cursor.point.line = min(self.cursor.point.line, target - 1);
         }
 
-        // Clamp saved cursor, since only primary cursor is scrolled into viewport.
+        // Move the saved cursor with the content, clamping it once its line left the viewport.
+        self.saved_cursor.point.line.0 =
+            self.saved_cursor.point.line.saturating_sub(required_scrolling);
         self.saved_cursor.point.line = min(self.saved_cursor.point.line, target - 1);
 
         self.raw.rotate((self.lines - target).0 as isize);
+        self.rotation = self.rotation.wrapping_sub((self.lines - target).0);
         self.raw.shrink_visible_lines(target);
         self.lines = target;
     }
@@ -118,11 +140,16 @@ impl<T: GridCell + Default + PartialEq + Clone> Grid<T> {
 
         let mut rows = self.raw.take_all();
 
+        // New row and column of every line's first cell, counting from the top.
+        let mut new_rows = vec![(0, 0); rows.len()];
+
         for (i, mut row) in rows.drain(..).enumerate().rev() {
             // Check if reflowing should be performed.
+            let len = reversed.len();
             let last_row = match reversed.last_mut() {
                 Some(last_row) if should_reflow(last_row) => last_row,
                 _ => {
+                    new_rows[i] = (len, 0);
                     reversed.push(row);
                     continue;
                 },
@@ -142,6 +169,9 @@ impl<T: GridCell + Default + PartialEq + Clone> Grid<T> {
                 last_len -= 1;
             }
 
+            // The start of the line is moved to the end of the previous row.
+            new_rows[i] = (len - 1, last_len);
+
             // Don't try to pull more cells from the next line than available.
             let mut num_wrapped = cols.0 - last_len;
             let len = min(row.len(), num_wrapped);
@@ -182,11 +212,15 @@ impl<T: GridCell + Default + PartialEq + Clone> Grid<T> {
                 let line_delta = (self.cursor.point.line - target.line).0;
 
                 if line_delta != 0 && row.is_clear() {
+                    last_row.insert_marks(row.marks());
                     continue;
                 }
 
                 cursor_line_delta += line_delta;
             } else if row.is_clear() {
+                // Keep shell integration marks of the merged row.
+                last_row.insert_marks(row.marks());
+
                 if i + reversed.len() >= self.lines.0 {
                     // Since we removed a line, rotate down the viewport.
                     self.display_offset = self.display_offset.saturating_sub(1);
@@ -226,6 +260,8 @@ impl<T: GridCell + Default + PartialEq + Clone> Grid<T> {
             self.cursor.point.line.0 = self.cursor.point.line.saturating_sub(overflow);
         }
 
+        self.reflow_vi_marks(&reversed, &new_rows);
+
         // Reverse iterator and fill all rows that are still too short.
         let mut new_raw = Vec::with_capacity(reversed.len());
         for mut row in reversed.drain(..).rev() {
@@ -255,7 +291,14 @@ impl<T: GridCell + Default + PartialEq + Clone> Grid<T> {
         let mut buffered: Option<Vec<T>> = None;
 
         let mut rows = self.raw.take_all();
+
+        // New row and column of every line's first cell, counting from the top.
+        let mut new_rows = vec![(0, 0); rows.len()];
+
         for (i, mut row) in rows.drain(..).enumerate().rev() {
+            let buffered_len = buffered.as_ref().map_or(0, Vec::len);
+            new_rows[i] = (new_raw.len(), buffered_len);
+
             // Append lines left over from the previous row.
             if let Some(buffered) = buffered.take() {
                 // Add a column for every cell added before the cursor, if it goes beyond the new
@@ -356,9 +399,20 @@ impl<T: GridCell + Default + PartialEq + Clone> Grid<T> {
             }
         }
 
+        self.reflow_vi_marks(&new_raw, &new_rows);
+
         // Reverse iterator and use it as the new grid storage.
         let mut reversed: Vec<Row<T>> = new_raw.drain(..).rev().collect();
-        reversed.truncate(self.max_scroll_limit + self.lines.0);
+        let max_lines = self.max_scroll_limit + self.lines.0;
+
+        // Move lines exceeding the history limit to disk, oldest first.
+        if let (Some(disk), true) = (&mut self.disk, reversed.len() > max_lines) {
+            for row in reversed.drain(max_lines..).rev() {
+                disk.push(row);
+            }
+        }
+
+        reversed.truncate(max_lines);
         self.raw.replace_inner(reversed);
 
         // Reflow the primary cursor, or clamp it if reflow is disabled.
@@ -376,4 +430,124 @@ impl<T: GridCell + Default + PartialEq + Clone> Grid<T> {
         // Clamp the saved cursor to the grid.
         self.saved_cursor.point.column = min(self.saved_cursor.point.column, cols - 1);
     }
+
+    /// Move vi mode marks to the cells their content was reflowed into.
+    ///
+    /// The first cell of every line in memory is moved to its row and column in `new_rows`,
+    /// counting from the top of the reflowed `rows`.
+    fn reflow_vi_marks(&mut self, rows: &[Row<T>], new_rows: &[(usize, usize)]) {
+        let rotation = self.rotation;
+        let len = rows.len();
+        let last_column = self.cols - 1;
+
+        // Number of cells a row contributes to its logical line.
+        let logical_len = |row: &Row<T>| match row.last() {
+            Some(cell) if cell.flags().contains(Flags::LEADING_WIDE_CHAR_SPACER) => row.len() - 1,
+            _ => row.len(),
+        };
+        let is_wrapped =
+            |row: &Row<T>| row.last().map_or(false, |cell| cell.flags().contains(Flags::WRAPLINE));
+
+        for point in self.vi_marks.points_mut() {
+            let line = point.line.wrapping_add(rotation);
+            let (mut row, mut column) = match new_rows.get(line) {
+                Some((row, column)) => (*row, column + point.column.0),
+                None => {
+                    // Lines on disk are only shifted by the change in the number of rows in memory.
+                    let line = line.wrapping_sub(new_rows.len()).wrapping_add(len);
+                    point.line = line.wrapping_sub(rotation);
+                    continue;
+                },
+            };
+
+            // Follow cells which were wrapped into the rows below.
+            while row + 1 < len && column >= logical_len(&rows[row]) && is_wrapped(&rows[row]) {
+                column -= logical_len(&rows[row]);
+                row += 1;
+            }
+
+            point.line = (len - 1).saturating_sub(row).wrapping_sub(rotation);
+            point.column = min(Column(column), last_column);
+        }
+    }
+
+    /// Check if a row is continued in the row below it.
+    fn is_wrapped(&self, line: usize) -> bool {
+        self.raw[line].last().map_or(false, |cell| cell.flags().contains(Flags::WRAPLINE))
+    }
+
+    /// Number of cells a row contributes to its logical line.
+    fn logical_len(&self, line: usize) -> usize {
+        let row = &self.raw[line];
+        match row.last() {
+            Some(cell) if cell.flags().contains(Flags::LEADING_WIDE_CHAR_SPACER) => row.len() - 1,
+            _ => row.len(),
+        }
+    }
+
+    /// Check if a row is the first row of its logical line.
+    fn is_logical_start(&self, line: usize) -> bool {
+        line + 1 == self.raw.len() || !self.is_wrapped(line + 1)
+    }
+
+    /// Position of the saved cursor within the logical lines of the buffer.
+    fn logical_saved_cursor(&self, from_top: bool) -> LogicalPoint {
+        let point = self.saved_cursor.point;
+        let line = (self.lines - point.line - 1).0;
+
+        // Find the start of the logical line, counting all cells in between.
+        let mut start = line;
+        let mut offset = point.column.0;
+        while !self.is_logical_start(start) {
+            start += 1;
+            offset += self.logical_len(start);
+        }
+
+        // Include the pending wrap, so it is kept when the cursor stays at the end of a row.
+        if self.saved_cursor.input_needs_wrap {
+            offset += 1;
+        }
+
+        let starts = if from_top { start + 1..self.raw.len() } else { 0..start };
+        let line = starts.filter(|line| self.is_logical_start(*line)).count();
+
+        LogicalPoint { line, offset }
+    }
+
+    /// Move the saved cursor back to its position within the reflowed logical lines.
+    fn restore_saved_cursor(&mut self, logical_point: LogicalPoint, from_top: bool) {
+        let total_lines = self.raw.len();
+        let mut starts = (0..total_lines).filter(|line| self.is_logical_start(*line));
+        let start = if from_top {
+            starts.rev().nth(logical_point.line)
+        } else {
+            starts.nth(logical_point.line)
+        };
+
+        // Move the cursor to the top if its line has been removed from the buffer.
+        let mut line = start.unwrap_or(total_lines - 1);
+        let mut offset = if start.is_some() { logical_point.offset } else { 0 };
+
+        // Walk down the logical line until the cursor's cell is reached.
+        while line > 0 && self.is_wrapped(line) && offset >= self.logical_len(line) {
+            offset -= self.logical_len(line);
+            line -= 1;
+        }
+
+        // Clamp the cursor to the viewport, since lines in history can't be restored.
+        let line = min(line, self.lines.0 - 1);
+        self.saved_cursor.point.line = self.lines - line - 1;
+        self.saved_cursor.point.column = Column(min(offset, self.cols.0 - 1));
+        self.saved_cursor.input_needs_wrap = offset >= self.cols.0;
+    }
+}
+
+/// Position of a point within its logical line, which is not affected by reflow.
+#[derive(Debug, Copy, Clone)]
+struct LogicalPoint {
+    /// Number of logical lines between the point and the top or bottom of the buffer.
+    line: usize,
+
+    /// Number of cells between the start of the logical line and the point.
+    offset: usize,
 }
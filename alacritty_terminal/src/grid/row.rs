@@ -6,12 +6,24 @@ use std::ops::{Range, RangeFrom, RangeFull, RangeTo, RangeToInclusive};
 use std::ptr;
 use std::slice;
 
+use bitflags::bitflags;
 use serde::{Deserialize, Serialize};
 
 use crate::grid::GridCell;
 use crate::index::Column;
 use crate::term::cell::ResetDiscriminant;
 
+bitflags! {
+    /// Shell integration marks (`OSC 133`) printed in a row.
+    #[derive(Serialize, Deserialize, Default)]
+    pub struct PromptMarks: u8 {
+        const PROMPT_START  = 0b0001;
+        const COMMAND_START = 0b0010;
+        const OUTPUT_START  = 0b0100;
+        const COMMAND_END   = 0b1000;
+    }
+}
+
 /// A row in the grid.
 #[derive(Serialize, Deserialize, Default, Clone, Debug)]
 pub struct Row<T> {
@@ -22,6 +34,10 @@ pub struct Row<T> {
     /// This is the upper bound on the number of elements in the row, which have been modified
     /// since the last reset. All cells after this point are guaranteed to be equal.
     pub(crate) occ: usize,
+
+    /// Shell integration marks.
+    #[serde(default, skip_serializing_if = "PromptMarks::is_empty")]
+    pub(crate) marks: PromptMarks,
 }
 
 impl<T: PartialEq> PartialEq for Row<T> {
@@ -52,7 +68,7 @@ impl<T: Clone + Default> Row<T> {
             inner.set_len(columns.0);
         }
 
-        Row { inner, occ: 0 }
+        Row { inner, occ: 0, marks: PromptMarks::empty() }
     }
 
     /// Increase the number of columns in the row.
@@ -111,6 +127,7 @@ impl<T: Clone + Default> Row<T> {
         }
 
         self.occ = 0;
+        self.marks = PromptMarks::empty();
     }
 }
 
@@ -118,7 +135,18 @@ impl<T: Clone + Default> Row<T> {
 impl<T> Row<T> {
     #[inline]
     pub fn from_vec(vec: Vec<T>, occ: usize) -> Row<T> {
-        Row { inner: vec, occ }
+        Row { inner: vec, occ, marks: PromptMarks::empty() }
+    }
+
+    /// Shell integration marks printed in this row.
+    #[inline]
+    pub fn marks(&self) -> PromptMarks {
+        self.marks
+    }
+
+    #[inline]
+    pub fn insert_marks(&mut self, marks: PromptMarks) {
+        self.marks.insert(marks);
     }
 
     #[inline]
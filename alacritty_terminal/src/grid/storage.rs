@@ -152,11 +152,13 @@ impl<T> Storage<T> {
     /// Exploits the known size of Row<T> to produce a slightly more efficient
     /// swap than going through slice::swap.
     ///
-    /// The default implementation from swap generates 8 movups and 4 movaps
-    /// instructions. This implementation achieves the swap in only 8 movups
-    /// instructions.
+    /// The default implementation from swap generates 8 movups, 4 movaps and
+    /// 6 movq instructions, going through the stack. Since the prompt marks
+    /// grow Row<T> to 5 qwords, this implementation achieves the swap in 8
+    /// movups and 4 movq instructions.
     pub fn swap(&mut self, a: usize, b: usize) {
-        debug_assert_eq!(mem::size_of::<Row<T>>(), mem::size_of::<usize>() * 4);
+        const QWORDS: usize = mem::size_of::<Row<()>>() / mem::size_of::<usize>();
+        debug_assert_eq!(mem::size_of::<Row<T>>(), mem::size_of::<usize>() * QWORDS);
 
         let a = self.compute_index(a);
         let b = self.compute_index(b);
@@ -172,7 +174,7 @@ impl<T> Storage<T> {
             //
             // The optimizer unrolls this loop and vectorizes it.
             let mut tmp: usize;
-            for i in 0..4 {
+            for i in 0..QWORDS as isize {
                 tmp = *a_ptr.offset(i);
                 *a_ptr.offset(i) = *b_ptr.offset(i);
                 *b_ptr.offset(i) = tmp;
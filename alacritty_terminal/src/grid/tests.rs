@@ -1,7 +1,10 @@
 //! Tests for the Grid.
 
+use std::sync::Arc;
+
 use super::*;
 
+use crate::graphics::{GraphicCell, GraphicData, GraphicId, Graphics};
 use crate::term::cell::Cell;
 
 impl GridCell for usize {
@@ -309,6 +312,91 @@ fn grow_reflow_multiline() {
     }
 }
 
+#[test]
+fn grow_reflow_prompt_marks() {
+    let mut grid = Grid::<Cell>::new(Line(2), Column(2), 0);
+    grid[Line(0)][Column(0)] = cell('1');
+    grid[Line(0)][Column(1)] = wrap_cell('2');
+    grid[Line(1)][Column(0)] = cell('3');
+    grid[Line(1)].insert_marks(PromptMarks::OUTPUT_START);
+
+    grid.resize(true, Line(2), Column(3));
+
+    assert_eq!(grid[1][Column(2)], cell('3'));
+    assert_eq!(grid[1].marks(), PromptMarks::OUTPUT_START);
+    assert_eq!(grid[0].marks(), PromptMarks::empty());
+}
+
+#[test]
+fn grow_reflow_saved_cursor() {
+    let mut grid = Grid::<Cell>::new(Line(3), Column(2), 0);
+    grid[Line(0)][Column(0)] = cell('1');
+    grid[Line(0)][Column(1)] = wrap_cell('2');
+    grid[Line(1)][Column(0)] = cell('3');
+    grid[Line(1)][Column(1)] = wrap_cell('4');
+    grid[Line(2)][Column(0)] = cell('5');
+    grid.cursor.point = Point::new(Line(2), Column(1));
+    grid.saved_cursor.point = Point::new(Line(1), Column(1));
+
+    grid.resize(true, Line(3), Column(5));
+
+    let saved_point = grid.saved_cursor.point;
+    assert_eq!(saved_point, Point::new(Line(0), Column(3)));
+    assert_eq!(grid[saved_point.line][saved_point.column], cell('4'));
+}
+
+#[test]
+fn shrink_reflow_saved_cursor() {
+    let mut grid = Grid::<Cell>::new(Line(3), Column(5), 2);
+    grid[Line(0)][Column(0)] = cell('1');
+    grid[Line(0)][Column(1)] = cell('2');
+    grid[Line(0)][Column(2)] = cell('3');
+    grid[Line(0)][Column(3)] = cell('4');
+    grid[Line(0)][Column(4)] = cell('5');
+    grid.cursor.point = Point::new(Line(1), Column(0));
+    grid.saved_cursor.point = Point::new(Line(0), Column(4));
+
+    grid.resize(true, Line(3), Column(2));
+
+    let saved_point = grid.saved_cursor.point;
+    assert_eq!(saved_point, Point::new(Line(0), Column(0)));
+    assert_eq!(grid[saved_point.line][saved_point.column], cell('5'));
+    assert!(!grid.saved_cursor.input_needs_wrap);
+}
+
+#[test]
+fn shrink_reflow_saved_cursor_wrap() {
+    let mut grid = Grid::<Cell>::new(Line(2), Column(4), 0);
+    grid[Line(0)][Column(0)] = cell('1');
+    grid[Line(0)][Column(1)] = cell('2');
+    grid[Line(0)][Column(2)] = cell('3');
+    grid[Line(0)][Column(3)] = cell('4');
+    grid.cursor.point = Point::new(Line(1), Column(0));
+    grid.saved_cursor.point = Point::new(Line(0), Column(3));
+    grid.saved_cursor.input_needs_wrap = true;
+
+    grid.resize(true, Line(2), Column(2));
+
+    // The pending wrap stays at the end of the logical line.
+    let saved_point = grid.saved_cursor.point;
+    assert_eq!(grid[saved_point.line][saved_point.column], cell('4'));
+    assert!(grid.saved_cursor.input_needs_wrap);
+}
+
+#[test]
+fn shrink_lines_saved_cursor() {
+    let mut grid = Grid::<Cell>::new(Line(3), Column(1), 2);
+    grid[Line(1)][Column(0)] = cell('1');
+    grid.cursor.point = Point::new(Line(2), Column(0));
+    grid.saved_cursor.point = Point::new(Line(1), Column(0));
+
+    grid.resize(true, Line(2), Column(1));
+
+    let saved_point = grid.saved_cursor.point;
+    assert_eq!(saved_point, Point::new(Line(0), Column(0)));
+    assert_eq!(grid[saved_point.line][saved_point.column], cell('1'));
+}
+
 #[test]
 fn grow_reflow_disabled() {
     let mut grid = Grid::<Cell>::new(Line(2), Column(2), 0);
@@ -350,6 +438,126 @@ fn shrink_reflow_disabled() {
     assert_eq!(grid[0][Column(1)], cell('2'));
 }
 
+#[test]
+fn reflow_vi_marks() {
+    let mut grid = Grid::<Cell>::new(Line(3), Column(4), 10);
+    for (i, c) in "abcd".chars().enumerate() {
+        grid[Line(0)][Column(i)] = cell(c);
+    }
+    for (i, c) in "efgh".chars().enumerate() {
+        grid[Line(1)][Column(i)] = cell(c);
+    }
+    grid[Line(2)][Column(0)] = cell('i');
+    grid.cursor.point = Point::new(Line(2), Column(1));
+
+    let point = grid.buffer_to_absolute(Point::new(2, Column(1)));
+    grid.vi_marks_mut().set('a', point);
+    let point = grid.buffer_to_absolute(Point::new(1, Column(0)));
+    grid.vi_marks_mut().set('b', point);
+
+    let mark = |grid: &Grid<Cell>, mark| {
+        let point = grid.absolute_to_buffer(grid.vi_marks().get(mark).unwrap()).unwrap();
+        grid[point.line][point.column].c
+    };
+
+    grid.resize(true, Line(3), Column(2));
+    assert_eq!(grid.total_lines(), 5);
+    assert_eq!(mark(&grid, 'a'), 'b');
+    assert_eq!(mark(&grid, 'b'), 'e');
+
+    grid.resize(true, Line(3), Column(4));
+    assert_eq!(grid.total_lines(), 3);
+    assert_eq!(mark(&grid, 'a'), 'b');
+    assert_eq!(mark(&grid, 'b'), 'e');
+}
+
+#[test]
+fn scroll_into_disk_history() {
+    let path = std::env::temp_dir().join(format!("alacritty-grid-{}", std::process::id()));
+    let disk = DiskHistory::create(path, 100, Column(2)).unwrap();
+
+    let mut grid = Grid::<Cell>::new(Line(2), Column(2), 1);
+    grid.set_disk_history(Some(disk));
+
+    for c in &['a', 'b', 'c', 'd'] {
+        grid[Line(1)][Column(0)] = cell(*c);
+        grid.scroll_up(&(Line(0)..Line(2)), Line(1));
+    }
+
+    // One line is kept in memory, the others are moved to disk.
+    assert_eq!(grid.history_size(), 4);
+    assert_eq!(grid.disk_history().map(DiskHistory::len), Some(3));
+    assert_eq!(grid[1][Column(0)], cell('d'));
+    assert_eq!(grid[2][Column(0)], cell('c'));
+    assert_eq!(grid[3][Column(0)], cell('b'));
+    assert_eq!(grid[4][Column(0)], cell('a'));
+
+    grid[4][Column(1)] = cell('x');
+    assert_eq!(grid[4][Column(1)], cell('x'));
+
+    grid.scroll_display(Scroll::Top);
+    assert_eq!(grid.display_offset(), 4);
+
+    grid.clear_history();
+    assert_eq!(grid.history_size(), 0);
+}
+
+#[test]
+fn clone_disk_history() {
+    let path = std::env::temp_dir().join(format!("alacritty-grid-clone-{}", std::process::id()));
+    let disk = DiskHistory::create(path, 100, Column(2)).unwrap();
+
+    let mut grid = Grid::<Cell>::new(Line(2), Column(2), 0);
+    grid.set_disk_history(Some(disk));
+
+    for c in &['a', 'b', 'c'] {
+        grid[Line(1)][Column(0)] = cell(*c);
+        grid.scroll_up(&(Line(0)..Line(2)), Line(1));
+    }
+    grid.scroll_display(Scroll::Top);
+
+    let mut cloned = grid.clone();
+    assert_eq!(cloned.history_size(), 3);
+    assert_eq!(cloned.display_offset(), 3);
+    assert_eq!(cloned[3][Column(0)], cell('a'));
+
+    // Both grids have their own history.
+    cloned[3][Column(0)] = cell('x');
+    assert_eq!(grid[3][Column(0)], cell('a'));
+}
+
+#[test]
+fn clear_history_drops_lines() {
+    let mut graphics = Graphics::default();
+    let graphic = GraphicData { id: GraphicId(0), width: 1, height: 1, pixels: vec![0; 4] };
+    let texture = graphics.insert(graphic);
+
+    let mut grid = Grid::<Cell>::new(Line(2), Column(2), 10);
+    grid[Line(0)][Column(0)].set_graphic(GraphicCell {
+        texture: texture.clone(),
+        offset_x: 0,
+        offset_y: 0,
+    });
+    grid[Line(1)][Column(0)] = cell('a');
+    grid.scroll_up(&(Line(0)..Line(2)), Line(1));
+
+    assert_eq!(grid.history_size(), 1);
+    assert_eq!(Arc::strong_count(&texture), 2);
+
+    // Cells in history are dropped immediately.
+    grid.clear_history();
+    assert_eq!(grid.history_size(), 0);
+    assert_eq!(Arc::strong_count(&texture), 1);
+    assert_eq!(grid[Line(0)][Column(0)], cell('a'));
+
+    // New history lines are still blank.
+    grid.scroll_up(&(Line(0)..Line(2)), Line(2));
+    assert_eq!(grid.history_size(), 2);
+    assert_eq!(grid[3][Column(0)], cell('a'));
+    assert_eq!(grid[2][Column(0)], Cell::default());
+    assert_eq!(grid[Line(0)][Column(0)], Cell::default());
+}
+
 // https://github.com/rust-lang/rust-clippy/pull/6375
 #[allow(clippy::all)]
 fn cell(c: char) -> Cell {
@@ -5,13 +5,14 @@
 //! when text is added/removed/scrolled on the screen. The selection should
 //! also be cleared if the user clicks off of the selection.
 
+use std::cmp::min;
 use std::convert::TryFrom;
 use std::mem;
 use std::ops::{Bound, Range, RangeBounds};
 
 use crate::ansi::CursorShape;
 use crate::grid::{Dimensions, GridCell, Indexed};
-use crate::index::{Column, Line, Point, Side};
+use crate::index::{Boundary, Column, Direction, Line, Point, Side};
 use crate::term::cell::{Cell, Flags};
 use crate::term::{RenderableCursor, Term};
 
@@ -91,6 +92,23 @@ pub enum SelectionType {
     Lines,
 }
 
+/// Keyboard motion for extending a selection.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SelectionMotion {
+    /// Move the selection end one cell to the left.
+    Left,
+    /// Move the selection end one cell to the right.
+    Right,
+    /// Move the selection end one line up.
+    Up,
+    /// Move the selection end one line down.
+    Down,
+    /// Move the selection end to the start of the previous semantic block.
+    SemanticLeft,
+    /// Move the selection end to the end of the next semantic block.
+    SemanticRight,
+}
+
 /// Describes a region of a 2-dimensional area.
 ///
 /// Used to track a text selection. There are four supported modes, each with its own constructor:
@@ -128,6 +146,69 @@ impl Selection {
         self.region.end = Anchor::new(point, side);
     }
 
+    /// Move the end of the selection using a keyboard motion.
+    ///
+    /// Returns the new end point of the selection.
+    pub fn extend<T>(&mut self, term: &Term<T>, motion: SelectionMotion) -> Point<usize> {
+        let Anchor { mut point, mut side } = self.region.end;
+        point.line = min(point.line, term.total_lines() - 1);
+
+        match motion {
+            // Deselect the last cell before moving to the previous one.
+            SelectionMotion::Left if side == Side::Right => {
+                point = term.expand_wide(point, Direction::Left);
+                side = Side::Left;
+            },
+            SelectionMotion::Left => {
+                let prev = point.sub_absolute(term, Boundary::Clamp, 1);
+                point = term.expand_wide(prev, Direction::Left);
+            },
+            // Select the current cell before moving to the next one.
+            SelectionMotion::Right if side == Side::Left => {
+                point = term.expand_wide(point, Direction::Right);
+                side = Side::Right;
+            },
+            SelectionMotion::Right => {
+                let next = point.add_absolute(term, Boundary::Clamp, 1);
+                point = term.expand_wide(next, Direction::Right);
+            },
+            SelectionMotion::Up => point.line = min(point.line + 1, term.total_lines() - 1),
+            SelectionMotion::Down => point.line = point.line.saturating_sub(1),
+            SelectionMotion::SemanticLeft => {
+                if side == Side::Left {
+                    point = point.sub_absolute(term, Boundary::Clamp, 1);
+                }
+
+                // Skip escape chars between the selection and the previous semantic block.
+                let first = Point::new(term.total_lines() - 1, Column(0));
+                while point != first && is_semantic_escape(term, point) {
+                    point = point.sub_absolute(term, Boundary::Clamp, 1);
+                }
+
+                point = term.semantic_search_left(point);
+                side = Side::Left;
+            },
+            SelectionMotion::SemanticRight => {
+                if side == Side::Right {
+                    point = point.add_absolute(term, Boundary::Clamp, 1);
+                }
+
+                // Skip escape chars between the selection and the next semantic block.
+                let last = Point::new(0, term.cols() - 1);
+                while point != last && is_semantic_escape(term, point) {
+                    point = point.add_absolute(term, Boundary::Clamp, 1);
+                }
+
+                point = term.semantic_search_right(point);
+                side = Side::Right;
+            },
+        }
+
+        self.update(point, side);
+
+        point
+    }
+
     pub fn rotate<D: Dimensions>(
         mut self,
         dimensions: &D,
@@ -415,6 +496,13 @@ impl Selection {
     }
 }
 
+/// Check if the cell at a point is a semantic escape char.
+fn is_semantic_escape<T>(term: &Term<T>, point: Point<usize>) -> bool {
+    let cell = &term.grid()[point.line][point.column];
+    term.semantic_escape_chars().contains(cell.c)
+        && !cell.flags.intersects(Flags::WIDE_CHAR_SPACER | Flags::LEADING_WIDE_CHAR_SPACER)
+}
+
 /// Tests for selection.
 ///
 /// There are comments on all of the tests describing the selection. Pictograms
@@ -428,6 +516,7 @@ impl Selection {
 mod tests {
     use super::*;
 
+    use crate::ansi::Handler;
     use crate::config::MockConfig;
     use crate::index::{Column, Line, Point, Side};
     use crate::term::{SizeInfo, Term};
@@ -695,4 +784,76 @@ mod tests {
         assert!(!selection.intersects_range(..=2));
         assert!(!selection.intersects_range(7..=8));
     }
+
+    #[test]
+    fn keyboard_extend_simple() {
+        let term = term(3, 5);
+        let mut selection =
+            Selection::new(SelectionType::Simple, Point::new(1, Column(2)), Side::Left);
+
+        selection.extend(&term, SelectionMotion::Right);
+        selection.extend(&term, SelectionMotion::Right);
+        assert_eq!(selection.to_range(&term).unwrap(), SelectionRange {
+            start: Point::new(1, Column(2)),
+            end: Point::new(1, Column(3)),
+            is_block: false,
+        });
+
+        selection.extend(&term, SelectionMotion::Left);
+        selection.extend(&term, SelectionMotion::Left);
+        assert!(selection.is_empty());
+
+        selection.extend(&term, SelectionMotion::Left);
+        selection.extend(&term, SelectionMotion::Up);
+        assert_eq!(selection.to_range(&term).unwrap(), SelectionRange {
+            start: Point::new(2, Column(1)),
+            end: Point::new(1, Column(1)),
+            is_block: false,
+        });
+
+        // Motions are clamped to the grid.
+        selection.extend(&term, SelectionMotion::Up);
+        let end = selection.extend(&term, SelectionMotion::Left);
+        assert_eq!(end, Point::new(2, Column(0)));
+    }
+
+    #[test]
+    fn keyboard_extend_block() {
+        let term = term(3, 5);
+        let mut selection =
+            Selection::new(SelectionType::Block, Point::new(2, Column(1)), Side::Left);
+
+        selection.extend(&term, SelectionMotion::Right);
+        selection.extend(&term, SelectionMotion::Right);
+        selection.extend(&term, SelectionMotion::Down);
+        selection.extend(&term, SelectionMotion::Down);
+        assert_eq!(selection.to_range(&term).unwrap(), SelectionRange {
+            start: Point::new(2, Column(1)),
+            end: Point::new(0, Column(2)),
+            is_block: true,
+        });
+    }
+
+    #[test]
+    fn keyboard_extend_semantic() {
+        let mut term = term(1, 10);
+        for c in "ab cd ef".chars() {
+            term.input(c);
+        }
+
+        let mut selection =
+            Selection::new(SelectionType::Simple, Point::new(0, Column(0)), Side::Left);
+
+        selection.extend(&term, SelectionMotion::SemanticRight);
+        assert_eq!(selection.to_range(&term).unwrap().end, Point::new(0, Column(1)));
+
+        selection.extend(&term, SelectionMotion::SemanticRight);
+        assert_eq!(selection.to_range(&term).unwrap().end, Point::new(0, Column(4)));
+
+        selection.extend(&term, SelectionMotion::SemanticLeft);
+        assert_eq!(selection.to_range(&term).unwrap().end, Point::new(0, Column(2)));
+
+        selection.extend(&term, SelectionMotion::SemanticLeft);
+        assert!(selection.is_empty());
+    }
 }
@@ -1,11 +1,15 @@
 use std::boxed::Box;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
 
 use bitflags::bitflags;
 use serde::{Deserialize, Serialize};
 
 use crate::ansi::{Color, NamedColor};
-use crate::grid::{self, GridCell};
+use crate::graphics::GraphicCell;
+use crate::grid::{self, Decoder, DiskCell, Encoder, GridCell};
 use crate::index::Column;
+use crate::term::color::Rgb;
 
 bitflags! {
     #[derive(Serialize, Deserialize)]
@@ -24,6 +28,10 @@ bitflags! {
         const STRIKEOUT                 = 0b0000_0010_0000_0000;
         const LEADING_WIDE_CHAR_SPACER  = 0b0000_0100_0000_0000;
         const DOUBLE_UNDERLINE          = 0b0000_1000_0000_0000;
+        const UNDERCURL                 = 0b0001_0000_0000_0000;
+        const DOTTED_UNDERLINE          = 0b0010_0000_0000_0000;
+        const DASHED_UNDERLINE          = 0b0100_0000_0000_0000;
+        const ALL_UNDERLINES            = 0b0111_1000_0000_1000;
     }
 }
 
@@ -45,6 +53,54 @@ impl ResetDiscriminant<Color> for Cell {
     }
 }
 
+/// Counter for hyperlinks without explicit ID.
+static HYPERLINK_ID_SUFFIX: AtomicU32 = AtomicU32::new(0);
+
+/// Hyperlink attached to a cell through the OSC 8 escape sequence.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct Hyperlink {
+    inner: Arc<HyperlinkInner>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+struct HyperlinkInner {
+    /// Identifier used to group cells belonging to the same link.
+    id: String,
+
+    /// Resource the hyperlink points to.
+    uri: String,
+}
+
+impl Hyperlink {
+    /// Create a new hyperlink.
+    ///
+    /// Links without an explicit ID receive a unique one, so separate links pointing to the same
+    /// URI are not treated as a single link.
+    pub fn new<T: ToString>(id: Option<T>, uri: T) -> Self {
+        let id = match id {
+            Some(id) => id.to_string(),
+            None => {
+                let suffix = HYPERLINK_ID_SUFFIX.fetch_add(1, Ordering::Relaxed);
+                format!("{}_alacritty", suffix)
+            },
+        };
+
+        Self { inner: Arc::new(HyperlinkInner { id, uri: uri.to_string() }) }
+    }
+
+    /// Identifier of the hyperlink.
+    #[inline]
+    pub fn id(&self) -> &str {
+        &self.inner.id
+    }
+
+    /// URI the hyperlink points to.
+    #[inline]
+    pub fn uri(&self) -> &str {
+        &self.inner.uri
+    }
+}
+
 /// Dynamically allocated cell content.
 ///
 /// This storage is reserved for cell attributes which are rarely set. This allows reducing the
@@ -53,6 +109,15 @@ impl ResetDiscriminant<Color> for Cell {
 #[derive(Serialize, Deserialize, Default, Debug, Clone, Eq, PartialEq)]
 struct CellExtra {
     zerowidth: Vec<char>,
+
+    #[serde(default)]
+    hyperlink: Option<Hyperlink>,
+
+    #[serde(default)]
+    underline_color: Option<Color>,
+
+    #[serde(skip)]
+    graphic: Option<GraphicCell>,
 }
 
 /// Content and attributes of a single cell in the terminal grid.
@@ -92,6 +157,60 @@ impl Cell {
         self.extra.get_or_insert_with(Default::default).zerowidth.push(c);
     }
 
+    /// Hyperlink attached to this cell.
+    #[inline]
+    pub fn hyperlink(&self) -> Option<Hyperlink> {
+        self.extra.as_ref()?.hyperlink.clone()
+    }
+
+    /// Attach a hyperlink to this cell.
+    #[inline]
+    pub fn set_hyperlink(&mut self, hyperlink: Option<Hyperlink>) {
+        match hyperlink {
+            Some(hyperlink) => {
+                self.extra.get_or_insert_with(Default::default).hyperlink = Some(hyperlink)
+            },
+            None => {
+                if let Some(extra) = self.extra.as_mut() {
+                    extra.hyperlink = None;
+                }
+            },
+        }
+    }
+
+    /// Color of this cell's underline, `None` to use the foreground color.
+    #[inline]
+    pub fn underline_color(&self) -> Option<Color> {
+        self.extra.as_ref()?.underline_color
+    }
+
+    /// Set the color of this cell's underline.
+    #[inline]
+    pub fn set_underline_color(&mut self, color: Option<Color>) {
+        match color {
+            Some(color) => {
+                self.extra.get_or_insert_with(Default::default).underline_color = Some(color)
+            },
+            None => {
+                if let Some(extra) = self.extra.as_mut() {
+                    extra.underline_color = None;
+                }
+            },
+        }
+    }
+
+    /// Graphic fragment displayed in this cell.
+    #[inline]
+    pub fn graphic(&self) -> Option<&GraphicCell> {
+        self.extra.as_ref()?.graphic.as_ref()
+    }
+
+    /// Display a graphic fragment in this cell.
+    #[inline]
+    pub fn set_graphic(&mut self, graphic: GraphicCell) {
+        self.extra.get_or_insert_with(Default::default).graphic = Some(graphic);
+    }
+
     /// Free all dynamically allocated cell storage.
     #[inline]
     pub fn drop_extra(&mut self) {
@@ -109,14 +228,18 @@ impl GridCell for Cell {
             && self.fg == Color::Named(NamedColor::Foreground)
             && !self.flags.intersects(
                 Flags::INVERSE
-                    | Flags::UNDERLINE
-                    | Flags::DOUBLE_UNDERLINE
+                    | Flags::ALL_UNDERLINES
                     | Flags::STRIKEOUT
                     | Flags::WRAPLINE
                     | Flags::WIDE_CHAR_SPACER
                     | Flags::LEADING_WIDE_CHAR_SPACER,
             )
-            && self.extra.as_ref().map(|extra| extra.zerowidth.is_empty()) != Some(false)
+            && self.extra.as_ref().map_or(true, |extra| {
+                extra.zerowidth.is_empty()
+                    && extra.hyperlink.is_none()
+                    && extra.underline_color.is_none()
+                    && extra.graphic.is_none()
+            })
     }
 
     #[inline]
@@ -135,6 +258,153 @@ impl GridCell for Cell {
     }
 }
 
+impl DiskCell for Cell {
+    /// Store the cell without its graphic, which is not kept in the disk history.
+    fn encode(&self, encoder: &mut Encoder<'_>) {
+        encoder.write_u32(self.c as u32);
+        encoder.write_u16(self.flags.bits());
+        encode_color(encoder, self.fg);
+        encode_color(encoder, self.bg);
+
+        let extra = match &self.extra {
+            Some(extra)
+                if !extra.zerowidth.is_empty()
+                    || extra.hyperlink.is_some()
+                    || extra.underline_color.is_some() =>
+            {
+                extra
+            },
+            _ => {
+                encoder.write_u8(0);
+                return;
+            },
+        };
+
+        encoder.write_u8(1);
+        encoder.write_u32(extra.zerowidth.len() as u32);
+        for c in &extra.zerowidth {
+            encoder.write_u32(*c as u32);
+        }
+
+        match &extra.hyperlink {
+            Some(hyperlink) => {
+                encoder.write_u8(1);
+                encoder.write_str(hyperlink.id());
+                encoder.write_str(hyperlink.uri());
+            },
+            None => encoder.write_u8(0),
+        }
+
+        match extra.underline_color {
+            Some(color) => {
+                encoder.write_u8(1);
+                encode_color(encoder, color);
+            },
+            None => encoder.write_u8(0),
+        }
+    }
+
+    fn decode(decoder: &mut Decoder<'_>) -> Option<Self> {
+        let c = std::char::from_u32(decoder.read_u32()?)?;
+        let flags = Flags::from_bits_truncate(decoder.read_u16()?);
+        let fg = decode_color(decoder)?;
+        let bg = decode_color(decoder)?;
+
+        let extra = match decoder.read_u8()? {
+            0 => None,
+            _ => {
+                let len = decoder.read_u32()?;
+                let zerowidth = (0..len)
+                    .map(|_| decoder.read_u32().and_then(std::char::from_u32))
+                    .collect::<Option<Vec<_>>>()?;
+
+                let hyperlink = match decoder.read_u8()? {
+                    0 => None,
+                    _ => {
+                        let id = decoder.read_str()?;
+                        Some(Hyperlink::new(Some(id), decoder.read_str()?))
+                    },
+                };
+
+                let underline_color = match decoder.read_u8()? {
+                    0 => None,
+                    _ => Some(decode_color(decoder)?),
+                };
+
+                Some(Box::new(CellExtra { zerowidth, hyperlink, underline_color, graphic: None }))
+            },
+        };
+
+        Some(Cell { c, fg, bg, flags, extra })
+    }
+}
+
+fn encode_color(encoder: &mut Encoder<'_>, color: Color) {
+    match color {
+        Color::Named(color) => {
+            encoder.write_u8(0);
+            encoder.write_u16(color as u16);
+        },
+        Color::Spec(rgb) => {
+            encoder.write_u8(1);
+            encoder.write_u8(rgb.r);
+            encoder.write_u8(rgb.g);
+            encoder.write_u8(rgb.b);
+        },
+        Color::Indexed(index) => {
+            encoder.write_u8(2);
+            encoder.write_u8(index);
+        },
+    }
+}
+
+fn decode_color(decoder: &mut Decoder<'_>) -> Option<Color> {
+    const NAMED_COLORS: [NamedColor; 29] = [
+        NamedColor::Black,
+        NamedColor::Red,
+        NamedColor::Green,
+        NamedColor::Yellow,
+        NamedColor::Blue,
+        NamedColor::Magenta,
+        NamedColor::Cyan,
+        NamedColor::White,
+        NamedColor::BrightBlack,
+        NamedColor::BrightRed,
+        NamedColor::BrightGreen,
+        NamedColor::BrightYellow,
+        NamedColor::BrightBlue,
+        NamedColor::BrightMagenta,
+        NamedColor::BrightCyan,
+        NamedColor::BrightWhite,
+        NamedColor::Foreground,
+        NamedColor::Background,
+        NamedColor::Cursor,
+        NamedColor::DimBlack,
+        NamedColor::DimRed,
+        NamedColor::DimGreen,
+        NamedColor::DimYellow,
+        NamedColor::DimBlue,
+        NamedColor::DimMagenta,
+        NamedColor::DimCyan,
+        NamedColor::DimWhite,
+        NamedColor::BrightForeground,
+        NamedColor::DimForeground,
+    ];
+
+    match decoder.read_u8()? {
+        0 => {
+            let index = decoder.read_u16()?;
+            NAMED_COLORS.iter().copied().find(|color| *color as u16 == index).map(Color::Named)
+        },
+        1 => {
+            let (r, g, b) = (decoder.read_u8()?, decoder.read_u8()?, decoder.read_u8()?);
+            Some(Color::Spec(Rgb { r, g, b }))
+        },
+        2 => decoder.read_u8().map(Color::Indexed),
+        _ => None,
+    }
+}
+
 impl From<Color> for Cell {
     #[inline]
     fn from(color: Color) -> Self {
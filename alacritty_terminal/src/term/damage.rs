@@ -0,0 +1,182 @@
+//! Tracking of the terminal regions which changed since the last frame.
+
+use std::cmp::{max, min};
+use std::slice;
+
+use crate::index::{Column, Line, Point};
+use crate::selection::SelectionRange;
+
+/// Damaged columns of a line in the viewport.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LineDamageBounds {
+    /// Viewport line.
+    pub line: Line,
+
+    /// Leftmost damaged column.
+    pub left: Column,
+
+    /// Rightmost damaged column.
+    pub right: Column,
+}
+
+impl LineDamageBounds {
+    #[inline]
+    pub fn new(line: Line, left: Column, right: Column) -> Self {
+        Self { line, left, right }
+    }
+
+    /// Bounds of a line without any damage.
+    #[inline]
+    pub fn undamaged(line: Line, num_cols: Column) -> Self {
+        Self { line, left: num_cols, right: Column(0) }
+    }
+
+    /// Grow the bounds to include all columns from `left` to `right`.
+    #[inline]
+    pub fn expand(&mut self, left: Column, right: Column) {
+        self.left = min(self.left, left);
+        self.right = max(self.right, right);
+    }
+
+    #[inline]
+    pub fn is_damaged(&self) -> bool {
+        self.left <= self.right
+    }
+}
+
+/// Terminal damage since the last call to `Term::reset_damage`.
+#[derive(Debug)]
+pub enum TermDamage<'a> {
+    /// The entire viewport needs to be redrawn.
+    Full,
+
+    /// Only the lines yielded by the iterator need to be redrawn.
+    Partial(TermDamageIterator<'a>),
+}
+
+/// Iterator over the damaged lines of the viewport.
+#[derive(Debug, Clone)]
+pub struct TermDamageIterator<'a> {
+    lines: slice::Iter<'a, LineDamageBounds>,
+}
+
+impl<'a> Iterator for TermDamageIterator<'a> {
+    type Item = LineDamageBounds;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.lines.find(|line| line.is_damaged()).copied()
+    }
+}
+
+/// Damage accumulated by the terminal between two frames.
+#[derive(Debug)]
+pub(crate) struct TermDamageState {
+    /// Everything needs to be redrawn, ignoring the damage of individual lines.
+    pub is_fully_damaged: bool,
+
+    /// Damage of each line in the viewport.
+    lines: Vec<LineDamageBounds>,
+
+    /// Viewport position of the cursor during the last frame.
+    pub last_cursor: Point,
+
+    /// Viewport position of the vi mode cursor during the last frame.
+    pub last_vi_cursor: Option<Point>,
+
+    /// Visible selection during the last frame.
+    pub last_selection: Option<SelectionRange<Line>>,
+
+    /// Display offset during the last frame.
+    pub last_display_offset: usize,
+}
+
+impl TermDamageState {
+    pub fn new(num_lines: Line, num_cols: Column) -> Self {
+        let lines = (0..num_lines.0).map(|line| LineDamageBounds::undamaged(Line(line), num_cols));
+
+        Self {
+            is_fully_damaged: true,
+            lines: lines.collect(),
+            last_cursor: Point::default(),
+            last_vi_cursor: None,
+            last_selection: None,
+            last_display_offset: 0,
+        }
+    }
+
+    /// Damaged lines, without accounting for full damage.
+    pub fn iter(&self) -> TermDamageIterator<'_> {
+        TermDamageIterator { lines: self.lines.iter() }
+    }
+
+    /// Damage the columns from `left` to `right` in a viewport line.
+    ///
+    /// Lines outside of the viewport are ignored.
+    #[inline]
+    pub fn damage_line(&mut self, line: usize, left: Column, right: Column) {
+        if let Some(bounds) = self.lines.get_mut(line) {
+            bounds.expand(left, right);
+        }
+    }
+
+    /// Damage every line touched by a selection in the viewport.
+    pub fn damage_selection(&mut self, selection: &SelectionRange<Line>, num_cols: Column) {
+        for line in selection.start.line.0..=selection.end.line.0 {
+            self.damage_line(line, Column(0), num_cols - 1);
+        }
+    }
+
+    /// Remove all damage.
+    pub fn reset(&mut self, num_cols: Column) {
+        self.is_fully_damaged = false;
+        for bounds in &mut self.lines {
+            *bounds = LineDamageBounds::undamaged(bounds.line, num_cols);
+        }
+    }
+
+    /// Discard all damage after the dimensions changed.
+    pub fn resize(&mut self, num_lines: Line, num_cols: Column) {
+        *self = Self::new(num_lines, num_cols);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_damage_bounds() {
+        let mut bounds = LineDamageBounds::undamaged(Line(0), Column(10));
+        assert!(!bounds.is_damaged());
+
+        bounds.expand(Column(5), Column(5));
+        assert_eq!(bounds, LineDamageBounds::new(Line(0), Column(5), Column(5)));
+
+        bounds.expand(Column(2), Column(3));
+        bounds.expand(Column(7), Column(8));
+        assert_eq!(bounds, LineDamageBounds::new(Line(0), Column(2), Column(8)));
+    }
+
+    #[test]
+    fn damage_state() {
+        let mut damage = TermDamageState::new(Line(5), Column(10));
+        assert!(damage.is_fully_damaged);
+
+        damage.reset(Column(10));
+        assert!(!damage.is_fully_damaged);
+        assert_eq!(damage.iter().next(), None);
+
+        damage.damage_line(3, Column(4), Column(4));
+        damage.damage_line(1, Column(0), Column(2));
+        damage.damage_line(5, Column(0), Column(9));
+
+        let lines: Vec<_> = damage.iter().collect();
+        assert_eq!(
+            lines,
+            vec![
+                LineDamageBounds::new(Line(1), Column(0), Column(2)),
+                LineDamageBounds::new(Line(3), Column(4), Column(4)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,514 @@
+//! Export of terminal content with its colors and text attributes.
+
+use crate::ansi::{Color, NamedColor};
+use crate::grid::Dimensions;
+use crate::index::{Column, Point};
+use crate::term::cell::{Cell, Flags, Hyperlink};
+use crate::term::color::{Colors, Rgb, COUNT};
+use crate::term::Term;
+
+/// URI schemes which are exported as HTML links.
+const LINK_SCHEMES: [&str; 4] = ["http", "https", "mailto", "file"];
+
+/// Format of exported terminal content.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Plain text without any attributes.
+    Text,
+
+    /// Text with SGR escape sequences for colors and attributes.
+    Ansi,
+
+    /// HTML with inline styles.
+    Html,
+}
+
+impl<T> Term<T> {
+    /// Export the active selection.
+    ///
+    /// Colors which have not been changed by the application are taken from `palette`.
+    pub fn export_selection(&self, format: ExportFormat, palette: &[Rgb; COUNT]) -> Option<String> {
+        let selection_range = self.selection.as_ref().and_then(|s| s.to_range(self))?;
+        Some(self.export(format, palette, |writer| self.write_selection(writer, selection_range)))
+    }
+
+    /// Export the range between two points.
+    pub fn export_bounds(
+        &self,
+        start: Point<usize>,
+        end: Point<usize>,
+        format: ExportFormat,
+        palette: &[Rgb; COUNT],
+    ) -> String {
+        self.export(format, palette, |writer| self.write_bounds(writer, start, end))
+    }
+
+    /// Export the scrollback history and all lines up to the cursor.
+    pub fn export_buffer(&self, format: ExportFormat, palette: &[Rgb; COUNT]) -> String {
+        let cursor_line = self.screen_lines().0 - 1 - self.grid.cursor.point.line.0;
+        let start = Point::new(self.total_lines() - 1, Column(0));
+        let end = Point::new(cursor_line, self.cols() - 1);
+        self.export_bounds(start, end, format, palette)
+    }
+
+    fn export<F>(&self, format: ExportFormat, palette: &[Rgb; COUNT], write: F) -> String
+    where
+        F: FnOnce(&mut dyn ContentWriter),
+    {
+        match format {
+            ExportFormat::Text => {
+                let mut text = String::new();
+                write(&mut text);
+                text
+            },
+            ExportFormat::Ansi => {
+                let mut writer = AnsiWriter::default();
+                write(&mut writer);
+                writer.finish()
+            },
+            ExportFormat::Html => {
+                let mut writer = HtmlWriter::new(palette, &self.colors);
+                write(&mut writer);
+                writer.finish()
+            },
+        }
+    }
+}
+
+/// Receiver for the characters of terminal content.
+pub(crate) trait ContentWriter {
+    /// Add a character with the attributes of its cell.
+    fn push(&mut self, cell: &Cell, c: char);
+
+    /// Add a line break.
+    fn push_newline(&mut self);
+}
+
+impl ContentWriter for String {
+    #[inline]
+    fn push(&mut self, _cell: &Cell, c: char) {
+        String::push(self, c);
+    }
+
+    #[inline]
+    fn push_newline(&mut self) {
+        String::push(self, '\n');
+    }
+}
+
+/// Attributes shared by consecutive characters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Style {
+    fg: Color,
+    bg: Color,
+    flags: Flags,
+    underline_color: Option<Color>,
+    hyperlink: Option<Hyperlink>,
+}
+
+impl Style {
+    fn new(cell: &Cell) -> Self {
+        let flags = cell.flags
+            & (Flags::BOLD
+                | Flags::DIM
+                | Flags::ITALIC
+                | Flags::ALL_UNDERLINES
+                | Flags::INVERSE
+                | Flags::HIDDEN
+                | Flags::STRIKEOUT);
+
+        // Underline colors are only visible on underlined text.
+        let underline_color =
+            cell.underline_color().filter(|_| flags.intersects(Flags::ALL_UNDERLINES));
+
+        Self { fg: cell.fg, bg: cell.bg, flags, underline_color, hyperlink: cell.hyperlink() }
+    }
+
+    /// Check if the style has no visible effect.
+    fn is_default(&self) -> bool {
+        self.fg == Color::Named(NamedColor::Foreground)
+            && self.bg == Color::Named(NamedColor::Background)
+            && self.flags.is_empty()
+            && self.hyperlink.is_none()
+    }
+}
+
+/// Writer for text with SGR escape sequences.
+#[derive(Default)]
+struct AnsiWriter {
+    text: String,
+    style: Option<Style>,
+}
+
+impl AnsiWriter {
+    /// Reset all attributes.
+    fn reset(&mut self) {
+        let style = match self.style.take() {
+            Some(style) if !style.is_default() => style,
+            _ => return,
+        };
+
+        if style.hyperlink.is_some() {
+            self.text.push_str("\x1b]8;;\x1b\\");
+        }
+
+        self.text.push_str("\x1b[0m");
+    }
+
+    fn finish(mut self) -> String {
+        self.reset();
+        self.text
+    }
+}
+
+impl ContentWriter for AnsiWriter {
+    fn push(&mut self, cell: &Cell, c: char) {
+        let style = Style::new(cell);
+        if self.style.as_ref() != Some(&style) {
+            self.reset();
+
+            if let Some(hyperlink) = &style.hyperlink {
+                self.text.push_str(&format!(
+                    "\x1b]8;id={};{}\x1b\\",
+                    hyperlink.id(),
+                    hyperlink.uri()
+                ));
+            }
+
+            let mut params = Vec::new();
+            for (flag, param) in &[
+                (Flags::BOLD, "1"),
+                (Flags::DIM, "2"),
+                (Flags::ITALIC, "3"),
+                (Flags::UNDERLINE, "4"),
+                (Flags::INVERSE, "7"),
+                (Flags::HIDDEN, "8"),
+                (Flags::STRIKEOUT, "9"),
+                (Flags::DOUBLE_UNDERLINE, "21"),
+                (Flags::UNDERCURL, "4:3"),
+                (Flags::DOTTED_UNDERLINE, "4:4"),
+                (Flags::DASHED_UNDERLINE, "4:5"),
+            ] {
+                if style.flags.contains(*flag) {
+                    params.push((*param).to_owned());
+                }
+            }
+            params.extend(sgr_color(style.fg, 30));
+            params.extend(sgr_color(style.bg, 40));
+
+            // Underline colors have no short form, named colors use their palette index instead.
+            let underline_color = match style.underline_color {
+                Some(Color::Named(color)) if (color as usize) < 16 => {
+                    Some(Color::Indexed(color as u8))
+                },
+                Some(Color::Named(_)) => None,
+                color => color,
+            };
+            params.extend(underline_color.and_then(|color| sgr_color(color, 50)));
+
+            if !params.is_empty() {
+                self.text.push_str(&format!("\x1b[{}m", params.join(";")));
+            }
+
+            self.style = Some(style);
+        }
+
+        self.text.push(c);
+    }
+
+    fn push_newline(&mut self) {
+        self.reset();
+        self.text.push('\n');
+    }
+}
+
+/// SGR parameters for a color, with `base` being the parameter of the first indexed color.
+///
+/// Default colors don't require any parameters, since attributes are always reset first.
+fn sgr_color(color: Color, base: u8) -> Option<String> {
+    match color {
+        Color::Spec(Rgb { r, g, b }) => Some(format!("{};2;{};{};{}", base + 8, r, g, b)),
+        Color::Indexed(index) => Some(format!("{};5;{}", base + 8, index)),
+        Color::Named(color) => {
+            // Dim colors are represented by the DIM flag instead.
+            let index = match color as usize {
+                index @ 0..=15 => index,
+                _ => color.to_bright() as usize,
+            };
+
+            match index {
+                0..=7 => Some((base as usize + index).to_string()),
+                8..=15 => Some((base as usize + 60 + index - 8).to_string()),
+                _ => None,
+            }
+        },
+    }
+}
+
+/// Writer for HTML with inline styles.
+struct HtmlWriter<'a> {
+    text: String,
+    style: Option<Style>,
+    palette: &'a [Rgb; COUNT],
+    colors: &'a Colors,
+}
+
+impl<'a> HtmlWriter<'a> {
+    fn new(palette: &'a [Rgb; COUNT], colors: &'a Colors) -> Self {
+        let mut writer = Self { text: String::new(), style: None, palette, colors };
+
+        let fg = writer.color(NamedColor::Foreground as usize);
+        let bg = writer.color(NamedColor::Background as usize);
+        writer.text = format!("<pre style=\"color:{};background-color:{}\">", css(fg), css(bg));
+
+        writer
+    }
+
+    /// Current RGB value of a color index.
+    fn color(&self, index: usize) -> Rgb {
+        self.colors[index].unwrap_or(self.palette[index])
+    }
+
+    fn rgb(&self, color: Color, flags: Flags) -> Rgb {
+        match color {
+            Color::Spec(rgb) => rgb,
+            Color::Named(color) if flags.contains(Flags::DIM) => {
+                self.color(color.to_dim() as usize)
+            },
+            Color::Named(color) => self.color(color as usize),
+            Color::Indexed(index @ 0..=7) if flags.contains(Flags::DIM) => {
+                self.color(NamedColor::DimBlack as usize + index as usize)
+            },
+            Color::Indexed(index) => self.color(index as usize),
+        }
+    }
+
+    /// Close the elements of the current style.
+    fn close(&mut self) {
+        let style = match self.style.take() {
+            Some(style) if !style.is_default() => style,
+            _ => return,
+        };
+
+        self.text.push_str("</span>");
+        if link_uri(&style).is_some() {
+            self.text.push_str("</a>");
+        }
+    }
+
+    /// Open the elements for a new style.
+    fn open(&mut self, style: &Style) {
+        if let Some(uri) = link_uri(style) {
+            self.text.push_str(&format!("<a href=\"{}\">", escape(uri)));
+        }
+
+        let mut fg = self.rgb(style.fg, style.flags);
+        let mut bg = self.rgb(style.bg, Flags::empty());
+        if style.flags.contains(Flags::INVERSE) {
+            std::mem::swap(&mut fg, &mut bg);
+        }
+        if style.flags.contains(Flags::HIDDEN) {
+            fg = bg;
+        }
+
+        let mut css_style = format!("color:{}", css(fg));
+        if style.bg != Color::Named(NamedColor::Background) || style.flags.contains(Flags::INVERSE)
+        {
+            css_style.push_str(&format!(";background-color:{}", css(bg)));
+        }
+        if style.flags.contains(Flags::BOLD) {
+            css_style.push_str(";font-weight:bold");
+        }
+        if style.flags.contains(Flags::ITALIC) {
+            css_style.push_str(";font-style:italic");
+        }
+
+        let mut decorations = Vec::new();
+        if style.flags.contains(Flags::DOUBLE_UNDERLINE) {
+            decorations.push("underline double");
+        } else if style.flags.contains(Flags::UNDERCURL) {
+            decorations.push("underline wavy");
+        } else if style.flags.contains(Flags::DOTTED_UNDERLINE) {
+            decorations.push("underline dotted");
+        } else if style.flags.contains(Flags::DASHED_UNDERLINE) {
+            decorations.push("underline dashed");
+        } else if style.flags.contains(Flags::UNDERLINE) {
+            decorations.push("underline");
+        }
+        if style.flags.contains(Flags::STRIKEOUT) {
+            decorations.push("line-through");
+        }
+        if !decorations.is_empty() {
+            css_style.push_str(&format!(";text-decoration:{}", decorations.join(" ")));
+        }
+        if let Some(color) = style.underline_color {
+            let color = self.rgb(color, Flags::empty());
+            css_style.push_str(&format!(";text-decoration-color:{}", css(color)));
+        }
+
+        self.text.push_str(&format!("<span style=\"{}\">", css_style));
+    }
+
+    fn finish(mut self) -> String {
+        self.close();
+        self.text.push_str("</pre>");
+        self.text
+    }
+}
+
+impl<'a> ContentWriter for HtmlWriter<'a> {
+    fn push(&mut self, cell: &Cell, c: char) {
+        let style = Style::new(cell);
+        if self.style.as_ref() != Some(&style) {
+            self.close();
+            if !style.is_default() {
+                self.open(&style);
+            }
+            self.style = Some(style);
+        }
+
+        match c {
+            '<' => self.text.push_str("&lt;"),
+            '>' => self.text.push_str("&gt;"),
+            '&' => self.text.push_str("&amp;"),
+            c => self.text.push(c),
+        }
+    }
+
+    fn push_newline(&mut self) {
+        self.text.push('\n');
+    }
+}
+
+/// Hyperlink target of a style, if it can be safely shared as an HTML link.
+///
+/// Since exported HTML is opened by other people, only URIs with schemes which cannot run any code
+/// are turned into links. Everything else is exported as plain text.
+fn link_uri(style: &Style) -> Option<&str> {
+    let uri = style.hyperlink.as_ref()?.uri();
+    let scheme = &uri[..uri.find(':')?];
+    LINK_SCHEMES.iter().find(|allowed| allowed.eq_ignore_ascii_case(scheme)).map(|_| uri)
+}
+
+/// CSS representation of a color.
+fn css(rgb: Rgb) -> String {
+    format!("#{:02x}{:02x}{:02x}", rgb.r, rgb.g, rgb.b)
+}
+
+/// Escape text for use in HTML attributes.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('"', "&quot;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::term::test::mock_term;
+
+    fn palette() -> [Rgb; COUNT] {
+        let mut palette = [Rgb::default(); COUNT];
+        palette[NamedColor::Red as usize] = Rgb { r: 255, g: 0, b: 0 };
+        palette[NamedColor::Foreground as usize] = Rgb { r: 255, g: 255, b: 255 };
+        palette
+    }
+
+    fn styled_term() -> Term<()> {
+        let mut term = mock_term("a<b\r\nc");
+
+        term.grid[1][Column(1)].fg = Color::Named(NamedColor::Red);
+        term.grid[1][Column(1)].flags.insert(Flags::BOLD);
+        term.grid[0][Column(0)].bg = Color::Spec(Rgb { r: 1, g: 2, b: 3 });
+        term.grid[0][Column(0)].set_hyperlink(Some(Hyperlink::new(Some("1"), "https://a.b")));
+
+        term
+    }
+
+    #[test]
+    fn export_text() {
+        let term = styled_term();
+
+        let start = Point::new(1, Column(0));
+        let end = Point::new(0, Column(2));
+        let text = term.export_bounds(start, end, ExportFormat::Text, &palette());
+        assert_eq!(text, "a<b\nc\n");
+    }
+
+    #[test]
+    fn export_ansi() {
+        let term = styled_term();
+
+        let start = Point::new(1, Column(0));
+        let end = Point::new(0, Column(2));
+        let text = term.export_bounds(start, end, ExportFormat::Ansi, &palette());
+        assert_eq!(
+            text,
+            "a\x1b[1;31m<\x1b[0mb\n\x1b]8;id=1;https://a.b\x1b\\\x1b[48;2;1;2;3mc\x1b]8;;\x1b\\\x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn export_html() {
+        let term = styled_term();
+
+        let start = Point::new(1, Column(0));
+        let end = Point::new(1, Column(2));
+        let text = term.export_bounds(start, end, ExportFormat::Html, &palette());
+        assert_eq!(
+            text,
+            "<pre style=\"color:#ffffff;background-color:#000000\">a<span \
+             style=\"color:#ff0000;font-weight:bold\">&lt;</span>b\n</pre>"
+        );
+
+        let start = Point::new(0, Column(0));
+        let end = Point::new(0, Column(0));
+        let text = term.export_bounds(start, end, ExportFormat::Html, &palette());
+        assert_eq!(
+            text,
+            "<pre style=\"color:#ffffff;background-color:#000000\"><a href=\"https://a.b\"><span \
+             style=\"color:#ffffff;background-color:#010203\">c</span></a></pre>"
+        );
+    }
+
+    #[test]
+    fn export_html_unsafe_links() {
+        let mut term = mock_term("ab");
+        let script = Hyperlink::new(Some("1"), "JavaScript:alert(1)");
+        term.grid[0][Column(0)].set_hyperlink(Some(script));
+        let quoted = Hyperlink::new(Some("1"), "https://a.b/\"><script>");
+        term.grid[0][Column(1)].set_hyperlink(Some(quoted));
+
+        let start = Point::new(0, Column(0));
+        let end = Point::new(0, Column(1));
+        let text = term.export_bounds(start, end, ExportFormat::Html, &palette());
+        assert_eq!(
+            text,
+            "<pre style=\"color:#ffffff;background-color:#000000\"><span \
+             style=\"color:#ffffff\">a</span><a \
+             href=\"https://a.b/&quot;&gt;&lt;script&gt;\"><span \
+             style=\"color:#ffffff\">b\n</span></a></pre>"
+        );
+    }
+
+    #[test]
+    fn export_underline_style() {
+        let mut term = mock_term("ab");
+        term.grid[0][Column(0)].flags.insert(Flags::UNDERCURL);
+        term.grid[0][Column(0)].set_underline_color(Some(Color::Named(NamedColor::Red)));
+        term.grid[0][Column(1)].flags.insert(Flags::DOTTED_UNDERLINE);
+
+        let start = Point::new(0, Column(0));
+        let end = Point::new(0, Column(1));
+        let text = term.export_bounds(start, end, ExportFormat::Ansi, &palette());
+        assert_eq!(text, "\x1b[4:3;58;5;1ma\x1b[0m\x1b[4:4mb\x1b[0m\n");
+
+        let text = term.export_bounds(start, end, ExportFormat::Html, &palette());
+        assert_eq!(
+            text,
+            "<pre style=\"color:#ffffff;background-color:#000000\"><span \
+             style=\"color:#ffffff;text-decoration:underline wavy;\
+             text-decoration-color:#ff0000\">a</span><span \
+             style=\"color:#ffffff;text-decoration:underline dotted\">b\n</span></pre>"
+        );
+    }
+}
@@ -2,29 +2,40 @@
 
 use std::cmp::{max, min};
 use std::ops::{Index, IndexMut, Range};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
-use std::{io, mem, ptr, str};
+use std::{fs, io, mem, process, ptr, str};
 
 use bitflags::bitflags;
-use log::{debug, trace};
+use log::{debug, error, trace};
 use serde::{Deserialize, Serialize};
 use unicode_width::UnicodeWidthChar;
 
 use crate::ansi::{
-    self, Attr, CharsetIndex, Color, CursorShape, CursorStyle, Handler, NamedColor, StandardCharset,
+    self, Attr, CharsetIndex, Color, CursorShape, CursorStyle, Handler, KeyboardModes,
+    KeyboardModesApplyBehavior, NamedColor, PromptMark, StandardCharset, StatusRequest,
 };
-use crate::config::Config;
+use crate::config::{Config, DiskScrolling};
 use crate::event::{Event, EventListener};
-use crate::grid::{Dimensions, DisplayIter, Grid, Scroll};
+use crate::graphics::{GraphicCell, GraphicData, Graphics, UpdateQueues};
+use crate::grid::{Dimensions, DiskHistory, DisplayIter, Grid, PromptMarks, Scroll};
 use crate::index::{self, Boundary, Column, Direction, IndexRange, Line, Point, Side};
-use crate::selection::{Selection, SelectionRange};
-use crate::term::cell::{Cell, Flags, LineLength};
+use crate::selection::{Selection, SelectionMotion, SelectionRange, SelectionType};
+use crate::term::cell::{Cell, Flags, Hyperlink, LineLength};
 use crate::term::color::{Colors, Rgb};
-use crate::vi_mode::{ViModeCursor, ViMotion};
+use crate::term::damage::{TermDamage, TermDamageState};
+use crate::term::export::ContentWriter;
+use crate::term::terminfo::Capability;
+use crate::vi_mode::{JumpList, ViModeCursor, ViMotion};
 
 pub mod cell;
 pub mod color;
+pub mod damage;
+pub mod export;
+pub mod prompt;
 pub mod search;
+pub mod terminfo;
 
 /// Minimum number of columns.
 ///
@@ -37,6 +48,9 @@ pub const MIN_SCREEN_LINES: usize = 1;
 /// Max size of the window title stack.
 const TITLE_STACK_MAX_DEPTH: usize = 4096;
 
+/// Max size of the keyboard modes stack.
+const KEYBOARD_MODE_STACK_MAX_DEPTH: usize = TITLE_STACK_MAX_DEPTH;
+
 /// Default tab interval, corresponding to terminfo `it` value.
 const INITIAL_TABSTOPS: usize = 8;
 
@@ -62,6 +76,8 @@ bitflags! {
         const ALTERNATE_SCROLL    = 0b0000_1000_0000_0000_0000;
         const VI                  = 0b0001_0000_0000_0000_0000;
         const URGENCY_HINTS       = 0b0010_0000_0000_0000_0000;
+        const SYNC_UPDATE         = 0b0100_0000_0000_0000_0000;
+        const LEFT_RIGHT_MARGIN   = 0b1000_0000_0000_0000_0000;
         const ANY                 = std::u32::MAX;
     }
 }
@@ -249,6 +265,12 @@ pub struct Term<T> {
     /// Range going from top to bottom of the terminal, indexed from the top of the viewport.
     scroll_region: Range<Line>,
 
+    /// Left and right margins.
+    ///
+    /// Range going from left to right, covering all columns unless left and right margin mode is
+    /// enabled.
+    horizontal_margins: Range<Column>,
+
     semantic_escape_chars: String,
 
     /// Modified terminal colors.
@@ -276,6 +298,70 @@ pub struct Term<T> {
     /// Information about cell dimensions.
     cell_width: usize,
     cell_height: usize,
+
+    /// Graphics displayed in the grid.
+    graphics: Graphics,
+
+    /// Active keyboard protocol modes.
+    keyboard_modes: KeyboardModes,
+
+    /// Stack of keyboard protocol modes for the active screen.
+    keyboard_mode_stack: Vec<KeyboardModes>,
+
+    /// Stack of keyboard protocol modes for the inactive screen.
+    inactive_keyboard_mode_stack: Vec<KeyboardModes>,
+
+    /// Scrollback history stored on disk.
+    disk_scrolling: DiskScrolling,
+
+    /// Regions of the viewport which changed since the last frame.
+    damage: TermDamageState,
+}
+
+/// Name of the history file restored by the next terminal.
+const RESTORE_HISTORY_FILE: &str = "scrollback";
+
+/// Counter for unique history file names within the process.
+static HISTORY_FILE_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Directory storing scrollback history files.
+fn history_dir() -> Option<PathBuf> {
+    let dir = dirs::cache_dir()?.join("alacritty");
+    match fs::create_dir_all(&dir) {
+        Ok(()) => Some(dir),
+        Err(err) => {
+            error!("Unable to create scrollback history directory: {}", err);
+            None
+        },
+    }
+}
+
+/// Create the disk history for a new terminal.
+fn disk_history(config: DiskScrolling, cols: Column) -> Option<DiskHistory<Cell>> {
+    if config.history == 0 {
+        return None;
+    }
+
+    let dir = history_dir()?;
+    let count = HISTORY_FILE_COUNT.fetch_add(1, Ordering::Relaxed);
+    let path = dir.join(format!("scrollback-{}-{}", process::id(), count));
+    let max_lines = config.history as usize;
+
+    // Claim the history of the last terminal, so it's only restored once.
+    if config.restore && fs::rename(dir.join(RESTORE_HISTORY_FILE), &path).is_ok() {
+        match DiskHistory::open(path.clone(), max_lines, cols) {
+            Ok(history) => return Some(history),
+            Err(err) => error!("Unable to restore scrollback history: {}", err),
+        }
+    }
+
+    match DiskHistory::create(path, max_lines, cols) {
+        Ok(history) => Some(history),
+        Err(err) => {
+            error!("Unable to create scrollback history: {}", err);
+            None
+        },
+    }
 }
 
 impl<T> Term<T> {
@@ -288,17 +374,29 @@ impl<T> Term<T> {
         self.event_proxy.send_event(Event::MouseCursorDirty);
     }
 
+    /// Free the lines of the disk history decompressed while reading the terminal content.
+    #[inline]
+    pub fn shrink_disk_cache(&mut self) {
+        self.grid.shrink_disk_cache();
+    }
+
     pub fn new<C>(config: &Config<C>, size: SizeInfo, event_proxy: T) -> Term<T> {
         let num_cols = size.cols;
         let num_lines = size.screen_lines;
 
         let history_size = config.scrolling.history() as usize;
-        let grid = Grid::new(num_lines, num_cols, history_size);
+        let mut grid = Grid::new(num_lines, num_cols, history_size);
         let alt = Grid::new(num_lines, num_cols, 0);
 
+        let disk_scrolling = config.scrolling.disk;
+        if history_size > 0 {
+            grid.set_disk_history(disk_history(disk_scrolling, num_cols));
+        }
+
         let tabs = TabStops::new(grid.cols());
 
         let scroll_region = Line(0)..grid.screen_lines();
+        let horizontal_margins = Column(0)..grid.cols();
 
         Term {
             grid,
@@ -308,6 +406,7 @@ impl<T> Term<T> {
             tabs,
             mode: Default::default(),
             scroll_region,
+            horizontal_margins,
             colors: color::Colors::default(),
             semantic_escape_chars: config.selection.semantic_escape_chars.to_owned(),
             cursor_style: None,
@@ -320,6 +419,12 @@ impl<T> Term<T> {
             selection: None,
             cell_width: size.cell_width as usize,
             cell_height: size.cell_height as usize,
+            graphics: Graphics::default(),
+            keyboard_modes: Default::default(),
+            keyboard_mode_stack: Vec::new(),
+            inactive_keyboard_mode_stack: Vec::new(),
+            disk_scrolling,
+            damage: TermDamageState::new(num_lines, num_cols),
         }
     }
 
@@ -338,60 +443,101 @@ impl<T> Term<T> {
 
         self.event_proxy.send_event(title_event);
 
-        if self.mode.contains(TermMode::ALT_SCREEN) {
-            self.inactive_grid.update_history(config.scrolling.history() as usize);
+        let grid = if self.mode.contains(TermMode::ALT_SCREEN) {
+            &mut self.inactive_grid
         } else {
-            self.grid.update_history(config.scrolling.history() as usize);
+            &mut self.grid
+        };
+
+        let history_size = config.scrolling.history() as usize;
+        grid.update_history(history_size);
+
+        // Only restore history on startup.
+        let disk_scrolling = config.scrolling.disk;
+        if disk_scrolling.history == 0 || history_size == 0 {
+            grid.set_disk_history(None);
+        } else if grid.disk_history().is_none() {
+            let disk_config = DiskScrolling { restore: false, ..disk_scrolling };
+            grid.set_disk_history(disk_history(disk_config, grid.cols()));
+        } else {
+            grid.update_disk_history(disk_scrolling.history as usize);
+        }
+        self.disk_scrolling = disk_scrolling;
+    }
+
+    /// Store the scrollback history, so it can be restored by the next terminal.
+    pub fn persist_history(&mut self) {
+        if !self.disk_scrolling.restore {
+            return;
+        }
+
+        let grid = if self.mode.contains(TermMode::ALT_SCREEN) {
+            &mut self.inactive_grid
+        } else {
+            &mut self.grid
+        };
+
+        if let Some(dir) = history_dir() {
+            if let Err(err) = grid.persist_history(&dir.join(RESTORE_HISTORY_FILE)) {
+                error!("Unable to store scrollback history: {}", err);
+            }
         }
     }
 
     /// Convert the active selection to a String.
     pub fn selection_to_string(&self) -> Option<String> {
         let selection_range = self.selection.as_ref().and_then(|s| s.to_range(self))?;
-        let SelectionRange { start, end, is_block } = selection_range;
 
-        let mut res = String::new();
+        let mut text = String::new();
+        self.write_selection(&mut text, selection_range);
+
+        Some(text)
+    }
+
+    /// Convert range between two points to a String.
+    pub fn bounds_to_string(&self, start: Point<usize>, end: Point<usize>) -> String {
+        let mut text = String::new();
+        self.write_bounds(&mut text, start, end);
+        text
+    }
+
+    /// Write the content of a selection.
+    fn write_selection(&self, writer: &mut dyn ContentWriter, selection_range: SelectionRange) {
+        let SelectionRange { start, end, is_block } = selection_range;
 
         if is_block {
             for line in (end.line + 1..=start.line).rev() {
-                res += &self.line_to_string(line, start.column..end.column, start.column.0 != 0);
+                self.write_line(writer, line, start.column..end.column, start.column.0 != 0);
 
                 // If the last column is included, newline is appended automatically.
                 if end.column != self.cols() - 1 {
-                    res += "\n";
+                    writer.push_newline();
                 }
             }
-            res += &self.line_to_string(end.line, start.column..end.column, true);
+            self.write_line(writer, end.line, start.column..end.column, true);
         } else {
-            res = self.bounds_to_string(start, end);
+            self.write_bounds(writer, start, end);
         }
-
-        Some(res)
     }
 
-    /// Convert range between two points to a String.
-    pub fn bounds_to_string(&self, start: Point<usize>, end: Point<usize>) -> String {
-        let mut res = String::new();
-
+    /// Write the content of the range between two points.
+    fn write_bounds(&self, writer: &mut dyn ContentWriter, start: Point<usize>, end: Point<usize>) {
         for line in (end.line..=start.line).rev() {
             let start_col = if line == start.line { start.column } else { Column(0) };
             let end_col = if line == end.line { end.column } else { self.cols() - 1 };
 
-            res += &self.line_to_string(line, start_col..end_col, line == end.line);
+            self.write_line(writer, line, start_col..end_col, line == end.line);
         }
-
-        res
     }
 
-    /// Convert a single line in the grid to a String.
-    fn line_to_string(
+    /// Write the content of a single line in the grid.
+    fn write_line(
         &self,
+        writer: &mut dyn ContentWriter,
         line: usize,
         mut cols: Range<Column>,
         include_wrapped_wide: bool,
-    ) -> String {
-        let mut text = String::new();
-
+    ) {
         let grid_line = &self.grid[line];
         let line_length = min(grid_line.line_length(), cols.end + 1);
 
@@ -419,11 +565,11 @@ impl<T> Term<T> {
 
             if !cell.flags.intersects(Flags::WIDE_CHAR_SPACER | Flags::LEADING_WIDE_CHAR_SPACER) {
                 // Push cells primary character.
-                text.push(cell.c);
+                writer.push(cell, cell.c);
 
                 // Push zero-width characters.
                 for c in cell.zerowidth().into_iter().flatten() {
-                    text.push(*c);
+                    writer.push(cell, *c);
                 }
             }
         }
@@ -432,7 +578,7 @@ impl<T> Term<T> {
             && (line_length.0 == 0
                 || !self.grid[line][line_length - 1].flags.contains(Flags::WRAPLINE))
         {
-            text.push('\n');
+            writer.push_newline();
         }
 
         // If wide char is not part of the selection, but leading spacer is, include it.
@@ -441,10 +587,9 @@ impl<T> Term<T> {
             && grid_line[line_length - 1].flags.contains(Flags::LEADING_WIDE_CHAR_SPACER)
             && include_wrapped_wide
         {
-            text.push(self.grid[line - 1][Column(0)].c);
+            let cell = &self.grid[line - 1][Column(0)];
+            writer.push(cell, cell.c);
         }
-
-        text
     }
 
     #[inline]
@@ -510,17 +655,19 @@ impl<T> Term<T> {
             self.selection = selection.rotate(self, &(Line(0)..num_lines), delta);
         }
 
-        let is_alt = self.mode.contains(TermMode::ALT_SCREEN);
-
-        self.grid.resize(!is_alt, num_lines, num_cols);
-        self.inactive_grid.resize(is_alt, num_lines, num_cols);
+        // Reflow both screens, to keep their wrapped lines intact when switching between them.
+        self.grid.resize(true, num_lines, num_cols);
+        self.inactive_grid.resize(true, num_lines, num_cols);
 
         // Clamp vi cursor to viewport.
         self.vi_mode_cursor.point.column = min(self.vi_mode_cursor.point.column, num_cols - 1);
         self.vi_mode_cursor.point.line = min(self.vi_mode_cursor.point.line, num_lines - 1);
 
-        // Reset scrolling region.
+        // Reset scrolling region and margins.
         self.scroll_region = Line(0)..self.screen_lines();
+        self.horizontal_margins = Column(0)..num_cols;
+
+        self.damage.resize(num_lines, num_cols);
     }
 
     /// Active terminal modes.
@@ -529,6 +676,12 @@ impl<T> Term<T> {
         &self.mode
     }
 
+    /// Active keyboard protocol modes.
+    #[inline]
+    pub fn keyboard_modes(&self) -> KeyboardModes {
+        self.keyboard_modes
+    }
+
     /// Swap primary and alternate screen buffer.
     pub fn swap_alt(&mut self) {
         if !self.mode.contains(TermMode::ALT_SCREEN) {
@@ -545,6 +698,11 @@ impl<T> Term<T> {
         mem::swap(&mut self.grid, &mut self.inactive_grid);
         self.mode ^= TermMode::ALT_SCREEN;
         self.selection = None;
+        self.mark_fully_damaged();
+
+        // Both screens keep track of their own keyboard modes.
+        mem::swap(&mut self.keyboard_mode_stack, &mut self.inactive_keyboard_mode_stack);
+        self.keyboard_modes = self.keyboard_mode_stack.last().copied().unwrap_or_default();
     }
 
     /// Get the selection within the viewport.
@@ -577,12 +735,21 @@ impl<T> Term<T> {
     fn scroll_down_relative(&mut self, origin: Line, mut lines: Line) {
         trace!("Scrolling down relative: origin={}, lines={}", origin, lines);
 
+        self.mark_fully_damaged();
+
         let num_lines = self.screen_lines();
 
         lines = min(lines, self.scroll_region.end - self.scroll_region.start);
         lines = min(lines, self.scroll_region.end - origin);
 
         let region = origin..self.scroll_region.end;
+
+        // Only move the cells within the left and right margins.
+        if self.has_horizontal_margins() {
+            self.scroll_within_margins(region, lines, Direction::Right);
+            return;
+        }
+
         let absolute_region = (num_lines - region.end)..(num_lines - region.start);
 
         // Scroll selection.
@@ -603,11 +770,20 @@ impl<T> Term<T> {
     fn scroll_up_relative(&mut self, origin: Line, mut lines: Line) {
         trace!("Scrolling up relative: origin={}, lines={}", origin, lines);
 
+        self.mark_fully_damaged();
+
         let num_lines = self.screen_lines();
 
         lines = min(lines, self.scroll_region.end - self.scroll_region.start);
 
         let region = origin..self.scroll_region.end;
+
+        // Only move the cells within the left and right margins.
+        if self.has_horizontal_margins() {
+            self.scroll_within_margins(region, lines, Direction::Left);
+            return;
+        }
+
         let absolute_region = (num_lines - region.end)..(num_lines - region.start);
 
         // Scroll selection.
@@ -618,16 +794,97 @@ impl<T> Term<T> {
         self.grid.scroll_up(&region, lines);
     }
 
+    /// Scroll the cells within the left and right margins of a region.
+    ///
+    /// Text moves up when scrolling towards [`Direction::Left`] and down otherwise. Since only
+    /// part of each line is moved, nothing is rotated into the history.
+    fn scroll_within_margins(&mut self, region: Range<Line>, lines: Line, direction: Direction) {
+        let columns = self.horizontal_margins.clone();
+        let bg = self.grid.cursor.template.bg;
+
+        let lines = min(lines, region.end - region.start);
+        let moved_lines = (region.end - region.start - lines).0;
+
+        // Copy the cells which are still inside the region after scrolling.
+        for i in 0..moved_lines {
+            let (destination, source) = match direction {
+                Direction::Left => (region.start + i, region.start + i + lines.0),
+                Direction::Right => (region.end - 1 - i, region.end - 1 - i - lines.0),
+            };
+
+            for column in columns.start.0..columns.end.0 {
+                let cell = self.grid[source][Column(column)].clone();
+                self.grid[destination][Column(column)] = cell;
+            }
+        }
+
+        // Clear the cells uncovered by the scrolled text.
+        let cleared = match direction {
+            Direction::Left => (region.end - lines).0..region.end.0,
+            Direction::Right => region.start.0..(region.start + lines).0,
+        };
+        for line in cleared {
+            for cell in &mut self.grid[Line(line)][columns.clone()] {
+                *cell = bg.into();
+            }
+        }
+    }
+
+    /// Check if a mode is currently enabled.
+    fn mode_enabled(&self, mode: &ansi::Mode) -> bool {
+        let flags = match mode {
+            ansi::Mode::BlinkingCursor => {
+                return self.cursor_style.unwrap_or(self.default_cursor_style).blinking;
+            },
+            ansi::Mode::DECCOLM => return false,
+            ansi::Mode::CursorKeys => TermMode::APP_CURSOR,
+            ansi::Mode::Insert => TermMode::INSERT,
+            ansi::Mode::Origin => TermMode::ORIGIN,
+            ansi::Mode::LineWrap => TermMode::LINE_WRAP,
+            ansi::Mode::LineFeedNewLine => TermMode::LINE_FEED_NEW_LINE,
+            ansi::Mode::ShowCursor => TermMode::SHOW_CURSOR,
+            ansi::Mode::LeftRightMargin => TermMode::LEFT_RIGHT_MARGIN,
+            ansi::Mode::ReportMouseClicks => TermMode::MOUSE_REPORT_CLICK,
+            ansi::Mode::ReportCellMouseMotion => TermMode::MOUSE_DRAG,
+            ansi::Mode::ReportAllMouseMotion => TermMode::MOUSE_MOTION,
+            ansi::Mode::ReportFocusInOut => TermMode::FOCUS_IN_OUT,
+            ansi::Mode::Utf8Mouse => TermMode::UTF8_MOUSE,
+            ansi::Mode::SgrMouse => TermMode::SGR_MOUSE,
+            ansi::Mode::AlternateScroll => TermMode::ALTERNATE_SCROLL,
+            ansi::Mode::UrgencyHints => TermMode::URGENCY_HINTS,
+            ansi::Mode::SwapScreenAndSetRestoreCursor => TermMode::ALT_SCREEN,
+            ansi::Mode::BracketedPaste => TermMode::BRACKETED_PASTE,
+            ansi::Mode::SyncUpdate => TermMode::SYNC_UPDATE,
+        };
+
+        self.mode.contains(flags)
+    }
+
+    /// Check if the left and right margins exclude any columns.
+    #[inline]
+    fn has_horizontal_margins(&self) -> bool {
+        self.horizontal_margins != (Column(0)..self.cols())
+    }
+
+    /// Check if a column is within the left and right margins.
+    #[inline]
+    fn in_horizontal_margins(&self, column: Column) -> bool {
+        self.horizontal_margins.contains(&column)
+    }
+
     fn deccolm(&mut self)
     where
         T: EventListener,
     {
         // Setting 132 column font makes no sense, but run the other side effects.
-        // Clear scrolling region.
+        // Clear scrolling region and margins.
         self.set_scrolling_region(1, None);
+        self.mode.remove(TermMode::LEFT_RIGHT_MARGIN);
+        self.horizontal_margins = Column(0)..self.cols();
 
         // Clear grid.
         self.grid.reset_region(..);
+        self.mark_fully_damaged();
     }
 
     #[inline]
@@ -645,6 +902,7 @@ impl<T> Term<T> {
         T: EventListener,
     {
         self.mode ^= TermMode::VI;
+        self.mark_fully_damaged();
 
         if self.mode.contains(TermMode::VI) {
             // Reset vi mode cursor position to match primary cursor.
@@ -688,6 +946,80 @@ impl<T> Term<T> {
         self.vi_mode_recompute_selection();
     }
 
+    /// Move vi cursor to a distant point in the grid, recording the jump in the jump list.
+    #[inline]
+    pub fn vi_jump(&mut self, point: Point<usize>)
+    where
+        T: EventListener,
+    {
+        self.vi_record_jump();
+        self.vi_goto_point(point);
+    }
+
+    /// Store the vi mode cursor position in a mark, like `m` in vi.
+    pub fn vi_set_mark(&mut self, mark: char) {
+        let point = self.visible_to_buffer(self.vi_mode_cursor.point);
+        let point = self.grid.buffer_to_absolute(point);
+        self.grid.vi_marks_mut().set(mark, point);
+    }
+
+    /// Buffer position of a vi mode mark.
+    ///
+    /// Returns `None` if the mark is not set, or its line has been removed from the buffer.
+    pub fn vi_mark(&self, mark: char) -> Option<Point<usize>> {
+        let point = self.grid.vi_marks().get(mark)?;
+        self.grid.absolute_to_buffer(point)
+    }
+
+    /// Record the vi mode cursor position in the jump list.
+    pub fn vi_record_jump(&mut self) {
+        // Require vi mode to be active.
+        if !self.mode.contains(TermMode::VI) {
+            return;
+        }
+
+        let point = self.visible_to_buffer(self.vi_mode_cursor.point);
+        let point = self.grid.buffer_to_absolute(point);
+        self.grid.vi_marks_mut().jumps.push(point);
+    }
+
+    /// Move vi cursor to the previous position in the jump list, like Ctrl-O in vi.
+    pub fn vi_jump_older(&mut self)
+    where
+        T: EventListener,
+    {
+        let point = self.visible_to_buffer(self.vi_mode_cursor.point);
+        let current = self.grid.buffer_to_absolute(point);
+        self.vi_jump_history(|jump_list| jump_list.older(current));
+    }
+
+    /// Move vi cursor to the next position in the jump list, like Ctrl-I in vi.
+    pub fn vi_jump_newer(&mut self)
+    where
+        T: EventListener,
+    {
+        self.vi_jump_history(JumpList::newer);
+    }
+
+    /// Move through the jump list, skipping positions which are no longer in the buffer.
+    fn vi_jump_history<F>(&mut self, mut next: F)
+    where
+        T: EventListener,
+        F: FnMut(&mut JumpList) -> Option<Point<usize>>,
+    {
+        // Require vi mode to be active.
+        if !self.mode.contains(TermMode::VI) {
+            return;
+        }
+
+        while let Some(point) = next(&mut self.grid.vi_marks_mut().jumps) {
+            if let Some(point) = self.grid.absolute_to_buffer(point) {
+                self.vi_goto_point(point);
+                break;
+            }
+        }
+    }
+
     /// Update the active selection to match the vi mode cursor position.
     #[inline]
     fn vi_mode_recompute_selection(&mut self) {
@@ -725,6 +1057,33 @@ impl<T> Term<T> {
         }
     }
 
+    /// Extend the active selection using a keyboard motion.
+    ///
+    /// If there is no active selection, a new selection of type `ty` is started at the cursor.
+    pub fn extend_selection(&mut self, ty: SelectionType, motion: SelectionMotion)
+    where
+        T: EventListener,
+    {
+        let mut selection = match self.selection.take() {
+            Some(selection) => selection,
+            None => {
+                let point = if self.mode.contains(TermMode::VI) {
+                    self.grid.visible_to_buffer(self.vi_mode_cursor.point)
+                } else {
+                    let cursor = self.grid.cursor.point;
+                    Point::new(self.screen_lines().0 - cursor.line.0 - 1, cursor.column)
+                };
+                Selection::new(ty, point, Side::Left)
+            },
+        };
+
+        let end = selection.extend(self, motion);
+        self.selection = Some(selection);
+
+        // Keep the end of the selection visible.
+        self.scroll_to_point(end);
+    }
+
     /// Jump to the end of a wide cell.
     pub fn expand_wide(&self, mut point: Point<usize>, direction: Direction) -> Point<usize> {
         let flags = self.grid[point.line][point.column].flags;
@@ -782,7 +1141,17 @@ impl<T> Term<T> {
 
         trace!("Wrapping input");
 
-        self.grid.cursor_cell().flags.insert(Flags::WRAPLINE);
+        // Rows are only connected when they wrap across the entire width.
+        if !self.has_horizontal_margins() {
+            self.grid.cursor_cell().flags.insert(Flags::WRAPLINE);
+        }
+
+        // Text within the margins continues at the left margin.
+        let column = if self.in_horizontal_margins(self.grid.cursor.point.column) {
+            self.horizontal_margins.start
+        } else {
+            Column(0)
+        };
 
         if (self.grid.cursor.point.line + 1) >= self.scroll_region.end {
             self.linefeed();
@@ -790,10 +1159,81 @@ impl<T> Term<T> {
             self.grid.cursor.point.line += 1;
         }
 
-        self.grid.cursor.point.column = Column(0);
+        self.grid.cursor.point.column = column;
         self.grid.cursor.input_needs_wrap = false;
     }
 
+    /// Graphics added or removed since the last call.
+    #[inline]
+    pub fn graphics_take_updates(&mut self) -> Option<UpdateQueues> {
+        self.graphics.take_updates()
+    }
+
+    /// Regions of the viewport which changed since the last call to [`Term::reset_damage`].
+    ///
+    /// Besides changes to the grid, this accounts for movement of the cursors, changes to the
+    /// selection and scrolling of the viewport.
+    pub fn damage(&mut self) -> TermDamage<'_> {
+        let display_offset = self.grid.display_offset();
+        let cursor = self.grid.cursor.point;
+        let cursor = Point::new(cursor.line + display_offset, cursor.column);
+        let vi_cursor =
+            if self.mode.contains(TermMode::VI) { Some(self.vi_mode_cursor.point) } else { None };
+        let selection = self.visible_selection();
+
+        let last_cursor = mem::replace(&mut self.damage.last_cursor, cursor);
+        let last_vi_cursor = mem::replace(&mut self.damage.last_vi_cursor, vi_cursor);
+        let last_selection = mem::replace(&mut self.damage.last_selection, selection);
+        let last_display_offset =
+            mem::replace(&mut self.damage.last_display_offset, display_offset);
+
+        if self.damage.is_fully_damaged || display_offset != last_display_offset {
+            return TermDamage::Full;
+        }
+
+        // Cursors are always redrawn, since their shape can change without touching the grid.
+        let cursors = [Some(last_cursor), Some(cursor), last_vi_cursor, vi_cursor];
+        for point in cursors.iter().flatten() {
+            self.damage_cursor(*point);
+        }
+
+        if selection != last_selection {
+            let num_cols = self.cols();
+            for selection in selection.iter().chain(&last_selection) {
+                self.damage.damage_selection(selection, num_cols);
+            }
+        }
+
+        TermDamage::Partial(self.damage.iter())
+    }
+
+    /// Clear the damage once it has been drawn.
+    #[inline]
+    pub fn reset_damage(&mut self) {
+        self.damage.reset(self.cols());
+    }
+
+    /// Redraw the entire viewport with the next frame.
+    #[inline]
+    pub fn mark_fully_damaged(&mut self) {
+        self.damage.is_fully_damaged = true;
+    }
+
+    /// Damage columns of a line in the active screen.
+    #[inline]
+    fn damage_line(&mut self, line: Line, left: Column, right: Column) {
+        let line = line.0 + self.grid.display_offset();
+        self.damage.damage_line(line, left, right);
+    }
+
+    /// Damage a cursor in the viewport, including both halves of wide characters.
+    #[inline]
+    fn damage_cursor(&mut self, point: Point) {
+        let left = Column(point.column.saturating_sub(1));
+        let right = min(point.column + 1, self.cols() - 1);
+        self.damage.damage_line(point.line.0, left, right);
+    }
+
     /// Write `c` to the cell at the cursor position.
     #[inline(always)]
     fn write_at_cursor(&mut self, c: char) -> &mut Cell {
@@ -801,6 +1241,11 @@ impl<T> Term<T> {
         let fg = self.grid.cursor.template.fg;
         let bg = self.grid.cursor.template.bg;
         let flags = self.grid.cursor.template.flags;
+        let hyperlink = self.grid.cursor.template.hyperlink();
+        let underline_color = self.grid.cursor.template.underline_color();
+
+        let point = self.grid.cursor.point;
+        self.damage_line(point.line, point.column, point.column);
 
         let cursor_cell = self.grid.cursor_cell();
 
@@ -811,6 +1256,14 @@ impl<T> Term<T> {
         cursor_cell.bg = bg;
         cursor_cell.flags = flags;
 
+        if hyperlink.is_some() {
+            cursor_cell.set_hyperlink(hyperlink);
+        }
+
+        if underline_color.is_some() {
+            cursor_cell.set_underline_color(underline_color);
+        }
+
         cursor_cell
     }
 }
@@ -857,6 +1310,7 @@ impl<T: EventListener> Handler for Term<T> {
             }
 
             self.grid[line][Column(col)].push_zerowidth(c);
+            self.damage_line(line, Column(col), Column(col));
             return;
         }
 
@@ -865,7 +1319,12 @@ impl<T: EventListener> Handler for Term<T> {
             self.wrapline();
         }
 
-        let num_cols = self.cols();
+        // Wrap at the right margin, unless the cursor is already beyond it.
+        let num_cols = if self.grid.cursor.point.column < self.horizontal_margins.end {
+            self.horizontal_margins.end
+        } else {
+            self.cols()
+        };
 
         // If in insert mode, first shift cells to the right.
         if self.mode.contains(TermMode::INSERT) && self.grid.cursor.point.column + width < num_cols
@@ -877,6 +1336,8 @@ impl<T: EventListener> Handler for Term<T> {
             for col in (col.0..(num_cols - width).0).rev() {
                 row.swap(col + width, col);
             }
+
+            self.damage_line(line, col, num_cols - 1);
         }
 
         if width == 1 {
@@ -913,6 +1374,8 @@ impl<T: EventListener> Handler for Term<T> {
     fn decaln(&mut self) {
         trace!("Decalnning");
 
+        self.mark_fully_damaged();
+
         for line in 0..self.screen_lines().0 {
             for column in 0..self.cols().0 {
                 let cell = &mut self.grid[line][Column(column)];
@@ -925,14 +1388,16 @@ impl<T: EventListener> Handler for Term<T> {
     #[inline]
     fn goto(&mut self, line: Line, col: Column) {
         trace!("Going to: line={}, col={}", line, col);
-        let (y_offset, max_y) = if self.mode.contains(TermMode::ORIGIN) {
-            (self.scroll_region.start, self.scroll_region.end - 1)
+        let (offset, max) = if self.mode.contains(TermMode::ORIGIN) {
+            let offset = Point::new(self.scroll_region.start, self.horizontal_margins.start);
+            let max = Point::new(self.scroll_region.end - 1, self.horizontal_margins.end - 1);
+            (offset, max)
         } else {
-            (Line(0), self.screen_lines() - 1)
+            (Point::new(Line(0), Column(0)), Point::new(self.screen_lines() - 1, self.cols() - 1))
         };
 
-        self.grid.cursor.point.line = min(line + y_offset, max_y);
-        self.grid.cursor.point.column = min(col, self.cols() - 1);
+        self.grid.cursor.point.line = min(line + offset.line, max.line);
+        self.grid.cursor.point.column = min(col + offset.column, max.column);
         self.grid.cursor.input_needs_wrap = false;
     }
 
@@ -953,12 +1418,18 @@ impl<T: EventListener> Handler for Term<T> {
         let cursor = &self.grid.cursor;
         let bg = cursor.template.bg;
 
-        // Ensure inserting within terminal bounds
-        let count = min(count, self.cols() - cursor.point.column);
+        // Cells outside of the left and right margins are not affected.
+        if !self.in_horizontal_margins(cursor.point.column) {
+            return;
+        }
+
+        // Ensure inserting within margins.
+        let right = self.horizontal_margins.end;
+        let count = min(count, right - cursor.point.column);
 
         let source = cursor.point.column;
         let destination = cursor.point.column + count;
-        let num_cells = (self.cols() - destination).0;
+        let num_cells = (right - destination).0;
 
         let line = cursor.point.line;
         let row = &mut self.grid[line][..];
@@ -972,6 +1443,8 @@ impl<T: EventListener> Handler for Term<T> {
         for cell in &mut row[source.0..destination.0] {
             *cell = bg.into();
         }
+
+        self.damage_line(line, source, right - 1);
     }
 
     #[inline]
@@ -1016,6 +1489,10 @@ impl<T: EventListener> Handler for Term<T> {
                 let version = version_number(env!("CARGO_PKG_VERSION"));
                 let _ = writer.write_all(format!("\x1b[>0;{};1c", version).as_bytes());
             },
+            Some('=') => {
+                trace!("Reporting tertiary device attributes");
+                let _ = writer.write_all(b"\x1bP!|00000000\x1b\\");
+            },
             _ => debug!("Unsupported device attributes intermediate"),
         }
     }
@@ -1036,6 +1513,30 @@ impl<T: EventListener> Handler for Term<T> {
         };
     }
 
+    #[inline]
+    fn report_mode<W: io::Write>(&mut self, writer: &mut W, private: bool, mode: u16) {
+        trace!("Reporting mode: {}", mode);
+
+        // Mode is not recognized (0), set (1), reset (2) or permanently reset (4).
+        let intermediate = if private { Some(&b'?') } else { None };
+        let state = match ansi::Mode::from_primitive(intermediate, mode) {
+            Some(ansi::Mode::DECCOLM) => 4,
+            Some(mode) if self.mode_enabled(&mode) => 1,
+            Some(_) => 2,
+            None => 0,
+        };
+
+        let prefix = if private { "?" } else { "" };
+        let _ = writer.write_all(format!("\x1b[{}{};{}$y", prefix, mode, state).as_bytes());
+    }
+
+    #[inline]
+    fn report_version<W: io::Write>(&mut self, writer: &mut W) {
+        trace!("Reporting terminal version");
+        let version = env!("CARGO_PKG_VERSION");
+        let _ = writer.write_all(format!("\x1bP>|alacritty({})\x1b\\", version).as_bytes());
+    }
+
     #[inline]
     fn move_down_and_cr(&mut self, lines: Line) {
         trace!("Moving down and cr: {}", lines);
@@ -1097,7 +1598,15 @@ impl<T: EventListener> Handler for Term<T> {
     #[inline]
     fn carriage_return(&mut self) {
         trace!("Carriage return");
-        self.grid.cursor.point.column = Column(0);
+
+        // Return to the left margin, unless the cursor is already left of it.
+        let column = self.grid.cursor.point.column;
+        self.grid.cursor.point.column =
+            if self.mode.contains(TermMode::ORIGIN) || column >= self.horizontal_margins.start {
+                self.horizontal_margins.start
+            } else {
+                Column(0)
+            };
         self.grid.cursor.input_needs_wrap = false;
     }
 
@@ -1107,7 +1616,10 @@ impl<T: EventListener> Handler for Term<T> {
         trace!("Linefeed");
         let next = self.grid.cursor.point.line + 1;
         if next == self.scroll_region.end {
-            self.scroll_up(Line(1));
+            // Text outside of the left and right margins is never scrolled.
+            if self.in_horizontal_margins(self.grid.cursor.point.column) {
+                self.scroll_up(Line(1));
+            }
         } else if next < self.screen_lines() {
             self.grid.cursor.point.line += 1;
         }
@@ -1178,9 +1690,9 @@ impl<T: EventListener> Handler for Term<T> {
     fn insert_blank_lines(&mut self, lines: Line) {
         trace!("Inserting blank {} lines", lines);
 
-        let origin = self.grid.cursor.point.line;
-        if self.scroll_region.contains(&origin) {
-            self.scroll_down_relative(origin, lines);
+        let origin = self.grid.cursor.point;
+        if self.scroll_region.contains(&origin.line) && self.in_horizontal_margins(origin.column) {
+            self.scroll_down_relative(origin.line, lines);
         }
     }
 
@@ -1191,7 +1703,10 @@ impl<T: EventListener> Handler for Term<T> {
 
         trace!("Deleting {} lines", lines);
 
-        if lines.0 > 0 && self.scroll_region.contains(&self.grid.cursor.point.line) {
+        if lines.0 > 0
+            && self.scroll_region.contains(&origin)
+            && self.in_horizontal_margins(self.grid.cursor.point.column)
+        {
             self.scroll_up_relative(origin, lines);
         }
     }
@@ -1212,20 +1727,29 @@ impl<T: EventListener> Handler for Term<T> {
         for cell in &mut row[start..end] {
             *cell = bg.into();
         }
+
+        if start < end {
+            self.damage_line(line, start, end - 1);
+        }
     }
 
     #[inline]
     fn delete_chars(&mut self, count: Column) {
-        let cols = self.cols();
         let cursor = &self.grid.cursor;
         let bg = cursor.template.bg;
 
-        // Ensure deleting within terminal bounds.
-        let count = min(count, cols);
+        // Cells outside of the left and right margins are not affected.
+        if !self.in_horizontal_margins(cursor.point.column) {
+            return;
+        }
 
+        // Ensure deleting within margins.
+        let right = self.horizontal_margins.end;
         let start = cursor.point.column;
-        let end = min(start + count, cols - 1);
-        let num_cells = (cols - end).0;
+        let count = min(count, right - start);
+
+        let end = start + count;
+        let num_cells = (right - end).0;
 
         let line = cursor.point.line;
         let row = &mut self.grid[line][..];
@@ -1234,12 +1758,12 @@ impl<T: EventListener> Handler for Term<T> {
             row.swap(start.0 + offset, end.0 + offset);
         }
 
-        // Clear last `count` cells in the row. If deleting 1 char, need to delete
-        // 1 cell.
-        let end = (cols - count).0;
-        for cell in &mut row[end..] {
+        // Clear last `count` cells before the right margin.
+        for cell in &mut row[(right - count).0..right.0] {
             *cell = bg.into();
         }
+
+        self.damage_line(line, start, right - 1);
     }
 
     #[inline]
@@ -1305,6 +1829,13 @@ impl<T: EventListener> Handler for Term<T> {
             },
         }
 
+        let (left, right) = match mode {
+            ansi::LineClearMode::Right => (point.column, self.cols() - 1),
+            ansi::LineClearMode::Left => (Column(0), point.column),
+            ansi::LineClearMode::All => (Column(0), self.cols() - 1),
+        };
+        self.damage_line(point.line, left, right);
+
         let cursor_buffer_line = (self.screen_lines() - self.grid.cursor.point.line - 1).0;
         self.selection = self
             .selection
@@ -1317,6 +1848,7 @@ impl<T: EventListener> Handler for Term<T> {
     fn set_color(&mut self, index: usize, color: Rgb) {
         trace!("Setting color[{}] = {:?}", index, color);
         self.colors[index] = Some(color);
+        self.mark_fully_damaged();
     }
 
     /// Write a foreground/background color escape sequence with the current color.
@@ -1341,6 +1873,7 @@ impl<T: EventListener> Handler for Term<T> {
     fn reset_color(&mut self, index: usize) {
         trace!("Resetting color[{}]", index);
         self.colors[index] = None;
+        self.mark_fully_damaged();
     }
 
     /// Store data into clipboard.
@@ -1383,6 +1916,7 @@ impl<T: EventListener> Handler for Term<T> {
     fn clear_screen(&mut self, mode: ansi::ClearMode) {
         trace!("Clearing screen: {:?}", mode);
         let bg = self.grid.cursor.template.bg;
+        self.mark_fully_damaged();
 
         let num_lines = self.screen_lines().0;
         let cursor_buffer_line = num_lines - self.grid.cursor.point.line.0 - 1;
@@ -1464,10 +1998,15 @@ impl<T: EventListener> Handler for Term<T> {
         self.grid.reset();
         self.inactive_grid.reset();
         self.scroll_region = Line(0)..self.screen_lines();
+        self.horizontal_margins = Column(0)..self.cols();
         self.tabs = TabStops::new(self.cols());
         self.title_stack = Vec::new();
         self.title = None;
         self.selection = None;
+        self.keyboard_modes = KeyboardModes::empty();
+        self.keyboard_mode_stack = Vec::new();
+        self.inactive_keyboard_mode_stack = Vec::new();
+        self.mark_fully_damaged();
 
         // Preserve vi mode across resets.
         self.mode &= TermMode::VI;
@@ -1482,7 +2021,10 @@ impl<T: EventListener> Handler for Term<T> {
         trace!("Reversing index");
         // If cursor is at the top.
         if self.grid.cursor.point.line == self.scroll_region.start {
-            self.scroll_down(Line(1));
+            // Text outside of the left and right margins is never scrolled.
+            if self.in_horizontal_margins(self.grid.cursor.point.column) {
+                self.scroll_down(Line(1));
+            }
         } else {
             self.grid.cursor.point.line = Line(self.grid.cursor.point.line.saturating_sub(1));
         }
@@ -1500,6 +2042,7 @@ impl<T: EventListener> Handler for Term<T> {
                 cursor.template.fg = Color::Named(NamedColor::Foreground);
                 cursor.template.bg = Color::Named(NamedColor::Background);
                 cursor.template.flags = Flags::empty();
+                cursor.template.set_underline_color(None);
             },
             Attr::Reverse => cursor.template.flags.insert(Flags::INVERSE),
             Attr::CancelReverse => cursor.template.flags.remove(Flags::INVERSE),
@@ -1510,16 +2053,27 @@ impl<T: EventListener> Handler for Term<T> {
             Attr::Italic => cursor.template.flags.insert(Flags::ITALIC),
             Attr::CancelItalic => cursor.template.flags.remove(Flags::ITALIC),
             Attr::Underline => {
-                cursor.template.flags.remove(Flags::DOUBLE_UNDERLINE);
+                cursor.template.flags.remove(Flags::ALL_UNDERLINES);
                 cursor.template.flags.insert(Flags::UNDERLINE);
             },
             Attr::DoubleUnderline => {
-                cursor.template.flags.remove(Flags::UNDERLINE);
+                cursor.template.flags.remove(Flags::ALL_UNDERLINES);
                 cursor.template.flags.insert(Flags::DOUBLE_UNDERLINE);
             },
-            Attr::CancelUnderline => {
-                cursor.template.flags.remove(Flags::UNDERLINE | Flags::DOUBLE_UNDERLINE);
+            Attr::Undercurl => {
+                cursor.template.flags.remove(Flags::ALL_UNDERLINES);
+                cursor.template.flags.insert(Flags::UNDERCURL);
+            },
+            Attr::DottedUnderline => {
+                cursor.template.flags.remove(Flags::ALL_UNDERLINES);
+                cursor.template.flags.insert(Flags::DOTTED_UNDERLINE);
             },
+            Attr::DashedUnderline => {
+                cursor.template.flags.remove(Flags::ALL_UNDERLINES);
+                cursor.template.flags.insert(Flags::DASHED_UNDERLINE);
+            },
+            Attr::CancelUnderline => cursor.template.flags.remove(Flags::ALL_UNDERLINES),
+            Attr::UnderlineColor(color) => cursor.template.set_underline_color(color),
             Attr::Hidden => cursor.template.flags.insert(Flags::HIDDEN),
             Attr::CancelHidden => cursor.template.flags.remove(Flags::HIDDEN),
             Attr::Strike => cursor.template.flags.insert(Flags::STRIKEOUT),
@@ -1560,6 +2114,7 @@ impl<T: EventListener> Handler for Term<T> {
             },
             ansi::Mode::ReportFocusInOut => self.mode.insert(TermMode::FOCUS_IN_OUT),
             ansi::Mode::BracketedPaste => self.mode.insert(TermMode::BRACKETED_PASTE),
+            ansi::Mode::SyncUpdate => self.mode.insert(TermMode::SYNC_UPDATE),
             // Mouse encodings are mutually exclusive.
             ansi::Mode::SgrMouse => {
                 self.mode.remove(TermMode::UTF8_MOUSE);
@@ -1575,6 +2130,7 @@ impl<T: EventListener> Handler for Term<T> {
             ansi::Mode::Origin => self.mode.insert(TermMode::ORIGIN),
             ansi::Mode::DECCOLM => self.deccolm(),
             ansi::Mode::Insert => self.mode.insert(TermMode::INSERT),
+            ansi::Mode::LeftRightMargin => self.mode.insert(TermMode::LEFT_RIGHT_MARGIN),
             ansi::Mode::BlinkingCursor => {
                 let style = self.cursor_style.get_or_insert(self.default_cursor_style);
                 style.blinking = true;
@@ -1609,6 +2165,7 @@ impl<T: EventListener> Handler for Term<T> {
             },
             ansi::Mode::ReportFocusInOut => self.mode.remove(TermMode::FOCUS_IN_OUT),
             ansi::Mode::BracketedPaste => self.mode.remove(TermMode::BRACKETED_PASTE),
+            ansi::Mode::SyncUpdate => self.mode.remove(TermMode::SYNC_UPDATE),
             ansi::Mode::SgrMouse => self.mode.remove(TermMode::SGR_MOUSE),
             ansi::Mode::Utf8Mouse => self.mode.remove(TermMode::UTF8_MOUSE),
             ansi::Mode::AlternateScroll => self.mode.remove(TermMode::ALTERNATE_SCROLL),
@@ -1617,6 +2174,10 @@ impl<T: EventListener> Handler for Term<T> {
             ansi::Mode::Origin => self.mode.remove(TermMode::ORIGIN),
             ansi::Mode::DECCOLM => self.deccolm(),
             ansi::Mode::Insert => self.mode.remove(TermMode::INSERT),
+            ansi::Mode::LeftRightMargin => {
+                self.mode.remove(TermMode::LEFT_RIGHT_MARGIN);
+                self.horizontal_margins = Column(0)..self.cols();
+            },
             ansi::Mode::BlinkingCursor => {
                 let style = self.cursor_style.get_or_insert(self.default_cursor_style);
                 style.blinking = false;
@@ -1649,6 +2210,28 @@ impl<T: EventListener> Handler for Term<T> {
         self.goto(Line(0), Column(0));
     }
 
+    #[inline]
+    fn set_left_right_margins(&mut self, left: usize, right: Option<usize>) {
+        // Without DECLRMM this escape saves the cursor position instead.
+        if !self.mode.contains(TermMode::LEFT_RIGHT_MARGIN) {
+            self.save_cursor_position();
+            return;
+        }
+
+        // Fallback to the last column as default.
+        let right = min(right.unwrap_or_else(|| self.cols().0), self.cols().0);
+
+        if left >= right {
+            debug!("Invalid left and right margins: ({};{})", left, right);
+            return;
+        }
+
+        trace!("Setting left and right margins: ({};{})", left, right);
+
+        self.horizontal_margins = Column(left - 1)..Column(right);
+        self.goto(Line(0), Column(0));
+    }
+
     #[inline]
     fn set_keypad_application_mode(&mut self) {
         trace!("Setting keypad application mode");
@@ -1691,6 +2274,27 @@ impl<T: EventListener> Handler for Term<T> {
         style.shape = shape;
     }
 
+    #[inline]
+    fn prompt_mark(&mut self, mark: PromptMark) {
+        trace!("Setting prompt mark {:?}", mark);
+
+        let marks = match mark {
+            PromptMark::PromptStart => PromptMarks::PROMPT_START,
+            PromptMark::CommandStart => PromptMarks::COMMAND_START,
+            PromptMark::OutputStart => PromptMarks::OUTPUT_START,
+            PromptMark::CommandEnd(_) => PromptMarks::COMMAND_END,
+        };
+
+        let line = self.grid.cursor.point.line;
+        self.grid[line].insert_marks(marks);
+    }
+
+    #[inline]
+    fn set_hyperlink(&mut self, hyperlink: Option<Hyperlink>) {
+        trace!("Setting hyperlink to {:?}", hyperlink);
+        self.grid.cursor.template.set_hyperlink(hyperlink);
+    }
+
     #[inline]
     fn set_title(&mut self, title: Option<String>) {
         trace!("Setting title to '{:?}'", title);
@@ -1741,6 +2345,196 @@ impl<T: EventListener> Handler for Term<T> {
     fn text_area_size_chars<W: io::Write>(&mut self, writer: &mut W) {
         let _ = write!(writer, "\x1b[8;{};{}t", self.screen_lines(), self.cols());
     }
+
+    #[inline]
+    fn insert_graphic(&mut self, graphic: GraphicData) {
+        trace!("Inserting graphic of {}x{} pixels", graphic.width, graphic.height);
+
+        let cell_width = max(self.cell_width, 1);
+        let cell_height = max(self.cell_height, 1);
+        let columns = (graphic.width + cell_width - 1) / cell_width;
+        let rows = (graphic.height + cell_height - 1) / cell_height;
+
+        let texture = self.graphics.insert(graphic);
+        self.mark_fully_damaged();
+
+        // Cells to the right of the screen are cut off.
+        let start = self.grid.cursor.point.column;
+        let end = min(start + columns, self.cols());
+
+        for row in 0..rows {
+            // Scroll the content if the graphic extends beyond the bottom of the screen.
+            if row > 0 {
+                self.linefeed();
+            }
+
+            let line = self.grid.cursor.point.line;
+            for (index, column) in (start.0..end.0).enumerate() {
+                let cell = &mut self.grid[line][Column(column)];
+                *cell = Cell::from(cell.bg);
+                cell.set_graphic(GraphicCell {
+                    texture: texture.clone(),
+                    offset_x: (index * cell_width) as u16,
+                    offset_y: (row * cell_height) as u16,
+                });
+            }
+        }
+
+        // Continue with text below the graphic.
+        self.linefeed();
+        self.grid.cursor.input_needs_wrap = false;
+    }
+
+    #[inline]
+    fn report_status<W: io::Write>(&mut self, writer: &mut W, request: Option<StatusRequest>) {
+        trace!("Reporting status: {:?}", request);
+
+        let status = match request {
+            Some(StatusRequest::Sgr) => format!("{}m", sgr_parameters(&self.grid.cursor.template)),
+            Some(StatusRequest::ScrollingRegion) => {
+                format!("{};{}r", self.scroll_region.start + 1, self.scroll_region.end)
+            },
+            Some(StatusRequest::LeftRightMargins) => {
+                format!("{};{}s", self.horizontal_margins.start + 1, self.horizontal_margins.end)
+            },
+            Some(StatusRequest::CursorStyle) => {
+                let style = self.cursor_style.unwrap_or(self.default_cursor_style);
+                let shape = match style.shape {
+                    CursorShape::Underline => 3,
+                    CursorShape::Beam => 5,
+                    _ => 1,
+                };
+                format!("{} q", shape + if style.blinking { 0 } else { 1 })
+            },
+            None => {
+                let _ = writer.write_all(b"\x1bP0$r\x1b\\");
+                return;
+            },
+        };
+
+        let _ = write!(writer, "\x1bP1$r{}\x1b\\", status);
+    }
+
+    #[inline]
+    fn report_capability<W: io::Write>(&mut self, writer: &mut W, name: &str) {
+        trace!("Reporting capability: {}", name);
+
+        let hex_name = hex_encode(name.as_bytes());
+        let _ = match terminfo::capability(name) {
+            Some(Capability::Boolean) => write!(writer, "\x1bP1+r{}\x1b\\", hex_name),
+            Some(Capability::Numeric(value)) => {
+                let value = hex_encode(value.to_string().as_bytes());
+                write!(writer, "\x1bP1+r{}={}\x1b\\", hex_name, value)
+            },
+            Some(Capability::String(value)) => {
+                let value = hex_encode(value.as_bytes());
+                write!(writer, "\x1bP1+r{}={}\x1b\\", hex_name, value)
+            },
+            None => write!(writer, "\x1bP0+r{}\x1b\\", hex_name),
+        };
+    }
+
+    #[inline]
+    fn push_keyboard_modes(&mut self, modes: KeyboardModes) {
+        trace!("Pushing keyboard modes: {:?}", modes);
+
+        if self.keyboard_mode_stack.len() >= KEYBOARD_MODE_STACK_MAX_DEPTH {
+            self.keyboard_mode_stack.remove(0);
+        }
+
+        self.keyboard_mode_stack.push(modes);
+        self.keyboard_modes = modes;
+    }
+
+    #[inline]
+    fn pop_keyboard_modes(&mut self, to_pop: u16) {
+        trace!("Popping {} keyboard modes", to_pop);
+
+        let new_len = self.keyboard_mode_stack.len().saturating_sub(to_pop as usize);
+        self.keyboard_mode_stack.truncate(new_len);
+
+        // Fall back to the legacy encoding once the stack is empty.
+        self.keyboard_modes = self.keyboard_mode_stack.last().copied().unwrap_or_default();
+    }
+
+    #[inline]
+    fn set_keyboard_modes(&mut self, modes: KeyboardModes, behavior: KeyboardModesApplyBehavior) {
+        trace!("Setting keyboard modes {:?} with {:?}", modes, behavior);
+
+        match behavior {
+            KeyboardModesApplyBehavior::Replace => self.keyboard_modes = modes,
+            KeyboardModesApplyBehavior::Union => self.keyboard_modes.insert(modes),
+            KeyboardModesApplyBehavior::Difference => self.keyboard_modes.remove(modes),
+        }
+
+        // Update the top of the stack, so the modes are restored after switching screens.
+        match self.keyboard_mode_stack.last_mut() {
+            Some(top) => *top = self.keyboard_modes,
+            None => self.keyboard_mode_stack.push(self.keyboard_modes),
+        }
+    }
+
+    #[inline]
+    fn report_keyboard_modes<W: io::Write>(&mut self, writer: &mut W) {
+        trace!("Reporting keyboard modes");
+        let _ = write!(writer, "\x1b[?{}u", self.keyboard_modes.bits());
+    }
+}
+
+/// SGR parameters which reproduce the attributes of a cell.
+fn sgr_parameters(cell: &Cell) -> String {
+    let mut parameters = String::from("0");
+
+    let flags = [
+        (Flags::BOLD, "1"),
+        (Flags::DIM, "2"),
+        (Flags::ITALIC, "3"),
+        (Flags::UNDERLINE, "4"),
+        (Flags::DOUBLE_UNDERLINE, "4:2"),
+        (Flags::UNDERCURL, "4:3"),
+        (Flags::DOTTED_UNDERLINE, "4:4"),
+        (Flags::DASHED_UNDERLINE, "4:5"),
+        (Flags::INVERSE, "7"),
+        (Flags::HIDDEN, "8"),
+        (Flags::STRIKEOUT, "9"),
+    ];
+    for (flag, parameter) in flags.iter() {
+        if cell.flags.contains(*flag) {
+            parameters.push(';');
+            parameters.push_str(parameter);
+        }
+    }
+
+    for (color, base) in [(cell.fg, 30), (cell.bg, 40)].iter() {
+        let color = match color {
+            Color::Named(named) if (*named as usize) < 8 => format!("{}", base + *named as usize),
+            Color::Named(named) if (*named as usize) < 16 => {
+                format!("{}", base + 60 + *named as usize - 8)
+            },
+            Color::Named(_) => continue,
+            Color::Indexed(index) => format!("{};5;{}", base + 8, index),
+            Color::Spec(rgb) => format!("{};2;{};{};{}", base + 8, rgb.r, rgb.g, rgb.b),
+        };
+        parameters.push(';');
+        parameters.push_str(&color);
+    }
+
+    // Underline colors have no short form, named colors use their palette index instead.
+    let underline_color = match cell.underline_color() {
+        Some(Color::Named(named)) if (named as usize) < 16 => format!("58;5;{}", named as usize),
+        Some(Color::Indexed(index)) => format!("58;5;{}", index),
+        Some(Color::Spec(rgb)) => format!("58;2;{};{};{}", rgb.r, rgb.g, rgb.b),
+        _ => return parameters,
+    };
+    parameters.push(';');
+    parameters.push_str(&underline_color);
+
+    parameters
+}
+
+/// Encode bytes as pairs of uppercase hexadecimal digits.
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02X}", byte)).collect()
 }
 
 /// Terminal version for escape sequence reports.
@@ -1944,10 +2738,12 @@ mod tests {
 
     use crate::ansi::{self, CharsetIndex, Handler, StandardCharset};
     use crate::config::MockConfig;
+    use crate::graphics::GraphicId;
     use crate::grid::{Grid, Scroll};
     use crate::index::{Column, Line, Point, Side};
     use crate::selection::{Selection, SelectionType};
     use crate::term::cell::{Cell, Flags};
+    use crate::term::damage::LineDamageBounds;
 
     #[test]
     fn semantic_selection_works() {
@@ -2053,6 +2849,416 @@ mod tests {
         assert_eq!(deserialized, grid);
     }
 
+    #[test]
+    fn input_hyperlink() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, ());
+
+        let hyperlink = Hyperlink::new(Some("id"), "https://example.org");
+        term.set_hyperlink(Some(hyperlink.clone()));
+        term.input('a');
+        term.set_hyperlink(None);
+        term.input('b');
+
+        assert_eq!(term.grid()[Line(0)][Column(0)].hyperlink(), Some(hyperlink));
+        assert_eq!(term.grid()[Line(0)][Column(1)].hyperlink(), None);
+    }
+
+    #[test]
+    fn input_underline_style() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, ());
+
+        term.terminal_attribute(Attr::Undercurl);
+        term.terminal_attribute(Attr::UnderlineColor(Some(Color::Indexed(1))));
+        term.input('a');
+        term.terminal_attribute(Attr::DashedUnderline);
+        term.terminal_attribute(Attr::UnderlineColor(None));
+        term.input('b');
+        term.terminal_attribute(Attr::UnderlineColor(Some(Color::Indexed(2))));
+        term.terminal_attribute(Attr::Reset);
+        term.input('c');
+
+        let cell = &term.grid()[Line(0)][Column(0)];
+        assert_eq!(cell.flags & Flags::ALL_UNDERLINES, Flags::UNDERCURL);
+        assert_eq!(cell.underline_color(), Some(Color::Indexed(1)));
+
+        let cell = &term.grid()[Line(0)][Column(1)];
+        assert_eq!(cell.flags & Flags::ALL_UNDERLINES, Flags::DASHED_UNDERLINE);
+        assert_eq!(cell.underline_color(), None);
+
+        let cell = &term.grid()[Line(0)][Column(2)];
+        assert!(!cell.flags.intersects(Flags::ALL_UNDERLINES));
+        assert_eq!(cell.underline_color(), None);
+    }
+
+    #[test]
+    fn report_status() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, ());
+        let mut writer = Vec::new();
+
+        term.terminal_attribute(Attr::Bold);
+        term.terminal_attribute(Attr::Foreground(Color::Named(NamedColor::Red)));
+        term.terminal_attribute(Attr::Background(Color::Indexed(100)));
+        term.report_status(&mut writer, Some(StatusRequest::Sgr));
+        assert_eq!(writer, b"\x1bP1$r0;1;31;48;5;100m\x1b\\");
+
+        writer.clear();
+        term.terminal_attribute(Attr::Undercurl);
+        term.terminal_attribute(Attr::UnderlineColor(Some(Color::Spec(Rgb { r: 1, g: 2, b: 3 }))));
+        term.report_status(&mut writer, Some(StatusRequest::Sgr));
+        assert_eq!(writer, b"\x1bP1$r0;1;4:3;31;48;5;100;58;2;1;2;3m\x1b\\");
+
+        writer.clear();
+        term.set_scrolling_region(2, Some(5));
+        term.report_status(&mut writer, Some(StatusRequest::ScrollingRegion));
+        assert_eq!(writer, b"\x1bP1$r2;5r\x1b\\");
+
+        writer.clear();
+        term.set_cursor_style(Some(CursorStyle { shape: CursorShape::Beam, blinking: false }));
+        term.report_status(&mut writer, Some(StatusRequest::CursorStyle));
+        assert_eq!(writer, b"\x1bP1$r6 q\x1b\\");
+
+        writer.clear();
+        term.set_mode(ansi::Mode::LeftRightMargin);
+        term.set_left_right_margins(2, Some(3));
+        term.report_status(&mut writer, Some(StatusRequest::LeftRightMargins));
+        assert_eq!(writer, b"\x1bP1$r2;3s\x1b\\");
+
+        writer.clear();
+        term.report_status(&mut writer, None);
+        assert_eq!(writer, b"\x1bP0$r\x1b\\");
+    }
+
+    /// Text of a line in the visible region.
+    fn line_text(term: &Term<()>, line: usize) -> String {
+        term.grid[Line(line)][..].iter().map(|cell| cell.c).collect()
+    }
+
+    /// Fill every line of the terminal with its line number.
+    fn fill_lines(term: &mut Term<()>) {
+        for line in 0..term.screen_lines().0 {
+            term.goto(Line(line), Column(0));
+            for _ in 0..term.cols().0 {
+                term.input(char::from(b'0' + line as u8));
+            }
+        }
+    }
+
+    #[test]
+    fn left_right_margins_mode() {
+        let size = SizeInfo::new(6.0, 3.0, 1.0, 1.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, ());
+
+        // Without DECLRMM the escape saves the cursor position.
+        term.goto(Line(1), Column(4));
+        term.set_left_right_margins(2, Some(4));
+        assert!(!term.has_horizontal_margins());
+        term.goto(Line(0), Column(0));
+        term.restore_cursor_position();
+        assert_eq!(term.grid.cursor.point, Point::new(Line(1), Column(4)));
+
+        term.set_mode(ansi::Mode::LeftRightMargin);
+        term.set_left_right_margins(2, Some(4));
+        assert_eq!(term.horizontal_margins, Column(1)..Column(4));
+        assert_eq!(term.grid.cursor.point, Point::new(Line(0), Column(0)));
+
+        // Invalid margins are ignored.
+        term.set_left_right_margins(4, Some(4));
+        assert_eq!(term.horizontal_margins, Column(1)..Column(4));
+
+        // Origin mode is relative to the margins.
+        term.set_mode(ansi::Mode::Origin);
+        term.goto(Line(0), Column(5));
+        assert_eq!(term.grid.cursor.point, Point::new(Line(0), Column(3)));
+        term.carriage_return();
+        assert_eq!(term.grid.cursor.point, Point::new(Line(0), Column(1)));
+        term.unset_mode(ansi::Mode::Origin);
+
+        // Resetting the mode clears the margins.
+        term.unset_mode(ansi::Mode::LeftRightMargin);
+        assert!(!term.has_horizontal_margins());
+    }
+
+    #[test]
+    fn left_right_margins_scroll() {
+        let size = SizeInfo::new(6.0, 4.0, 1.0, 1.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, ());
+        fill_lines(&mut term);
+
+        term.set_mode(ansi::Mode::LeftRightMargin);
+        term.set_left_right_margins(2, Some(4));
+        term.scroll_up(Line(1));
+
+        assert_eq!(line_text(&term, 0), "011100");
+        assert_eq!(line_text(&term, 1), "122211");
+        assert_eq!(line_text(&term, 2), "233322");
+        assert_eq!(line_text(&term, 3), "3   33");
+        assert_eq!(term.history_size(), 0);
+
+        term.scroll_down(Line(2));
+
+        assert_eq!(line_text(&term, 0), "0   00");
+        assert_eq!(line_text(&term, 1), "1   11");
+        assert_eq!(line_text(&term, 2), "211122");
+        assert_eq!(line_text(&term, 3), "322233");
+
+        // Lines are only inserted when the cursor is within the margins.
+        term.goto(Line(2), Column(0));
+        term.insert_blank_lines(Line(1));
+        assert_eq!(line_text(&term, 2), "211122");
+        term.goto(Line(2), Column(1));
+        term.insert_blank_lines(Line(1));
+        assert_eq!(line_text(&term, 2), "2   22");
+        assert_eq!(line_text(&term, 3), "311133");
+    }
+
+    #[test]
+    fn left_right_margins_edit() {
+        let size = SizeInfo::new(6.0, 1.0, 1.0, 1.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, ());
+        for c in "abcdef".chars() {
+            term.input(c);
+        }
+
+        term.set_mode(ansi::Mode::LeftRightMargin);
+        term.set_left_right_margins(2, Some(5));
+
+        term.goto(Line(0), Column(2));
+        term.insert_blank(Column(1));
+        assert_eq!(line_text(&term, 0), "ab cdf");
+
+        term.delete_chars(Column(10));
+        assert_eq!(line_text(&term, 0), "ab   f");
+
+        // Cells outside of the margins are never shifted.
+        term.goto(Line(0), Column(5));
+        term.delete_chars(Column(1));
+        assert_eq!(line_text(&term, 0), "ab   f");
+    }
+
+    #[test]
+    fn left_right_margins_wrap() {
+        let size = SizeInfo::new(6.0, 3.0, 1.0, 1.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, ());
+
+        term.set_mode(ansi::Mode::LeftRightMargin);
+        term.set_left_right_margins(2, Some(4));
+        term.set_scrolling_region(1, Some(2));
+        term.goto(Line(0), Column(1));
+        for c in "abcdefghi".chars() {
+            term.input(c);
+        }
+
+        assert_eq!(line_text(&term, 0), " def  ");
+        assert_eq!(line_text(&term, 1), " ghi  ");
+        assert!(!term.grid[Line(0)][Column(5)].flags.contains(Flags::WRAPLINE));
+        assert_eq!(term.grid.cursor.point, Point::new(Line(1), Column(3)));
+    }
+
+    #[test]
+    fn report_mode() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, ());
+        let mut writer = Vec::new();
+
+        term.report_mode(&mut writer, true, 2004);
+        assert_eq!(writer, b"\x1b[?2004;2$y");
+
+        writer.clear();
+        term.set_mode(ansi::Mode::BracketedPaste);
+        term.report_mode(&mut writer, true, 2004);
+        assert_eq!(writer, b"\x1b[?2004;1$y");
+
+        writer.clear();
+        term.report_mode(&mut writer, false, 4);
+        assert_eq!(writer, b"\x1b[4;2$y");
+
+        writer.clear();
+        term.report_mode(&mut writer, true, 3);
+        assert_eq!(writer, b"\x1b[?3;4$y");
+
+        writer.clear();
+        term.report_mode(&mut writer, true, 9999);
+        assert_eq!(writer, b"\x1b[?9999;0$y");
+
+        writer.clear();
+        term.report_mode(&mut writer, false, 2004);
+        assert_eq!(writer, b"\x1b[2004;0$y");
+    }
+
+    #[test]
+    fn report_version() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, ());
+        let mut writer = Vec::new();
+
+        term.report_version(&mut writer);
+        let expected = format!("\x1bP>|alacritty({})\x1b\\", env!("CARGO_PKG_VERSION"));
+        assert_eq!(writer, expected.as_bytes());
+
+        writer.clear();
+        term.identify_terminal(&mut writer, Some('='));
+        assert_eq!(writer, b"\x1bP!|00000000\x1b\\");
+    }
+
+    #[test]
+    fn report_capability() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, ());
+        let mut writer = Vec::new();
+
+        term.report_capability(&mut writer, "Co");
+        assert_eq!(writer, b"\x1bP1+r436F=323536\x1b\\");
+
+        writer.clear();
+        term.report_capability(&mut writer, "am");
+        assert_eq!(writer, b"\x1bP1+r616D\x1b\\");
+
+        writer.clear();
+        term.report_capability(&mut writer, "xx");
+        assert_eq!(writer, b"\x1bP0+r7878\x1b\\");
+    }
+
+    #[test]
+    fn keyboard_mode_stack() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, ());
+        let mut writer = Vec::new();
+
+        term.push_keyboard_modes(KeyboardModes::DISAMBIGUATE_ESC_CODES);
+        term.push_keyboard_modes(KeyboardModes::REPORT_ALL_KEYS_AS_ESC);
+        term.set_keyboard_modes(
+            KeyboardModes::REPORT_EVENT_TYPES,
+            KeyboardModesApplyBehavior::Union,
+        );
+        term.report_keyboard_modes(&mut writer);
+        assert_eq!(writer, b"\x1b[?10u");
+
+        // Alternate screen has its own stack.
+        term.set_mode(ansi::Mode::SwapScreenAndSetRestoreCursor);
+        assert_eq!(term.keyboard_modes(), KeyboardModes::empty());
+        term.push_keyboard_modes(KeyboardModes::REPORT_ALTERNATE_KEYS);
+        term.unset_mode(ansi::Mode::SwapScreenAndSetRestoreCursor);
+        let modes = KeyboardModes::REPORT_ALL_KEYS_AS_ESC | KeyboardModes::REPORT_EVENT_TYPES;
+        assert_eq!(term.keyboard_modes(), modes);
+
+        term.pop_keyboard_modes(1);
+        assert_eq!(term.keyboard_modes(), KeyboardModes::DISAMBIGUATE_ESC_CODES);
+
+        term.pop_keyboard_modes(5);
+        writer.clear();
+        term.report_keyboard_modes(&mut writer);
+        assert_eq!(writer, b"\x1b[?0u");
+
+        // Modes set without pushing are kept while the alternate screen is active.
+        term.set_keyboard_modes(
+            KeyboardModes::DISAMBIGUATE_ESC_CODES,
+            KeyboardModesApplyBehavior::Replace,
+        );
+        term.set_mode(ansi::Mode::SwapScreenAndSetRestoreCursor);
+        term.unset_mode(ansi::Mode::SwapScreenAndSetRestoreCursor);
+        assert_eq!(term.keyboard_modes(), KeyboardModes::DISAMBIGUATE_ESC_CODES);
+    }
+
+    #[test]
+    fn keyboard_modes_unsupported() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, ());
+        let mut writer = Vec::new();
+
+        // Associated text is not reported back as supported.
+        let mut parser = ansi::Processor::new();
+        for byte in b"\x1b[>31u\x1b[?u" {
+            parser.advance(&mut term, *byte, &mut writer);
+        }
+        assert_eq!(writer, b"\x1b[?15u");
+    }
+
+    #[test]
+    fn insert_graphic() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, ());
+        term.graphics_take_updates();
+
+        // Graphic covering two columns and two lines.
+        let pixels = vec![255; 4 * 6 * 4];
+        let graphic = GraphicData { id: GraphicId(0), width: 4, height: 6, pixels };
+        term.goto(Line(1), Column(1));
+        term.insert_graphic(graphic);
+
+        let updates = term.graphics_take_updates().unwrap();
+        assert_eq!(updates.pending.len(), 1);
+        let id = updates.pending[0].id;
+
+        let graphic = term.grid[Line(2)][Column(2)].graphic().unwrap();
+        assert_eq!(graphic.texture.id, id);
+        assert_eq!((graphic.offset_x, graphic.offset_y), (3, 3));
+        assert!(term.grid[Line(1)][Column(1)].graphic().is_some());
+        assert!(term.grid[Line(1)][Column(3)].graphic().is_none());
+        assert_eq!(term.grid.cursor.point, Point::new(Line(3), Column(1)));
+
+        // Graphics are moved into history with the rest of the screen.
+        term.clear_screen(ansi::ClearMode::All);
+        assert!(term.grid[Line(2)][Column(2)].graphic().is_none());
+        assert!(term.graphics_take_updates().is_none());
+
+        // Graphics are removed once no cell references them.
+        term.clear_screen(ansi::ClearMode::Saved);
+        assert_eq!(term.graphics_take_updates().unwrap().remove_queue, vec![id]);
+    }
+
+    #[test]
+    fn damage_tracking() {
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
+        let mut term = Term::new(&MockConfig::default(), size, ());
+        let damaged_lines = |term: &mut Term<()>| {
+            let damage = match term.damage() {
+                TermDamage::Full => None,
+                TermDamage::Partial(lines) => Some(lines.collect::<Vec<_>>()),
+            };
+            term.reset_damage();
+            damage
+        };
+
+        // Everything is damaged before the first frame.
+        assert_eq!(damaged_lines(&mut term), None);
+
+        // The cursor is always redrawn.
+        let cursor_damage = LineDamageBounds::new(Line(0), Column(0), Column(1));
+        assert_eq!(damaged_lines(&mut term), Some(vec![cursor_damage]));
+
+        // Both the old and the new cursor position are damaged.
+        term.goto(Line(2), Column(3));
+        assert_eq!(
+            damaged_lines(&mut term),
+            Some(vec![cursor_damage, LineDamageBounds::new(Line(2), Column(2), Column(4))])
+        );
+
+        term.input('a');
+        assert_eq!(
+            damaged_lines(&mut term),
+            Some(vec![LineDamageBounds::new(Line(2), Column(2), Column(5))])
+        );
+
+        // Selection changes damage the selected lines.
+        term.selection =
+            Some(Selection::new(SelectionType::Lines, Point::new(12, Column(0)), Side::Left));
+        assert_eq!(
+            damaged_lines(&mut term),
+            Some(vec![
+                LineDamageBounds::new(Line(2), Column(3), Column(5)),
+                LineDamageBounds::new(Line(4), Column(0), Column(6)),
+            ])
+        );
+
+        // Scrolling damages the entire viewport.
+        term.goto(Line(16), Column(0));
+        term.linefeed();
+        assert_eq!(damaged_lines(&mut term), None);
+    }
+
     #[test]
     fn input_line_drawing_character() {
         let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
@@ -2178,6 +3384,42 @@ mod tests {
         assert_eq!(term.grid.cursor.point, Point::new(Line(4), Column(0)));
     }
 
+    #[test]
+    fn reflow_inactive_vi_marks() {
+        #[rustfmt::skip]
+        let mut term = crate::term::test::mock_term("\
+            abcd\r\n\
+            efgh\r\n\
+            i\
+        ");
+        term.grid.cursor.point = Point::new(Line(2), Column(1));
+
+        // Set mark on the primary screen.
+        term.vi_mode_cursor = ViModeCursor::new(Point::new(Line(1), Column(2)));
+        term.vi_set_mark('a');
+
+        // Enter alt screen.
+        term.set_mode(ansi::Mode::SwapScreenAndSetRestoreCursor);
+        assert_eq!(term.vi_mark('a'), None);
+
+        // Reflow both grids.
+        let size = SizeInfo::new(2., 3., 1., 1., 0., 0., false);
+        term.resize(size);
+
+        // Leave alt screen.
+        term.unset_mode(ansi::Mode::SwapScreenAndSetRestoreCursor);
+
+        let mark = term.vi_mark('a').unwrap();
+        assert_eq!(term.grid[mark.line][mark.column].c, 'g');
+
+        // Reflow the mark back into its original line.
+        let size = SizeInfo::new(4., 3., 1., 1., 0., 0., false);
+        term.resize(size);
+
+        let mark = term.vi_mark('a').unwrap();
+        assert_eq!(term.grid[mark.line][mark.column].c, 'g');
+    }
+
     #[test]
     fn window_title() {
         let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0.0, 0.0, false);
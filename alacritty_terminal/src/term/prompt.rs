@@ -0,0 +1,160 @@
+//! Navigation using the shell integration prompt marks (`OSC 133`).
+
+use std::cmp::min;
+use std::ops::RangeInclusive;
+
+use crate::grid::{Dimensions, PromptMarks};
+use crate::index::{Column, Direction, Point};
+use crate::term::Term;
+
+impl<T> Term<T> {
+    /// Find the closest prompt start in the specified direction.
+    ///
+    /// The search starts at the line following `line`, both are in buffer coordinates.
+    pub fn prompt_search(&self, line: usize, direction: Direction) -> Option<usize> {
+        let is_prompt = |line: &usize| self.grid[*line].marks().contains(PromptMarks::PROMPT_START);
+
+        match direction {
+            Direction::Left => (line + 1..self.total_lines()).find(is_prompt),
+            Direction::Right => (0..min(line, self.total_lines())).rev().find(is_prompt),
+        }
+    }
+
+    /// Output of the command which belongs to the closest prompt at or above `line`.
+    pub fn command_output(&self, line: usize) -> Option<RangeInclusive<Point<usize>>> {
+        let prompt = (line..self.total_lines())
+            .find(|line| self.grid[*line].marks().contains(PromptMarks::PROMPT_START))?;
+
+        // Find the start of the output before the next prompt.
+        for line in (0..=prompt).rev() {
+            let marks = self.grid[line].marks();
+            if marks.contains(PromptMarks::OUTPUT_START) {
+                return self.output_from(line);
+            } else if line != prompt && marks.contains(PromptMarks::PROMPT_START) {
+                break;
+            }
+        }
+
+        None
+    }
+
+    /// Output of the most recent command.
+    pub fn last_command_output(&self) -> Option<RangeInclusive<Point<usize>>> {
+        let start = (0..self.total_lines())
+            .find(|line| self.grid[*line].marks().contains(PromptMarks::OUTPUT_START))?;
+        self.output_from(start)
+    }
+
+    /// Lines of command output starting at `start`.
+    ///
+    /// The output ends above the next command end or prompt mark. Without either of them, the
+    /// command is still running and its output ends at the cursor.
+    fn output_from(&self, start: usize) -> Option<RangeInclusive<Point<usize>>> {
+        // Commands without any output end in the same line.
+        if self.grid[start].marks().contains(PromptMarks::COMMAND_END) {
+            return None;
+        }
+
+        let end_marks = PromptMarks::PROMPT_START | PromptMarks::COMMAND_END;
+        let next_end = (0..start).rev().find(|line| self.grid[*line].marks().intersects(end_marks));
+        let end = match next_end {
+            Some(end) => end + 1,
+            None => {
+                let cursor_line = self.grid.cursor.point.line.0;
+                min(self.screen_lines().0 - 1 - cursor_line, start)
+            },
+        };
+
+        Some(Point::new(start, Column(0))..=Point::new(end, self.cols() - 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::ansi::{Handler, PromptMark};
+    use crate::index::Line;
+    use crate::term::test::mock_term;
+
+    /// Terminal with two commands and an active prompt.
+    ///
+    /// ```text
+    /// 7 [$][ ][l][s][ ]   PROMPT_START
+    /// 6 [a][ ][ ][ ][ ]   OUTPUT_START
+    /// 5 [b][ ][ ][ ][ ]
+    /// 4 [$][ ][x][ ][ ]   PROMPT_START | COMMAND_END
+    /// 3 [ ][ ][ ][ ][ ]   OUTPUT_START | COMMAND_END
+    /// 2 [$][ ][t][o][p]   PROMPT_START
+    /// 1 [c][ ][ ][ ][ ]   OUTPUT_START
+    /// 0 [ ][ ][ ][ ][ ]
+    /// ```
+    fn prompt_term() -> Term<()> {
+        #[rustfmt::skip]
+        let mut term = mock_term("\
+            $ ls\r\n\
+            a\r\n\
+            b\r\n\
+            $ x\r\n\
+            \r\n\
+            $ top\r\n\
+            c\r\n\
+        ");
+
+        term.grid[7].insert_marks(PromptMarks::PROMPT_START);
+        term.grid[6].insert_marks(PromptMarks::OUTPUT_START);
+        term.grid[4].insert_marks(PromptMarks::PROMPT_START | PromptMarks::COMMAND_END);
+        term.grid[3].insert_marks(PromptMarks::OUTPUT_START | PromptMarks::COMMAND_END);
+        term.grid[2].insert_marks(PromptMarks::PROMPT_START);
+        term.grid[1].insert_marks(PromptMarks::OUTPUT_START);
+        term.grid.cursor.point.line = Line(7);
+
+        term
+    }
+
+    #[test]
+    fn prompt_search() {
+        let term = prompt_term();
+
+        assert_eq!(term.prompt_search(4, Direction::Left), Some(7));
+        assert_eq!(term.prompt_search(7, Direction::Left), None);
+        assert_eq!(term.prompt_search(7, Direction::Right), Some(4));
+        assert_eq!(term.prompt_search(4, Direction::Right), Some(2));
+        assert_eq!(term.prompt_search(2, Direction::Right), None);
+    }
+
+    #[test]
+    fn command_output() {
+        let term = prompt_term();
+
+        let output = Point::new(6, Column(0))..=Point::new(5, Column(4));
+        assert_eq!(term.command_output(7), Some(output.clone()));
+        assert_eq!(term.command_output(5), Some(output));
+
+        // Commands without output.
+        assert_eq!(term.command_output(4), None);
+
+        // Running command.
+        let output = Point::new(1, Column(0))..=Point::new(0, Column(4));
+        assert_eq!(term.command_output(2), Some(output.clone()));
+        assert_eq!(term.last_command_output(), Some(output));
+    }
+
+    #[test]
+    fn marks_rotate_into_history() {
+        #[rustfmt::skip]
+        let mut term = mock_term("\
+            $ \r\n\
+            \r\n\
+        ");
+
+        term.grid.cursor.point.line = Line(0);
+        term.prompt_mark(PromptMark::PromptStart);
+        for _ in 0..4 {
+            term.linefeed();
+        }
+
+        assert_eq!(term.grid.history_size(), 2);
+        assert_eq!(term.prompt_search(0, Direction::Left), Some(4));
+    }
+}
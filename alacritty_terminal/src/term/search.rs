@@ -1,4 +1,4 @@
-use std::cmp::min;
+use std::cmp::{min, Ordering};
 use std::mem;
 use std::ops::RangeInclusive;
 
@@ -14,6 +14,36 @@ const BRACKET_PAIRS: [(char, char); 4] = [('(', ')'), ('[', ']'), ('{', '}'), ('
 
 pub type Match = RangeInclusive<Point<usize>>;
 
+/// Case sensitivity of the search.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SearchCase {
+    /// Case sensitive only if the search contains uppercase characters.
+    Smart,
+    /// Always case sensitive.
+    Sensitive,
+    /// Always case insensitive.
+    Insensitive,
+}
+
+impl Default for SearchCase {
+    fn default() -> Self {
+        SearchCase::Smart
+    }
+}
+
+/// Options controlling how the search text is matched.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct SearchOptions {
+    /// Match the search text literally, instead of as a regex.
+    pub literal: bool,
+
+    /// Case sensitivity.
+    pub case: SearchCase,
+
+    /// Only accept matches which are not surrounded by other word characters.
+    pub whole_word: bool,
+}
+
 /// Terminal regex search state.
 pub struct RegexSearch {
     /// Locate end of match searching right.
@@ -25,17 +55,37 @@ pub struct RegexSearch {
     left_fdfa: DenseDFA<Vec<usize>, usize>,
     /// Locate end of match searching left.
     left_rdfa: DenseDFA<Vec<usize>, usize>,
+
+    /// Only accept whole word matches.
+    whole_word: bool,
 }
 
 impl RegexSearch {
     /// Build the forward and backward search DFAs.
     pub fn new(search: &str) -> Result<RegexSearch, RegexError> {
-        // Check case info for smart case
-        let has_uppercase = search.chars().any(|c| c.is_uppercase());
+        Self::with_options(search, SearchOptions::default())
+    }
+
+    /// Build the search DFAs, using the supplied options.
+    pub fn with_options(search: &str, options: SearchOptions) -> Result<RegexSearch, RegexError> {
+        let case_insensitive = match options.case {
+            // Check case info for smart case
+            SearchCase::Smart => !search.chars().any(|c| c.is_uppercase()),
+            SearchCase::Sensitive => false,
+            SearchCase::Insensitive => true,
+        };
+
+        let escaped;
+        let search = if options.literal {
+            escaped = escape(search);
+            &escaped
+        } else {
+            search
+        };
 
         // Create Regex DFAs for all search directions.
         let mut builder = dense::Builder::new();
-        let builder = builder.case_insensitive(!has_uppercase);
+        let builder = builder.case_insensitive(case_insensitive);
 
         let left_fdfa = builder.clone().reverse(true).build(search)?;
         let left_rdfa = builder.clone().anchored(true).longest_match(true).build(search)?;
@@ -43,10 +93,23 @@ impl RegexSearch {
         let right_fdfa = builder.clone().build(search)?;
         let right_rdfa = builder.anchored(true).longest_match(true).reverse(true).build(search)?;
 
-        Ok(RegexSearch { right_fdfa, right_rdfa, left_fdfa, left_rdfa })
+        let whole_word = options.whole_word;
+        Ok(RegexSearch { right_fdfa, right_rdfa, left_fdfa, left_rdfa, whole_word })
     }
 }
 
+/// Escape all regex meta characters, to match the text literally.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\.+*?()|[]{}^$#&-~".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
 impl<T> Term<T> {
     /// Get next search match in the specified direction.
     pub fn search_next(
@@ -183,6 +246,38 @@ impl<T> Term<T> {
         Some(match_start..=match_end)
     }
 
+    /// Check if a match is not surrounded by other word characters.
+    fn is_whole_word(&self, regex_match: &Match) -> bool {
+        let is_word = |point: Point<usize>| {
+            let c = self.grid[point.line][point.column].c;
+            c.is_alphanumeric() || c == '_'
+        };
+        let last_col = self.cols() - 1;
+
+        // Lines are only connected across their boundaries if they are wrapped.
+        let start = *regex_match.start();
+        let before = if start.column > Column(0) {
+            Some(Point::new(start.line, start.column - 1))
+        } else if start.line + 1 < self.total_lines()
+            && self.grid[start.line + 1][last_col].flags.contains(Flags::WRAPLINE)
+        {
+            Some(Point::new(start.line + 1, last_col))
+        } else {
+            None
+        };
+
+        let end = self.expand_wide(*regex_match.end(), Direction::Right);
+        let after = if end.column < last_col {
+            Some(Point::new(end.line, end.column + 1))
+        } else if end.line > 0 && self.grid[end.line][last_col].flags.contains(Flags::WRAPLINE) {
+            Some(Point::new(end.line - 1, Column(0)))
+        } else {
+            None
+        };
+
+        !before.map_or(false, is_word) && !after.map_or(false, is_word)
+    }
+
     /// Find the next regex match.
     ///
     /// This will always return the side of the first match which is farthest from the start point.
@@ -469,26 +564,175 @@ impl<'a, T> Iterator for RegexIter<'a, T> {
     type Item = Match;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.point == self.end {
-            self.done = true;
-        } else if self.done {
-            return None;
+        loop {
+            if self.done {
+                return None;
+            }
+
+            // Since the end itself might be a single cell match, we search one more time.
+            if self.point == self.end {
+                self.done = true;
+            }
+
+            let regex_match = self.next_match()?;
+
+            self.point = *regex_match.end();
+            if self.point == self.end {
+                // Stop when the match terminates right on the end limit, instead of wrapping around.
+                self.done = true;
+            } else {
+                self.skip();
+            }
+
+            // Skip matches which are part of a bigger word in whole word mode.
+            if !self.dfas.whole_word || self.term.is_whole_word(&regex_match) {
+                return Some(regex_match);
+            }
         }
+    }
+}
 
-        let regex_match = self.next_match()?;
+/// Incremental count of all regex matches in the terminal buffer.
+///
+/// Every call to [`MatchCounter::step`] only searches a limited number of lines, which allows
+/// spreading the search of large buffers across multiple frames. Matches are stored with absolute
+/// lines, so they stay valid while new content is written to the terminal.
+#[derive(Debug, Default, Clone)]
+pub struct MatchCounter {
+    /// Matches found so far, ordered from the top of the history.
+    matches: Vec<Match>,
 
-        self.point = *regex_match.end();
-        self.skip();
+    /// Absolute line where the next step starts searching.
+    next_line: Option<usize>,
 
-        Some(regex_match)
+    /// Whether the entire buffer has been searched.
+    done: bool,
+}
+
+impl MatchCounter {
+    /// Discard all matches and restart at the top of the history.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Number of matches found so far.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.matches.len()
     }
+
+    /// Check if no matches have been found so far.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.matches.is_empty()
+    }
+
+    /// Check if the entire buffer has been searched.
+    #[inline]
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Position of a match in the list of all matches.
+    pub fn index_of<T>(&self, term: &Term<T>, regex_match: &Match) -> Option<usize> {
+        let grid = term.grid();
+        let start = *regex_match.start();
+
+        // Matches rotated out of the history are always at the top.
+        self.matches
+            .binary_search_by(|regex_match| match grid.absolute_to_buffer(*regex_match.start()) {
+                Some(point) if point == start => Ordering::Equal,
+                Some(point) if !is_before(point, start) => Ordering::Greater,
+                _ => Ordering::Less,
+            })
+            .ok()
+    }
+
+    /// Get a match by its index, if it is still part of the buffer.
+    pub fn get<T>(&self, term: &Term<T>, index: usize) -> Option<Match> {
+        let regex_match = self.matches.get(index)?;
+        let start = term.grid().absolute_to_buffer(*regex_match.start())?;
+        let end = term.grid().absolute_to_buffer(*regex_match.end())?;
+        Some(start..=end)
+    }
+
+    /// Search the next `max_lines` lines for matches.
+    ///
+    /// Once the entire buffer has been searched, every step will search the content added to the
+    /// bottom of the buffer since the last step.
+    pub fn step<T>(&mut self, term: &mut Term<T>, dfas: &RegexSearch, max_lines: usize) {
+        self.search(term, dfas, max_lines);
+
+        // Free the disk history lines decompressed by the search.
+        term.shrink_disk_cache();
+    }
+
+    /// Search the next `max_lines` lines, without freeing the disk history lines it reads.
+    fn search<T>(&mut self, term: &Term<T>, dfas: &RegexSearch, max_lines: usize) {
+        let grid = term.grid();
+
+        // Remove matches which have been rotated out of the history.
+        let rotated = self
+            .matches
+            .iter()
+            .take_while(|regex_match| grid.absolute_to_buffer(*regex_match.start()).is_none())
+            .count();
+        self.matches.drain(..rotated);
+
+        // Continue below the last step, or at the top if that line is gone.
+        let start_line = self
+            .next_line
+            .and_then(|line| grid.absolute_to_buffer(Point::new(line, Column(0))))
+            .map_or(term.total_lines() - 1, |point| point.line);
+
+        // Remove matches from lines which will be searched again.
+        let searched = self
+            .matches
+            .iter()
+            .rev()
+            .take_while(|regex_match| {
+                let start = grid.absolute_to_buffer(*regex_match.start());
+                start.map_or(false, |start| start.line <= start_line)
+            })
+            .count();
+        self.matches.truncate(self.matches.len() - searched);
+
+        // Always stop at the end of a line, to make sure no match is split across steps.
+        let start = Point::new(start_line, Column(0));
+        let end_line = start_line.saturating_sub(max_lines.max(1) - 1);
+        let end = term.line_search_right(Point::new(end_line, Column(0)));
+
+        // Stop once the iterator wraps around to the top of the search region.
+        let matches = RegexIter::new(start, end, Direction::Right, term, dfas)
+            .take_while(|regex_match| {
+                let match_start = *regex_match.start();
+                !is_before(match_start, start) && !is_before(end, match_start)
+            })
+            .map(|regex_match| {
+                let start = grid.buffer_to_absolute(*regex_match.start());
+                let end = grid.buffer_to_absolute(*regex_match.end());
+                start..=end
+            });
+        self.matches.extend(matches);
+
+        // Search the last line again in the next step, since it might still change.
+        self.done = end.line == 0;
+        let next_line = if self.done { term.line_search_left(end).line } else { end.line - 1 };
+        self.next_line = Some(grid.buffer_to_absolute(Point::new(next_line, Column(0))).line);
+    }
+}
+
+/// Check if a buffer point is located before another one.
+fn is_before(point: Point<usize>, other: Point<usize>) -> bool {
+    point.line > other.line || (point.line == other.line && point.column < other.column)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    use crate::index::Column;
+    use crate::grid::DiskHistory;
+    use crate::index::{Column, Line};
     use crate::term::test::mock_term;
 
     #[test]
@@ -511,6 +755,31 @@ mod tests {
         assert_eq!(term.regex_search_right(&dfas, start, end), Some(match_start..=match_end));
     }
 
+    #[test]
+    fn regex_disk_history() {
+        #[rustfmt::skip]
+        let mut term = mock_term("\
+            Alacritty\n\
+            testing66\r\n\
+            123\
+        ");
+
+        // Move the first two lines to the disk history.
+        let path = std::env::temp_dir().join(format!("alacritty-search-{}", std::process::id()));
+        let disk = DiskHistory::create(path, 100, term.cols()).unwrap();
+        term.grid.update_history(0);
+        term.grid.set_disk_history(Some(disk));
+        term.grid.scroll_up(&(Line(0)..Line(3)), Line(2));
+        assert_eq!(term.grid.disk_history().map(DiskHistory::len), Some(2));
+
+        let dfas = RegexSearch::new("Ala.*66").unwrap();
+        let start = Point::new(0, Column(8));
+        let end = Point::new(4, Column(0));
+        let match_start = Point::new(4, Column(0));
+        let match_end = Point::new(3, Column(8));
+        assert_eq!(term.regex_search_left(&dfas, start, end), Some(match_start..=match_end));
+    }
+
     #[test]
     fn regex_left() {
         #[rustfmt::skip]
@@ -765,4 +1034,133 @@ mod tests {
         let match_end = Point::new(0, Column(1));
         assert_eq!(term.regex_search_left(&dfas, start, end), Some(match_start..=match_end));
     }
+
+    #[test]
+    fn regex_iter_end() {
+        let term = mock_term("abab");
+
+        // Iteration stops when the last match ends on the end point.
+        let dfas = RegexSearch::new("ab").unwrap();
+        let start = Point::new(0, Column(0));
+        let end = Point::new(0, Column(3));
+        let iter = RegexIter::new(start, end, Direction::Right, &term, &dfas);
+        assert_eq!(iter.take(3).count(), 2);
+
+        // Single cell matches on the end point are still found, without wrapping around.
+        let dfas = RegexSearch::new("b").unwrap();
+        let iter = RegexIter::new(start, end, Direction::Right, &term, &dfas);
+        let matches: Vec<_> = iter.take(3).collect();
+        assert_eq!(matches, vec![
+            Point::new(0, Column(1))..=Point::new(0, Column(1)),
+            Point::new(0, Column(3))..=Point::new(0, Column(3)),
+        ]);
+    }
+
+    #[test]
+    fn match_counter() {
+        #[rustfmt::skip]
+        let mut term = mock_term("\
+            abc\r\n\
+            xxx\r\n\
+            abc\r\n\
+            abcab\
+        ");
+
+        let dfas = RegexSearch::new("ab").unwrap();
+        let mut counter = MatchCounter::default();
+
+        // Search the buffer in two steps.
+        counter.step(&mut term, &dfas, 2);
+        assert_eq!(counter.len(), 1);
+        assert!(!counter.is_done());
+        counter.step(&mut term, &dfas, 2);
+        assert_eq!(counter.len(), 4);
+        assert!(counter.is_done());
+
+        let last_match = Point::new(0, Column(3))..=Point::new(0, Column(4));
+        assert_eq!(counter.index_of(&term, &last_match), Some(3));
+        let second_match = Point::new(1, Column(0))..=Point::new(1, Column(1));
+        assert_eq!(counter.index_of(&term, &second_match), Some(1));
+        let no_match = Point::new(2, Column(0))..=Point::new(2, Column(1));
+        assert_eq!(counter.index_of(&term, &no_match), None);
+        let first_match = Point::new(3, Column(0))..=Point::new(3, Column(1));
+        assert_eq!(counter.get(&term, 0), Some(first_match));
+
+        // Changes to the last line are picked up by the next step.
+        term.grid[0][Column(0)].c = 'x';
+        counter.step(&mut term, &dfas, 2);
+        assert_eq!(counter.len(), 3);
+
+        // Matches are not affected by scrolling content into the history.
+        term.grid.scroll_up(&(Line(0)..Line(4)), Line(1));
+        counter.step(&mut term, &dfas, 2);
+        assert_eq!(counter.len(), 3);
+        let first_match = Point::new(4, Column(0))..=Point::new(4, Column(1));
+        assert_eq!(counter.index_of(&term, &first_match), Some(0));
+    }
+
+    #[test]
+    fn literal_search() {
+        let term = mock_term("a.b axb foo(");
+
+        let options = SearchOptions { literal: true, ..SearchOptions::default() };
+        let dfas = RegexSearch::with_options("a.b", options).unwrap();
+        let start = Point::new(0, Column(2));
+        let end = Point::new(0, Column(11));
+        let match_start = Point::new(0, Column(0));
+        let match_end = Point::new(0, Column(2));
+        assert_eq!(
+            term.search_next(&dfas, start, Direction::Right, Side::Left, None),
+            Some(match_start..=match_end)
+        );
+
+        let dfas = RegexSearch::with_options("foo(", options).unwrap();
+        let match_start = Point::new(0, Column(8));
+        assert_eq!(term.regex_search_right(&dfas, start, end), Some(match_start..=end));
+
+        // Without literal mode, the search text is still a regex.
+        assert!(RegexSearch::new("foo(").is_err());
+    }
+
+    #[test]
+    fn case_search() {
+        let term = mock_term("Alacritty");
+        let start = Point::new(0, Column(0));
+        let end = Point::new(0, Column(8));
+
+        let options = SearchOptions { case: SearchCase::Sensitive, ..SearchOptions::default() };
+        let dfas = RegexSearch::with_options("alacritty", options).unwrap();
+        assert_eq!(term.regex_search_right(&dfas, start, end), None);
+
+        let options = SearchOptions { case: SearchCase::Insensitive, ..SearchOptions::default() };
+        let dfas = RegexSearch::with_options("ALACRITTY", options).unwrap();
+        assert_eq!(term.regex_search_right(&dfas, start, end), Some(start..=end));
+
+        // Smart case is only case sensitive with uppercase characters.
+        let dfas = RegexSearch::new("alacritty").unwrap();
+        assert_eq!(term.regex_search_right(&dfas, start, end), Some(start..=end));
+        let dfas = RegexSearch::new("ALACRITTY").unwrap();
+        assert_eq!(term.regex_search_right(&dfas, start, end), None);
+    }
+
+    #[test]
+    fn whole_word_search() {
+        #[rustfmt::skip]
+        let term = mock_term("\
+            foobar\r\n\
+            bar fo\n\
+            o foo\
+        ");
+
+        let options = SearchOptions { whole_word: true, ..SearchOptions::default() };
+        let dfas = RegexSearch::with_options("foo", options).unwrap();
+        let start = Point::new(2, Column(0));
+        let end = Point::new(0, Column(4));
+        let matches: Vec<_> = RegexIter::new(start, end, Direction::Right, &term, &dfas).collect();
+
+        // Matches across wrapped lines are checked like any other match.
+        let wrapped_match = Point::new(1, Column(4))..=Point::new(0, Column(0));
+        let last_match = Point::new(0, Column(2))..=Point::new(0, Column(4));
+        assert_eq!(matches, vec![wrapped_match, last_match]);
+    }
 }
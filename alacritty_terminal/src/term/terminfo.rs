@@ -0,0 +1,316 @@
+//! Terminfo capabilities built into the terminal.
+//!
+//! These mirror the `alacritty` entry in `extra/alacritty.info`, allowing applications to query
+//! supported features at runtime instead of relying on the terminfo database of the system.
+
+/// Value of a terminfo capability.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Capability {
+    /// Capability which is present without a value.
+    Boolean,
+
+    /// Numeric capability.
+    Numeric(u32),
+
+    /// String capability, with all terminfo escapes already resolved.
+    String(&'static str),
+}
+
+/// Terminal name reported for the `TN` capability.
+pub const NAME: &str = "alacritty";
+
+/// Look up a capability by its name.
+///
+/// Besides the terminfo names, the termcap names commonly used by XTGETTCAP clients are
+/// supported for the terminal name and color count.
+pub fn capability(name: &str) -> Option<Capability> {
+    match name {
+        "TN" | "name" => return Some(Capability::String(NAME)),
+        "Co" => return capability("colors"),
+        // Direct color support is available independent of the terminfo entry.
+        "RGB" => return Some(Capability::String("8/8/8")),
+        _ => (),
+    }
+
+    let index = CAPABILITIES.binary_search_by_key(&name, |&(name, _)| name).ok()?;
+    Some(CAPABILITIES[index].1)
+}
+
+/// All capabilities of the `alacritty` terminfo entry, sorted by name.
+const CAPABILITIES: &[(&str, Capability)] = &[
+    ("AX", Capability::Boolean),
+    ("Cr", Capability::String("\x1b]112\x07")),
+    ("Cs", Capability::String("\x1b]12;%p1%s\x07")),
+    ("E3", Capability::String("\x1b[3J")),
+    ("Ms", Capability::String("\x1b]52;%p1%s;%p2%s\x07")),
+    ("OTbs", Capability::Boolean),
+    ("Se", Capability::String("\x1b[0 q")),
+    ("Smulx", Capability::String("\x1b[4:%p1%dm")),
+    ("Ss", Capability::String("\x1b[%p1%d q")),
+    ("XT", Capability::Boolean),
+    ("acsc", Capability::String("``aaffggiijjkkllmmnnooppqqrrssttuuvvwwxxyyzz{{||}}~~")),
+    ("am", Capability::Boolean),
+    ("bce", Capability::Boolean),
+    ("bel", Capability::String("\x07")),
+    ("blink", Capability::String("\x1b[5m")),
+    ("bold", Capability::String("\x1b[1m")),
+    ("cbt", Capability::String("\x1b[Z")),
+    ("ccc", Capability::Boolean),
+    ("civis", Capability::String("\x1b[?25l")),
+    ("clear", Capability::String("\x1b[H\x1b[2J")),
+    ("cnorm", Capability::String("\x1b[?12l\x1b[?25h")),
+    ("colors", Capability::Numeric(256)),
+    ("cols", Capability::Numeric(80)),
+    ("cr", Capability::String("\x0d")),
+    ("csr", Capability::String("\x1b[%i%p1%d;%p2%dr")),
+    ("cub", Capability::String("\x1b[%p1%dD")),
+    ("cub1", Capability::String("\x08")),
+    ("cud", Capability::String("\x1b[%p1%dB")),
+    ("cud1", Capability::String("\x0a")),
+    ("cuf", Capability::String("\x1b[%p1%dC")),
+    ("cuf1", Capability::String("\x1b[C")),
+    ("cup", Capability::String("\x1b[%i%p1%d;%p2%dH")),
+    ("cuu", Capability::String("\x1b[%p1%dA")),
+    ("cuu1", Capability::String("\x1b[A")),
+    ("cvvis", Capability::String("\x1b[?12;25h")),
+    ("dch", Capability::String("\x1b[%p1%dP")),
+    ("dch1", Capability::String("\x1b[P")),
+    ("dim", Capability::String("\x1b[2m")),
+    ("dl", Capability::String("\x1b[%p1%dM")),
+    ("dl1", Capability::String("\x1b[M")),
+    ("dsl", Capability::String("\x1b]2;\x07")),
+    ("ech", Capability::String("\x1b[%p1%dX")),
+    ("ed", Capability::String("\x1b[J")),
+    ("el", Capability::String("\x1b[K")),
+    ("el1", Capability::String("\x1b[1K")),
+    ("flash", Capability::String("\x1b[?5h$<100/>\x1b[?5l")),
+    ("fsl", Capability::String("\x07")),
+    ("home", Capability::String("\x1b[H")),
+    ("hpa", Capability::String("\x1b[%i%p1%dG")),
+    ("hs", Capability::Boolean),
+    ("ht", Capability::String("\x09")),
+    ("hts", Capability::String("\x1bH")),
+    ("il", Capability::String("\x1b[%p1%dL")),
+    ("il1", Capability::String("\x1b[L")),
+    ("ind", Capability::String("\x0a")),
+    ("indn", Capability::String("\x1b[%p1%dS")),
+    ("initc", Capability::String("\x1b]4;%p1%d;rgb:%p2%{255}%*%{1000}%/%2.2X/%p3%{255}%*%{1000}%/%2.2X/%p4%{255}%*%{1000}%/%2.2X\x1b\\")),
+    ("invis", Capability::String("\x1b[8m")),
+    ("is2", Capability::String("\x1b[!p\x1b[?3;4l\x1b[4l\x1b>")),
+    ("it", Capability::Numeric(8)),
+    ("kDC", Capability::String("\x1b[3;2~")),
+    ("kDC3", Capability::String("\x1b[3;3~")),
+    ("kDC4", Capability::String("\x1b[3;4~")),
+    ("kDC5", Capability::String("\x1b[3;5~")),
+    ("kDC6", Capability::String("\x1b[3;6~")),
+    ("kDC7", Capability::String("\x1b[3;7~")),
+    ("kDN", Capability::String("\x1b[1;2B")),
+    ("kDN3", Capability::String("\x1b[1;3B")),
+    ("kDN4", Capability::String("\x1b[1;4B")),
+    ("kDN5", Capability::String("\x1b[1;5B")),
+    ("kDN6", Capability::String("\x1b[1;6B")),
+    ("kDN7", Capability::String("\x1b[1;7B")),
+    ("kEND", Capability::String("\x1b[1;2F")),
+    ("kEND3", Capability::String("\x1b[1;3F")),
+    ("kEND4", Capability::String("\x1b[1;4F")),
+    ("kEND5", Capability::String("\x1b[1;5F")),
+    ("kEND6", Capability::String("\x1b[1;6F")),
+    ("kEND7", Capability::String("\x1b[1;7F")),
+    ("kHOM", Capability::String("\x1b[1;2H")),
+    ("kHOM3", Capability::String("\x1b[1;3H")),
+    ("kHOM4", Capability::String("\x1b[1;4H")),
+    ("kHOM5", Capability::String("\x1b[1;5H")),
+    ("kHOM6", Capability::String("\x1b[1;6H")),
+    ("kHOM7", Capability::String("\x1b[1;7H")),
+    ("kIC", Capability::String("\x1b[2;2~")),
+    ("kIC3", Capability::String("\x1b[2;3~")),
+    ("kIC4", Capability::String("\x1b[2;4~")),
+    ("kIC5", Capability::String("\x1b[2;5~")),
+    ("kIC6", Capability::String("\x1b[2;6~")),
+    ("kIC7", Capability::String("\x1b[2;7~")),
+    ("kLFT", Capability::String("\x1b[1;2D")),
+    ("kLFT3", Capability::String("\x1b[1;3D")),
+    ("kLFT4", Capability::String("\x1b[1;4D")),
+    ("kLFT5", Capability::String("\x1b[1;5D")),
+    ("kLFT6", Capability::String("\x1b[1;6D")),
+    ("kLFT7", Capability::String("\x1b[1;7D")),
+    ("kNXT", Capability::String("\x1b[6;2~")),
+    ("kNXT3", Capability::String("\x1b[6;3~")),
+    ("kNXT4", Capability::String("\x1b[6;4~")),
+    ("kNXT5", Capability::String("\x1b[6;5~")),
+    ("kNXT6", Capability::String("\x1b[6;6~")),
+    ("kNXT7", Capability::String("\x1b[6;7~")),
+    ("kPRV", Capability::String("\x1b[5;2~")),
+    ("kPRV3", Capability::String("\x1b[5;3~")),
+    ("kPRV4", Capability::String("\x1b[5;4~")),
+    ("kPRV5", Capability::String("\x1b[5;5~")),
+    ("kPRV6", Capability::String("\x1b[5;6~")),
+    ("kPRV7", Capability::String("\x1b[5;7~")),
+    ("kRIT", Capability::String("\x1b[1;2C")),
+    ("kRIT3", Capability::String("\x1b[1;3C")),
+    ("kRIT4", Capability::String("\x1b[1;4C")),
+    ("kRIT5", Capability::String("\x1b[1;5C")),
+    ("kRIT6", Capability::String("\x1b[1;6C")),
+    ("kRIT7", Capability::String("\x1b[1;7C")),
+    ("kUP", Capability::String("\x1b[1;2A")),
+    ("kUP3", Capability::String("\x1b[1;3A")),
+    ("kUP4", Capability::String("\x1b[1;4A")),
+    ("kUP5", Capability::String("\x1b[1;5A")),
+    ("kUP6", Capability::String("\x1b[1;6A")),
+    ("kUP7", Capability::String("\x1b[1;7A")),
+    ("kb2", Capability::String("\x1bOE")),
+    ("kbs", Capability::String("\x7f")),
+    ("kcbt", Capability::String("\x1b[Z")),
+    ("kcub1", Capability::String("\x1bOD")),
+    ("kcud1", Capability::String("\x1bOB")),
+    ("kcuf1", Capability::String("\x1bOC")),
+    ("kcuu1", Capability::String("\x1bOA")),
+    ("kdch1", Capability::String("\x1b[3~")),
+    ("kend", Capability::String("\x1bOF")),
+    ("kent", Capability::String("\x1bOM")),
+    ("kf1", Capability::String("\x1bOP")),
+    ("kf10", Capability::String("\x1b[21~")),
+    ("kf11", Capability::String("\x1b[23~")),
+    ("kf12", Capability::String("\x1b[24~")),
+    ("kf13", Capability::String("\x1b[1;2P")),
+    ("kf14", Capability::String("\x1b[1;2Q")),
+    ("kf15", Capability::String("\x1b[1;2R")),
+    ("kf16", Capability::String("\x1b[1;2S")),
+    ("kf17", Capability::String("\x1b[15;2~")),
+    ("kf18", Capability::String("\x1b[17;2~")),
+    ("kf19", Capability::String("\x1b[18;2~")),
+    ("kf2", Capability::String("\x1bOQ")),
+    ("kf20", Capability::String("\x1b[19;2~")),
+    ("kf21", Capability::String("\x1b[20;2~")),
+    ("kf22", Capability::String("\x1b[21;2~")),
+    ("kf23", Capability::String("\x1b[23;2~")),
+    ("kf24", Capability::String("\x1b[24;2~")),
+    ("kf25", Capability::String("\x1b[1;5P")),
+    ("kf26", Capability::String("\x1b[1;5Q")),
+    ("kf27", Capability::String("\x1b[1;5R")),
+    ("kf28", Capability::String("\x1b[1;5S")),
+    ("kf29", Capability::String("\x1b[15;5~")),
+    ("kf3", Capability::String("\x1bOR")),
+    ("kf30", Capability::String("\x1b[17;5~")),
+    ("kf31", Capability::String("\x1b[18;5~")),
+    ("kf32", Capability::String("\x1b[19;5~")),
+    ("kf33", Capability::String("\x1b[20;5~")),
+    ("kf34", Capability::String("\x1b[21;5~")),
+    ("kf35", Capability::String("\x1b[23;5~")),
+    ("kf36", Capability::String("\x1b[24;5~")),
+    ("kf37", Capability::String("\x1b[1;6P")),
+    ("kf38", Capability::String("\x1b[1;6Q")),
+    ("kf39", Capability::String("\x1b[1;6R")),
+    ("kf4", Capability::String("\x1bOS")),
+    ("kf40", Capability::String("\x1b[1;6S")),
+    ("kf41", Capability::String("\x1b[15;6~")),
+    ("kf42", Capability::String("\x1b[17;6~")),
+    ("kf43", Capability::String("\x1b[18;6~")),
+    ("kf44", Capability::String("\x1b[19;6~")),
+    ("kf45", Capability::String("\x1b[20;6~")),
+    ("kf46", Capability::String("\x1b[21;6~")),
+    ("kf47", Capability::String("\x1b[23;6~")),
+    ("kf48", Capability::String("\x1b[24;6~")),
+    ("kf49", Capability::String("\x1b[1;3P")),
+    ("kf5", Capability::String("\x1b[15~")),
+    ("kf50", Capability::String("\x1b[1;3Q")),
+    ("kf51", Capability::String("\x1b[1;3R")),
+    ("kf52", Capability::String("\x1b[1;3S")),
+    ("kf53", Capability::String("\x1b[15;3~")),
+    ("kf54", Capability::String("\x1b[17;3~")),
+    ("kf55", Capability::String("\x1b[18;3~")),
+    ("kf56", Capability::String("\x1b[19;3~")),
+    ("kf57", Capability::String("\x1b[20;3~")),
+    ("kf58", Capability::String("\x1b[21;3~")),
+    ("kf59", Capability::String("\x1b[23;3~")),
+    ("kf6", Capability::String("\x1b[17~")),
+    ("kf60", Capability::String("\x1b[24;3~")),
+    ("kf61", Capability::String("\x1b[1;4P")),
+    ("kf62", Capability::String("\x1b[1;4Q")),
+    ("kf63", Capability::String("\x1b[1;4R")),
+    ("kf7", Capability::String("\x1b[18~")),
+    ("kf8", Capability::String("\x1b[19~")),
+    ("kf9", Capability::String("\x1b[20~")),
+    ("khome", Capability::String("\x1bOH")),
+    ("kich1", Capability::String("\x1b[2~")),
+    ("kind", Capability::String("\x1b[1;2B")),
+    ("km", Capability::Boolean),
+    ("kmous", Capability::String("\x1b[M")),
+    ("knp", Capability::String("\x1b[6~")),
+    ("kpp", Capability::String("\x1b[5~")),
+    ("kri", Capability::String("\x1b[1;2A")),
+    ("lines", Capability::Numeric(24)),
+    ("mc0", Capability::String("\x1b[i")),
+    ("mc4", Capability::String("\x1b[4i")),
+    ("mc5", Capability::String("\x1b[5i")),
+    ("mc5i", Capability::Boolean),
+    ("meml", Capability::String("\x1bl")),
+    ("memu", Capability::String("\x1bm")),
+    ("mir", Capability::Boolean),
+    ("msgr", Capability::Boolean),
+    ("npc", Capability::Boolean),
+    ("oc", Capability::String("\x1b]104\x07")),
+    ("op", Capability::String("\x1b[39;49m")),
+    ("pairs", Capability::Numeric(32767)),
+    ("rc", Capability::String("\x1b8")),
+    ("rep", Capability::String("%p1%c\x1b[%p2%{1}%-%db")),
+    ("rev", Capability::String("\x1b[7m")),
+    ("ri", Capability::String("\x1bM")),
+    ("rin", Capability::String("\x1b[%p1%dT")),
+    ("ritm", Capability::String("\x1b[23m")),
+    ("rmacs", Capability::String("\x1b(B")),
+    ("rmam", Capability::String("\x1b[?7l")),
+    ("rmcup", Capability::String("\x1b[?1049l\x1b[23;0;0t")),
+    ("rmir", Capability::String("\x1b[4l")),
+    ("rmkx", Capability::String("\x1b[?1l\x1b>")),
+    ("rmm", Capability::String("\x1b[?1034l")),
+    ("rmso", Capability::String("\x1b[27m")),
+    ("rmul", Capability::String("\x1b[24m")),
+    ("rmxx", Capability::String("\x1b[29m")),
+    ("rs1", Capability::String("\x1bc\x1b]104\x07")),
+    ("rs2", Capability::String("\x1b[!p\x1b[?3;4l\x1b[4l\x1b>")),
+    ("sc", Capability::String("\x1b7")),
+    ("setab", Capability::String("\x1b[%?%p1%{8}%<%t4%p1%d%e%p1%{16}%<%t10%p1%{8}%-%d%e48;5;%p1%d%;m")),
+    ("setaf", Capability::String("\x1b[%?%p1%{8}%<%t3%p1%d%e%p1%{16}%<%t9%p1%{8}%-%d%e38;5;%p1%d%;m")),
+    ("sgr", Capability::String("%?%p9%t\x1b(0%e\x1b(B%;\x1b[0%?%p6%t;1%;%?%p5%t;2%;%?%p2%t;4%;%?%p1%p3%|%t;7%;%?%p4%t;5%;%?%p7%t;8%;m")),
+    ("sgr0", Capability::String("\x1b(B\x1b[m")),
+    ("sitm", Capability::String("\x1b[3m")),
+    ("smacs", Capability::String("\x1b(0")),
+    ("smam", Capability::String("\x1b[?7h")),
+    ("smcup", Capability::String("\x1b[?1049h\x1b[22;0;0t")),
+    ("smir", Capability::String("\x1b[4h")),
+    ("smkx", Capability::String("\x1b[?1h\x1b=")),
+    ("smm", Capability::String("\x1b[?1034h")),
+    ("smso", Capability::String("\x1b[7m")),
+    ("smul", Capability::String("\x1b[4m")),
+    ("smxx", Capability::String("\x1b[9m")),
+    ("tbc", Capability::String("\x1b[3g")),
+    ("tsl", Capability::String("\x1b]2;")),
+    ("u6", Capability::String("\x1b[%i%d;%dR")),
+    ("u7", Capability::String("\x1b[6n")),
+    ("u8", Capability::String("\x1b[?%[;0123456789]c")),
+    ("u9", Capability::String("\x1b[c")),
+    ("vpa", Capability::String("\x1b[%i%p1%dd")),
+    ("xenl", Capability::Boolean),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capabilities_sorted() {
+        assert!(CAPABILITIES.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+
+    #[test]
+    fn lookup() {
+        assert_eq!(capability("colors"), Some(Capability::Numeric(256)));
+        assert_eq!(capability("Co"), Some(Capability::Numeric(256)));
+        assert_eq!(capability("TN"), Some(Capability::String("alacritty")));
+        assert_eq!(capability("smcup"), Some(Capability::String("\x1b[?1049h\x1b[22;0;0t")));
+        assert_eq!(capability("am"), Some(Capability::Boolean));
+        assert_eq!(capability("setf"), None);
+        assert_eq!(capability("invalid"), None);
+    }
+}
@@ -1,6 +1,6 @@
 //! TTY related functionality.
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::{env, io};
 
 use crate::config::Config;
@@ -59,8 +59,13 @@ pub trait EventedPty: EventedReadWrite {
     fn next_child_event(&mut self) -> Option<ChildEvent>;
 }
 
+/// Environment variable containing the path of the IPC socket.
+pub const ALACRITTY_SOCKET_ENV: &str = "ALACRITTY_SOCKET";
+
 /// Setup environment variables.
-pub fn setup_env<C>(config: &Config<C>) {
+///
+/// The `socket` path is advertised to child processes, so they can talk to this instance.
+pub fn setup_env<C>(config: &Config<C>, socket: Option<&Path>) {
     // Default to 'alacritty' terminfo if it is available, otherwise
     // default to 'xterm-256color'. May be overridden by user's config
     // below.
@@ -73,6 +78,12 @@ pub fn setup_env<C>(config: &Config<C>) {
     // Prevent child processes from inheriting startup notification env.
     env::remove_var("DESKTOP_STARTUP_ID");
 
+    // Advertise the IPC socket, or make sure children don't talk to an unrelated instance.
+    match socket {
+        Some(socket) => env::set_var(ALACRITTY_SOCKET_ENV, socket),
+        None => env::remove_var(ALACRITTY_SOCKET_ENV),
+    }
+
     // Set env vars from config.
     for (key, value) in config.env.iter() {
         env::set_var(key, value);
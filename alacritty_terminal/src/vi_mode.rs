@@ -1,9 +1,10 @@
 use std::cmp::{max, min};
+use std::collections::VecDeque;
 
 use alacritty_config_derive::ConfigDeserialize;
 
 use crate::event::EventListener;
-use crate::grid::{Dimensions, GridCell};
+use crate::grid::{BidirectionalIterator, Dimensions, GridCell};
 use crate::index::{Boundary, Column, Direction, Line, Point, Side};
 use crate::term::cell::Flags;
 use crate::term::Term;
@@ -49,6 +50,415 @@ pub enum ViMotion {
     WordRightEnd,
     /// Move to opposing bracket.
     Bracket,
+    /// Move to the previous shell prompt.
+    PreviousPrompt,
+    /// Move to the next shell prompt.
+    NextPrompt,
+}
+
+/// Largest count accepted for vi mode commands.
+const MAX_COUNT: usize = 99_999;
+
+/// Number of marks, one for every lowercase ASCII letter.
+const MARK_COUNT: usize = 26;
+
+/// Maximum number of positions in the jump list.
+const MAX_JUMPS: usize = 100;
+
+/// Search for a character in the current line, like f/F/t/T in vi.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CharSearch {
+    /// Character to search for.
+    pub c: char,
+
+    /// Direction of the search.
+    pub direction: Direction,
+
+    /// Stop right before the character instead of on it.
+    pub till: bool,
+}
+
+impl CharSearch {
+    /// Same search in the opposite direction, like `,` in vi.
+    pub fn reversed(self) -> Self {
+        Self { direction: self.direction.opposite(), ..self }
+    }
+}
+
+/// Kind of text covered by a text object.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TextObjectKind {
+    /// Semantically separated word.
+    Semantic,
+    /// Whitespace separated word.
+    Word,
+    /// String delimited by a quote character.
+    Quote(char),
+    /// Text enclosed by an opening and closing bracket.
+    Bracket(char, char),
+}
+
+/// Text object around the cursor, like `iw` or `a(` in vi.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TextObject {
+    pub kind: TextObjectKind,
+
+    /// Exclude delimiters and surrounding whitespace.
+    pub inner: bool,
+}
+
+/// Movement of the vi mode cursor.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ViMovement {
+    /// Regular cursor motion.
+    Motion(ViMotion),
+    /// Character search in the current line.
+    Find(CharSearch),
+}
+
+impl ViMovement {
+    /// Check if operators using this movement affect entire lines.
+    fn is_linewise(self) -> bool {
+        match self {
+            ViMovement::Motion(motion) => matches!(
+                motion,
+                ViMotion::Up
+                    | ViMotion::Down
+                    | ViMotion::High
+                    | ViMotion::Middle
+                    | ViMotion::Low
+                    | ViMotion::PreviousPrompt
+                    | ViMotion::NextPrompt
+            ),
+            ViMovement::Find(_) => false,
+        }
+    }
+
+    /// Check if operators using this movement include the cell at the target.
+    fn is_inclusive(self) -> bool {
+        match self {
+            ViMovement::Motion(motion) => matches!(
+                motion,
+                ViMotion::Last
+                    | ViMotion::SemanticLeftEnd
+                    | ViMotion::SemanticRightEnd
+                    | ViMotion::WordLeftEnd
+                    | ViMotion::WordRightEnd
+                    | ViMotion::Bracket
+            ),
+            ViMovement::Find(search) => search.direction == Direction::Right,
+        }
+    }
+}
+
+/// Text affected by a vi mode operator.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ViTarget {
+    /// Text between the cursor and the target of a movement.
+    Movement(ViMovement),
+    /// Entire lines starting at the cursor, like `yy` in vi.
+    Lines,
+    /// Text object around the cursor.
+    TextObject(TextObject),
+}
+
+/// Register receiving yanked text.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ViRegister {
+    /// System clipboard, `"+` in vi.
+    Clipboard,
+    /// Primary selection, `"*` in vi.
+    Selection,
+}
+
+impl Default for ViRegister {
+    fn default() -> Self {
+        ViRegister::Clipboard
+    }
+}
+
+/// Complete vi mode command.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ViCommand {
+    /// Repeat a cursor movement.
+    Move { movement: ViMovement, count: usize },
+    /// Copy text to a register.
+    Yank { register: ViRegister, target: ViTarget, count: usize },
+    /// Copy the active selection to a register.
+    YankSelection(ViRegister),
+    /// Store the cursor position in a mark.
+    SetMark(char),
+    /// Jump to a mark, or to the first non-empty cell in its line when `line` is set.
+    GotoMark { mark: char, line: bool },
+}
+
+impl ViCommand {
+    /// Register written by this command.
+    pub fn register(&self) -> Option<ViRegister> {
+        match *self {
+            ViCommand::Yank { register, .. } | ViCommand::YankSelection(register) => Some(register),
+            ViCommand::Move { .. } | ViCommand::SetMark(_) | ViCommand::GotoMark { .. } => None,
+        }
+    }
+}
+
+/// Argument a partial vi mode command is waiting for.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Pending {
+    /// Register name after `"`.
+    Register,
+    /// Character for f/F/t/T.
+    Find { direction: Direction, till: bool },
+    /// Text object kind after `i` or `a`.
+    TextObject { inner: bool },
+    /// Mark name after `m`.
+    SetMark,
+    /// Mark name after `` ` `` or `'`.
+    GotoMark { line: bool },
+}
+
+/// Parser for multi-key vi mode commands like `5j`, `"*yiw` or `2fx`.
+#[derive(Debug, Default)]
+pub struct ViCommandParser {
+    count: Option<usize>,
+    operator_count: Option<usize>,
+    operator: bool,
+    register: Option<ViRegister>,
+    pending: Option<Pending>,
+    last_find: Option<CharSearch>,
+}
+
+impl ViCommandParser {
+    /// Check if a command was started, but not completed yet.
+    pub fn is_pending(&self) -> bool {
+        self.count.is_some() || self.operator || self.register.is_some() || self.pending.is_some()
+    }
+
+    /// Check if `c` is part of the current command, instead of being resolved to its motion.
+    ///
+    /// This is the case for arguments like the character after `f` and for digits extending the
+    /// count.
+    pub fn expects(&self, c: Option<char>) -> bool {
+        self.pending.is_some()
+            || c.map_or(false, |c| c.is_ascii_digit() && (c != '0' || self.count.is_some()))
+    }
+
+    /// Abort the current command.
+    pub fn reset(&mut self) {
+        *self = Self { last_find: self.last_find, ..Self::default() };
+    }
+
+    /// Process the next character of a command.
+    ///
+    /// While `visual` is set, the yank operator copies the active selection immediately. Motions
+    /// are not resolved from characters, they need to be passed using [`Self::motion`].
+    pub fn advance(&mut self, c: char, visual: bool) -> Option<ViCommand> {
+        match self.pending.take() {
+            Some(Pending::Register) => {
+                match c {
+                    '+' => self.register = Some(ViRegister::Clipboard),
+                    '*' => self.register = Some(ViRegister::Selection),
+                    _ => self.reset(),
+                }
+                return None;
+            },
+            Some(Pending::Find { direction, till }) => {
+                let search = CharSearch { c, direction, till };
+                self.last_find = Some(search);
+                return self.complete(ViTarget::Movement(ViMovement::Find(search)));
+            },
+            Some(Pending::TextObject { inner }) => {
+                let kind = match c {
+                    'w' => TextObjectKind::Semantic,
+                    'W' => TextObjectKind::Word,
+                    '"' | '\'' | '`' => TextObjectKind::Quote(c),
+                    '(' | ')' | 'b' => TextObjectKind::Bracket('(', ')'),
+                    '[' | ']' => TextObjectKind::Bracket('[', ']'),
+                    '{' | '}' | 'B' => TextObjectKind::Bracket('{', '}'),
+                    '<' | '>' => TextObjectKind::Bracket('<', '>'),
+                    _ => {
+                        self.reset();
+                        return None;
+                    },
+                };
+                return self.complete(ViTarget::TextObject(TextObject { kind, inner }));
+            },
+            Some(Pending::SetMark) => {
+                self.reset();
+                return Some(ViCommand::SetMark(c));
+            },
+            Some(Pending::GotoMark { line }) => {
+                self.reset();
+                return Some(ViCommand::GotoMark { mark: c, line });
+            },
+            None => (),
+        }
+
+        let target = match c {
+            '0'..='9' if c != '0' || self.count.is_some() => {
+                let digit = c.to_digit(10).unwrap_or_default() as usize;
+                let count = self.count.unwrap_or(0).saturating_mul(10).saturating_add(digit);
+                self.count = Some(min(count, MAX_COUNT));
+                return None;
+            },
+            '"' if !self.operator => {
+                self.pending = Some(Pending::Register);
+                return None;
+            },
+            'y' | 'Y' if visual => {
+                let register = self.register.unwrap_or_default();
+                self.reset();
+                return Some(ViCommand::YankSelection(register));
+            },
+            'y' if self.operator => ViTarget::Lines,
+            'y' => {
+                self.operator = true;
+                self.operator_count = self.count.take();
+                return None;
+            },
+            'Y' if !self.operator => {
+                self.operator = true;
+                ViTarget::Lines
+            },
+            'm' if !self.operator => {
+                self.pending = Some(Pending::SetMark);
+                return None;
+            },
+            '`' | '\'' if !self.operator => {
+                self.pending = Some(Pending::GotoMark { line: c == '\'' });
+                return None;
+            },
+            'i' | 'a' if self.operator => {
+                self.pending = Some(Pending::TextObject { inner: c == 'i' });
+                return None;
+            },
+            'f' | 'F' | 't' | 'T' => {
+                let direction = if c.is_lowercase() { Direction::Right } else { Direction::Left };
+                self.pending = Some(Pending::Find { direction, till: c == 't' || c == 'T' });
+                return None;
+            },
+            ';' | ',' => match self.last_find {
+                Some(search) if c == ';' => ViTarget::Movement(ViMovement::Find(search)),
+                Some(search) => ViTarget::Movement(ViMovement::Find(search.reversed())),
+                None => {
+                    self.reset();
+                    return None;
+                },
+            },
+            _ => {
+                self.reset();
+                return None;
+            },
+        };
+
+        self.complete(target)
+    }
+
+    /// Complete the current command with a motion.
+    pub fn motion(&mut self, motion: ViMotion) -> Option<ViCommand> {
+        self.complete(ViTarget::Movement(ViMovement::Motion(motion)))
+    }
+
+    /// Finish the current command.
+    fn complete(&mut self, target: ViTarget) -> Option<ViCommand> {
+        let count = self.operator_count.unwrap_or(1).saturating_mul(self.count.unwrap_or(1));
+        let count = min(count, MAX_COUNT);
+        let register = self.register.unwrap_or_default();
+        let operator = self.operator;
+        self.reset();
+
+        match target {
+            ViTarget::Movement(movement) if !operator => Some(ViCommand::Move { movement, count }),
+            target => Some(ViCommand::Yank { register, target, count }),
+        }
+    }
+}
+
+/// Positions the vi mode cursor jumped away from, like the jump list in vi.
+#[derive(Debug, Default, Clone)]
+pub struct JumpList {
+    /// Recorded positions, oldest first.
+    jumps: VecDeque<Point<usize>>,
+
+    /// Index of the active position, equal to the number of jumps when not navigating the list.
+    index: usize,
+}
+
+impl JumpList {
+    /// Record the position before a jump.
+    pub fn push(&mut self, point: Point<usize>) {
+        // Like vi, only keep the latest jump for every line.
+        self.jumps.retain(|jump| jump.line != point.line);
+
+        self.jumps.push_back(point);
+        if self.jumps.len() > MAX_JUMPS {
+            self.jumps.pop_front();
+        }
+
+        self.index = self.jumps.len();
+    }
+
+    /// Move to the previous position, starting from `current`.
+    pub fn older(&mut self, current: Point<usize>) -> Option<Point<usize>> {
+        // Record the current position, to allow jumping back to it.
+        if self.index >= self.jumps.len() {
+            self.push(current);
+            self.index -= 1;
+        }
+
+        if self.index == 0 {
+            return None;
+        }
+
+        self.index -= 1;
+        self.jumps.get(self.index).copied()
+    }
+
+    /// Move to the next position.
+    pub fn newer(&mut self) -> Option<Point<usize>> {
+        if self.index + 1 >= self.jumps.len() {
+            return None;
+        }
+
+        self.index += 1;
+        self.jumps.get(self.index).copied()
+    }
+}
+
+/// Vi mode marks and jump list of a grid.
+///
+/// All positions are stored as absolute points, to keep them correct while the grid scrolls.
+#[derive(Debug, Default, Clone)]
+pub struct ViMarks {
+    /// Positions of the marks `a` to `z`.
+    marks: [Option<Point<usize>>; MARK_COUNT],
+
+    /// Positions the vi mode cursor jumped away from.
+    pub jumps: JumpList,
+}
+
+impl ViMarks {
+    /// Store a position in a mark, like `m` in vi.
+    pub fn set(&mut self, mark: char, point: Point<usize>) {
+        if let Some(index) = mark_index(mark) {
+            self.marks[index] = Some(point);
+        }
+    }
+
+    /// Position of a mark.
+    pub fn get(&self, mark: char) -> Option<Point<usize>> {
+        self.marks[mark_index(mark)?]
+    }
+
+    /// Remove all marks and jumps.
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// All positions of marks and jumps.
+    pub fn points_mut(&mut self) -> impl Iterator<Item = &mut Point<usize>> {
+        self.marks.iter_mut().flatten().chain(self.jumps.jumps.iter_mut())
+    }
 }
 
 /// Cursor tracking vi mode position.
@@ -152,6 +562,16 @@ impl ViModeCursor {
             ViMotion::Bracket => {
                 buffer_point = term.bracket_search(buffer_point).unwrap_or(buffer_point);
             },
+            ViMotion::PreviousPrompt => {
+                if let Some(line) = term.prompt_search(buffer_point.line, Direction::Left) {
+                    buffer_point = Point::new(line, Column(0));
+                }
+            },
+            ViMotion::NextPrompt => {
+                if let Some(line) = term.prompt_search(buffer_point.line, Direction::Right) {
+                    buffer_point = Point::new(line, Column(0));
+                }
+            },
         }
 
         term.scroll_to_point(buffer_point);
@@ -190,6 +610,119 @@ impl ViModeCursor {
     }
 }
 
+impl<T: EventListener> Term<T> {
+    /// Execute a vi mode command.
+    ///
+    /// Returns the text copied by yank commands.
+    pub fn vi_command(&mut self, command: ViCommand) -> Option<String> {
+        match command {
+            ViCommand::Move { movement, count } => {
+                for _ in 0..count {
+                    self.vi_movement(movement);
+                }
+
+                None
+            },
+            ViCommand::Yank { target, count, .. } => {
+                let (start, end, cursor) = self.vi_target_bounds(target, count)?;
+                self.vi_goto_point(cursor);
+                Some(self.bounds_to_string(start, end))
+            },
+            ViCommand::YankSelection(_) => {
+                let text = self.selection_to_string();
+                self.selection = None;
+                text
+            },
+            ViCommand::SetMark(mark) => {
+                self.vi_set_mark(mark);
+                None
+            },
+            ViCommand::GotoMark { mark, line } => {
+                let mut point = self.vi_mark(mark)?;
+                if line {
+                    point = first_occupied_in_line(self, point.line)
+                        .unwrap_or_else(|| Point::new(point.line, Column(0)));
+                }
+                self.vi_jump(point);
+                None
+            },
+        }
+    }
+
+    /// Move the vi mode cursor once.
+    fn vi_movement(&mut self, movement: ViMovement) {
+        match movement {
+            ViMovement::Motion(motion) => self.vi_motion(motion),
+            ViMovement::Find(search) => {
+                let point = self.visible_to_buffer(self.vi_mode_cursor.point);
+                if let Some(point) = find_char(self, point, search) {
+                    self.vi_goto_point(point);
+                }
+            },
+        }
+    }
+
+    /// Find the text affected by an operator.
+    ///
+    /// Returns the start and end of the text, followed by the new vi mode cursor position.
+    fn vi_target_bounds(
+        &mut self,
+        target: ViTarget,
+        count: usize,
+    ) -> Option<(Point<usize>, Point<usize>, Point<usize>)> {
+        let origin = self.visible_to_buffer(self.vi_mode_cursor.point);
+        let last_column = self.cols() - 1;
+
+        match target {
+            ViTarget::Lines => {
+                let start = Point::new(origin.line, Column(0));
+                let end = Point::new(origin.line.saturating_sub(count - 1), last_column);
+                Some((start, end, origin))
+            },
+            ViTarget::TextObject(object) => {
+                let (start, end) = text_object(self, origin, object)?;
+                Some((start, end, start))
+            },
+            ViTarget::Movement(movement) => {
+                for _ in 0..count {
+                    self.vi_movement(movement);
+                }
+
+                let target = self.visible_to_buffer(self.vi_mode_cursor.point);
+                let (start, mut end) =
+                    if is_before(target, origin) { (target, origin) } else { (origin, target) };
+
+                if movement.is_linewise() {
+                    let end = Point::new(end.line, last_column);
+                    return Some((Point::new(start.line, Column(0)), end, start));
+                }
+
+                if !movement.is_inclusive() {
+                    if start == end {
+                        return None;
+                    }
+
+                    // Like vi, stop at the end of the previous line instead of the start of a new
+                    // one.
+                    let previous = end.sub_absolute(self, Boundary::Clamp, 1);
+                    end = match last_occupied_in_line(self, previous.line) {
+                        Some(occupied)
+                            if end.column.0 == 0
+                                && !is_wrap(self, previous)
+                                && !is_before(occupied, start) =>
+                        {
+                            occupied
+                        },
+                        _ => previous,
+                    };
+                }
+
+                Some((start, end, start))
+            },
+        }
+    }
+}
+
 /// Find next end of line to move to.
 fn last<T>(term: &Term<T>, mut point: Point<usize>) -> Point<usize> {
     let cols = term.cols();
@@ -350,6 +883,202 @@ fn word<T: EventListener>(
     point
 }
 
+/// Find a character in the current line, like f/F/t/T in vi.
+fn find_char<T>(term: &Term<T>, point: Point<usize>, search: CharSearch) -> Option<Point<usize>> {
+    let mut point = term.expand_wide(point, search.direction);
+
+    // Skip the adjacent cell when stopping before the character, so searches can be repeated.
+    if search.till {
+        point = advance_in_line(term, point, search.direction)?;
+    }
+
+    loop {
+        let next = advance_in_line(term, point, search.direction)?;
+
+        let cell = &term.grid()[next.line][next.column];
+        if cell.c == search.c
+            && !cell.flags.intersects(Flags::WIDE_CHAR_SPACER | Flags::LEADING_WIDE_CHAR_SPACER)
+        {
+            return Some(if search.till { term.expand_wide(point, Direction::Left) } else { next });
+        }
+
+        point = next;
+    }
+}
+
+/// Find the bounds of a text object around a point.
+fn text_object<T>(
+    term: &Term<T>,
+    point: Point<usize>,
+    object: TextObject,
+) -> Option<(Point<usize>, Point<usize>)> {
+    let point = term.expand_wide(point, Direction::Left);
+
+    match object.kind {
+        TextObjectKind::Semantic => Some(word_object(term, point, true, object.inner)),
+        TextObjectKind::Word => Some(word_object(term, point, false, object.inner)),
+        TextObjectKind::Quote(quote) => quote_object(term, point, quote, object.inner),
+        TextObjectKind::Bracket(open, close) => {
+            bracket_object(term, point, open, close, object.inner)
+        },
+    }
+}
+
+/// Find the bounds of the word at a point, like `iw` and `aw` in vi.
+fn word_object<T>(
+    term: &Term<T>,
+    point: Point<usize>,
+    semantic: bool,
+    inner: bool,
+) -> (Point<usize>, Point<usize>) {
+    // Group cells into whitespace, semantic escape chars and everything else.
+    let class = |point: Point<usize>| {
+        let cell = &term.grid()[point.line][point.column];
+        if is_space(term, point) {
+            0
+        } else if semantic
+            && !cell.flags.intersects(Flags::WIDE_CHAR_SPACER | Flags::LEADING_WIDE_CHAR_SPACER)
+            && term.semantic_escape_chars().contains(cell.c)
+        {
+            1
+        } else {
+            2
+        }
+    };
+
+    // Expand across all adjacent cells of the same group.
+    let expand = |mut point: Point<usize>, direction: Direction| {
+        let point_class = class(point);
+        while let Some(next) =
+            advance_in_line(term, point, direction).filter(|&next| class(next) == point_class)
+        {
+            point = next;
+        }
+        point
+    };
+
+    let mut start = expand(point, Direction::Left);
+    let mut end = expand(point, Direction::Right);
+
+    if inner {
+        return (start, end);
+    }
+
+    // Include the following word when starting on whitespace.
+    let next = advance_in_line(term, end, Direction::Right);
+    if class(point) == 0 {
+        return (start, next.map(|next| expand(next, Direction::Right)).unwrap_or(end));
+    }
+
+    // Include trailing whitespace, or leading whitespace when the word ends the line.
+    let trailing = next.filter(|&next| class(next) == 0).map(|next| expand(next, Direction::Right));
+    match trailing {
+        Some(trailing) if advance_in_line(term, trailing, Direction::Right).is_some() => {
+            end = trailing;
+        },
+        _ => {
+            let previous = advance_in_line(term, start, Direction::Left);
+            if let Some(previous) = previous.filter(|&previous| class(previous) == 0) {
+                start = expand(previous, Direction::Left);
+            }
+        },
+    }
+
+    (start, end)
+}
+
+/// Find the bounds of the quoted string at a point, like `i"` and `a"` in vi.
+fn quote_object<T>(
+    term: &Term<T>,
+    point: Point<usize>,
+    quote: char,
+    inner: bool,
+) -> Option<(Point<usize>, Point<usize>)> {
+    let mut line_start = point;
+    while let Some(previous) = advance_in_line(term, line_start, Direction::Left) {
+        line_start = previous;
+    }
+
+    // Collect all unescaped quotes in the line.
+    let mut quotes = Vec::new();
+    let mut cursor_index = 0;
+    let mut escaped = false;
+    let mut current = Some(line_start);
+    let mut index = 0;
+    while let Some(point_in_line) = current {
+        if point_in_line == point {
+            cursor_index = index;
+        }
+
+        let c = term.grid()[point_in_line.line][point_in_line.column].c;
+        if c == quote && !escaped {
+            quotes.push((index, point_in_line));
+        }
+        escaped = c == '\\' && !escaped;
+
+        current = advance_in_line(term, point_in_line, Direction::Right);
+        index += 1;
+    }
+
+    // Use the first quote pair which contains the point or follows it.
+    let (open, close) = quotes
+        .chunks_exact(2)
+        .map(|pair| (pair[0], pair[1]))
+        .find(|(_, close)| close.0 >= cursor_index)?;
+
+    if !inner {
+        return Some((open.1, close.1));
+    } else if close.0 == open.0 + 1 {
+        return None;
+    }
+
+    let start = advance_in_line(term, open.1, Direction::Right)?;
+    let end = advance_in_line(term, close.1, Direction::Left)?;
+    Some((start, end))
+}
+
+/// Find the bounds of the bracket pair enclosing a point, like `i(` and `a(` in vi.
+fn bracket_object<T>(
+    term: &Term<T>,
+    point: Point<usize>,
+    open: char,
+    close: char,
+    inner: bool,
+) -> Option<(Point<usize>, Point<usize>)> {
+    let c = term.grid()[point.line][point.column].c;
+    let start = if c == open {
+        point
+    } else if c == close {
+        term.bracket_search(point)?
+    } else {
+        // Find the closest unmatched opening bracket.
+        let mut iter = term.grid().iter_from(point);
+        let mut depth = 0;
+        loop {
+            let cell = iter.prev()?;
+            if cell.c == close {
+                depth += 1;
+            } else if cell.c == open && depth == 0 {
+                break cell.point;
+            } else if cell.c == open {
+                depth -= 1;
+            }
+        }
+    };
+    let end = term.bracket_search(start)?;
+
+    if !inner {
+        return Some((start, end));
+    }
+
+    let inner_start = start.add_absolute(term, Boundary::Clamp, 1);
+    if inner_start == end {
+        return None;
+    }
+
+    Some((inner_start, end.sub_absolute(term, Boundary::Clamp, 1)))
+}
+
 /// Find first non-empty cell in line.
 fn first_occupied_in_line<T>(term: &Term<T>, line: usize) -> Option<Point<usize>> {
     (0..term.cols().0)
@@ -373,6 +1102,48 @@ fn advance<T>(term: &Term<T>, point: Point<usize>, direction: Direction) -> Poin
     }
 }
 
+/// Advance point based on direction, without leaving the current line.
+///
+/// Wrapped lines are considered part of the same line.
+fn advance_in_line<T>(
+    term: &Term<T>,
+    point: Point<usize>,
+    direction: Direction,
+) -> Option<Point<usize>> {
+    let cols = term.cols();
+
+    match direction {
+        Direction::Right if point.column + 1 < cols => {
+            Some(Point::new(point.line, point.column + 1))
+        },
+        Direction::Right if is_wrap(term, point) => Some(Point::new(point.line - 1, Column(0))),
+        Direction::Left if point.column.0 > 0 => Some(Point::new(point.line, point.column - 1)),
+        Direction::Left => {
+            let wrap_point = Point::new(point.line + 1, cols - 1);
+            if point.line + 1 < term.total_lines() && is_wrap(term, wrap_point) {
+                Some(wrap_point)
+            } else {
+                None
+            }
+        },
+        Direction::Right => None,
+    }
+}
+
+/// Index of a mark in the list of marks.
+fn mark_index(mark: char) -> Option<usize> {
+    if mark.is_ascii_lowercase() {
+        Some(mark as usize - 'a' as usize)
+    } else {
+        None
+    }
+}
+
+/// Check if a buffer point comes before another one in the text.
+fn is_before(point: Point<usize>, other: Point<usize>) -> bool {
+    point.line > other.line || (point.line == other.line && point.column < other.column)
+}
+
 /// Check if cell at point contains whitespace.
 fn is_space<T>(term: &Term<T>, point: Point<usize>) -> bool {
     let cell = &term.grid()[point.line][point.column];
@@ -396,7 +1167,9 @@ fn is_boundary<T>(term: &Term<T>, point: Point<usize>, direction: Direction) ->
 mod tests {
     use super::*;
 
+    use crate::ansi::{ClearMode, Handler};
     use crate::config::MockConfig;
+    use crate::grid::PromptMarks;
     use crate::index::{Column, Line};
     use crate::term::{SizeInfo, Term};
 
@@ -509,6 +1282,30 @@ mod tests {
         assert_eq!(cursor.point, Point::new(Line(0), Column(0)));
     }
 
+    #[test]
+    fn motion_prompt() {
+        let mut term = term();
+        term.grid_mut()[Line(2)].insert_marks(PromptMarks::PROMPT_START);
+        term.grid_mut()[Line(5)].insert_marks(PromptMarks::PROMPT_START);
+
+        let mut cursor = ViModeCursor::new(Point::new(Line(9), Column(3)));
+
+        cursor = cursor.motion(&mut term, ViMotion::PreviousPrompt);
+        assert_eq!(cursor.point, Point::new(Line(5), Column(0)));
+
+        cursor = cursor.motion(&mut term, ViMotion::PreviousPrompt);
+        assert_eq!(cursor.point, Point::new(Line(2), Column(0)));
+
+        cursor = cursor.motion(&mut term, ViMotion::PreviousPrompt);
+        assert_eq!(cursor.point, Point::new(Line(2), Column(0)));
+
+        cursor = cursor.motion(&mut term, ViMotion::NextPrompt);
+        assert_eq!(cursor.point, Point::new(Line(5), Column(0)));
+
+        cursor = cursor.motion(&mut term, ViMotion::NextPrompt);
+        assert_eq!(cursor.point, Point::new(Line(5), Column(0)));
+    }
+
     fn motion_semantic_term() -> Term<()> {
         let mut term = term();
 
@@ -764,4 +1561,255 @@ mod tests {
         cursor = cursor.motion(&mut term, ViMotion::WordLeft);
         assert_eq!(cursor.point, Point::new(Line(0), Column(0)));
     }
+
+    /// Pass keys to the parser, resolving motions like the default key bindings.
+    fn parse(parser: &mut ViCommandParser, input: &str) -> Option<ViCommand> {
+        input.chars().fold(None, |_, c| {
+            let motion = match c {
+                'h' => ViMotion::Left,
+                'j' => ViMotion::Down,
+                '0' => ViMotion::First,
+                '$' => ViMotion::Last,
+                'b' => ViMotion::SemanticLeft,
+                'w' => ViMotion::SemanticRight,
+                'e' => ViMotion::SemanticRightEnd,
+                _ => return parser.advance(c, false),
+            };
+
+            if parser.expects(Some(c)) {
+                parser.advance(c, false)
+            } else {
+                parser.motion(motion)
+            }
+        })
+    }
+
+    fn text_term(text: &str) -> Term<()> {
+        let mut term = term();
+        for (i, c) in text.chars().enumerate() {
+            term.grid_mut()[Line(0)][Column(i)].c = c;
+        }
+        term.toggle_vi_mode();
+        term
+    }
+
+    fn yank(term: &mut Term<()>, column: usize, input: &str) -> Option<String> {
+        term.vi_mode_cursor.point = Point::new(Line(0), Column(column));
+        let command = parse(&mut ViCommandParser::default(), input)?;
+        term.vi_command(command)
+    }
+
+    #[test]
+    fn parse_count() {
+        let mut parser = ViCommandParser::default();
+
+        let movement = ViMovement::Motion(ViMotion::Down);
+        assert_eq!(parse(&mut parser, "12j"), Some(ViCommand::Move { movement, count: 12 }));
+        assert!(!parser.is_pending());
+
+        // Zero only extends an existing count, otherwise it's resolved to its motion.
+        assert!(!parser.expects(Some('0')));
+        parser.advance('1', false);
+        assert!(parser.expects(Some('0')));
+        assert!(!parser.expects(Some('j')));
+        assert!(!parser.expects(None));
+        parser.reset();
+
+        let movement = ViMovement::Motion(ViMotion::First);
+        assert_eq!(
+            parse(&mut parser, "y0"),
+            Some(ViCommand::Yank {
+                register: ViRegister::Clipboard,
+                target: ViTarget::Movement(movement),
+                count: 1,
+            })
+        );
+
+        let movement = ViMovement::Motion(ViMotion::SemanticRight);
+        assert_eq!(
+            parse(&mut parser, "2y3w"),
+            Some(ViCommand::Yank {
+                register: ViRegister::Clipboard,
+                target: ViTarget::Movement(movement),
+                count: 6,
+            })
+        );
+    }
+
+    #[test]
+    fn parse_operator() {
+        let mut parser = ViCommandParser::default();
+
+        assert_eq!(
+            parse(&mut parser, "3yy"),
+            Some(ViCommand::Yank {
+                register: ViRegister::Clipboard,
+                target: ViTarget::Lines,
+                count: 3,
+            })
+        );
+
+        let object = TextObject { kind: TextObjectKind::Bracket('(', ')'), inner: false };
+        assert_eq!(
+            parse(&mut parser, "\"*ya)"),
+            Some(ViCommand::Yank {
+                register: ViRegister::Selection,
+                target: ViTarget::TextObject(object),
+                count: 1,
+            })
+        );
+
+        assert_eq!(parse(&mut parser, "yi"), None);
+        assert!(parser.is_pending());
+        assert_eq!(parse(&mut parser, "x"), None);
+        assert!(!parser.is_pending());
+
+        parser.advance('"', true);
+        parser.advance('*', true);
+        let command = parser.advance('y', true);
+        assert_eq!(command, Some(ViCommand::YankSelection(ViRegister::Selection)));
+    }
+
+    #[test]
+    fn parse_find() {
+        let mut parser = ViCommandParser::default();
+
+        let search = CharSearch { c: 'x', direction: Direction::Left, till: true };
+        let movement = ViMovement::Find(search);
+        assert_eq!(parse(&mut parser, "2Tx"), Some(ViCommand::Move { movement, count: 2 }));
+
+        let movement = ViMovement::Find(search.reversed());
+        assert_eq!(parse(&mut parser, ","), Some(ViCommand::Move { movement, count: 1 }));
+    }
+
+    #[test]
+    fn find_char_motion() {
+        let mut term = text_term("a.b.c.d");
+        let mut parser = ViCommandParser::default();
+        term.vi_mode_cursor.point = Point::new(Line(0), Column(0));
+
+        term.vi_command(parse(&mut parser, "f.").unwrap());
+        assert_eq!(term.vi_mode_cursor.point, Point::new(Line(0), Column(1)));
+
+        term.vi_command(parse(&mut parser, "2;").unwrap());
+        assert_eq!(term.vi_mode_cursor.point, Point::new(Line(0), Column(5)));
+
+        term.vi_command(parse(&mut parser, ",").unwrap());
+        assert_eq!(term.vi_mode_cursor.point, Point::new(Line(0), Column(3)));
+
+        term.vi_command(parse(&mut parser, "tc").unwrap());
+        assert_eq!(term.vi_mode_cursor.point, Point::new(Line(0), Column(3)));
+
+        term.vi_command(parse(&mut parser, "Ta").unwrap());
+        assert_eq!(term.vi_mode_cursor.point, Point::new(Line(0), Column(1)));
+
+        term.vi_command(parse(&mut parser, "fz").unwrap());
+        assert_eq!(term.vi_mode_cursor.point, Point::new(Line(0), Column(1)));
+    }
+
+    #[test]
+    fn yank_motion() {
+        let mut term = text_term("foo bar.baz");
+
+        assert_eq!(yank(&mut term, 0, "yw").as_deref(), Some("foo "));
+        assert_eq!(yank(&mut term, 4, "ye").as_deref(), Some("bar.baz"));
+        assert_eq!(yank(&mut term, 4, "yt.").as_deref(), Some("bar"));
+        assert_eq!(yank(&mut term, 4, "y$").as_deref(), Some("bar.baz"));
+        assert_eq!(yank(&mut term, 4, "yb").as_deref(), Some("foo "));
+        assert_eq!(term.vi_mode_cursor.point, Point::new(Line(0), Column(0)));
+        assert_eq!(yank(&mut term, 0, "yh"), None);
+        assert_eq!(yank(&mut term, 8, "yy").as_deref(), Some("foo bar.baz\n"));
+        assert_eq!(term.vi_mode_cursor.point, Point::new(Line(0), Column(8)));
+    }
+
+    #[test]
+    fn yank_text_object() {
+        let mut term = text_term("a \"b c\" (x (y) z)");
+
+        assert_eq!(yank(&mut term, 5, "yiw").as_deref(), Some("c"));
+        assert_eq!(yank(&mut term, 5, "yaw").as_deref(), Some(" c"));
+        assert_eq!(yank(&mut term, 3, "yaW").as_deref(), Some("\"b "));
+        assert_eq!(yank(&mut term, 4, "yi\"").as_deref(), Some("b c"));
+        assert_eq!(yank(&mut term, 0, "ya\"").as_deref(), Some("\"b c\""));
+        assert_eq!(yank(&mut term, 10, "yib").as_deref(), Some("x (y) z"));
+        assert_eq!(yank(&mut term, 12, "ya(").as_deref(), Some("(y)"));
+        assert_eq!(term.vi_mode_cursor.point, Point::new(Line(0), Column(11)));
+        assert_eq!(yank(&mut term, 0, "yi("), None);
+    }
+
+    #[test]
+    fn marks_follow_scrolling() {
+        let mut term = term();
+        term.toggle_vi_mode();
+        let mut parser = ViCommandParser::default();
+
+        term.vi_mode_cursor.point = Point::new(Line(5), Column(3));
+        term.vi_command(parse(&mut parser, "ma").unwrap());
+
+        // Scroll the marked line into history.
+        term.grid_mut().scroll_up(&(Line(0)..Line(20)), Line(10));
+        assert_eq!(term.vi_mark('a'), Some(Point::new(24, Column(3))));
+
+        term.vi_mode_cursor.point = Point::new(Line(19), Column(0));
+        term.vi_command(parse(&mut parser, "'a").unwrap());
+        assert_eq!(term.grid().display_offset(), 5);
+        assert_eq!(term.vi_mode_cursor.point, Point::new(Line(0), Column(0)));
+
+        term.vi_command(parse(&mut parser, "`a").unwrap());
+        assert_eq!(term.vi_mode_cursor.point, Point::new(Line(0), Column(3)));
+
+        // Clearing the history removes all marks.
+        term.clear_screen(ClearMode::Saved);
+        assert_eq!(term.vi_mark('a'), None);
+    }
+
+    #[test]
+    fn marks_per_grid() {
+        let mut term = term();
+        term.toggle_vi_mode();
+        let mut parser = ViCommandParser::default();
+
+        term.vi_mode_cursor.point = Point::new(Line(5), Column(3));
+        term.vi_command(parse(&mut parser, "ma").unwrap());
+
+        // Marks of the primary screen are not used in the alternate screen.
+        term.swap_alt();
+        assert_eq!(term.vi_mark('a'), None);
+        assert_eq!(term.vi_command(parse(&mut parser, "`a").unwrap()), None);
+
+        term.vi_mode_cursor.point = Point::new(Line(2), Column(1));
+        term.vi_command(parse(&mut parser, "ma").unwrap());
+        assert_eq!(term.vi_mark('a'), Some(Point::new(17, Column(1))));
+
+        term.swap_alt();
+        assert_eq!(term.vi_mark('a'), Some(Point::new(14, Column(3))));
+    }
+
+    #[test]
+    fn jump_list() {
+        let mut term = term();
+        term.grid_mut().scroll_up(&(Line(0)..Line(20)), Line(20));
+        term.toggle_vi_mode();
+
+        term.vi_mode_cursor.point = Point::new(Line(19), Column(0));
+        term.vi_jump(Point::new(39, Column(1)));
+        term.vi_jump(Point::new(30, Column(2)));
+        assert_eq!(term.visible_to_buffer(term.vi_mode_cursor.point), Point::new(30, Column(2)));
+
+        term.vi_jump_older();
+        assert_eq!(term.visible_to_buffer(term.vi_mode_cursor.point), Point::new(39, Column(1)));
+
+        term.vi_jump_older();
+        assert_eq!(term.visible_to_buffer(term.vi_mode_cursor.point), Point::new(0, Column(0)));
+
+        term.vi_jump_older();
+        assert_eq!(term.visible_to_buffer(term.vi_mode_cursor.point), Point::new(0, Column(0)));
+
+        term.vi_jump_newer();
+        term.vi_jump_newer();
+        assert_eq!(term.visible_to_buffer(term.vi_mode_cursor.point), Point::new(30, Column(2)));
+
+        term.vi_jump_newer();
+        assert_eq!(term.visible_to_buffer(term.vi_mode_cursor.point), Point::new(30, Column(2)));
+    }
 }
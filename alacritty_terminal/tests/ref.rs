@@ -67,6 +67,9 @@ ref_tests! {
     saved_cursor_alt
     sgr
     underline
+    alt_screen_reflow
+    saved_cursor_reflow
+    saved_cursor_grow_reflow
 }
 
 fn read_u8<P>(path: P) -> Vec<u8>
@@ -84,6 +87,25 @@ struct RefConfig {
     history_size: u32,
 }
 
+/// Terminal dimensions starting at a position of the recording.
+#[derive(Deserialize, Copy, Clone)]
+struct RefResize {
+    offset: usize,
+    cols: usize,
+    screen_lines: usize,
+}
+
+impl RefResize {
+    /// Create a size with these dimensions and the cell metrics of the final size.
+    fn size_info(&self, size: &SizeInfo) -> SizeInfo {
+        let (cell_width, cell_height) = (size.cell_width(), size.cell_height());
+        let (padding_x, padding_y) = (size.padding_x(), size.padding_y());
+        let width = self.cols as f32 * cell_width + 2. * padding_x;
+        let height = self.screen_lines as f32 * cell_height + 2. * padding_y;
+        SizeInfo::new(width, height, cell_width, cell_height, padding_x, padding_y, false)
+    }
+}
+
 #[derive(Copy, Clone)]
 struct Mock;
 
@@ -101,16 +123,32 @@ fn ref_test(dir: &Path) {
     let grid: Grid<Cell> = json::from_str(&serialized_grid).unwrap();
     let ref_config: RefConfig = json::from_str(&serialized_cfg).unwrap();
 
+    // Resizes are optional, most recordings use a fixed size.
+    let mut resizes: Vec<RefResize> = fs::read_to_string(dir.join("resize.json"))
+        .map(|serialized_resizes| json::from_str(&serialized_resizes).unwrap())
+        .unwrap_or_default();
+    resizes.reverse();
+
     let mut config = MockConfig::default();
     config.scrolling.set_history(ref_config.history_size);
 
-    let mut terminal = Term::new(&config, size, Mock);
+    let initial_size = resizes.pop().map_or(size, |resize| resize.size_info(&size));
+    let mut terminal = Term::new(&config, initial_size, Mock);
     let mut parser = ansi::Processor::new();
 
-    for byte in recording {
+    for (offset, byte) in recording.into_iter().enumerate() {
+        while resizes.last().map_or(false, |resize| resize.offset <= offset) {
+            let resize = resizes.pop().unwrap();
+            terminal.resize(resize.size_info(&size));
+        }
+
         parser.advance(&mut terminal, byte, &mut io::sink());
     }
 
+    for resize in resizes.drain(..).rev() {
+        terminal.resize(resize.size_info(&size));
+    }
+
     // Truncate invisible lines from the grid.
     let mut term_grid = terminal.grid().clone();
     term_grid.initialize_all();
@@ -35,6 +35,34 @@ complete -c alacritty \
   -s "o" \
   -l "option" \
   -d "Override config file options"
+complete -c alacritty \
+  -f \
+  -l "socket" \
+  -d "Path for IPC socket creation"
+
+# Recording
+complete -c alacritty \
+  -F \
+  -l "record" \
+  -d "Record the session to an asciicast file"
+complete -c alacritty \
+  -F \
+  -l "play" \
+  -d "Play back an asciicast recording"
+complete -c alacritty \
+  -x \
+  -l "speed" \
+  -d "Playback speed multiplier"
+complete -c alacritty \
+  -F \
+  -l "screenshot" \
+  -d "Render the terminal to a PNG file without a window"
+
+# IPC
+complete -c alacritty \
+  -n "__fish_use_subcommand" \
+  -a "msg" \
+  -d "Send a message to the Alacritty socket"
 
 # Output
 complete \